@@ -0,0 +1,76 @@
+//! Listing spaces with and without the in-memory metadata cache
+//!
+//! Run with `cargo bench --bench space_metadata_cache`. Mirrors what
+//! `load_all_spaces_cached` in src/spaces.rs does per listing: walk the spaces
+//! directory, then either parse each .space-metadata.json or look it up in an
+//! LRU sized to the number of spaces.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use lru::LruCache;
+use serde_json::{json, Value};
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use tempfile::TempDir;
+
+const SPACES: usize = 200;
+const METADATA_FILE: &str = ".space-metadata.json";
+
+fn create_spaces(dir: &Path) {
+    for i in 0..SPACES {
+        let id = format!("space-{}", i);
+        let space_dir = dir.join(&id);
+        fs::create_dir(&space_dir).unwrap();
+        let metadata = json!({
+            "id": id,
+            "name": format!("Space {}", i),
+            "path": space_dir,
+            "claude_md_path": space_dir.join("CLAUDE.md"),
+            "created_at": 1_700_000_000_000i64,
+            "last_accessed_at": 1_700_000_000_000i64,
+        });
+        fs::write(space_dir.join(METADATA_FILE), metadata.to_string()).unwrap();
+    }
+}
+
+fn list_uncached(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .unwrap()
+        .flatten()
+        .filter_map(|entry| fs::read_to_string(entry.path().join(METADATA_FILE)).ok())
+        .filter_map(|contents| serde_json::from_str::<Value>(&contents).ok())
+        .count()
+}
+
+fn list_cached(dir: &Path, cache: &mut LruCache<String, Value>) -> usize {
+    fs::read_dir(dir)
+        .unwrap()
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry.file_name().to_string_lossy().to_string();
+            cache.get(&id).cloned()
+        })
+        .count()
+}
+
+fn bench_listing(c: &mut Criterion) {
+    let dir = TempDir::new().unwrap();
+    create_spaces(dir.path());
+
+    let mut cache = LruCache::new(NonZeroUsize::new(SPACES).unwrap());
+    for entry in fs::read_dir(dir.path()).unwrap().flatten() {
+        let contents = fs::read_to_string(entry.path().join(METADATA_FILE)).unwrap();
+        cache.put(
+            entry.file_name().to_string_lossy().to_string(),
+            serde_json::from_str::<Value>(&contents).unwrap(),
+        );
+    }
+
+    let mut group = c.benchmark_group("space_listing");
+    group.bench_function("uncached", |b| b.iter(|| list_uncached(dir.path())));
+    group.bench_function("cached", |b| b.iter(|| list_cached(dir.path(), &mut cache)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_listing);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+//! Insert throughput of the tuned WAL connection against SQLite's defaults
+//!
+//! Run with `cargo bench --bench sqlite_journal_mode`. Before measuring, the
+//! bench prints a single-run comparison of the tuned connection against the
+//! default rollback journal, for a quick read on what the PRAGMAs buy.
+
+#[path = "../src/db.rs"]
+mod db;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use rusqlite::{params, Connection};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+const INSERTS: usize = 1000;
+
+fn open(dir: &TempDir, tuned: bool) -> Connection {
+    let conn = Connection::open(dir.path().join("bench.db")).unwrap();
+    if tuned {
+        db::configure_connection(&conn).unwrap();
+    }
+    conn.execute(
+        "CREATE TABLE messages (id INTEGER PRIMARY KEY, body TEXT NOT NULL)",
+        [],
+    )
+    .unwrap();
+    conn
+}
+
+// Each insert is its own transaction, matching how conversations are saved
+fn insert_rows(conn: &Connection) {
+    for i in 0..INSERTS {
+        conn.execute(
+            "INSERT INTO messages (body) VALUES (?1)",
+            params![format!("message {}", i)],
+        )
+        .unwrap();
+    }
+}
+
+fn time_inserts(tuned: bool) -> Duration {
+    let dir = TempDir::new().unwrap();
+    let conn = open(&dir, tuned);
+    let start = Instant::now();
+    insert_rows(&conn);
+    start.elapsed()
+}
+
+fn report_wal_speedup() {
+    let default = time_inserts(false);
+    let tuned = time_inserts(true);
+    let speedup = default.as_secs_f64() / tuned.as_secs_f64();
+    println!(
+        "{} inserts - default: {:?}, tuned: {:?}, speedup: {:.1}x",
+        INSERTS, default, tuned, speedup
+    );
+}
+
+fn bench_inserts(c: &mut Criterion) {
+    report_wal_speedup();
+
+    let mut group = c.benchmark_group("sqlite_inserts");
+    group.sample_size(10);
+
+    for (name, tuned) in [("default", false), ("wal_tuned", true)] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || {
+                    let dir = TempDir::new().unwrap();
+                    let conn = open(&dir, tuned);
+                    (dir, conn)
+                },
+                |(_dir, conn)| insert_rows(&conn),
+                BatchSize::PerIteration,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_inserts);
+criterion_main!(benches);
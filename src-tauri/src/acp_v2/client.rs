@@ -3,8 +3,9 @@
 
 use agent_client_protocol::Client;
 use agent_client_protocol_schema::{
-    CreateTerminalRequest, CreateTerminalResponse, Error, ExtNotification, ExtRequest, ExtResponse,
-    KillTerminalCommandRequest, KillTerminalCommandResponse, PermissionOptionId,
+    AvailableCommand, CreateTerminalRequest, CreateTerminalResponse, Error, ExtNotification,
+    ExtRequest, ExtResponse, KillTerminalCommandRequest, KillTerminalCommandResponse,
+    PermissionOption, PermissionOptionId, PermissionOptionKind, Plan, PromptResponse,
     ReadTextFileRequest, ReadTextFileResponse, ReleaseTerminalRequest, ReleaseTerminalResponse,
     RequestPermissionOutcome, RequestPermissionRequest, RequestPermissionResponse,
     SessionNotification, SessionUpdate, TerminalExitStatus, TerminalOutputRequest,
@@ -14,12 +15,25 @@ use agent_client_protocol_schema::{
 use async_trait::async_trait;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tauri::{AppHandle, Emitter};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 
 use crate::terminal::TerminalManager;
 
+/// How long to wait for the user to answer a permission request before cancelling it
+const DEFAULT_PERMISSION_TIMEOUT_SECS: u64 = 60;
+
+/// How often buffered events are sent to the frontend when batching is on
+const EVENT_BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Context window that ContextUsage percentages are measured against
+const CONTEXT_WINDOW_TOKENS: u64 = 200_000;
+
 /// Permission request sent to frontend for user approval
 #[derive(Debug, Clone, Serialize)]
 pub struct FrontendPermissionRequest {
@@ -47,6 +61,246 @@ pub struct FrontendPermissionResponse {
     pub cancelled: bool,
 }
 
+/// Skip the approval UI for tool kinds matching `tool_kind_pattern`
+/// The pattern is compared case-insensitively with the tool kind ("Read", "Execute", ...);
+/// "*" matches every kind. The first matching rule wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionRule {
+    pub tool_kind_pattern: String,
+    /// true = approve without asking, false = reject without asking
+    pub auto_approve: bool,
+}
+
+impl PermissionRule {
+    fn matches(&self, kind: &str) -> bool {
+        self.tool_kind_pattern == "*" || self.tool_kind_pattern.eq_ignore_ascii_case(kind)
+    }
+}
+
+/// Pick the option a rule decides on: the broadest allow for approvals,
+/// a reject for denials (None if the agent offered no reject option)
+fn auto_decided_option(
+    options: &[PermissionOption],
+    auto_approve: bool,
+) -> Option<&PermissionOption> {
+    let preferred: &[PermissionOptionKind] = if auto_approve {
+        &[
+            PermissionOptionKind::AllowAlways,
+            PermissionOptionKind::AllowOnce,
+        ]
+    } else {
+        &[
+            PermissionOptionKind::RejectOnce,
+            PermissionOptionKind::RejectAlways,
+        ]
+    };
+
+    let found = preferred
+        .iter()
+        .find_map(|kind| options.iter().find(|opt| opt.kind == *kind));
+
+    if auto_approve {
+        found.or_else(|| options.first())
+    } else {
+        found
+    }
+}
+
+/// A protocol enum as it appears on the wire ("allow_once", "in_progress", ...)
+fn protocol_label<T: Serialize + std::fmt::Debug>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{:?}", value))
+}
+
+fn option_kind_label(kind: &PermissionOptionKind) -> String {
+    protocol_label(kind)
+}
+
+/// The agent's current plan, flattened to strings for the frontend's task list
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanUpdate {
+    pub session_id: String,
+    pub request_id: Option<u64>,
+    pub entries: Vec<PlanUpdateEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanUpdateEntry {
+    pub content: String,
+    /// "high" | "medium" | "low"
+    pub priority: String,
+    /// "pending" | "in_progress" | "completed"
+    pub status: String,
+}
+
+impl PlanUpdate {
+    fn from_plan(session_id: String, request_id: Option<u64>, plan: &Plan) -> Self {
+        Self {
+            session_id,
+            request_id,
+            entries: plan
+                .entries
+                .iter()
+                .map(|entry| PlanUpdateEntry {
+                    content: entry.content.clone(),
+                    priority: protocol_label(&entry.priority),
+                    status: protocol_label(&entry.status),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Tool output from a tool call update, split out for display
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallResult {
+    pub tool_call_id: String,
+    pub output_text: Option<String>,
+    pub output_files: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl ToolCallResult {
+    /// Build from the update's `content` in its wire form: text blocks become
+    /// output text, diffs and resources become file paths. A failed tool's
+    /// text is reported as the error instead.
+    fn from_update(tool_call_id: String, failed: bool, content: &serde_json::Value) -> Self {
+        let mut texts = Vec::new();
+        let mut output_files = Vec::new();
+
+        for item in content.as_array().into_iter().flatten() {
+            match item.get("type").and_then(|t| t.as_str()) {
+                Some("content") => {
+                    let Some(block) = item.get("content") else {
+                        continue;
+                    };
+                    match block.get("type").and_then(|t| t.as_str()) {
+                        Some("text") => {
+                            if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                                texts.push(text.to_string());
+                            }
+                        }
+                        Some("resource_link") => {
+                            if let Some(uri) = block.get("uri").and_then(|u| u.as_str()) {
+                                output_files.push(uri.to_string());
+                            }
+                        }
+                        Some("resource") => {
+                            if let Some(uri) =
+                                block.pointer("/resource/uri").and_then(|u| u.as_str())
+                            {
+                                output_files.push(uri.to_string());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Some("diff") => {
+                    if let Some(path) = item.get("path").and_then(|p| p.as_str()) {
+                        output_files.push(path.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let text = (!texts.is_empty()).then(|| texts.join("\n"));
+        let (output_text, error) = if failed {
+            (
+                None,
+                Some(text.unwrap_or_else(|| "Tool call failed".to_string())),
+            )
+        } else {
+            (text, None)
+        };
+
+        Self {
+            tool_call_id,
+            output_text,
+            output_files,
+            error,
+        }
+    }
+}
+
+/// Token counts reported by the agent for a single prompt turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    /// PromptResponse has no usage field in the schema, so adapters that
+    /// report it do so under `_meta.usage`
+    pub fn from_prompt_response(response: &PromptResponse) -> Option<Self> {
+        let value = serde_json::to_value(response).ok()?;
+        Self::from_meta(value.get("_meta")?)
+    }
+
+    /// Session updates carry usage the same way, under the notification's `_meta`
+    pub fn from_notification(notification: &SessionNotification) -> Option<Self> {
+        let value = serde_json::to_value(notification).ok()?;
+        Self::from_meta(value.get("_meta")?)
+    }
+
+    fn from_meta(meta: &serde_json::Value) -> Option<Self> {
+        let usage = meta.get("usage")?;
+        let count = |camel: &str, snake: &str| {
+            usage
+                .get(camel)
+                .or_else(|| usage.get(snake))
+                .and_then(serde_json::Value::as_u64)
+        };
+
+        Some(Self {
+            input_tokens: count("inputTokens", "input_tokens")?,
+            output_tokens: count("outputTokens", "output_tokens")?,
+        })
+    }
+}
+
+/// Running token total for a session, and how much of the context window it fills
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub context_window_pct: f32,
+}
+
+impl ContextUsage {
+    fn add(&mut self, usage: TokenUsage) {
+        self.input_tokens += usage.input_tokens;
+        self.output_tokens += usage.output_tokens;
+        let total = self.input_tokens + self.output_tokens;
+        self.context_window_pct = total as f32 / CONTEXT_WINDOW_TOKENS as f32 * 100.0;
+    }
+}
+
+/// Context about the request being processed, added to its events
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestMetadata {
+    pub space_name: Option<String>,
+    /// When agent_v2_send_message received the request (Unix ms)
+    pub request_ts: i64,
+}
+
+impl RequestMetadata {
+    /// Add spaceName and requestTs to an object payload
+    pub fn apply(&self, payload: &mut serde_json::Value) {
+        if let Some(object) = payload.as_object_mut() {
+            object.insert("spaceName".to_string(), serde_json::json!(self.space_name));
+            object.insert("requestTs".to_string(), serde_json::json!(self.request_ts));
+        }
+    }
+}
+
 /// ThinkingSpaceClient implements the ACP Client trait
 /// The agent calls methods on this when it needs something from us
 #[derive(Clone)]
@@ -55,34 +309,91 @@ pub struct ThinkingSpaceClient {
 
     // For permission handling
     permission_tx: mpsc::UnboundedSender<FrontendPermissionRequest>,
-    permission_rx: Arc<Mutex<mpsc::UnboundedReceiver<FrontendPermissionResponse>>>,
+
+    // Permission requests waiting for the user's answer, by request id
+    pending_permissions: Arc<Mutex<HashMap<String, oneshot::Sender<FrontendPermissionResponse>>>>,
 
     // Track current request ID for event emission
     current_request_id: Arc<Mutex<Option<u64>>>,
 
+    // Space name and receive time of each session's current request, added to its events
+    request_metadata: Arc<Mutex<HashMap<String, RequestMetadata>>>,
+
+    // Seconds to wait for a permission response before cancelling
+    permission_timeout_seconds: Arc<AtomicU64>,
+
+    // Rules that answer permission requests without asking the user
+    permission_rules: Arc<Mutex<Vec<PermissionRule>>>,
+
+    // Database for the permission audit log (None = default sessions.db)
+    sessions_db_path: Option<PathBuf>,
+
+    // Whether agent thought chunks are forwarded to the frontend
+    show_thoughts: Arc<AtomicBool>,
+
+    // Last command list the agent advertised, per session
+    available_commands: Arc<Mutex<HashMap<String, Vec<AvailableCommand>>>>,
+
+    // Current agent mode per session (also persisted to sessions.db)
+    session_modes: Arc<Mutex<HashMap<String, String>>>,
+
+    // Text of the most recent agent message chunk sent to the frontend, per session
+    last_message_chunks: Arc<Mutex<HashMap<String, String>>>,
+
+    // Sessions whose agent text is collected here instead of streamed (e.g. title generation)
+    captured_sessions: Arc<Mutex<HashMap<String, String>>>,
+
+    // Tokens reported so far, per session
+    context_usage: Arc<Mutex<HashMap<String, ContextUsage>>>,
+
     // Terminal management
     terminal_manager: Arc<TerminalManager>,
+
+    // Whether events are buffered and sent as "agent-event-batch"
+    batch_events: Arc<AtomicBool>,
+
+    // Events waiting for the next batch flush
+    event_buffer: Arc<Mutex<Vec<(String, serde_json::Value)>>>,
+
+    // Bumped each time batching is turned on, so an older flush loop knows to stop
+    event_batch_generation: Arc<AtomicU64>,
+
+    // Events emitted so far, so tests can inspect them without an app handle
+    #[cfg(test)]
+    emitted_events: Arc<Mutex<Vec<(String, serde_json::Value)>>>,
 }
 
 impl ThinkingSpaceClient {
-    pub fn new() -> (Self, mpsc::UnboundedSender<FrontendPermissionResponse>) {
+    pub fn new() -> Self {
         let (permission_tx, _internal_permission_rx) =
             mpsc::unbounded_channel::<FrontendPermissionRequest>();
-        let (external_permission_tx, permission_rx) =
-            mpsc::unbounded_channel::<FrontendPermissionResponse>();
 
-        let client = Self {
+        Self {
             app_handle: Arc::new(Mutex::new(None)),
             permission_tx,
-            permission_rx: Arc::new(Mutex::new(permission_rx)),
+            pending_permissions: Arc::new(Mutex::new(HashMap::new())),
             current_request_id: Arc::new(Mutex::new(None)),
+            request_metadata: Arc::new(Mutex::new(HashMap::new())),
+            permission_timeout_seconds: Arc::new(AtomicU64::new(DEFAULT_PERMISSION_TIMEOUT_SECS)),
+            permission_rules: Arc::new(Mutex::new(Vec::new())),
+            sessions_db_path: None,
+            show_thoughts: Arc::new(AtomicBool::new(false)),
+            available_commands: Arc::new(Mutex::new(HashMap::new())),
+            session_modes: Arc::new(Mutex::new(HashMap::new())),
+            last_message_chunks: Arc::new(Mutex::new(HashMap::new())),
+            captured_sessions: Arc::new(Mutex::new(HashMap::new())),
+            context_usage: Arc::new(Mutex::new(HashMap::new())),
             terminal_manager: Arc::new(TerminalManager::new()),
-        };
-
-        (client, external_permission_tx)
+            batch_events: Arc::new(AtomicBool::new(false)),
+            event_buffer: Arc::new(Mutex::new(Vec::new())),
+            event_batch_generation: Arc::new(AtomicU64::new(0)),
+            #[cfg(test)]
+            emitted_events: Arc::new(Mutex::new(Vec::new())),
+        }
     }
 
     pub fn set_app_handle(&self, handle: AppHandle) {
+        self.terminal_manager.set_app_handle(handle.clone());
         *self.app_handle.lock() = Some(handle);
     }
 
@@ -90,7 +401,256 @@ impl ThinkingSpaceClient {
         *self.current_request_id.lock() = Some(request_id);
     }
 
+    /// Attach metadata to the events of the session's current request
+    /// (those carrying both its sessionId and a requestId)
+    pub fn set_request_metadata(&self, session_id: &str, metadata: RequestMetadata) {
+        self.request_metadata
+            .lock()
+            .insert(session_id.to_string(), metadata);
+        // A new request has no agent text yet
+        self.last_message_chunks.lock().remove(session_id);
+    }
+
+    pub fn terminal_manager(&self) -> Arc<TerminalManager> {
+        self.terminal_manager.clone()
+    }
+
+    pub fn set_permission_timeout(&self, seconds: u64) {
+        self.permission_timeout_seconds
+            .store(seconds, Ordering::Relaxed);
+    }
+
+    /// Shared session_id -> mode map, updated on every mode notification
+    pub fn session_modes(&self) -> Arc<Mutex<HashMap<String, String>>> {
+        self.session_modes.clone()
+    }
+
+    /// Run `f` against sessions.db on the blocking pool, so SQLite never stalls
+    /// the runtime the agent connection is driven from
+    async fn with_sessions_db<T, F>(&self, f: F) -> Result<T, String>
+    where
+        F: FnOnce(&rusqlite::Connection) -> Result<T, String> + Send + 'static,
+        T: Send + 'static,
+    {
+        let db_path = self.sessions_db_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let path = db_path.map_or_else(crate::sessions::get_db_path, Ok)?;
+            let conn = crate::sessions::get_connection_at(&path)?;
+            f(&conn)
+        })
+        .await
+        .map_err(|e| format!("Database task failed: {}", e))?
+    }
+
+    /// Remember the session's mode in memory and in sessions.db
+    async fn record_session_mode(&self, session_id: &str, mode: &str) {
+        self.session_modes
+            .lock()
+            .insert(session_id.to_string(), mode.to_string());
+
+        let session_id = session_id.to_string();
+        let patch = serde_json::json!({ "currentMode": mode });
+        let result = self
+            .with_sessions_db(move |conn| {
+                crate::sessions::update_session_metadata_internal(conn, &session_id, &patch)
+            })
+            .await;
+
+        if let Err(e) = result {
+            eprintln!("[ACP V2] Failed to persist session mode: {}", e);
+        }
+    }
+
+    /// Text of the session's most recent agent message chunk, if any
+    pub fn last_message_chunk(&self, session_id: &str) -> Option<String> {
+        self.last_message_chunks.lock().get(session_id).cloned()
+    }
+
+    /// Collect the agent's text for `session_id` instead of sending it to the frontend
+    pub fn capture_session_text(&self, session_id: &str) {
+        self.captured_sessions
+            .lock()
+            .insert(session_id.to_string(), String::new());
+    }
+
+    /// Stop capturing `session_id` and return the text collected so far
+    pub fn take_captured_text(&self, session_id: &str) -> Option<String> {
+        self.captured_sessions.lock().remove(session_id)
+    }
+
+    /// Drop everything tracked for a session that won't be used again
+    pub fn forget_session(&self, session_id: &str) {
+        self.captured_sessions.lock().remove(session_id);
+        self.context_usage.lock().remove(session_id);
+        self.available_commands.lock().remove(session_id);
+        self.session_modes.lock().remove(session_id);
+        self.request_metadata.lock().remove(session_id);
+    }
+
+    /// Tokens used by `session_id` so far (None if the agent hasn't reported any)
+    pub fn context_usage(&self, session_id: &str) -> Option<ContextUsage> {
+        self.context_usage.lock().get(session_id).copied()
+    }
+
+    /// Add `usage` to the session's running total and return the new total
+    fn record_context_usage(&self, session_id: &str, usage: TokenUsage) -> ContextUsage {
+        let mut totals = self.context_usage.lock();
+        let total = totals.entry(session_id.to_string()).or_default();
+        total.add(usage);
+        *total
+    }
+
+    /// Commands from the agent's most recent update for `session_id` (empty if none yet)
+    pub fn available_commands(&self, session_id: &str) -> Vec<AvailableCommand> {
+        self.available_commands
+            .lock()
+            .get(session_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Tell the frontend to reset its plan panel (a new prompt is starting)
+    pub fn clear_plan(&self, session_id: &str) {
+        let request_id = self.current_request_id.lock().clone();
+        self.emit_event(
+            "agent-plan-cleared",
+            serde_json::json!({
+                "sessionId": session_id,
+                "requestId": request_id,
+            }),
+        );
+    }
+
+    pub fn set_show_thoughts(&self, enabled: bool) {
+        self.show_thoughts.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn set_permission_rules(&self, rules: Vec<PermissionRule>) {
+        *self.permission_rules.lock() = rules;
+    }
+
+    fn matching_permission_rule(&self, kind: &str) -> Option<PermissionRule> {
+        self.permission_rules
+            .lock()
+            .iter()
+            .find(|rule| rule.matches(kind))
+            .cloned()
+    }
+
+    /// Record a permission decision in the audit log
+    /// Failures are logged but never block the agent
+    async fn log_permission_decision(
+        &self,
+        args: &RequestPermissionRequest,
+        request_id: &str,
+        kind: &str,
+        outcome: &str,
+    ) {
+        let session_id = args.session_id.0.to_string();
+        let tool_call_id = args.tool_call.id.0.to_string();
+        let request_id = request_id.to_string();
+        let kind = kind.to_string();
+        let outcome = outcome.to_string();
+        let result = self
+            .with_sessions_db(move |conn| {
+                crate::sessions::insert_permission_log(
+                    conn,
+                    &session_id,
+                    &request_id,
+                    &tool_call_id,
+                    &kind,
+                    &outcome,
+                )
+            })
+            .await;
+
+        if let Err(e) = result {
+            eprintln!("[ACP V2] Failed to log permission decision: {}", e);
+        }
+    }
+
+    /// Deliver the frontend's answer to the permission request it belongs to
+    /// Answers to requests nobody is waiting for (e.g. ones that already timed out) are discarded
+    pub fn send_permission_response(&self, response: FrontendPermissionResponse) {
+        let waiter = self.pending_permissions.lock().remove(&response.request_id);
+        match waiter {
+            Some(tx) => {
+                let _ = tx.send(response);
+            }
+            None => println!(
+                "[ACP V2] Ignoring stale permission response for {}",
+                response.request_id
+            ),
+        }
+    }
+
+    /// Buffer events and send them every EVENT_BATCH_INTERVAL instead of one at a time
+    /// Must be called inside a tokio runtime, which runs the flush loop
+    pub fn set_event_batching(&self, enabled: bool) {
+        if enabled {
+            if !self.batch_events.swap(true, Ordering::SeqCst) {
+                let generation = self.event_batch_generation.fetch_add(1, Ordering::SeqCst) + 1;
+                tokio::spawn(self.clone().flush_events_periodically(generation));
+            }
+        } else {
+            self.batch_events.store(false, Ordering::SeqCst);
+            self.flush_events();
+        }
+    }
+
+    async fn flush_events_periodically(self, generation: u64) {
+        let mut interval = tokio::time::interval(EVENT_BATCH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if !self.batch_events.load(Ordering::SeqCst)
+                || self.event_batch_generation.load(Ordering::SeqCst) != generation
+            {
+                break;
+            }
+            self.flush_events();
+        }
+    }
+
+    /// Send any buffered events now as one "agent-event-batch"
+    pub fn flush_events(&self) {
+        let events = std::mem::take(&mut *self.event_buffer.lock());
+        if events.is_empty() {
+            return;
+        }
+
+        let events: Vec<serde_json::Value> = events
+            .into_iter()
+            .map(|(event, payload)| serde_json::json!({ "type": event, "payload": payload }))
+            .collect();
+        self.emit_now("agent-event-batch", serde_json::json!({ "events": events }));
+    }
+
     fn emit_event(&self, event: &str, payload: impl Serialize + Clone) {
+        let mut payload = serde_json::to_value(&payload).unwrap_or_default();
+        if payload.get("requestId").is_some() {
+            let session_id = payload.get("sessionId").and_then(|id| id.as_str());
+            if let Some(metadata) =
+                session_id.and_then(|id| self.request_metadata.lock().get(id).cloned())
+            {
+                metadata.apply(&mut payload);
+            }
+        }
+
+        if self.batch_events.load(Ordering::SeqCst) {
+            self.event_buffer.lock().push((event.to_string(), payload));
+            return;
+        }
+
+        self.emit_now(event, payload);
+    }
+
+    fn emit_now(&self, event: &str, payload: impl Serialize + Clone) {
+        #[cfg(test)]
+        self.emitted_events.lock().push((
+            event.to_string(),
+            serde_json::to_value(&payload).unwrap_or_default(),
+        ));
+
         if let Some(handle) = self.app_handle.lock().as_ref() {
             println!("[ACP V2] Emitting event: {}", event);
             let _ = handle.emit(event, payload);
@@ -127,6 +687,35 @@ impl Client for ThinkingSpaceClient {
             .unwrap_or_default();
         let raw_input = args.tool_call.fields.raw_input.clone().unwrap_or_default();
 
+        // Rules can answer without involving the user
+        if let Some(rule) = self.matching_permission_rule(&kind) {
+            println!(
+                "[ACP V2] Permission rule '{}' matched tool kind {} (auto_approve={})",
+                rule.tool_kind_pattern, kind, rule.auto_approve
+            );
+
+            let outcome = match auto_decided_option(&args.options, rule.auto_approve) {
+                Some(option) => {
+                    let label = format!("auto_{}", option_kind_label(&option.kind));
+                    self.log_permission_decision(&args, &request_id, &kind, &label)
+                        .await;
+                    RequestPermissionOutcome::Selected {
+                        option_id: option.id.clone(),
+                    }
+                }
+                None => {
+                    self.log_permission_decision(&args, &request_id, &kind, "auto_cancelled")
+                        .await;
+                    RequestPermissionOutcome::Cancelled
+                }
+            };
+
+            return Ok(RequestPermissionResponse {
+                outcome,
+                meta: None,
+            });
+        }
+
         let current_request_id = self.current_request_id.lock().clone();
 
         // Convert to frontend format
@@ -135,7 +724,7 @@ impl Client for ThinkingSpaceClient {
             session_id: args.session_id.0.to_string(),
             tool_call_id: args.tool_call.id.0.to_string(),
             title,
-            kind,
+            kind: kind.clone(),
             raw_input,
             options: args
                 .options
@@ -157,24 +746,57 @@ impl Client for ThinkingSpaceClient {
             );
         }
 
+        // Register before sending so an immediate answer isn't dropped
+        let (response_tx, response_rx) = oneshot::channel();
+        self.pending_permissions
+            .lock()
+            .insert(request_id.clone(), response_tx);
+
         // Send to frontend
         self.emit_event("permission-request", frontend_request_json);
 
-        // Wait for user response
-        let response = self
-            .permission_rx
-            .lock()
-            .recv()
-            .await
-            .ok_or_else(|| Error::internal_error())?;
+        // Wait for user response, cancelling if nobody answers in time
+        let timeout_secs = self.permission_timeout_seconds.load(Ordering::Relaxed);
+        let wait = tokio::time::timeout(Duration::from_secs(timeout_secs), response_rx);
+        let response = match wait.await {
+            Ok(response) => response.map_err(|_| Error::internal_error())?,
+            Err(_) => {
+                self.pending_permissions.lock().remove(&request_id);
+                println!(
+                    "[ACP V2] Permission request {} timed out after {}s",
+                    request_id, timeout_secs
+                );
+                self.emit_event(
+                    "permission-request-timeout",
+                    serde_json::json!({ "requestId": request_id }),
+                );
+                self.log_permission_decision(&args, &request_id, &kind, "timed_out")
+                    .await;
+                return Ok(RequestPermissionResponse {
+                    outcome: RequestPermissionOutcome::Cancelled,
+                    meta: None,
+                });
+            }
+        };
 
         // Convert response
         if response.cancelled {
+            self.log_permission_decision(&args, &request_id, &kind, "cancelled")
+                .await;
             Ok(RequestPermissionResponse {
                 outcome: RequestPermissionOutcome::Cancelled,
                 meta: None,
             })
         } else if let Some(option_id) = response.option_id {
+            let label = args
+                .options
+                .iter()
+                .find(|opt| opt.id.0.as_ref() == option_id)
+                .map(|opt| option_kind_label(&opt.kind))
+                .unwrap_or_else(|| option_id.clone());
+            self.log_permission_decision(&args, &request_id, &kind, &label)
+                .await;
+
             Ok(RequestPermissionResponse {
                 outcome: RequestPermissionOutcome::Selected {
                     option_id: PermissionOptionId(Arc::from(option_id.as_str())),
@@ -190,9 +812,28 @@ impl Client for ThinkingSpaceClient {
     async fn session_notification(&self, args: SessionNotification) -> Result<(), Error> {
         let session_id = args.session_id.0.to_string();
 
+        // Captured sessions (titles, summaries) keep their text and send nothing to the chat
+        if let Some(buffer) = self.captured_sessions.lock().get_mut(&session_id) {
+            if let SessionUpdate::AgentMessageChunk {
+                content: agent_client_protocol_schema::ContentBlock::Text(ref text),
+            } = args.update
+            {
+                buffer.push_str(&text.text);
+            }
+            return Ok(());
+        }
+
+        let usage = match args.update {
+            SessionUpdate::AgentMessageChunk { .. } => TokenUsage::from_notification(&args),
+            _ => None,
+        };
+
         match args.update {
             SessionUpdate::AgentMessageChunk { content } => {
                 println!("[ACP V2] Agent message chunk received");
+                let context_usage =
+                    usage.map(|usage| self.record_context_usage(&session_id, usage));
+
                 // Stream agent text to frontend
                 if let agent_client_protocol_schema::ContentBlock::Text(text) = content {
                     println!(
@@ -201,6 +842,9 @@ impl Client for ThinkingSpaceClient {
                     );
 
                     let request_id = self.current_request_id.lock().clone();
+                    self.last_message_chunks
+                        .lock()
+                        .insert(session_id.clone(), text.text.clone());
 
                     self.emit_event(
                         "agent-message-chunk",
@@ -213,6 +857,18 @@ impl Client for ThinkingSpaceClient {
                 } else {
                     println!("[ACP V2] Agent chunk was not text: {:?}", content);
                 }
+
+                if let Some(total) = context_usage {
+                    self.emit_event(
+                        "agent-context-usage",
+                        serde_json::json!({
+                            "sessionId": session_id,
+                            "inputTokens": total.input_tokens,
+                            "outputTokens": total.output_tokens,
+                            "contextWindowPct": total.context_window_pct,
+                        }),
+                    );
+                }
             }
 
             SessionUpdate::UserMessageChunk { content } => {
@@ -261,6 +917,11 @@ impl Client for ThinkingSpaceClient {
                 println!("[ACP V2] Tool call update: {}", update.id.0);
 
                 let request_id = self.current_request_id.lock().clone();
+                let failed = update
+                    .fields
+                    .status
+                    .as_ref()
+                    .is_some_and(|status| protocol_label(status) == "failed");
 
                 // Send tool call update to frontend
                 self.emit_event(
@@ -273,10 +934,23 @@ impl Client for ThinkingSpaceClient {
                         "content": update.fields.content,
                     }),
                 );
+
+                // Structured version of the output for the tool result view
+                if update.fields.content.is_some() || failed {
+                    let content = serde_json::to_value(&update.fields.content).unwrap_or_default();
+                    let result =
+                        ToolCallResult::from_update(update.id.0.to_string(), failed, &content);
+                    self.emit_event(
+                        "tool-call-result",
+                        serde_json::to_value(&result).unwrap_or_default(),
+                    );
+                }
             }
 
             SessionUpdate::CurrentModeUpdate { current_mode_id } => {
                 println!("[ACP V2] Mode update: {}", current_mode_id);
+                self.record_session_mode(&session_id, &current_mode_id.to_string())
+                    .await;
                 self.emit_event(
                     "mode-update",
                     serde_json::json!({
@@ -286,15 +960,63 @@ impl Client for ThinkingSpaceClient {
                 );
             }
 
-            // Handle new variants we don't care about yet
-            SessionUpdate::AgentThoughtChunk { .. } => {
-                println!("[ACP V2] Agent thought chunk (not displayed)");
+            SessionUpdate::AgentThoughtChunk { content } => {
+                // Same payload shape as agent-message-chunk
+                if !self.show_thoughts.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+
+                if let agent_client_protocol_schema::ContentBlock::Text(text) = content {
+                    let request_id = self.current_request_id.lock().clone();
+
+                    self.emit_event(
+                        "agent-thought-chunk",
+                        serde_json::json!({
+                            "sessionId": session_id,
+                            "requestId": request_id,
+                            "text": text.text,
+                        }),
+                    );
+                }
             }
-            SessionUpdate::Plan(_) => {
-                println!("[ACP V2] Plan update (not displayed)");
+
+            // Handle new variants we don't care about yet
+            SessionUpdate::Plan(plan) => {
+                println!("[ACP V2] Plan update: {} entries", plan.entries.len());
+
+                let request_id = self.current_request_id.lock().clone();
+                self.emit_event(
+                    "agent-plan-update",
+                    PlanUpdate::from_plan(session_id, request_id, &plan),
+                );
             }
-            SessionUpdate::AvailableCommandsUpdate { .. } => {
-                println!("[ACP V2] Available commands update (not displayed)");
+            SessionUpdate::AvailableCommandsUpdate { available_commands } => {
+                println!(
+                    "[ACP V2] Available commands update: {} commands",
+                    available_commands.len()
+                );
+
+                let commands: Vec<serde_json::Value> = available_commands
+                    .iter()
+                    .map(|command| {
+                        serde_json::json!({
+                            "name": command.name,
+                            "description": command.description,
+                        })
+                    })
+                    .collect();
+
+                self.available_commands
+                    .lock()
+                    .insert(session_id.clone(), available_commands);
+
+                self.emit_event(
+                    "agent-available-commands",
+                    serde_json::json!({
+                        "sessionId": session_id,
+                        "commands": commands,
+                    }),
+                );
             }
         }
 
@@ -472,3 +1194,802 @@ impl Client for ThinkingSpaceClient {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn permission_request(kind: &str) -> RequestPermissionRequest {
+        serde_json::from_value(serde_json::json!({
+            "sessionId": "session-1",
+            "toolCall": {
+                "toolCallId": "tool-1",
+                "title": "Run tool",
+                "kind": kind,
+            },
+            "options": [
+                { "optionId": "allow-once", "name": "Allow", "kind": "allow_once" },
+                { "optionId": "allow-always", "name": "Always allow", "kind": "allow_always" },
+                { "optionId": "reject-once", "name": "Reject", "kind": "reject_once" },
+            ],
+        }))
+        .unwrap()
+    }
+
+    /// Client whose permission audit log goes to a temporary database
+    fn client_with_temp_db() -> (ThinkingSpaceClient, tempfile::TempDir) {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut client = ThinkingSpaceClient::new();
+        client.sessions_db_path = Some(temp_dir.path().join("sessions.db"));
+        (client, temp_dir)
+    }
+
+    fn logged_outcomes(temp_dir: &tempfile::TempDir) -> Vec<String> {
+        let conn =
+            crate::sessions::get_connection_at(&temp_dir.path().join("sessions.db")).unwrap();
+        let mut stmt = conn
+            .prepare("SELECT outcome FROM permission_log ORDER BY id")
+            .unwrap();
+        let outcomes = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        outcomes
+    }
+
+    fn emitted(client: &ThinkingSpaceClient, event: &str) -> Vec<serde_json::Value> {
+        client
+            .emitted_events
+            .lock()
+            .iter()
+            .filter(|(name, _)| name == event)
+            .map(|(_, payload)| payload.clone())
+            .collect()
+    }
+
+    /// Answer the next permission request with `option_id`, as the frontend would
+    async fn answer_permission(client: &ThinkingSpaceClient, option_id: &str) {
+        loop {
+            if let Some(request) = emitted(client, "permission-request").last() {
+                client.send_permission_response(FrontendPermissionResponse {
+                    request_id: request["request_id"].as_str().unwrap().to_string(),
+                    option_id: Some(option_id.to_string()),
+                    cancelled: false,
+                });
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    fn selected_option(response: RequestPermissionResponse) -> Option<String> {
+        match response.outcome {
+            RequestPermissionOutcome::Selected { option_id } => Some(option_id.0.to_string()),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_permission_rule_auto_approves() {
+        let (client, _temp) = client_with_temp_db();
+        client.set_permission_rules(vec![PermissionRule {
+            tool_kind_pattern: "read".to_string(),
+            auto_approve: true,
+        }]);
+
+        let response = client
+            .request_permission(permission_request("read"))
+            .await
+            .unwrap();
+
+        assert_eq!(selected_option(response).as_deref(), Some("allow-always"));
+        assert!(emitted(&client, "permission-request").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_permission_rule_denies() {
+        let (client, _temp) = client_with_temp_db();
+        client.set_permission_rules(vec![
+            PermissionRule {
+                tool_kind_pattern: "Execute".to_string(),
+                auto_approve: false,
+            },
+            PermissionRule {
+                tool_kind_pattern: "*".to_string(),
+                auto_approve: true,
+            },
+        ]);
+
+        let response = client
+            .request_permission(permission_request("execute"))
+            .await
+            .unwrap();
+
+        assert_eq!(selected_option(response).as_deref(), Some("reject-once"));
+        assert!(emitted(&client, "permission-request").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_permission_uses_normal_flow() {
+        let (client, _temp) = client_with_temp_db();
+        client.set_permission_rules(vec![PermissionRule {
+            tool_kind_pattern: "read".to_string(),
+            auto_approve: true,
+        }]);
+
+        let (response, _) = tokio::join!(
+            client.request_permission(permission_request("delete")),
+            answer_permission(&client, "reject-once"),
+        );
+
+        assert_eq!(
+            selected_option(response.unwrap()).as_deref(),
+            Some("reject-once")
+        );
+        assert_eq!(emitted(&client, "permission-request").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_permission_decisions_are_logged() {
+        let (client, temp) = client_with_temp_db();
+        client.set_permission_rules(vec![PermissionRule {
+            tool_kind_pattern: "read".to_string(),
+            auto_approve: true,
+        }]);
+
+        // Auto-approved by rule
+        client
+            .request_permission(permission_request("read"))
+            .await
+            .unwrap();
+
+        // Approved by the user
+        let (response, _) = tokio::join!(
+            client.request_permission(permission_request("edit")),
+            answer_permission(&client, "allow-once"),
+        );
+        response.unwrap();
+
+        assert_eq!(
+            logged_outcomes(&temp),
+            vec!["auto_allow_always", "allow_once"]
+        );
+    }
+
+    fn thought_notification(text: &str) -> SessionNotification {
+        serde_json::from_value(serde_json::json!({
+            "sessionId": "session-1",
+            "update": {
+                "sessionUpdate": "agent_thought_chunk",
+                "content": { "type": "text", "text": text },
+            },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_thought_chunks_hidden_by_default() {
+        let (client, _temp) = client_with_temp_db();
+
+        client
+            .session_notification(thought_notification("considering options"))
+            .await
+            .unwrap();
+
+        assert!(emitted(&client, "agent-thought-chunk").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_thought_chunks_emitted_when_enabled() {
+        let (client, _temp) = client_with_temp_db();
+        client.set_show_thoughts(true);
+        client.set_current_request_id(7);
+
+        client
+            .session_notification(thought_notification("considering options"))
+            .await
+            .unwrap();
+
+        let thoughts = emitted(&client, "agent-thought-chunk");
+        assert_eq!(thoughts.len(), 1);
+        assert_eq!(
+            thoughts[0],
+            serde_json::json!({
+                "sessionId": "session-1",
+                "requestId": 7,
+                "text": "considering options",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plan_update_event() {
+        let (client, _temp) = client_with_temp_db();
+        client.set_current_request_id(3);
+
+        let notification: SessionNotification = serde_json::from_value(serde_json::json!({
+            "sessionId": "session-1",
+            "update": {
+                "sessionUpdate": "plan",
+                "entries": [
+                    { "content": "Read the file", "priority": "high", "status": "completed" },
+                    { "content": "Write tests", "priority": "medium", "status": "in_progress" },
+                    { "content": "Clean up", "priority": "low", "status": "pending" },
+                ],
+            },
+        }))
+        .unwrap();
+
+        client.session_notification(notification).await.unwrap();
+
+        let updates = emitted(&client, "agent-plan-update");
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0],
+            serde_json::json!({
+                "sessionId": "session-1",
+                "requestId": 3,
+                "entries": [
+                    { "content": "Read the file", "priority": "high", "status": "completed" },
+                    { "content": "Write tests", "priority": "medium", "status": "in_progress" },
+                    { "content": "Clean up", "priority": "low", "status": "pending" },
+                ],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clear_plan_event() {
+        let (client, _temp) = client_with_temp_db();
+        client.set_current_request_id(4);
+
+        client.clear_plan("session-1");
+
+        assert_eq!(
+            emitted(&client, "agent-plan-cleared"),
+            vec![serde_json::json!({ "sessionId": "session-1", "requestId": 4 })]
+        );
+    }
+
+    fn commands_notification(session_id: &str, names: &[&str]) -> SessionNotification {
+        let commands: Vec<serde_json::Value> = names
+            .iter()
+            .map(|name| serde_json::json!({ "name": name, "description": format!("Run {}", name) }))
+            .collect();
+
+        serde_json::from_value(serde_json::json!({
+            "sessionId": session_id,
+            "update": {
+                "sessionUpdate": "available_commands_update",
+                "availableCommands": commands,
+            },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_available_commands_cached() {
+        let (client, _temp) = client_with_temp_db();
+        assert!(client.available_commands("session-1").is_empty());
+
+        client
+            .session_notification(commands_notification("session-1", &["init", "review"]))
+            .await
+            .unwrap();
+
+        let names = |commands: Vec<AvailableCommand>| -> Vec<String> {
+            commands.into_iter().map(|c| c.name).collect()
+        };
+        assert_eq!(
+            names(client.available_commands("session-1")),
+            vec!["init", "review"]
+        );
+        assert_eq!(
+            emitted(&client, "agent-available-commands")[0],
+            serde_json::json!({
+                "sessionId": "session-1",
+                "commands": [
+                    { "name": "init", "description": "Run init" },
+                    { "name": "review", "description": "Run review" },
+                ],
+            })
+        );
+
+        // Each update replaces the cached list; other sessions are unaffected
+        client
+            .session_notification(commands_notification("session-1", &["compact"]))
+            .await
+            .unwrap();
+        client
+            .session_notification(commands_notification("session-2", &["init"]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            names(client.available_commands("session-1")),
+            vec!["compact"]
+        );
+        assert_eq!(names(client.available_commands("session-2")), vec!["init"]);
+    }
+
+    #[tokio::test]
+    async fn test_mode_update_persisted() {
+        let (client, temp) = client_with_temp_db();
+
+        let notification: SessionNotification = serde_json::from_value(serde_json::json!({
+            "sessionId": "session-1",
+            "update": {
+                "sessionUpdate": "current_mode_update",
+                "currentModeId": "plan",
+            },
+        }))
+        .unwrap();
+        client.session_notification(notification).await.unwrap();
+
+        let conn = crate::sessions::get_connection_at(&temp.path().join("sessions.db")).unwrap();
+        let persisted = crate::sessions::get_session_mode_internal(&conn, "session-1").unwrap();
+        let in_memory = client.session_modes().lock().get("session-1").cloned();
+
+        assert_eq!(persisted.as_deref(), Some("plan"));
+        assert_eq!(in_memory, persisted);
+        assert_eq!(emitted(&client, "mode-update").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_permission_request_times_out() {
+        let (client, _temp) = client_with_temp_db();
+        client.set_permission_timeout(1);
+
+        let start = Instant::now();
+        let response = client
+            .request_permission(permission_request("execute"))
+            .await
+            .unwrap();
+        let elapsed = start.elapsed();
+
+        assert!(matches!(
+            response.outcome,
+            RequestPermissionOutcome::Cancelled
+        ));
+        assert!(elapsed >= Duration::from_secs(1));
+        assert!(elapsed < Duration::from_secs(2));
+
+        let request = &emitted(&client, "permission-request")[0];
+        let timeouts = emitted(&client, "permission-request-timeout");
+        assert_eq!(timeouts.len(), 1);
+        assert_eq!(timeouts[0]["requestId"], request["request_id"]);
+    }
+
+    #[tokio::test]
+    async fn test_permission_response_before_timeout() {
+        let (client, _temp) = client_with_temp_db();
+        client.set_permission_timeout(5);
+
+        // A stale answer for an earlier request is skipped
+        client.send_permission_response(FrontendPermissionResponse {
+            request_id: "old-request".to_string(),
+            option_id: Some("reject-once".to_string()),
+            cancelled: false,
+        });
+
+        let (response, _) = tokio::join!(
+            client.request_permission(permission_request("edit")),
+            answer_permission(&client, "allow-once"),
+        );
+
+        match response.unwrap().outcome {
+            RequestPermissionOutcome::Selected { option_id } => {
+                assert_eq!(option_id.0.as_ref(), "allow-once")
+            }
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+        assert!(emitted(&client, "permission-request-timeout").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_permission_requests_get_their_own_answers() {
+        let (client, _temp) = client_with_temp_db();
+        client.set_permission_timeout(5);
+
+        let answer_in_reverse = async {
+            let requests = loop {
+                let requests = emitted(&client, "permission-request");
+                if requests.len() == 2 {
+                    break requests;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            };
+            for (request, option_id) in requests.iter().rev().zip(["reject-once", "allow-once"]) {
+                client.send_permission_response(FrontendPermissionResponse {
+                    request_id: request["request_id"].as_str().unwrap().to_string(),
+                    option_id: Some(option_id.to_string()),
+                    cancelled: false,
+                });
+            }
+        };
+
+        let (first, second, _) = tokio::join!(
+            client.request_permission(permission_request("edit")),
+            client.request_permission(permission_request("delete")),
+            answer_in_reverse,
+        );
+
+        assert_eq!(
+            selected_option(first.unwrap()).as_deref(),
+            Some("allow-once")
+        );
+        assert_eq!(
+            selected_option(second.unwrap()).as_deref(),
+            Some("reject-once")
+        );
+        assert!(client.pending_permissions.lock().is_empty());
+    }
+
+    #[test]
+    fn test_token_usage_from_meta() {
+        let camel = serde_json::json!({ "usage": { "inputTokens": 120, "outputTokens": 45 } });
+        let snake = serde_json::json!({ "usage": { "input_tokens": 7, "output_tokens": 3 } });
+
+        assert_eq!(
+            TokenUsage::from_meta(&camel),
+            Some(TokenUsage {
+                input_tokens: 120,
+                output_tokens: 45
+            })
+        );
+        assert_eq!(
+            TokenUsage::from_meta(&snake),
+            Some(TokenUsage {
+                input_tokens: 7,
+                output_tokens: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_token_usage_missing_or_partial() {
+        assert_eq!(TokenUsage::from_meta(&serde_json::json!({})), None);
+        assert_eq!(
+            TokenUsage::from_meta(&serde_json::json!({ "usage": { "inputTokens": 1 } })),
+            None
+        );
+
+        let response: PromptResponse =
+            serde_json::from_value(serde_json::json!({ "stopReason": "end_turn" })).unwrap();
+        assert_eq!(TokenUsage::from_prompt_response(&response), None);
+    }
+
+    fn tool_call_update(status: &str, content: serde_json::Value) -> SessionNotification {
+        serde_json::from_value(serde_json::json!({
+            "sessionId": "session-1",
+            "update": {
+                "sessionUpdate": "tool_call_update",
+                "toolCallId": "tool-1",
+                "status": status,
+                "content": content,
+            },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_result_text_output() {
+        let (client, _temp) = client_with_temp_db();
+
+        client
+            .session_notification(tool_call_update(
+                "completed",
+                serde_json::json!([
+                    { "type": "content", "content": { "type": "text", "text": "total 2" } },
+                    { "type": "content", "content": { "type": "text", "text": "notes.md" } },
+                ]),
+            ))
+            .await
+            .unwrap();
+
+        let results = emitted(&client, "tool-call-result");
+        assert_eq!(
+            results,
+            vec![serde_json::json!({
+                "toolCallId": "tool-1",
+                "outputText": "total 2\nnotes.md",
+                "outputFiles": [],
+                "error": null,
+            })]
+        );
+        // The raw update is still sent
+        assert_eq!(emitted(&client, "tool-call-update").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_result_file_output() {
+        let (client, _temp) = client_with_temp_db();
+
+        client
+            .session_notification(tool_call_update(
+                "completed",
+                serde_json::json!([
+                    { "type": "diff", "path": "/space/notes.md", "oldText": "a", "newText": "b" },
+                    {
+                        "type": "content",
+                        "content": { "type": "resource_link", "uri": "file:///space/report.pdf", "name": "report.pdf" },
+                    },
+                ]),
+            ))
+            .await
+            .unwrap();
+
+        let results = emitted(&client, "tool-call-result");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["outputText"], serde_json::Value::Null);
+        assert_eq!(
+            results[0]["outputFiles"],
+            serde_json::json!(["/space/notes.md", "file:///space/report.pdf"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_result_error_output() {
+        let (client, _temp) = client_with_temp_db();
+
+        client
+            .session_notification(tool_call_update(
+                "failed",
+                serde_json::json!([
+                    { "type": "content", "content": { "type": "text", "text": "No such file" } },
+                ]),
+            ))
+            .await
+            .unwrap();
+
+        let results = emitted(&client, "tool-call-result");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["error"], "No such file");
+        assert_eq!(results[0]["outputText"], serde_json::Value::Null);
+
+        let result =
+            ToolCallResult::from_update("tool-2".to_string(), true, &serde_json::Value::Null);
+        assert_eq!(result.error.as_deref(), Some("Tool call failed"));
+    }
+
+    fn message_chunk(text: &str) -> SessionNotification {
+        serde_json::from_value(serde_json::json!({
+            "sessionId": "session-1",
+            "update": {
+                "sessionUpdate": "agent_message_chunk",
+                "content": { "type": "text", "text": text },
+            },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_captured_session_text_is_not_streamed() {
+        let (client, _temp) = client_with_temp_db();
+        client.capture_session_text("session-1");
+
+        client
+            .session_notification(message_chunk("Planning a "))
+            .await
+            .unwrap();
+        client
+            .session_notification(message_chunk("garden"))
+            .await
+            .unwrap();
+        let mode_update: SessionNotification = serde_json::from_value(serde_json::json!({
+            "sessionId": "session-1",
+            "update": {
+                "sessionUpdate": "current_mode_update",
+                "currentModeId": "plan",
+            },
+        }))
+        .unwrap();
+        client.session_notification(mode_update).await.unwrap();
+
+        assert!(emitted(&client, "agent-message-chunk").is_empty());
+        assert!(emitted(&client, "mode-update").is_empty());
+        assert_eq!(
+            client.take_captured_text("session-1").as_deref(),
+            Some("Planning a garden")
+        );
+
+        // Once taken, chunks stream as usual again
+        client
+            .session_notification(message_chunk("Hello"))
+            .await
+            .unwrap();
+        assert_eq!(emitted(&client, "agent-message-chunk").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_event_batching_coalesces_bursts() {
+        let client = ThinkingSpaceClient::new();
+        client.set_event_batching(true);
+
+        for i in 0..20 {
+            client.emit_event(
+                "tool-call-update",
+                serde_json::json!({ "toolCallId": format!("tool-{}", i) }),
+            );
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert!(emitted(&client, "tool-call-update").is_empty());
+        let batches = emitted(&client, "agent-event-batch");
+        assert!(!batches.is_empty() && batches.len() <= 2);
+
+        let events: Vec<serde_json::Value> = batches
+            .iter()
+            .flat_map(|batch| batch["events"].as_array().unwrap().clone())
+            .collect();
+        assert_eq!(events.len(), 20);
+        assert_eq!(events[0]["type"], "tool-call-update");
+        assert_eq!(events[19]["payload"]["toolCallId"], "tool-19");
+    }
+
+    #[tokio::test]
+    async fn test_event_batching_disabled_emits_immediately() {
+        let client = ThinkingSpaceClient::new();
+        client.set_event_batching(true);
+        client.emit_event("tool-call", serde_json::json!({ "toolCallId": "buffered" }));
+
+        // Turning batching off sends what was buffered, then emits directly again
+        client.set_event_batching(false);
+        assert_eq!(emitted(&client, "agent-event-batch").len(), 1);
+
+        client.emit_event("tool-call", serde_json::json!({ "toolCallId": "direct" }));
+        assert_eq!(emitted(&client, "tool-call").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_metadata_added_to_message_chunks() {
+        let client = ThinkingSpaceClient::new();
+        client.set_current_request_id(9);
+        client.set_request_metadata(
+            "session-1",
+            RequestMetadata {
+                space_name: Some("Research".to_string()),
+                request_ts: 1697500000000,
+            },
+        );
+        client.set_request_metadata(
+            "session-2",
+            RequestMetadata {
+                space_name: Some("Notes".to_string()),
+                request_ts: 1697500001000,
+            },
+        );
+
+        let notification: SessionNotification = serde_json::from_value(serde_json::json!({
+            "sessionId": "session-1",
+            "update": {
+                "sessionUpdate": "agent_message_chunk",
+                "content": { "type": "text", "text": "Hello" },
+            },
+        }))
+        .unwrap();
+        client.session_notification(notification).await.unwrap();
+
+        let chunks = emitted(&client, "agent-message-chunk");
+        assert_eq!(chunks[0]["requestId"], 9);
+        assert_eq!(chunks[0]["spaceName"], "Research");
+        assert_eq!(chunks[0]["requestTs"], 1697500000000i64);
+
+        // Another session's prompt keeps its own metadata
+        client.clear_plan("session-2");
+        let cleared = emitted(&client, "agent-plan-cleared");
+        assert_eq!(cleared[0]["spaceName"], "Notes");
+        assert_eq!(cleared[0]["requestTs"], 1697500001000i64);
+
+        // Events outside a request aren't touched
+        client.emit_event("terminal-output", serde_json::json!({ "terminalId": "t" }));
+        assert!(emitted(&client, "terminal-output")[0]
+            .get("spaceName")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_last_message_chunk_is_per_session() {
+        let (client, _temp) = client_with_temp_db();
+        let other_session: SessionNotification = serde_json::from_value(serde_json::json!({
+            "sessionId": "session-2",
+            "update": {
+                "sessionUpdate": "agent_message_chunk",
+                "content": { "type": "text", "text": "Other reply" },
+            },
+        }))
+        .unwrap();
+
+        client
+            .session_notification(message_chunk("First reply"))
+            .await
+            .unwrap();
+        client.session_notification(other_session).await.unwrap();
+
+        assert_eq!(
+            client.last_message_chunk("session-1").as_deref(),
+            Some("First reply")
+        );
+        assert_eq!(
+            client.last_message_chunk("session-2").as_deref(),
+            Some("Other reply")
+        );
+
+        // A new request for one session doesn't touch the other
+        client.set_request_metadata(
+            "session-1",
+            RequestMetadata {
+                space_name: None,
+                request_ts: 0,
+            },
+        );
+        assert_eq!(client.last_message_chunk("session-1"), None);
+        assert_eq!(
+            client.last_message_chunk("session-2").as_deref(),
+            Some("Other reply")
+        );
+    }
+
+    fn message_chunk_with_usage(text: &str, input: u64, output: u64) -> SessionNotification {
+        serde_json::from_value(serde_json::json!({
+            "sessionId": "session-1",
+            "update": {
+                "sessionUpdate": "agent_message_chunk",
+                "content": { "type": "text", "text": text },
+            },
+            "_meta": { "usage": { "inputTokens": input, "outputTokens": output } },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_context_window_pct() {
+        let mut usage = ContextUsage::default();
+        usage.add(TokenUsage {
+            input_tokens: 40_000,
+            output_tokens: 10_000,
+        });
+        assert_eq!(usage.context_window_pct, 25.0);
+
+        usage.add(TokenUsage {
+            input_tokens: 150_000,
+            output_tokens: 0,
+        });
+        assert_eq!(usage.context_window_pct, 100.0);
+    }
+
+    #[tokio::test]
+    async fn test_context_usage_accumulates_across_chunks() {
+        let client = ThinkingSpaceClient::new();
+        assert_eq!(client.context_usage("session-1"), None);
+
+        client
+            .session_notification(message_chunk_with_usage("Hello", 1_000, 20))
+            .await
+            .unwrap();
+        client
+            .session_notification(message_chunk_with_usage(" there", 1_500, 30))
+            .await
+            .unwrap();
+        // Chunks without usage leave the total alone and emit nothing
+        client
+            .session_notification(message_chunk("!"))
+            .await
+            .unwrap();
+
+        let usage = client.context_usage("session-1").unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (2_500, 50));
+        assert!((usage.context_window_pct - 1.275).abs() < 1e-4);
+
+        let events = emitted(&client, "agent-context-usage");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["inputTokens"], 1_000);
+        assert_eq!(events[1]["sessionId"], "session-1");
+        assert_eq!(events[1]["inputTokens"], 2_500);
+        assert_eq!(events[1]["outputTokens"], 50);
+        assert!((events[1]["contextWindowPct"].as_f64().unwrap() - 1.275).abs() < 1e-4);
+        assert_eq!(client.context_usage("session-2"), None);
+    }
+}
@@ -1,37 +1,674 @@
 // AcpManager - Manages the lifecycle of the ACP connection
 // Handles process spawning, connection setup, and request/response coordination
 
-use super::client::{FrontendPermissionResponse, ThinkingSpaceClient};
+use super::client::{
+    ContextUsage, FrontendPermissionResponse, PermissionRule, RequestMetadata, ThinkingSpaceClient,
+    TokenUsage,
+};
+use super::workers::WorkerPool;
+use crate::config::AcpConfig;
 use crate::mcp_config::McpConfig;
+use crate::settings::EffectiveSettings;
+use crate::spaces::FileWatcherManager;
+use crate::terminal::{TerminalOutputRange, TerminalPolicy, TerminalSnapshot};
+use crate::tray::{ActivityCounter, TrayIconState};
 use agent_client_protocol::{Agent, ClientSideConnection};
 use agent_client_protocol_schema::{
-    ClientCapabilities, ContentBlock, InitializeRequest, NewSessionRequest, PromptRequest,
-    SessionId, TextContent, VERSION,
+    AvailableCommand, CancelNotification, ClientCapabilities, ContentBlock, InitializeRequest,
+    InitializeResponse, LoadSessionRequest, McpServer, NewSessionRequest, PromptRequest, SessionId,
+    StopReason, TextContent, VERSION,
 };
-use parking_lot::Mutex;
+use parking_lot::{Condvar, Mutex};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter};
-use tokio::sync::{mpsc, oneshot};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::oneshot;
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
+impl AcpConfig {
+    /// The adapter command with piped stdio, ready to spawn
+    fn command(&self, api_key: Option<String>) -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new(&self.adapter_command);
+        cmd.args(&self.adapter_args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+
+        // Only set ANTHROPIC_API_KEY if we have one (for API key auth)
+        // Otherwise, adapter will use Claude Code's OAuth credentials
+        if let Some(key) = api_key {
+            println!("[ACP V2] Using API key authentication");
+            cmd.env("ANTHROPIC_API_KEY", key);
+        } else {
+            println!("[ACP V2] Using Claude Code OAuth credentials");
+        }
+
+        cmd
+    }
+}
+
+/// Oldest ACP protocol version the adapter may speak
+const MIN_SUPPORTED_VERSION: u64 = 1;
+
+/// Newest ACP protocol version this client has been tested against
+const MAX_TESTED_VERSION: u64 = 1;
+
+/// How the adapter's protocol version compares to what we support
+#[derive(Debug, PartialEq)]
+enum AdapterVersionCheck {
+    Supported,
+    /// Newer than anything tested; probably fine, but worth a warning
+    Untested,
+    /// Too old to talk to
+    Unsupported,
+}
+
+fn check_adapter_version(version: u64) -> AdapterVersionCheck {
+    if version < MIN_SUPPORTED_VERSION {
+        AdapterVersionCheck::Unsupported
+    } else if version > MAX_TESTED_VERSION {
+        AdapterVersionCheck::Untested
+    } else {
+        AdapterVersionCheck::Supported
+    }
+}
+
+/// The protocol version the adapter answered `initialize` with
+fn adapter_protocol_version(response: &InitializeResponse) -> Result<u64, String> {
+    serde_json::to_value(&response.protocol_version)
+        .ok()
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| {
+            format!(
+                "Unrecognised adapter protocol version: {:?}",
+                response.protocol_version
+            )
+        })
+}
+
+/// Who we are, sent to the adapter as the initialize request's `_meta`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientInfo {
+    pub client: String,
+    pub version: String,
+    pub platform: String,
+}
+
+fn client_info() -> ClientInfo {
+    ClientInfo {
+        client: "thinking-space".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        platform: std::env::consts::OS.to_string(),
+    }
+}
+
+fn initialize_request() -> InitializeRequest {
+    InitializeRequest {
+        protocol_version: VERSION,
+        client_capabilities: ClientCapabilities {
+            terminal: true, // Enable terminal support
+            ..Default::default()
+        },
+        meta: serde_json::to_value(client_info()).ok(),
+    }
+}
+
+/// What the current connection negotiated during the initialize handshake
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolMetadata {
+    pub protocol_version: u64,
+    /// Whether the agent can resume sessions (its only capability we rely on)
+    pub load_session: bool,
+    pub client_info: ClientInfo,
+}
+
+fn protocol_metadata(response: &InitializeResponse) -> Result<ProtocolMetadata, String> {
+    Ok(ProtocolMetadata {
+        protocol_version: adapter_protocol_version(response)?,
+        load_session: response.agent_capabilities.load_session,
+        client_info: client_info(),
+    })
+}
+
+/// npm package the default adapter config runs
+const ADAPTER_PACKAGE: &str = "@zed-industries/claude-code-acp";
+
+/// npm registry entry for the adapter's newest release
+const ADAPTER_REGISTRY_URL: &str =
+    "https://registry.npmjs.org/@zed-industries/claude-code-acp/latest";
+
+/// How long an update check result is reused before asking the registry again
+const UPDATE_CHECK_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Result of comparing the adapter package against the npm registry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpdateCheckResult {
+    /// Version the running adapter reported, else the one pinned in its args
+    /// (None = not known)
+    pub current_version: Option<String>,
+    pub latest_version: String,
+    pub update_available: bool,
+}
+
+/// Package version the adapter reports about itself in the `initialize` response's
+/// `_meta` (as `agentInfo.version` or `version`), the same way we send our client info
+/// (`protocolVersion` is the protocol's version, not the package's)
+fn reported_adapter_version(response: &InitializeResponse) -> Option<String> {
+    let meta = response.meta.as_ref()?;
+    meta.get("agentInfo")
+        .and_then(|info| info.get("version"))
+        .or_else(|| meta.get("version"))
+        .and_then(|version| version.as_str())
+        .filter(|version| !version.is_empty())
+        .map(str::to_string)
+}
+
+/// Version of the adapter package being run: what it reported, or what the
+/// launch args pin it to
+fn running_adapter_version(
+    response: Option<&InitializeResponse>,
+    config: &AcpConfig,
+) -> Option<String> {
+    response
+        .and_then(reported_adapter_version)
+        .or_else(|| pinned_adapter_version(config))
+}
+
+/// Package version pinned in the launch args, e.g. "@zed-industries/claude-code-acp@0.5.1"
+fn pinned_adapter_version(config: &AcpConfig) -> Option<String> {
+    config.adapter_args.iter().find_map(|arg| {
+        arg.strip_prefix(ADAPTER_PACKAGE)
+            .and_then(|rest| rest.strip_prefix('@'))
+            .filter(|version| !version.is_empty() && *version != "latest")
+            .map(str::to_string)
+    })
+}
+
+/// Compare dotted versions numerically; pre-release suffixes are ignored
+fn is_newer_version(latest: &str, current: &str) -> bool {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    parts(latest) > parts(current)
+}
+
+async fn fetch_latest_adapter_version(registry_url: &str) -> Result<String, String> {
+    #[derive(Deserialize)]
+    struct RegistryRelease {
+        version: String,
+    }
+
+    let response = reqwest::Client::new()
+        .get(registry_url)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to contact npm registry: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "npm registry returned status {}",
+            response.status()
+        ));
+    }
+
+    let release: RegistryRelease = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse npm registry response: {}", e))?;
+
+    Ok(release.version)
+}
+
+/// Check for a newer adapter release, reusing a result younger than UPDATE_CHECK_TTL
+async fn check_adapter_update_with(
+    cache: &Mutex<Option<(Instant, UpdateCheckResult)>>,
+    registry_url: &str,
+    current_version: Option<String>,
+    now: Instant,
+) -> Result<UpdateCheckResult, String> {
+    let cached = cache.lock().clone();
+    if let Some((checked_at, result)) = cached {
+        if now.saturating_duration_since(checked_at) < UPDATE_CHECK_TTL
+            && result.current_version == current_version
+        {
+            return Ok(result);
+        }
+    }
+
+    let latest_version = fetch_latest_adapter_version(registry_url).await?;
+    let result = UpdateCheckResult {
+        update_available: current_version
+            .as_deref()
+            .is_some_and(|current| is_newer_version(&latest_version, current)),
+        current_version,
+        latest_version,
+    };
+
+    *cache.lock() = Some((now, result.clone()));
+    Ok(result)
+}
+
+/// Check for a newer adapter and emit "adapter-update-available" if there is one
+async fn announce_adapter_update(
+    cache: Arc<Mutex<Option<(Instant, UpdateCheckResult)>>>,
+    app_handle: Arc<Mutex<Option<AppHandle>>>,
+    current_version: Option<String>,
+) {
+    match check_adapter_update_with(
+        &cache,
+        ADAPTER_REGISTRY_URL,
+        current_version,
+        Instant::now(),
+    )
+    .await
+    {
+        Ok(result) if result.update_available => {
+            println!(
+                "[ACP V2] Adapter update available: {:?} -> {}",
+                result.current_version, result.latest_version
+            );
+            if let Some(handle) = app_handle.lock().as_ref() {
+                let _ = handle.emit("adapter-update-available", &result);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("[ACP V2] Adapter update check failed: {}", e),
+    }
+}
+
+/// Adapter stderr lines kept for the logs pane
+const MAX_ADAPTER_LOG_LINES: usize = 500;
+
+/// Append a line to the adapter log, dropping the oldest beyond the cap
+fn push_adapter_log(logs: &Mutex<VecDeque<String>>, line: String) {
+    let mut logs = logs.lock();
+    if logs.len() >= MAX_ADAPTER_LOG_LINES {
+        logs.pop_front();
+    }
+    logs.push_back(line);
+}
+
+/// Read the adapter's stderr line by line into the log buffer, handing each line to `emit`
+async fn forward_adapter_stderr<R: AsyncRead + Unpin>(
+    stderr: R,
+    logs: Arc<Mutex<VecDeque<String>>>,
+    emit: impl Fn(serde_json::Value),
+) {
+    let mut lines = BufReader::new(stderr).lines();
+
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                eprintln!("[ACP V2] adapter: {}", line);
+                emit(serde_json::json!({
+                    "level": "error",
+                    "message": line,
+                }));
+                push_adapter_log(&logs, line);
+            }
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("[ACP V2] Failed to read adapter stderr: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+/// How long the adapter gets to exit after SIGTERM before it is killed
+const ADAPTER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Ask a child process to exit with SIGTERM, killing it if it is still running
+/// after `timeout` (on Windows it is killed straight away)
+async fn graceful_kill(child: &mut tokio::process::Child, timeout: Duration) -> Result<(), String> {
+    // Already exited and reaped
+    let Some(pid) = child.id() else {
+        return Ok(());
+    };
+
+    #[cfg(unix)]
+    {
+        // SAFETY: kill(2) has no memory effects; pid is our own unreaped child
+        if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } != 0 {
+            return Err(format!(
+                "Failed to send SIGTERM to {}: {}",
+                pid,
+                std::io::Error::last_os_error()
+            ));
+        }
+
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            let status = child
+                .try_wait()
+                .map_err(|e| format!("Failed to check process {}: {}", pid, e))?;
+            if status.is_some() {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        println!(
+            "[ACP V2] Process {} still running {:?} after SIGTERM, killing it",
+            pid, timeout
+        );
+    }
+    #[cfg(not(unix))]
+    let _ = timeout;
+
+    child
+        .kill()
+        .await
+        .map_err(|e| format!("Failed to kill process {}: {}", pid, e))
+}
+
+/// Completion notifications show at most this much of the agent's reply
+const NOTIFICATION_BODY_CHARS: usize = 80;
+
+/// Desktop side of completion notifications, so the decision can be tested without a window
+trait CompletionNotifier {
+    fn is_window_focused(&self) -> bool;
+    fn notify(&self, title: &str, body: &str);
+}
+
+struct TauriNotifier<'a>(&'a AppHandle);
+
+impl CompletionNotifier for TauriNotifier<'_> {
+    fn is_window_focused(&self) -> bool {
+        self.0
+            .get_webview_window("main")
+            .and_then(|window| window.is_focused().ok())
+            .unwrap_or(true)
+    }
+
+    fn notify(&self, title: &str, body: &str) {
+        if let Err(e) = self
+            .0
+            .notification()
+            .builder()
+            .title(title)
+            .body(body)
+            .show()
+        {
+            eprintln!("[ACP V2] Failed to show notification: {}", e);
+        }
+    }
+}
+
+/// Notify that the agent finished, unless disabled or the user is already looking
+/// Returns whether a notification was sent
+fn notify_completion(
+    notifier: &dyn CompletionNotifier,
+    enabled: bool,
+    last_chunk: Option<&str>,
+) -> bool {
+    if !enabled || notifier.is_window_focused() {
+        return false;
+    }
+
+    let body = match last_chunk.map(str::trim).filter(|text| !text.is_empty()) {
+        Some(text) => text.chars().take(NOTIFICATION_BODY_CHARS).collect(),
+        None => "The agent has finished responding".to_string(),
+    };
+    notifier.notify("Thinking Space", &body);
+    true
+}
+
+/// Messages accepted per window before the limiter pushes back
+const DEFAULT_RATE_LIMIT_MAX_REQUESTS: u32 = 30;
+const DEFAULT_RATE_LIMIT_WINDOW_SECS: u64 = 60;
+
+struct Bucket {
+    max_requests: u32,
+    window_seconds: u64,
+    tokens: u32,
+    // Bumped when the limit changes so the refill timer restarts its window
+    generation: u64,
+    closed: bool,
+}
+
+struct RateLimiterShared {
+    bucket: Mutex<Bucket>,
+    changed: Condvar,
+}
+
+/// Token bucket guarding agent_v2_send_message against runaway callers
+/// Holds `max_requests` tokens; a background timer tops it back up every
+/// `window_seconds`
+pub struct RateLimiter {
+    shared: Arc<RateLimiterShared>,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests: u32, window_seconds: u64) -> Result<Self, String> {
+        validate_rate_limit(max_requests, window_seconds)?;
+
+        let shared = Arc::new(RateLimiterShared {
+            bucket: Mutex::new(Bucket {
+                max_requests,
+                window_seconds,
+                tokens: max_requests,
+                generation: 0,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let timer = shared.clone();
+        std::thread::Builder::new()
+            .name("acp-rate-limit-refill".to_string())
+            .spawn(move || Self::refill_loop(&timer))
+            .map_err(|e| format!("Failed to start rate limit timer: {}", e))?;
+
+        Ok(Self { shared })
+    }
+
+    fn refill_loop(shared: &RateLimiterShared) {
+        let mut bucket = shared.bucket.lock();
+
+        while !bucket.closed {
+            let generation = bucket.generation;
+            let window = Duration::from_secs(bucket.window_seconds);
+            let timeout = shared.changed.wait_while_for(
+                &mut bucket,
+                |b| !b.closed && b.generation == generation,
+                window,
+            );
+
+            if timeout.timed_out() {
+                bucket.tokens = bucket.max_requests;
+            }
+        }
+    }
+
+    /// Take a token, or fail with "rate_limit_exceeded" if the bucket is empty
+    pub fn try_acquire(&self) -> Result<(), String> {
+        let mut bucket = self.shared.bucket.lock();
+        if bucket.tokens == 0 {
+            return Err("rate_limit_exceeded".to_string());
+        }
+        bucket.tokens -= 1;
+        Ok(())
+    }
+
+    /// Change the limit; the bucket starts full and a new window begins
+    pub fn set_limit(&self, max_requests: u32, window_seconds: u64) -> Result<(), String> {
+        validate_rate_limit(max_requests, window_seconds)?;
+
+        let mut bucket = self.shared.bucket.lock();
+        bucket.max_requests = max_requests;
+        bucket.window_seconds = window_seconds;
+        bucket.tokens = max_requests;
+        bucket.generation += 1;
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+}
+
+impl Drop for RateLimiter {
+    fn drop(&mut self) {
+        let mut bucket = self.shared.bucket.lock();
+        bucket.closed = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+fn validate_rate_limit(max_requests: u32, window_seconds: u64) -> Result<(), String> {
+    if max_requests == 0 {
+        return Err("Rate limit must allow at least one request".to_string());
+    }
+    if window_seconds == 0 {
+        return Err("Rate limit window must be at least one second".to_string());
+    }
+    Ok(())
+}
+
+/// Message workers used when none is configured
+const DEFAULT_MESSAGE_WORKERS: usize = 4;
+
+/// A message waiting for a worker, with a channel to report how it went
+pub struct WorkItem {
+    pub params: SendMessageParams,
+    /// When the message was received (Unix ms)
+    pub received_at: i64,
+    pub completion_tx: oneshot::Sender<Result<(), String>>,
+}
+
+/// A one-off question for the agent, answered in a throwaway session
+pub struct AdHocPrompt {
+    pub cwd: PathBuf,
+    pub prompt: String,
+    pub result_tx: oneshot::Sender<Result<String, String>>,
+}
+
+/// Work queued for the message workers
+pub enum WorkerJob {
+    Message(WorkItem),
+    AdHocPrompt(AdHocPrompt),
+}
+
+/// Shared state a message worker needs to run a prompt
+#[derive(Clone)]
+struct MessageContext {
+    connection: Arc<Mutex<Option<Arc<ClientSideConnection>>>>,
+    sessions: Arc<Mutex<HashMap<String, SessionId>>>,
+    session_mcp_configs: Arc<Mutex<HashMap<String, McpConfig>>>,
+    app_handle: Arc<Mutex<Option<AppHandle>>>,
+    client: Arc<ThinkingSpaceClient>,
+    notify_on_completion: Arc<AtomicBool>,
+    ambient_context_enabled: Arc<AtomicBool>,
+    auto_summarise_enabled: Arc<AtomicBool>,
+    // Last known space name per working directory
+    space_names: Arc<Mutex<HashMap<String, String>>>,
+    // Model each space's most recent prompt asked for
+    last_used_models: Arc<Mutex<HashMap<String, String>>>,
+    // Requests being processed, driving the tray's busy icon
+    activity: Arc<ActivityCounter>,
+}
+
+impl MessageContext {
+    fn signal_activity(&self, state: Option<TrayIconState>) {
+        // Clone the handle so the lock isn't held while the tray updates
+        let handle = self.app_handle.lock().clone();
+        if let (Some(state), Some(app)) = (state, handle) {
+            crate::tray::set_tray_state(&app, state);
+        }
+    }
+}
+
 pub struct AcpManager {
     process: Arc<Mutex<Option<tokio::process::Child>>>,
+    adapter_config: Arc<Mutex<AcpConfig>>,
     connection: Arc<Mutex<Option<Arc<ClientSideConnection>>>>,
     client: Arc<ThinkingSpaceClient>,
-    permission_response_tx: mpsc::UnboundedSender<FrontendPermissionResponse>,
     runtime: tokio::runtime::Runtime,
     // Map of working_directory -> SessionId to support multiple spaces
     sessions: Arc<Mutex<HashMap<String, SessionId>>>,
+    // MCP config each cached session was started with, by working directory
+    session_mcp_configs: Arc<Mutex<HashMap<String, McpConfig>>>,
+    // Map of session_id -> current agent mode (shared with the client)
+    session_modes: Arc<Mutex<HashMap<String, String>>>,
     app_handle: Arc<Mutex<Option<AppHandle>>>,
     shutdown_tx: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    // Whether to show a desktop notification when a response completes in the background
+    notify_on_completion: Arc<AtomicBool>,
+    // Whether prompts get the space's recent git commits appended
+    ambient_context_enabled: Arc<AtomicBool>,
+    // Whether a conversation is summarised when a prompt hits the context limit
+    auto_summarise_enabled: Arc<AtomicBool>,
+    // Last known space name per working directory, added to request events
+    space_names: Arc<Mutex<HashMap<String, String>>>,
+    // Model each space's most recent prompt asked for (working directory -> model)
+    last_used_models: Arc<Mutex<HashMap<String, String>>>,
+    // Most recent adapter stderr lines, oldest first
+    adapter_logs: Arc<Mutex<VecDeque<String>>>,
+    // Long-running workers that process queued messages, one queue per space
+    message_workers: WorkerPool<WorkerJob>,
+    // Caps how fast the frontend can send messages
+    rate_limiter: RateLimiter,
+    // Requests in flight (shared with the message workers)
+    activity: Arc<ActivityCounter>,
+    // Protocol version reported by the running adapter
+    adapter_version: Arc<Mutex<Option<String>>>,
+    // The running adapter's answer to the initialize handshake
+    initialize_response: Arc<Mutex<Option<InitializeResponse>>>,
+    // Last npm registry check for a newer adapter, reused for UPDATE_CHECK_TTL
+    last_update_check: Arc<Mutex<Option<(Instant, UpdateCheckResult)>>>,
+    // Spaces whose files are watched for outside edits
+    file_watchers: FileWatcherManager,
 }
 
 impl AcpManager {
     pub fn new() -> Self {
-        let (client, permission_response_tx) = ThinkingSpaceClient::new();
+        Self::with_settings(
+            DEFAULT_MESSAGE_WORKERS,
+            crate::settings::load_settings().ok(),
+        )
+    }
+
+    /// A manager configured from `saved_settings` rather than the settings file
+    fn with_settings(workers: usize, saved_settings: Option<crate::settings::Settings>) -> Self {
+        let client = ThinkingSpaceClient::new();
+
+        // Apply the saved terminal policy (if any) before the agent can run commands
+        if let Some(policy) = saved_settings
+            .as_ref()
+            .and_then(|s| s.terminal_policy.clone())
+        {
+            client.terminal_manager().set_policy(policy);
+        }
+
+        let notify_on_completion = saved_settings
+            .as_ref()
+            .map(|s| s.notify_on_completion)
+            .unwrap_or(true);
+
+        let ambient_context_enabled = saved_settings
+            .as_ref()
+            .is_some_and(|s| s.ambient_context_enabled);
+
+        let auto_summarise_enabled = saved_settings
+            .as_ref()
+            .map(|s| s.auto_summarise_enabled)
+            .unwrap_or(true);
+
+        let adapter_config = saved_settings
+            .and_then(|s| s.acp_config)
+            .unwrap_or_default();
 
         // Create multi-threaded runtime for spawning tasks
         let runtime = tokio::runtime::Builder::new_multi_thread()
@@ -39,15 +676,76 @@ impl AcpManager {
             .build()
             .expect("Failed to create tokio runtime");
 
-        Self {
-            process: Arc::new(Mutex::new(None)),
+        let session_modes = client.session_modes();
+
+        let context = MessageContext {
             connection: Arc::new(Mutex::new(None)),
-            client: Arc::new(client),
-            permission_response_tx,
-            runtime,
             sessions: Arc::new(Mutex::new(HashMap::new())),
+            session_mcp_configs: Arc::new(Mutex::new(HashMap::new())),
             app_handle: Arc::new(Mutex::new(None)),
+            client: Arc::new(client),
+            notify_on_completion: Arc::new(AtomicBool::new(notify_on_completion)),
+            ambient_context_enabled: Arc::new(AtomicBool::new(ambient_context_enabled)),
+            auto_summarise_enabled: Arc::new(AtomicBool::new(auto_summarise_enabled)),
+            space_names: Arc::new(Mutex::new(HashMap::new())),
+            last_used_models: Arc::new(Mutex::new(HashMap::new())),
+            activity: Arc::new(ActivityCounter::default()),
+        };
+
+        let worker_context = context.clone();
+        let message_workers =
+            WorkerPool::new("acp-message-worker", workers, move |job: WorkerJob| {
+                let context = worker_context.clone();
+                async move {
+                    match job {
+                        WorkerJob::Message(item) => {
+                            let request_id = item.params.request_id;
+                            context.signal_activity(context.activity.begin());
+                            let result =
+                                process_message(context.clone(), item.params, item.received_at)
+                                    .await;
+                            context.signal_activity(context.activity.end());
+                            if let Err(ref e) = result {
+                                eprintln!("[ACP V2] Request {} failed: {}", request_id, e);
+                            }
+                            let _ = item.completion_tx.send(result);
+                        }
+                        WorkerJob::AdHocPrompt(job) => {
+                            let result = run_ad_hoc_prompt(&context, job.cwd, job.prompt).await;
+                            let _ = job.result_tx.send(result);
+                        }
+                    }
+                }
+            });
+
+        Self {
+            process: Arc::new(Mutex::new(None)),
+            adapter_config: Arc::new(Mutex::new(adapter_config)),
+            connection: context.connection,
+            client: context.client,
+            runtime,
+            sessions: context.sessions,
+            session_mcp_configs: context.session_mcp_configs,
+            session_modes,
+            app_handle: context.app_handle,
+            notify_on_completion: context.notify_on_completion,
+            ambient_context_enabled: context.ambient_context_enabled,
+            auto_summarise_enabled: context.auto_summarise_enabled,
+            space_names: context.space_names,
+            last_used_models: context.last_used_models,
+            activity: context.activity,
+            adapter_version: Arc::new(Mutex::new(None)),
+            initialize_response: Arc::new(Mutex::new(None)),
+            last_update_check: Arc::new(Mutex::new(None)),
+            file_watchers: FileWatcherManager::default(),
             shutdown_tx: Arc::new(Mutex::new(None)),
+            adapter_logs: Arc::new(Mutex::new(VecDeque::new())),
+            message_workers,
+            rate_limiter: RateLimiter::new(
+                DEFAULT_RATE_LIMIT_MAX_REQUESTS,
+                DEFAULT_RATE_LIMIT_WINDOW_SECS,
+            )
+            .expect("Failed to create rate limiter"),
         }
     }
 
@@ -56,6 +754,12 @@ impl AcpManager {
         *self.app_handle.lock() = Some(handle);
     }
 
+    /// Persist settings changed from an agent control, announcing the change
+    fn save_settings(&self, settings: crate::settings::Settings) -> Result<(), String> {
+        let handle = self.app_handle.lock().clone();
+        crate::settings::save_settings_from(handle.as_ref(), settings)
+    }
+
     pub fn start(&self, api_key: Option<String>) -> Result<(), String> {
         // Check if already running (scope the lock)
         {
@@ -65,7 +769,12 @@ impl AcpManager {
             }
         } // Lock is dropped here
 
-        println!("[ACP V2] Starting claude-code-acp adapter...");
+        let adapter_config = self.adapter_config.lock().clone();
+        println!(
+            "[ACP V2] Starting adapter: {} {}",
+            adapter_config.adapter_command,
+            adapter_config.adapter_args.join(" ")
+        );
 
         // Get API key - if not provided, adapter will use Claude Code's OAuth credentials
         let api_key_value = api_key.or_else(|| std::env::var("ANTHROPIC_API_KEY").ok());
@@ -75,6 +784,13 @@ impl AcpManager {
         let process_arc = self.process.clone();
         let shutdown_tx_arc = self.shutdown_tx.clone();
         let app_handle_arc = self.app_handle.clone();
+        let adapter_logs = self.adapter_logs.clone();
+        let sessions_arc = self.sessions.clone();
+        let session_mcp_configs_arc = self.session_mcp_configs.clone();
+        let session_modes_arc = self.session_modes.clone();
+        let adapter_version_arc = self.adapter_version.clone();
+        let initialize_response_arc = self.initialize_response.clone();
+        let last_update_check = self.last_update_check.clone();
 
         // Create shutdown channel
         let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
@@ -95,20 +811,7 @@ impl AcpManager {
             // Use run_until instead of block_on - this keeps LocalSet alive
             let result: Result<(), String> = rt.block_on(local_set.run_until(async move {
                 // Spawn the ACP adapter process
-                let mut cmd = tokio::process::Command::new("npx");
-                cmd.arg("@zed-industries/claude-code-acp")
-                    .stdin(std::process::Stdio::piped())
-                    .stdout(std::process::Stdio::piped())
-                    .stderr(std::process::Stdio::inherit());
-
-                // Only set ANTHROPIC_API_KEY if we have one (for API key auth)
-                // Otherwise, adapter will use Claude Code's OAuth credentials
-                if let Some(key) = api_key_value {
-                    println!("[ACP V2] Using API key authentication");
-                    cmd.env("ANTHROPIC_API_KEY", key);
-                } else {
-                    println!("[ACP V2] Using Claude Code OAuth credentials");
-                }
+                let mut cmd = adapter_config.command(api_key_value);
 
                 let mut child = cmd
                     .spawn()
@@ -116,6 +819,19 @@ impl AcpManager {
 
                 println!("[ACP V2] Adapter process spawned");
 
+                // Forward the adapter's stderr to the frontend's logs pane
+                let stderr = child.stderr.take().unwrap();
+                let log_handle = app_handle_arc.clone();
+                tokio::task::spawn_local(forward_adapter_stderr(
+                    stderr,
+                    adapter_logs,
+                    move |payload| {
+                        if let Some(handle) = log_handle.lock().as_ref() {
+                            let _ = handle.emit("agent-adapter-log", payload);
+                        }
+                    },
+                ));
+
                 // Get stdin/stdout with compat wrappers for futures traits
                 let stdin = child.stdin.take().unwrap().compat_write();
                 let stdout = child.stdout.take().unwrap().compat();
@@ -136,14 +852,7 @@ impl AcpManager {
 
                 // Initialize the connection
                 let init_response = conn
-                    .initialize(InitializeRequest {
-                        protocol_version: VERSION,
-                        client_capabilities: ClientCapabilities {
-                            terminal: true, // Enable terminal support
-                            ..Default::default()
-                        },
-                        meta: None,
-                    })
+                    .initialize(initialize_request())
                     .await
                     .map_err(|e| format!("Initialize failed: {}", e))?;
 
@@ -156,10 +865,127 @@ impl AcpManager {
                     init_response.agent_capabilities.load_session
                 );
 
+                let version = adapter_protocol_version(&init_response)?;
+                let package_version = running_adapter_version(Some(&init_response), &adapter_config);
+                *adapter_version_arc.lock() = Some(version.to_string());
+                *initialize_response_arc.lock() = Some(init_response.clone());
+                match check_adapter_version(version) {
+                    AdapterVersionCheck::Supported => {}
+                    AdapterVersionCheck::Untested => {
+                        println!(
+                            "[ACP V2] Adapter protocol version {} is newer than tested ({})",
+                            version, MAX_TESTED_VERSION
+                        );
+                        if let Some(handle) = app_handle_arc.lock().as_ref() {
+                            let _ = handle.emit(
+                                "agent-version-warning",
+                                serde_json::json!({
+                                    "version": version.to_string(),
+                                    "maxTestedVersion": MAX_TESTED_VERSION.to_string(),
+                                }),
+                            );
+                        }
+                    }
+                    AdapterVersionCheck::Unsupported => {
+                        let _ = child.kill().await;
+                        return Err(format!(
+                            "Adapter protocol version {} is older than the minimum supported version {}",
+                            version, MIN_SUPPORTED_VERSION
+                        ));
+                    }
+                }
+
                 // Store connection and process (wrap connection in Arc)
-                *connection_arc.lock() = Some(Arc::new(conn));
+                let conn = Arc::new(conn);
+                *connection_arc.lock() = Some(conn.clone());
                 *process_arc.lock() = Some(child);
 
+                // Sessions kept across a restart: ask the new adapter to load them again
+                let can_load_sessions = init_response.agent_capabilities.load_session;
+                let outcomes =
+                    resume_cached_sessions(&sessions_arc, |working_directory, session_id| {
+                        let conn = conn.clone();
+                        let session_mcp_configs = session_mcp_configs_arc.clone();
+                        async move {
+                            if !can_load_sessions {
+                                return Err("Agent does not support resuming sessions".to_string());
+                            }
+                            let mcp_config = load_space_mcp_config(&working_directory);
+                            conn.load_session(LoadSessionRequest {
+                                mcp_servers: acp_mcp_servers(&mcp_config),
+                                cwd: PathBuf::from(&working_directory),
+                                session_id,
+                                meta: None,
+                            })
+                            .await
+                            .map_err(|e| e.to_string())?;
+                            session_mcp_configs
+                                .lock()
+                                .insert(working_directory, mcp_config);
+                            Ok(())
+                        }
+                    })
+                    .await;
+
+                for outcome in outcomes {
+                    let (event, payload) = match outcome.error {
+                        None => {
+                            println!("[ACP V2] Resumed session {}", outcome.session_id);
+                            (
+                                "agent-session-resumed",
+                                serde_json::json!({
+                                    "sessionId": outcome.session_id,
+                                    "workingDirectory": outcome.working_directory,
+                                }),
+                            )
+                        }
+                        Some(error) => {
+                            println!("[ACP V2] Lost session {}: {}", outcome.session_id, error);
+                            session_modes_arc.lock().remove(&outcome.session_id);
+                            (
+                                "agent-session-lost",
+                                serde_json::json!({
+                                    "sessionId": outcome.session_id,
+                                    "workingDirectory": outcome.working_directory,
+                                    "error": error,
+                                }),
+                            )
+                        }
+                    };
+                    if let Some(handle) = app_handle_arc.lock().as_ref() {
+                        let _ = handle.emit(event, payload);
+                    }
+                }
+
+                // Cached sessions the database has seen go idle are likely crash leftovers
+                let stale = crate::sessions::get_db_path()
+                    .and_then(|path| crate::sessions::get_connection_at(&path))
+                    .and_then(|db| {
+                        drop_stale_sessions(
+                            &db,
+                            &sessions_arc,
+                            STALE_SESSION_THRESHOLD_MINUTES,
+                            chrono::Utc::now().timestamp(),
+                        )
+                    });
+                match stale {
+                    Ok(dropped) if !dropped.is_empty() => {
+                        println!("[ACP V2] Dropped {} stale session(s)", dropped.len());
+                        let mut session_modes = session_modes_arc.lock();
+                        for session_id in &dropped {
+                            session_modes.remove(session_id);
+                        }
+                        if let Some(handle) = app_handle_arc.lock().as_ref() {
+                            let _ = handle.emit(
+                                "agent-stale-sessions-cleaned",
+                                serde_json::json!({ "count": dropped.len() }),
+                            );
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => println!("[ACP V2] Failed to check for stale sessions: {}", e),
+                }
+
                 println!("[ACP V2] Connection ready, waiting for shutdown signal...");
 
                 // Emit ready event to frontend
@@ -168,6 +994,23 @@ impl AcpManager {
                     println!("[ACP V2] Emitted agent-ready event");
                 }
 
+                // Now that the adapter has said which version it is, look for a newer one
+                tokio::task::spawn_local(announce_adapter_update(
+                    last_update_check,
+                    app_handle_arc.clone(),
+                    package_version,
+                ));
+
+                // Periodically drop exited terminals nobody is reading anymore
+                let terminal_manager = client.terminal_manager();
+                tokio::task::spawn_local(async move {
+                    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
+                    loop {
+                        interval.tick().await;
+                        terminal_manager.prune_exited_terminals();
+                    }
+                });
+
                 // CRITICAL: Wait for shutdown signal to keep LocalSet alive
                 // This is like Zed's interactive loop - keeps the IO task running
                 let _ = shutdown_rx.await;
@@ -186,6 +1029,29 @@ impl AcpManager {
     }
 
     pub fn stop(&self) -> Result<(), String> {
+        self.shutdown_adapter();
+
+        self.sessions.lock().clear();
+        self.session_mcp_configs.lock().clear();
+        self.session_modes.lock().clear();
+        println!("[ACP V2] Stopped");
+        Ok(())
+    }
+
+    /// Replace the adapter process but keep the space -> session map,
+    /// so the new adapter can resume the existing sessions
+    pub fn restart_preserving_sessions(&self, api_key: Option<String>) -> Result<(), String> {
+        println!(
+            "[ACP V2] Restarting adapter, keeping {} cached session(s)",
+            self.sessions.lock().len()
+        );
+
+        self.shutdown_adapter();
+        self.start(api_key)
+    }
+
+    /// Signal the connection thread to exit and kill the adapter process
+    fn shutdown_adapter(&self) {
         // Send shutdown signal to background thread
         if let Some(tx) = self.shutdown_tx.lock().take() {
             let _ = tx.send(());
@@ -194,311 +1060,2671 @@ impl AcpManager {
 
         // Clear connection
         *self.connection.lock() = None;
+        *self.adapter_version.lock() = None;
+        *self.initialize_response.lock() = None;
 
-        // Kill the adapter process
+        // Give the adapter a chance to clean up before it is killed, without
+        // holding up the caller (stop/restart run on the UI thread)
         if let Some(mut child) = self.process.lock().take() {
-            self.runtime.block_on(async move {
-                let _ = child.kill().await;
+            self.runtime.spawn(async move {
+                if let Err(e) = graceful_kill(&mut child, ADAPTER_SHUTDOWN_TIMEOUT).await {
+                    println!("[ACP V2] {}", e);
+                    let _ = child.kill().await;
+                }
                 let _ = child.wait().await;
             });
         }
-
-        self.sessions.lock().clear();
-        println!("[ACP V2] Stopped");
-        Ok(())
     }
 
     pub fn send_permission_response(
         &self,
         response: FrontendPermissionResponse,
     ) -> Result<(), String> {
-        self.permission_response_tx
-            .send(response)
-            .map_err(|e| format!("Failed to send permission response: {}", e))
+        self.client.send_permission_response(response);
+        Ok(())
     }
-}
 
-// Tauri command types
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SendMessageParams {
-    pub request_id: u64,
-    pub message: String,
-    pub working_directory: String,
-    pub system_prompt: Option<String>,
-    pub conversation_history: Option<Vec<ConversationMessage>>,
-}
+    /// Change how the adapter is launched and persist it to settings
+    /// Only allowed while the adapter is stopped
+    pub fn set_adapter_config(&self, config: AcpConfig) -> Result<(), String> {
+        if self.shutdown_tx.lock().is_some() || self.process.lock().is_some() {
+            return Err("Stop the agent before changing the adapter configuration".to_string());
+        }
+        if config.adapter_command.trim().is_empty() {
+            return Err("Adapter command cannot be empty".to_string());
+        }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ConversationMessage {
-    pub role: String,
-    pub content: String,
-}
+        *self.adapter_config.lock() = config.clone();
 
-// Tauri commands
-#[tauri::command]
-pub fn agent_v2_send_message(
-    state: tauri::State<'_, Arc<AcpManager>>,
-    params: SendMessageParams,
-) -> Result<(), String> {
-    println!(
-        "[ACP V2] Sending message (request_id={}): {}",
-        params.request_id,
-        params.message.chars().take(50).collect::<String>()
-    );
+        let mut settings = crate::settings::load_settings()?;
+        settings.acp_config = Some(config);
+        self.save_settings(settings)
+    }
 
-    let conn = {
-        let lock = state.connection.lock();
-        lock.as_ref().ok_or("Not connected")?.clone()
-    };
+    /// Apply a terminal allow/deny policy and persist it to settings
+    pub fn set_terminal_policy(&self, policy: TerminalPolicy) -> Result<(), String> {
+        self.client.terminal_manager().set_policy(policy.clone());
+
+        let mut settings = crate::settings::load_settings()?;
+        settings.terminal_policy = Some(policy);
+        self.save_settings(settings)
+    }
+
+    pub fn set_permission_timeout(&self, seconds: u64) -> Result<(), String> {
+        if seconds == 0 {
+            return Err("Permission timeout must be at least 1 second".to_string());
+        }
+
+        self.client.set_permission_timeout(seconds);
+        Ok(())
+    }
+
+    /// Toggle completion notifications and persist the choice to settings
+    pub fn set_notifications(&self, enabled: bool) -> Result<(), String> {
+        self.notify_on_completion.store(enabled, Ordering::Relaxed);
+
+        let mut settings = crate::settings::load_settings()?;
+        settings.notify_on_completion = enabled;
+        self.save_settings(settings)
+    }
+
+    /// Send agent events to the frontend in 50 ms batches instead of one at a time
+    pub fn set_event_batching(&self, enabled: bool) {
+        let _runtime = self.runtime.enter();
+        self.client.set_event_batching(enabled);
+    }
+
+    /// Toggle appending recent git commits to prompts and persist the choice
+    pub fn set_ambient_context(&self, enabled: bool) -> Result<(), String> {
+        self.ambient_context_enabled
+            .store(enabled, Ordering::Relaxed);
+
+        let mut settings = crate::settings::load_settings()?;
+        settings.ambient_context_enabled = enabled;
+        self.save_settings(settings)
+    }
+
+    /// Toggle summarising conversations that hit the context limit and persist the choice
+    pub fn set_auto_summarise(&self, enabled: bool) -> Result<(), String> {
+        self.auto_summarise_enabled
+            .store(enabled, Ordering::Relaxed);
+
+        let mut settings = crate::settings::load_settings()?;
+        settings.auto_summarise_enabled = enabled;
+        self.save_settings(settings)
+    }
 
-    let sessions_map = state.sessions.clone();
-    let working_directory = params.working_directory.clone();
-    let _system_prompt = params.system_prompt.clone(); // Reserved for future use
-    let message = params.message.clone();
-    let conversation_history = params.conversation_history.clone();
-    let app_handle_arc = state.app_handle.clone();
-    let request_id = params.request_id;
-    let client = state.client.clone();
+    /// Number of messages currently being processed
+    pub fn active_requests(&self) -> usize {
+        self.activity.active()
+    }
+
+    pub fn set_rate_limit(&self, max_requests: u32, window_seconds: u64) -> Result<(), String> {
+        self.rate_limiter.set_limit(max_requests, window_seconds)
+    }
+
+    pub fn set_show_thoughts(&self, enabled: bool) {
+        self.client.set_show_thoughts(enabled);
+    }
+
+    pub fn set_permission_rules(&self, rules: Vec<PermissionRule>) {
+        self.client.set_permission_rules(rules);
+    }
+
+    /// Ask the agent a one-off question in a throwaway session and collect the
+    /// answer, without streaming it to the frontend or touching space sessions
+    /// Queued behind the messages already waiting for the same directory
+    pub fn ad_hoc_prompt(
+        &self,
+        cwd: PathBuf,
+        prompt: String,
+    ) -> Result<oneshot::Receiver<Result<String, String>>, String> {
+        if self.connection.lock().is_none() {
+            return Err("Not connected".to_string());
+        }
+        let (result_tx, result_rx) = oneshot::channel();
+        let key = cwd.to_string_lossy().to_string();
+
+        self.message_workers.enqueue(
+            &key,
+            WorkerJob::AdHocPrompt(AdHocPrompt {
+                cwd,
+                prompt,
+                result_tx,
+            }),
+        )?;
+
+        Ok(result_rx)
+    }
+
+    /// Re-read a space's MCP config and, if its servers changed, drop the space's
+    /// session so the next message starts one with the new server list
+    pub fn reload_mcp_config(&self, space_id: &str) -> Result<McpReloadResult, String> {
+        let space_dir = crate::spaces::get_space_dir(space_id)?;
+        Ok(self.reload_mcp_config_for(space_id, &space_dir.to_string_lossy()))
+    }
+
+    fn reload_mcp_config_for(&self, space_id: &str, working_directory: &str) -> McpReloadResult {
+        // Spaces without a session pick the new config up when their next session starts
+        let has_session = self.sessions.lock().contains_key(working_directory);
+        let old_config = has_session
+            .then(|| {
+                self.session_mcp_configs
+                    .lock()
+                    .get(working_directory)
+                    .cloned()
+            })
+            .flatten();
+        let Some(old_config) = old_config else {
+            return McpReloadResult::default();
+        };
+
+        let new_config = load_space_mcp_config(working_directory);
+        let diff = diff_mcp_configs(&old_config, &new_config);
+        if diff.is_empty() {
+            return McpReloadResult::default();
+        }
 
-    println!("[ACP V2] About to spawn thread for request {}", request_id);
+        // The next message creates a session and replays the conversation history into it.
+        // A prompt already running keeps its session until it finishes.
+        self.sessions.lock().remove(working_directory);
+        self.session_mcp_configs.lock().remove(working_directory);
 
-    // Spawn in new thread with LocalSet - returns immediately
-    std::thread::spawn(move || {
         println!(
-            "[ACP V2] Thread spawned, creating runtime for request {}",
-            request_id
+            "[ACP V2] Reloaded MCP config for '{}': +{:?} -{:?} ~{:?}, session will be recreated",
+            working_directory, diff.added, diff.removed, diff.changed
         );
 
-        // Create runtime for this thread
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .unwrap();
+        if let Some(handle) = self.app_handle.lock().as_ref() {
+            let _ = handle.emit(
+                "agent-mcp-reloaded",
+                serde_json::json!({
+                    "addedServers": diff.added,
+                    "removedServers": diff.removed,
+                    "spaceId": space_id,
+                }),
+            );
+        }
+
+        McpReloadResult {
+            added_servers: diff.added,
+            removed_servers: diff.removed,
+            changed_servers: diff.changed,
+            session_reset: true,
+        }
+    }
+
+    /// Queue a message for its space's worker
+    /// Messages for the same space run one at a time, in order
+    pub fn enqueue_message(
+        &self,
+        params: SendMessageParams,
+    ) -> Result<oneshot::Receiver<Result<(), String>>, String> {
+        let (completion_tx, completion_rx) = oneshot::channel();
+        let key = params.working_directory.clone();
+
+        self.message_workers.enqueue(
+            &key,
+            WorkerJob::Message(WorkItem {
+                params,
+                received_at: chrono::Utc::now().timestamp_millis(),
+                completion_tx,
+            }),
+        )?;
+
+        Ok(completion_rx)
+    }
+
+    /// Emit "space-file-changed" whenever a file in the space changes on disk
+    pub fn start_space_file_watcher(&self, space_id: &str) -> Result<(), String> {
+        let space_dir = crate::spaces::get_space_dir(space_id)?;
+        let app_handle = self.app_handle.clone();
+
+        self.file_watchers
+            .watch(space_id, &space_dir, move |change| {
+                if let Some(handle) = app_handle.lock().as_ref() {
+                    let _ = handle.emit("space-file-changed", change);
+                }
+            })
+            .map_err(String::from)
+    }
+
+    pub fn stop_space_file_watcher(&self, space_id: &str) -> Result<(), String> {
+        self.file_watchers.unwatch(space_id).map_err(String::from)
+    }
 
+    /// Drop the cached name of the space at `working_directory` (after a rename)
+    pub fn forget_space_name(&self, working_directory: &str) {
+        self.space_names.lock().remove(working_directory);
+    }
+
+    /// Model the space's most recent prompt asked for (None = adapter default)
+    pub fn last_used_model(&self, working_directory: &str) -> Option<String> {
+        self.last_used_models.lock().get(working_directory).cloned()
+    }
+
+    /// Protocol version of the running adapter, once it has initialized
+    pub fn adapter_version(&self) -> Option<String> {
+        self.adapter_version.lock().clone()
+    }
+
+    /// Handshake details of the current connection
+    pub fn protocol_metadata(&self) -> Result<ProtocolMetadata, String> {
+        let response = self.initialize_response.lock();
+        protocol_metadata(response.as_ref().ok_or("Not connected")?)
+    }
+
+    /// Ask the npm registry whether a newer adapter has been released (cached for a day)
+    pub async fn check_adapter_update(&self) -> Result<UpdateCheckResult, String> {
+        let current_version = running_adapter_version(
+            self.initialize_response.lock().as_ref(),
+            &self.adapter_config.lock(),
+        );
+        check_adapter_update_with(
+            &self.last_update_check,
+            ADAPTER_REGISTRY_URL,
+            current_version,
+            Instant::now(),
+        )
+        .await
+    }
+
+    /// The most recent adapter stderr lines, oldest first
+    pub fn adapter_logs(&self, limit: Option<usize>) -> Vec<String> {
+        let logs = self.adapter_logs.lock();
+        let skip = limit.map_or(0, |limit| logs.len().saturating_sub(limit));
+        logs.iter().skip(skip).cloned().collect()
+    }
+
+    pub fn create_terminal_group(&self, label: String) -> Result<String, String> {
+        self.client.terminal_manager().create_group(label)
+    }
+
+    pub fn add_terminal_to_group(&self, group_id: &str, terminal_id: &str) -> Result<(), String> {
+        self.client
+            .terminal_manager()
+            .add_to_group(group_id, terminal_id)
+    }
+
+    pub fn get_group_output(&self, group_id: &str) -> Result<HashMap<String, String>, String> {
+        self.client.terminal_manager().group_output(group_id)
+    }
+
+    pub fn get_terminal_output_range(
+        &self,
+        terminal_id: &str,
+        start_line: usize,
+        end_line: usize,
+    ) -> Result<TerminalOutputRange, String> {
+        self.client
+            .terminal_manager()
+            .get_output_range(terminal_id, start_line, end_line)
+    }
+
+    /// Snapshot all terminals so the frontend can restore its display after a reconnect
+    pub fn list_terminals(&self) -> Vec<TerminalSnapshot> {
+        let mut terminals: Vec<TerminalSnapshot> = self
+            .client
+            .terminal_manager()
+            .snapshot()
+            .into_values()
+            .collect();
+        terminals.sort_by(|a, b| a.id.cmp(&b.id));
+        terminals
+    }
+}
+
+/// The space's effective MCP config (global merged with .mcp.json), or none if it can't be read
+fn load_space_mcp_config(working_directory: &str) -> McpConfig {
+    McpConfig::load_from_space(Path::new(working_directory)).unwrap_or_else(|e| {
         println!(
-            "[ACP V2] Runtime created, creating LocalSet for request {}",
-            request_id
+            "[ACP V2] Failed to load MCP config: {}, using no servers",
+            e
         );
-        let local_set = tokio::task::LocalSet::new();
+        McpConfig {
+            mcp_servers: HashMap::new(),
+        }
+    })
+}
 
+/// MCP servers configured for a space, with environment variables resolved
+fn acp_mcp_servers(mcp_config: &McpConfig) -> Vec<McpServer> {
+    let (mcp_config, unresolved_vars) = mcp_config.with_resolved_env_vars();
+    if !unresolved_vars.is_empty() {
         println!(
-            "[ACP V2] About to block_on LocalSet for request {}",
-            request_id
+            "[ACP V2] MCP config references unset environment variables: {}",
+            unresolved_vars.join(", ")
         );
-        let _ = local_set.block_on(&rt, async move {
-            println!(
-                "[ACP V2] Inside LocalSet async block for request {}",
-                request_id
-            );
-            // Get or create session for this space
-            let cached_session_id = {
-                let lock = sessions_map.lock();
-                lock.get(&working_directory).cloned()
-            };
-
-            // Set the current request ID so the client can include it in events
-            client.set_current_request_id(request_id);
-
-            // Determine if we need to create a new session
-            // We ONLY create a new session if no cached session exists for this space
-            // Having conversation_history doesn't mean we need a new session -
-            // it's sent on every message by the frontend
-            let need_new_session = cached_session_id.is_none();
-
-            let mut session_id = cached_session_id;
-
-            // If we need a new session (first message or restoring conversation), create it
-            if need_new_session {
-                println!("[ACP V2] Creating new session for conversation...");
-
-                // Load MCP configuration from the Space directory
-                let mcp_config = McpConfig::load_from_space(Path::new(&working_directory))
-                    .unwrap_or_else(|e| {
-                        println!("[ACP V2] Failed to load MCP config: {}, using no servers", e);
-                        McpConfig {
-                            mcp_servers: HashMap::new(),
-                        }
-                    });
+    }
 
-                let mcp_servers = mcp_config.to_acp_servers();
-
-                if !mcp_servers.is_empty() {
-                    let server_names: Vec<&str> = mcp_servers.iter().map(|s| match s {
-                        agent_client_protocol_schema::McpServer::Stdio { name, .. } => name.as_str(),
-                        agent_client_protocol_schema::McpServer::Http { name, .. } => name.as_str(),
-                        agent_client_protocol_schema::McpServer::Sse { name, .. } => name.as_str(),
-                    }).collect();
-                    println!("[ACP V2] Loaded {} MCP server(s): {}",
-                        mcp_servers.len(),
-                        server_names.join(", ")
-                    );
-                }
+    mcp_config.to_acp_servers()
+}
 
-                // Create new session
-                let session_response = conn
-                    .new_session(NewSessionRequest {
-                        mcp_servers,
-                        cwd: PathBuf::from(working_directory.clone()),
-                        meta: None,
-                    })
-                    .await
-                    .map_err(|e| format!("Failed to create session: {}", e))?;
+/// MCP servers that differ between two configs, by name (sorted)
+#[derive(Debug, Default, PartialEq)]
+struct McpConfigDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+}
 
-                session_id = Some(session_response.session_id.clone());
+impl McpConfigDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
 
-                // Store session ID for this space
-                sessions_map.lock().insert(
-                    working_directory.clone(),
-                    session_response.session_id.clone()
-                );
+fn diff_mcp_configs(old: &McpConfig, new: &McpConfig) -> McpConfigDiff {
+    let mut diff = McpConfigDiff::default();
 
-                println!(
-                    "[ACP V2] New session created for space '{}': {}",
-                    working_directory,
-                    session_response.session_id.0
-                );
+    for (name, config) in &new.mcp_servers {
+        match old.mcp_servers.get(name) {
+            None => diff.added.push(name.clone()),
+            Some(old_config) if old_config != config => diff.changed.push(name.clone()),
+            Some(_) => {}
+        }
+    }
+    diff.removed = old
+        .mcp_servers
+        .keys()
+        .filter(|name| !new.mcp_servers.contains_key(*name))
+        .cloned()
+        .collect();
+
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changed.sort();
+    diff
+}
 
-                // Emit session created event to frontend
-                if let Some(handle) = app_handle_arc.lock().as_ref() {
-                    let _ = handle.emit(
-                        "agent-session-created",
-                        serde_json::json!({
-                            "sessionId": session_response.session_id.0,
-                        }),
-                    );
-                }
+/// Outcome of reloading a space's MCP config
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct McpReloadResult {
+    pub added_servers: Vec<String>,
+    pub removed_servers: Vec<String>,
+    /// Servers whose settings changed; they restart with the new session
+    pub changed_servers: Vec<String>,
+    /// The space's session was dropped and is recreated on the next message
+    pub session_reset: bool,
+}
 
+/// What happened to a cached session when the adapter restarted
+#[derive(Debug, Clone, PartialEq)]
+struct SessionResumeOutcome {
+    working_directory: String,
+    session_id: String,
+    error: Option<String>,
+}
+
+/// Active sessions idle for longer than this are treated as stale on startup
+const STALE_SESSION_THRESHOLD_MINUTES: u32 = 60;
+
+/// Remove cached sessions the database reports as stale and mark them inactive,
+/// returning their IDs
+fn drop_stale_sessions(
+    db: &rusqlite::Connection,
+    sessions: &Mutex<HashMap<String, SessionId>>,
+    threshold_minutes: u32,
+    now: i64,
+) -> Result<Vec<String>, String> {
+    let stale = crate::sessions::stale_session_ids_internal(db, threshold_minutes, now)?;
+
+    let mut dropped = Vec::new();
+    sessions.lock().retain(|_, session_id| {
+        let is_stale = stale.iter().any(|id| id.as_str() == &*session_id.0);
+        if is_stale {
+            dropped.push(session_id.0.to_string());
+        }
+        !is_stale
+    });
+    dropped.sort();
+
+    crate::sessions::deactivate_sessions_internal(db, &dropped)?;
+    Ok(dropped)
+}
+
+/// Try to resume every cached session, dropping the ones that fail from the map
+async fn resume_cached_sessions<F, Fut>(
+    sessions: &Mutex<HashMap<String, SessionId>>,
+    resume: F,
+) -> Vec<SessionResumeOutcome>
+where
+    F: Fn(String, SessionId) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    let mut cached: Vec<(String, SessionId)> = sessions
+        .lock()
+        .iter()
+        .map(|(dir, id)| (dir.clone(), id.clone()))
+        .collect();
+    cached.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut outcomes = Vec::with_capacity(cached.len());
+    for (working_directory, session_id) in cached {
+        let result = resume(working_directory.clone(), session_id.clone()).await;
+
+        if result.is_err() {
+            // Leave the entry alone if a new session replaced it in the meantime
+            let mut sessions = sessions.lock();
+            if sessions.get(&working_directory) == Some(&session_id) {
+                sessions.remove(&working_directory);
             }
+        }
 
-            // Prepare the current prompt
-            // If we just created a new session and have conversation history,
-            // include the history in this first prompt so the SDK can see
-            // the full conversation for context compaction
-            let prompt_text = if need_new_session && conversation_history.is_some() {
-                let history = conversation_history.as_ref().unwrap();
-                if !history.is_empty() {
-                    println!(
-                        "[ACP V2] Including {} previous messages as context in first prompt",
-                        history.len()
-                    );
-
-                    // Format history as text that the SDK can use for context
-                    let mut history_text = String::from("This session is being continued from a previous conversation. Here is the conversation history:\n\n");
-
-                    for msg in history.iter() {
-                        history_text.push_str(&format!("<previous_{}>\n{}\n</previous_{}>\n\n",
-                            msg.role, msg.content, msg.role));
-                    }
+        outcomes.push(SessionResumeOutcome {
+            working_directory,
+            session_id: session_id.0.to_string(),
+            error: result.err(),
+        });
+    }
 
-                    history_text.push_str("--- End of previous conversation ---\n\nCurrent message:\n");
-                    history_text.push_str(&message);
+    outcomes
+}
 
-                    history_text
-                } else {
-                    message.clone()
-                }
-            } else {
-                message.clone()
-            };
-
-            // Send the prompt
-            println!("[ACP V2] Sending prompt ({} chars)...", prompt_text.len());
-
-            let prompt_result = if let Some(ref sid) = session_id {
-                conn.prompt(PromptRequest {
-                    session_id: sid.clone(),
-                    prompt: vec![ContentBlock::Text(TextContent {
-                        text: prompt_text,
-                        annotations: None,
-                        meta: None,
-                    })],
-                    meta: None,
-                })
-                .await
-            } else {
-                // This should never happen now
-                return Err("[ACP V2] No session available after creation attempt".to_string());
-            };
-
-            // Handle the prompt result
-            match prompt_result {
-                Ok(response) => {
-                    println!(
-                        "[ACP V2] Prompt completed with stop reason: {:?}",
-                        response.stop_reason
-                    );
-
-                    // Check if we hit max tokens
-                    use agent_client_protocol_schema::StopReason;
-                    if matches!(response.stop_reason, StopReason::MaxTokens) {
-                        eprintln!("[ACP V2] WARNING: Hit max tokens limit!");
-                        // Emit special event for max tokens
-                        if let Some(handle) = app_handle_arc.lock().as_ref() {
-                            let _ = handle.emit(
-                                "agent-max-tokens",
-                                serde_json::json!({
-                                    "requestId": request_id,
-                                    "message": "Conversation has reached the maximum context window. Consider starting a fresh conversation.",
-                                }),
-                            );
-                        }
-                    }
+/// `_meta` for new sessions: appends the space's extra system prompt
+/// (claude-code-acp reads `systemPrompt.append`; other adapters ignore unknown meta)
+fn session_meta(settings: &EffectiveSettings) -> Option<serde_json::Value> {
+    settings.extra_system_prompt.as_ref().map(|extra| {
+        serde_json::json!({
+            "systemPrompt": { "append": extra },
+        })
+    })
+}
 
-                    // Emit completion event to frontend
-                    if let Some(handle) = app_handle_arc.lock().as_ref() {
-                        let _ = handle.emit(
-                            "agent-message-complete",
-                            serde_json::json!({
-                                "requestId": request_id,
-                                "stopReason": format!("{:?}", response.stop_reason),
-                            }),
-                        );
-                    }
-                }
-                Err(e) => {
-                    eprintln!("[ACP V2] Prompt failed: {}", e);
+/// `_meta` for prompts: the configured model and token limit, for adapters that support them
+fn prompt_meta(settings: &EffectiveSettings) -> Option<serde_json::Value> {
+    let mut meta = serde_json::Map::new();
 
-                    // Emit error event to frontend
-                    if let Some(handle) = app_handle_arc.lock().as_ref() {
-                        let _ = handle.emit(
-                            "agent-message-error",
-                            serde_json::json!({
-                                "requestId": request_id,
-                                "error": e.to_string(),
-                            }),
-                        );
-                    }
+    if let Some(ref model) = settings.model {
+        meta.insert("model".to_string(), serde_json::json!(model));
+    }
+    if let Some(max_tokens) = settings.max_tokens {
+        meta.insert("maxTokens".to_string(), serde_json::json!(max_tokens));
+    }
+
+    (!meta.is_empty()).then_some(serde_json::Value::Object(meta))
+}
+
+/// Models a single request may switch to
+const ALLOWED_MODELS: &[&str] = &[
+    "claude-opus-4-5",
+    "claude-sonnet-4-5",
+    "claude-haiku-4-5",
+    "claude-3-5-sonnet-20241022",
+];
+
+fn validate_model_override(model: &str) -> Result<(), String> {
+    if ALLOWED_MODELS.contains(&model) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unsupported model '{}'. Valid models: {}",
+            model,
+            ALLOWED_MODELS.join(", ")
+        ))
+    }
+}
+
+/// A per-request model wins over the space and global settings
+fn apply_model_override(
+    settings: &mut EffectiveSettings,
+    model_override: Option<String>,
+) -> Result<(), String> {
+    if let Some(model) = model_override {
+        validate_model_override(&model)?;
+        settings.model = Some(model);
+    }
+    Ok(())
+}
+
+// Tauri command types
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendMessageParams {
+    pub request_id: u64,
+    pub message: String,
+    pub working_directory: String,
+    pub system_prompt: Option<String>,
+    pub conversation_history: Option<Vec<ConversationMessage>>,
+    /// Run this prompt in another directory (inside the home directory)
+    /// instead of the space root, in a session of its own
+    pub working_directory_override: Option<String>,
+    /// Files to put in front of the message, read under the same rules as read_file_content
+    #[serde(default)]
+    pub attached_files: Option<Vec<AttachedFile>>,
+    /// Use this model for this prompt only, instead of the configured one
+    #[serde(default)]
+    pub model_override: Option<String>,
+}
+
+/// How an attached file is given to the agent
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentMode {
+    /// The file's content goes into the prompt
+    Inline,
+    /// Only the path, for the agent to read itself
+    Reference,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachedFile {
+    pub path: String,
+    pub include_as: AttachmentMode,
+}
+
+/// Total size of inlined attachments allowed in one prompt
+const MAX_INLINE_ATTACHMENT_BYTES: usize = 100 * 1024;
+
+fn escape_xml_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Put attached files in front of the message: inline files as
+/// `<file path="..."><![CDATA[...]]></file>`, references as `<file_reference path="..."/>`
+fn with_attached_files(
+    message: &str,
+    files: &[AttachedFile],
+    home_dir: &Path,
+) -> Result<String, String> {
+    if files.is_empty() {
+        return Ok(message.to_string());
+    }
+
+    let mut blocks = Vec::with_capacity(files.len());
+    let mut inline_bytes = 0;
+
+    for file in files {
+        let canonical = crate::spaces::validate_readable_path_in(&file.path, home_dir)
+            .map_err(|e| format!("Cannot attach {}: {}", file.path, e))?;
+        let path = escape_xml_attribute(&canonical.to_string_lossy());
+
+        match file.include_as {
+            AttachmentMode::Reference => {
+                blocks.push(format!("<file_reference path=\"{}\"/>", path))
+            }
+            AttachmentMode::Inline => {
+                // Check the size first so an oversized file is never read into memory
+                let size = std::fs::metadata(&canonical)
+                    .map_err(|e| format!("Failed to read {}: {}", file.path, e))?
+                    .len();
+
+                inline_bytes = inline_bytes.saturating_add(size as usize);
+                if inline_bytes > MAX_INLINE_ATTACHMENT_BYTES {
+                    return Err(format!(
+                        "Attached files exceed the {} KB inline limit; attach some as references instead",
+                        MAX_INLINE_ATTACHMENT_BYTES / 1024
+                    ));
                 }
+
+                let content = std::fs::read_to_string(&canonical)
+                    .map_err(|e| format!("Failed to read {}: {}", file.path, e))?;
+
+                // "]]>" would end the CDATA section early, so split it across two sections
+                let content = content.replace("]]>", "]]]]><![CDATA[>");
+                blocks.push(format!(
+                    "<file path=\"{}\"><![CDATA[{}]]></file>",
+                    path, content
+                ));
             }
+        }
+    }
 
-            Ok::<(), String>(())
-        });
+    Ok(format!("{}\n\n{}", blocks.join("\n"), message))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// Directory a prompt's session runs in; sessions are cached under this path
+/// Without an override this is the space root
+fn session_cwd(
+    working_directory: &str,
+    working_directory_override: Option<&str>,
+    home_dir: &Path,
+) -> Result<PathBuf, String> {
+    let Some(dir) = working_directory_override else {
+        return Ok(PathBuf::from(working_directory));
+    };
+
+    let cwd = crate::spaces::resolve_within(Path::new(dir), home_dir)?;
+    if !cwd.is_dir() {
+        return Err(format!(
+            "Working directory override is not a directory: {}",
+            dir
+        ));
+    }
+    Ok(cwd)
+}
+
+fn new_session_request(
+    cwd: PathBuf,
+    mcp_servers: Vec<McpServer>,
+    settings: &EffectiveSettings,
+) -> NewSessionRequest {
+    NewSessionRequest {
+        mcp_servers,
+        cwd,
+        meta: session_meta(settings),
+    }
+}
+
+/// Recent commits included in ambient git context
+const AMBIENT_CONTEXT_COMMITS: usize = 5;
+
+/// Summaries of the latest commits in `dir`, if it is the root of a git repository
+fn recent_commit_summaries(dir: &Path) -> Option<Vec<String>> {
+    let repo = git2::Repository::open(dir).ok()?;
+    let mut revwalk = repo.revwalk().ok()?;
+    revwalk.push_head().ok()?;
+
+    let summaries = revwalk
+        .filter_map(|oid| repo.find_commit(oid.ok()?).ok())
+        .take(AMBIENT_CONTEXT_COMMITS)
+        .map(|commit| {
+            let id = commit.id().to_string();
+            format!(
+                "{} {}",
+                &id[..7],
+                commit.summary().unwrap_or_default().trim()
+            )
+        })
+        .collect();
+    Some(summaries)
+}
+
+/// When enabled, append the directory's recent commits as a `<git_context>` block
+/// (only if it's a git repository)
+fn with_ambient_context(prompt: String, dir: &Path, enabled: bool) -> String {
+    if !enabled {
+        return prompt;
+    }
+
+    match recent_commit_summaries(dir) {
+        Some(summaries) if !summaries.is_empty() => {
+            let mut text = prompt;
+            text.push_str("\n\n<git_context>\nRecent commits:\n");
+            for summary in summaries {
+                text.push_str(&format!("- {}\n", summary));
+            }
+            text.push_str("</git_context>");
+            text
+        }
+        _ => prompt,
+    }
+}
+
+/// The name of the space at `working_directory`, remembered after the first lookup
+/// Spaces are loaded on the blocking pool, since listing them reads every space's metadata
+async fn resolve_space_name(
+    space_names: &Mutex<HashMap<String, String>>,
+    working_directory: &str,
+    load_spaces: impl FnOnce() -> Result<Vec<crate::spaces::Space>, String> + Send + 'static,
+) -> Option<String> {
+    if let Some(name) = space_names.lock().get(working_directory) {
+        return Some(name.clone());
+    }
+
+    let space = tokio::task::spawn_blocking(load_spaces)
+        .await
+        .ok()?
+        .ok()?
+        .into_iter()
+        .find(|space| Path::new(&space.path) == Path::new(working_directory))?;
+    space_names
+        .lock()
+        .insert(working_directory.to_string(), space.name.clone());
+    Some(space.name)
+}
+
+fn completion_payload(
+    request_id: u64,
+    stop_reason: String,
+    usage: Option<TokenUsage>,
+    metadata: &RequestMetadata,
+) -> serde_json::Value {
+    let mut payload = serde_json::json!({
+        "requestId": request_id,
+        "stopReason": stop_reason,
+        "usage": usage,
     });
+    metadata.apply(&mut payload);
+    payload
+}
 
-    Ok(())
+/// Sent to a session whose prompt stopped at the context limit
+const SUMMARY_PROMPT: &str = "This conversation has reached the context limit. \
+Summarise the conversation so far in 500 words or fewer, keeping the decisions made, \
+open questions and anything needed to carry on. Reply with the summary only.";
+
+/// Prompt `session_id` and return the agent's reply instead of streaming it
+async fn prompt_for_text(
+    conn: &ClientSideConnection,
+    client: &ThinkingSpaceClient,
+    session_id: &SessionId,
+    text: String,
+) -> Result<String, String> {
+    client.capture_session_text(&session_id.0);
+    let prompt_result = conn
+        .prompt(PromptRequest {
+            session_id: session_id.clone(),
+            prompt: vec![ContentBlock::Text(TextContent {
+                text,
+                annotations: None,
+                meta: None,
+            })],
+            meta: None,
+        })
+        .await;
+    let reply = client.take_captured_text(&session_id.0).unwrap_or_default();
+
+    prompt_result.map_err(|e| format!("Prompt failed: {}", e))?;
+    Ok(reply)
 }
 
-#[tauri::command]
-pub fn agent_v2_start(
-    state: tauri::State<'_, Arc<AcpManager>>,
-    api_key: Option<String>,
-) -> Result<(), String> {
-    state.start(api_key)
+/// Run an ad-hoc prompt in a new session, then let the session go
+async fn run_ad_hoc_prompt(
+    ctx: &MessageContext,
+    cwd: PathBuf,
+    prompt: String,
+) -> Result<String, String> {
+    let conn = ctx.connection.lock().clone().ok_or("Not connected")?;
+    let session = conn
+        .new_session(NewSessionRequest {
+            mcp_servers: Vec::new(),
+            cwd,
+            meta: None,
+        })
+        .await
+        .map_err(|e| format!("Failed to create session: {}", e))?;
+
+    let result = prompt_for_text(&conn, &ctx.client, &session.session_id, prompt).await;
+    close_session(&conn, &ctx.client, &session.session_id).await;
+    result
 }
 
-#[tauri::command]
-pub fn agent_v2_stop(state: tauri::State<'_, Arc<AcpManager>>) -> Result<(), String> {
-    state.stop()
+/// Close a session we won't prompt again
+/// ACP has no session/close request yet, so this cancels anything the adapter
+/// still has running for it and drops our own per-session state
+async fn close_session(
+    conn: &ClientSideConnection,
+    client: &ThinkingSpaceClient,
+    session_id: &SessionId,
+) {
+    if let Err(e) = conn
+        .cancel(CancelNotification {
+            session_id: session_id.clone(),
+            meta: None,
+        })
+        .await
+    {
+        eprintln!("[ACP V2] Failed to close session {}: {}", session_id.0, e);
+    }
+    client.forget_session(&session_id.0);
 }
 
-#[tauri::command]
-pub fn agent_v2_send_permission_response(
-    state: tauri::State<'_, Arc<AcpManager>>,
-    response: FrontendPermissionResponse,
-) -> Result<(), String> {
-    state.send_permission_response(response)
+/// Ask the agent for a summary if the prompt stopped at the context limit
+/// Ok(None) when it didn't, or auto-summarising is off
+async fn summarise_if_max_tokens<F, Fut>(
+    stop_reason: &StopReason,
+    enabled: bool,
+    ask: F,
+    original_message_count: usize,
+) -> Result<Option<crate::conversations::Message>, String>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    if !enabled || !matches!(stop_reason, StopReason::MaxTokens) {
+        return Ok(None);
+    }
+
+    let summary = ask(SUMMARY_PROMPT.to_string()).await?;
+    let summary = summary.trim();
+    if summary.is_empty() {
+        return Err("The agent returned an empty summary".to_string());
+    }
+
+    Ok(Some(crate::conversations::Message {
+        id: uuid::Uuid::new_v4().to_string(),
+        role: "summary".to_string(),
+        content: summary.to_string(),
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        metadata: serde_json::json!({
+            "auto_summarised": true,
+            "original_message_count": original_message_count,
+        }),
+    }))
 }
 
-// Note: Session management is now automatic and per-space
-// Sessions are created on-demand and cached in the sessions HashMap
-// No need for manual get/set session ID commands
+/// Run one queued message: find or create the space's session, then prompt the agent
+async fn process_message(
+    ctx: MessageContext,
+    params: SendMessageParams,
+    received_at: i64,
+) -> Result<(), String> {
+    let conn = {
+        let lock = ctx.connection.lock();
+        lock.as_ref().ok_or("Not connected")?.clone()
+    };
+
+    let SendMessageParams {
+        request_id,
+        message,
+        working_directory,
+        system_prompt: _, // Reserved for future use
+        conversation_history,
+        working_directory_override,
+        attached_files: _, // Already folded into the message
+        model_override,
+    } = params;
+    let sessions_map = ctx.sessions;
+    let app_handle_arc = ctx.app_handle;
+    let client = ctx.client;
+    let notify_on_completion = ctx.notify_on_completion;
+    let ambient_context_enabled = ctx.ambient_context_enabled.load(Ordering::Relaxed);
+    let auto_summarise_enabled = ctx.auto_summarise_enabled.load(Ordering::Relaxed);
+
+    // An override gets its own session so the space's session keeps its directory
+    let home_dir = dirs::home_dir().ok_or("Cannot determine home directory")?;
+    let cwd = session_cwd(
+        &working_directory,
+        working_directory_override.as_deref(),
+        &home_dir,
+    )?;
+    let session_key = cwd.to_string_lossy().to_string();
+
+    // Get or create session for this space
+    let cached_session_id = {
+        let lock = sessions_map.lock();
+        lock.get(&session_key).cloned()
+    };
+
+    // Set the current request ID so the client can include it in events
+    client.set_current_request_id(request_id);
+    let request_metadata = RequestMetadata {
+        space_name: resolve_space_name(&ctx.space_names, &working_directory, || {
+            crate::spaces::list_spaces(Some(true)).map_err(String::from)
+        })
+        .await,
+        request_ts: received_at,
+    };
+
+    // Resolve global settings with this space's overrides (model, system prompt)
+    let mut effective_settings = EffectiveSettings::for_space_path(Path::new(&working_directory))
+        .unwrap_or_else(|e| {
+            println!(
+                "[ACP V2] Failed to load effective settings: {}, using defaults",
+                e
+            );
+            EffectiveSettings {
+                model: None,
+                max_tokens: None,
+                extra_system_prompt: None,
+            }
+        });
+
+    apply_model_override(&mut effective_settings, model_override)?;
+    if let Some(ref model) = effective_settings.model {
+        ctx.last_used_models
+            .lock()
+            .insert(working_directory.clone(), model.clone());
+    }
+
+    // Determine if we need to create a new session
+    // We ONLY create a new session if no cached session exists for this space
+    // Having conversation_history doesn't mean we need a new session -
+    // it's sent on every message by the frontend
+    let need_new_session = cached_session_id.is_none();
+
+    let mut session_id = cached_session_id;
+
+    // If we need a new session (first message or restoring conversation), create it
+    if need_new_session {
+        println!("[ACP V2] Creating new session for conversation...");
+
+        // Load MCP configuration from the Space directory
+        let mcp_config = load_space_mcp_config(&working_directory);
+        let mcp_servers = acp_mcp_servers(&mcp_config);
+
+        if !mcp_servers.is_empty() {
+            let server_names: Vec<&str> = mcp_servers
+                .iter()
+                .map(|s| match s {
+                    McpServer::Stdio { name, .. } => name.as_str(),
+                    McpServer::Http { name, .. } => name.as_str(),
+                    McpServer::Sse { name, .. } => name.as_str(),
+                })
+                .collect();
+            println!(
+                "[ACP V2] Loaded {} MCP server(s): {}",
+                mcp_servers.len(),
+                server_names.join(", ")
+            );
+        }
+
+        // Create new session
+        let session_response = conn
+            .new_session(new_session_request(
+                cwd.clone(),
+                mcp_servers,
+                &effective_settings,
+            ))
+            .await
+            .map_err(|e| format!("Failed to create session: {}", e))?;
+
+        session_id = Some(session_response.session_id.clone());
+
+        // Store session ID for this space (or override directory)
+        sessions_map
+            .lock()
+            .insert(session_key.clone(), session_response.session_id.clone());
+        ctx.session_mcp_configs
+            .lock()
+            .insert(session_key.clone(), mcp_config);
+
+        println!(
+            "[ACP V2] New session created for '{}': {}",
+            session_key, session_response.session_id.0
+        );
+
+        // Emit session created event to frontend
+        if let Some(handle) = app_handle_arc.lock().as_ref() {
+            let _ = handle.emit(
+                "agent-session-created",
+                serde_json::json!({
+                    "sessionId": session_response.session_id.0,
+                }),
+            );
+        }
+    }
+
+    if let Some(ref sid) = session_id {
+        client.set_request_metadata(&sid.0, request_metadata.clone());
+    }
+
+    if working_directory_override.is_some() {
+        println!(
+            "[ACP V2] Using working directory override for space '{}': {}",
+            working_directory, session_key
+        );
+        if let Some(handle) = app_handle_arc.lock().as_ref() {
+            let mut payload = serde_json::json!({
+                "requestId": request_id,
+                "spaceDirectory": working_directory,
+                "workingDirectory": session_key,
+                "sessionId": session_id.as_ref().map(|sid| sid.0.to_string()),
+            });
+            request_metadata.apply(&mut payload);
+            let _ = handle.emit("agent-working-directory-changed", payload);
+        }
+    }
+
+    // Prepare the current prompt
+    // If we just created a new session and have conversation history,
+    // include the history in this first prompt so the SDK can see
+    // the full conversation for context compaction
+    // A summarised conversation arrives as its summary message plus whatever
+    // followed it, since the frontend replaces the older messages
+    let prompt_text = if need_new_session && conversation_history.is_some() {
+        let history = conversation_history.as_ref().unwrap();
+        if !history.is_empty() {
+            println!(
+                "[ACP V2] Including {} previous messages as context in first prompt",
+                history.len()
+            );
+
+            // Format history as text that the SDK can use for context
+            let mut history_text = String::from("This session is being continued from a previous conversation. Here is the conversation history:\n\n");
+
+            for msg in history.iter() {
+                history_text.push_str(&format!(
+                    "<previous_{}>\n{}\n</previous_{}>\n\n",
+                    msg.role, msg.content, msg.role
+                ));
+            }
+
+            history_text.push_str("--- End of previous conversation ---\n\nCurrent message:\n");
+            history_text.push_str(&message);
+
+            history_text
+        } else {
+            message.clone()
+        }
+    } else {
+        message.clone()
+    };
+
+    let prompt_text = with_ambient_context(
+        prompt_text,
+        Path::new(&working_directory),
+        ambient_context_enabled,
+    );
+
+    // Send the prompt
+    println!("[ACP V2] Sending prompt ({} chars)...", prompt_text.len());
+
+    let prompt_result = if let Some(ref sid) = session_id {
+        // A new prompt starts a new plan
+        client.clear_plan(&sid.0);
+
+        conn.prompt(PromptRequest {
+            session_id: sid.clone(),
+            prompt: vec![ContentBlock::Text(TextContent {
+                text: prompt_text,
+                annotations: None,
+                meta: None,
+            })],
+            meta: prompt_meta(&effective_settings),
+        })
+        .await
+    } else {
+        // This should never happen now
+        return Err("[ACP V2] No session available after creation attempt".to_string());
+    };
+
+    // Streamed events still waiting in a batch must reach the frontend before completion
+    client.flush_events();
+
+    // Handle the prompt result
+    match prompt_result {
+        Ok(response) => {
+            println!(
+                "[ACP V2] Prompt completed with stop reason: {:?}",
+                response.stop_reason
+            );
+
+            // Check if we hit max tokens
+            if matches!(response.stop_reason, StopReason::MaxTokens) {
+                eprintln!("[ACP V2] WARNING: Hit max tokens limit!");
+                // Emit special event for max tokens
+                if let Some(handle) = app_handle_arc.lock().as_ref() {
+                    let mut payload = serde_json::json!({
+                        "requestId": request_id,
+                        "message": "Conversation has reached the maximum context window. Consider starting a fresh conversation.",
+                    });
+                    request_metadata.apply(&mut payload);
+                    let _ = handle.emit("agent-max-tokens", payload);
+                }
+            }
+
+            if let Some(sid) = session_id.as_ref() {
+                // The history plus this prompt and its reply
+                let original_message_count = conversation_history.as_ref().map_or(0, Vec::len) + 2;
+                let summary = summarise_if_max_tokens(
+                    &response.stop_reason,
+                    auto_summarise_enabled,
+                    |prompt| prompt_for_text(&conn, &client, sid, prompt),
+                    original_message_count,
+                )
+                .await;
+
+                match summary {
+                    Ok(Some(summary)) => {
+                        // The full session is dropped; the frontend swaps its messages
+                        // for the summary, which the next session is started from
+                        sessions_map.lock().remove(&session_key);
+                        ctx.session_mcp_configs.lock().remove(&session_key);
+
+                        if let Some(handle) = app_handle_arc.lock().as_ref() {
+                            let mut payload = serde_json::json!({
+                                "requestId": request_id,
+                                "sessionId": sid.0,
+                                "summary": summary,
+                            });
+                            request_metadata.apply(&mut payload);
+                            let _ = handle.emit("agent-conversation-summarised", payload);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("[ACP V2] Failed to summarise conversation: {}", e),
+                }
+            }
+
+            let usage = TokenUsage::from_prompt_response(&response);
+            if let Some(usage) = usage {
+                println!(
+                    "[ACP V2] Token usage: {} in, {} out",
+                    usage.input_tokens, usage.output_tokens
+                );
+            }
+
+            // Emit completion event to frontend
+            // Clone the handle so the lock isn't held while the notification is shown
+            let handle = app_handle_arc.lock().clone();
+            if let Some(handle) = handle {
+                let _ = handle.emit(
+                    "agent-message-complete",
+                    completion_payload(
+                        request_id,
+                        format!("{:?}", response.stop_reason),
+                        usage,
+                        &request_metadata,
+                    ),
+                );
+
+                let last_chunk = session_id
+                    .as_ref()
+                    .and_then(|sid| client.last_message_chunk(&sid.0));
+                notify_completion(
+                    &TauriNotifier(&handle),
+                    notify_on_completion.load(Ordering::Relaxed),
+                    last_chunk.as_deref(),
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!("[ACP V2] Prompt failed: {}", e);
+
+            // Emit error event to frontend
+            if let Some(handle) = app_handle_arc.lock().as_ref() {
+                let mut payload = serde_json::json!({
+                    "requestId": request_id,
+                    "error": e.to_string(),
+                });
+                request_metadata.apply(&mut payload);
+                let _ = handle.emit("agent-message-error", payload);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Tauri commands
+#[tauri::command]
+pub fn agent_v2_send_message(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    mut params: SendMessageParams,
+) -> Result<(), String> {
+    println!(
+        "[ACP V2] Sending message (request_id={}): {}",
+        params.request_id,
+        params.message.chars().take(50).collect::<String>()
+    );
+
+    if state.connection.lock().is_none() {
+        return Err("Not connected".to_string());
+    }
+
+    // Reject a bad override now rather than failing later in the worker
+    if let Some(ref dir) = params.working_directory_override {
+        let home_dir = dirs::home_dir().ok_or("Cannot determine home directory")?;
+        session_cwd(&params.working_directory, Some(dir), &home_dir)?;
+    }
+
+    if let Some(ref model) = params.model_override {
+        validate_model_override(model)?;
+    }
+
+    if let Some(files) = params.attached_files.take() {
+        let home_dir = dirs::home_dir().ok_or("Cannot determine home directory")?;
+        params.message = with_attached_files(&params.message, &files, &home_dir)?;
+    }
+
+    state.rate_limiter.try_acquire()?;
+
+    // Queued and processed by a message worker - returns immediately
+    state.enqueue_message(params).map(|_| ())
+}
+
+/// Summarise a conversation's first prompt into a short title and store it
+/// A conversation that already has a title keeps it
+#[tauri::command]
+pub async fn generate_conversation_title(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    space_id: String,
+) -> Result<String, String> {
+    if let Some(title) = crate::conversations::conversation_title(&space_id).await? {
+        return Ok(title);
+    }
+
+    let prompt = crate::conversations::conversation_title_prompt(&space_id).await?;
+    let cwd = crate::spaces::get_space_dir(&space_id)?;
+
+    let answer = state
+        .ad_hoc_prompt(cwd, prompt)?
+        .await
+        .map_err(|_| "Title generation was interrupted".to_string())??;
+    let title = crate::conversations::store_generated_title(&space_id, &answer).await?;
+
+    println!("[ACP V2] Generated title for {}: {}", space_id, title);
+    if let Some(handle) = state.app_handle.lock().as_ref() {
+        let _ = handle.emit(
+            "conversation-title-generated",
+            serde_json::json!({
+                "spaceId": space_id,
+                "title": title,
+            }),
+        );
+    }
+
+    Ok(title)
+}
+
+#[tauri::command]
+pub fn reload_mcp_config(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    space_id: String,
+) -> Result<McpReloadResult, String> {
+    state.reload_mcp_config(&space_id)
+}
+
+#[tauri::command]
+pub fn agent_v2_start(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    api_key: Option<String>,
+) -> Result<(), String> {
+    state.start(api_key)
+}
+
+#[tauri::command]
+pub fn agent_v2_stop(state: tauri::State<'_, Arc<AcpManager>>) -> Result<(), String> {
+    state.stop()
+}
+
+#[tauri::command]
+pub fn agent_v2_restart(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    api_key: Option<String>,
+) -> Result<(), String> {
+    state.restart_preserving_sessions(api_key)
+}
+
+#[tauri::command]
+pub fn agent_v2_send_permission_response(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    response: FrontendPermissionResponse,
+) -> Result<(), String> {
+    state.send_permission_response(response)
+}
+
+#[tauri::command]
+pub fn agent_v2_set_permission_timeout(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    seconds: u64,
+) -> Result<(), String> {
+    state.set_permission_timeout(seconds)
+}
+
+#[tauri::command]
+pub fn agent_v2_set_permission_rules(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    rules: Vec<PermissionRule>,
+) -> Result<(), String> {
+    state.set_permission_rules(rules);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn agent_v2_set_show_thoughts(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    enabled: bool,
+) -> Result<(), String> {
+    state.set_show_thoughts(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn agent_v2_set_notifications(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    enabled: bool,
+) -> Result<(), String> {
+    state.set_notifications(enabled)
+}
+
+#[tauri::command]
+pub fn agent_v2_set_event_batching(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    enabled: bool,
+) -> Result<(), String> {
+    state.set_event_batching(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn agent_v2_set_ambient_context(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    enabled: bool,
+) -> Result<(), String> {
+    state.set_ambient_context(enabled)
+}
+
+#[tauri::command]
+pub fn agent_v2_set_auto_summarise(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    enabled: bool,
+) -> Result<(), String> {
+    state.set_auto_summarise(enabled)
+}
+
+/// Limit agent_v2_send_message to `max_requests` calls per `window_seconds`
+#[tauri::command]
+pub fn agent_v2_set_rate_limit(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    max_requests: u32,
+    window_seconds: u64,
+) -> Result<(), String> {
+    state.set_rate_limit(max_requests, window_seconds)
+}
+
+#[tauri::command]
+pub fn agent_v2_get_available_commands(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    session_id: String,
+) -> Result<Vec<AvailableCommand>, String> {
+    Ok(state.client.available_commands(&session_id))
+}
+
+/// Tokens the session has used so far, as last sent in "agent-context-usage"
+#[tauri::command]
+pub fn get_context_usage(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    session_id: String,
+) -> Result<Option<ContextUsage>, String> {
+    Ok(state.client.context_usage(&session_id))
+}
+
+#[tauri::command]
+pub fn agent_v2_set_adapter_config(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    config: AcpConfig,
+) -> Result<(), String> {
+    state.set_adapter_config(config)
+}
+
+#[tauri::command]
+pub fn agent_v2_get_adapter_logs(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    limit: Option<usize>,
+) -> Result<Vec<String>, String> {
+    Ok(state.adapter_logs(limit))
+}
+
+/// Watch a space's directory for changes made outside the app
+#[tauri::command]
+pub fn start_space_file_watcher(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    space_id: String,
+) -> Result<(), String> {
+    state.start_space_file_watcher(&space_id)
+}
+
+#[tauri::command]
+pub fn stop_space_file_watcher(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    space_id: String,
+) -> Result<(), String> {
+    state.stop_space_file_watcher(&space_id)
+}
+
+#[tauri::command]
+pub fn agent_v2_get_adapter_version(
+    state: tauri::State<'_, Arc<AcpManager>>,
+) -> Result<Option<String>, String> {
+    Ok(state.adapter_version())
+}
+
+#[tauri::command]
+pub fn agent_v2_get_protocol_metadata(
+    state: tauri::State<'_, Arc<AcpManager>>,
+) -> Result<ProtocolMetadata, String> {
+    state.protocol_metadata()
+}
+
+/// Model the space's most recent prompt asked for (None = adapter default)
+#[tauri::command]
+pub fn get_last_used_model(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    working_directory: String,
+) -> Result<Option<String>, String> {
+    Ok(state.last_used_model(&working_directory))
+}
+
+#[tauri::command]
+pub async fn agent_v2_check_adapter_update(
+    state: tauri::State<'_, Arc<AcpManager>>,
+) -> Result<UpdateCheckResult, String> {
+    state.check_adapter_update().await
+}
+
+#[tauri::command]
+pub fn agent_v2_set_terminal_policy(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    policy: TerminalPolicy,
+) -> Result<(), String> {
+    state.set_terminal_policy(policy)
+}
+
+#[tauri::command]
+pub fn agent_v2_list_terminals(
+    state: tauri::State<'_, Arc<AcpManager>>,
+) -> Result<Vec<TerminalSnapshot>, String> {
+    Ok(state.list_terminals())
+}
+
+/// Start a named group of terminals, returning the group ID
+#[tauri::command]
+pub fn create_terminal_group(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    label: String,
+) -> Result<String, String> {
+    state.create_terminal_group(label)
+}
+
+#[tauri::command]
+pub fn add_terminal_to_group(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    group_id: String,
+    terminal_id: String,
+) -> Result<(), String> {
+    state.add_terminal_to_group(&group_id, &terminal_id)
+}
+
+/// Output of every terminal in a group, keyed by terminal ID
+#[tauri::command]
+pub fn get_group_output(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    group_id: String,
+) -> Result<HashMap<String, String>, String> {
+    state.get_group_output(&group_id)
+}
+
+/// A page of a terminal's retained output lines, for scrolling back through long runs
+#[tauri::command]
+pub fn get_terminal_output_range(
+    state: tauri::State<'_, Arc<AcpManager>>,
+    terminal_id: String,
+    start_line: usize,
+    end_line: usize,
+) -> Result<TerminalOutputRange, String> {
+    state.get_terminal_output_range(&terminal_id, start_line, end_line)
+}
+
+// Note: Session management is now automatic and per-space
+// Sessions are created on-demand and cached in the sessions HashMap
+// No need for manual get/set session ID commands
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp_config::McpServerConfig;
+
+    #[test]
+    fn test_default_adapter_config() {
+        let cmd = AcpConfig::default().command(None);
+        let std_cmd = cmd.as_std();
+
+        assert_eq!(std_cmd.get_program(), "npx");
+        let args: Vec<_> = std_cmd.get_args().collect();
+        assert_eq!(args, vec!["@zed-industries/claude-code-acp"]);
+    }
+
+    #[test]
+    fn test_configured_adapter_command_and_args() {
+        let config = AcpConfig {
+            adapter_command: "/usr/local/bin/claude-code-acp".to_string(),
+            adapter_args: vec!["--verbose".to_string(), "--port=0".to_string()],
+        };
+
+        let cmd = config.command(Some("sk-test".to_string()));
+        let std_cmd = cmd.as_std();
+
+        assert_eq!(std_cmd.get_program(), "/usr/local/bin/claude-code-acp");
+        let args: Vec<_> = std_cmd.get_args().collect();
+        assert_eq!(args, vec!["--verbose", "--port=0"]);
+        assert!(std_cmd
+            .get_envs()
+            .any(|(key, value)| key == "ANTHROPIC_API_KEY"
+                && value == Some(std::ffi::OsStr::new("sk-test"))));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawned_process_receives_configured_args() {
+        let config = AcpConfig {
+            adapter_command: "echo".to_string(),
+            adapter_args: vec!["hello".to_string(), "adapter".to_string()],
+        };
+
+        let output = config.command(None).output().await.unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "hello adapter\n");
+    }
+
+    #[test]
+    fn test_adapter_config_rejected_while_running() {
+        let manager = AcpManager::with_settings(DEFAULT_MESSAGE_WORKERS, None);
+        let (tx, _rx) = oneshot::channel();
+        *manager.shutdown_tx.lock() = Some(tx);
+
+        let result = manager.set_adapter_config(AcpConfig::default());
+
+        assert!(result.unwrap_err().contains("Stop the agent"));
+    }
+
+    #[tokio::test]
+    async fn test_adapter_stderr_is_emitted_and_buffered() {
+        let logs = Arc::new(Mutex::new(VecDeque::new()));
+        let emitted = Arc::new(Mutex::new(Vec::new()));
+        let recorder = emitted.clone();
+
+        forward_adapter_stderr(
+            &b"starting adapter\nauth failed\n"[..],
+            logs.clone(),
+            move |payload| recorder.lock().push(payload),
+        )
+        .await;
+
+        assert_eq!(
+            *emitted.lock(),
+            vec![
+                serde_json::json!({ "level": "error", "message": "starting adapter" }),
+                serde_json::json!({ "level": "error", "message": "auth failed" }),
+            ]
+        );
+        assert_eq!(
+            logs.lock().iter().cloned().collect::<Vec<_>>(),
+            vec!["starting adapter", "auth failed"]
+        );
+    }
+
+    #[test]
+    fn test_adapter_log_buffer_is_capped() {
+        let manager = AcpManager::with_settings(DEFAULT_MESSAGE_WORKERS, None);
+        for i in 0..MAX_ADAPTER_LOG_LINES + 10 {
+            push_adapter_log(&manager.adapter_logs, format!("line {}", i));
+        }
+
+        let all = manager.adapter_logs(None);
+        assert_eq!(all.len(), MAX_ADAPTER_LOG_LINES);
+        assert_eq!(all[0], "line 10");
+
+        let recent = manager.adapter_logs(Some(2));
+        assert_eq!(
+            recent,
+            vec![
+                format!("line {}", MAX_ADAPTER_LOG_LINES + 8),
+                format!("line {}", MAX_ADAPTER_LOG_LINES + 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_queued_message_reports_completion() {
+        let manager = AcpManager::with_settings(1, None);
+
+        let completion = manager
+            .enqueue_message(SendMessageParams {
+                request_id: 1,
+                message: "Hello".to_string(),
+                working_directory: "/tmp/space".to_string(),
+                system_prompt: None,
+                conversation_history: None,
+                working_directory_override: None,
+                attached_files: None,
+                model_override: None,
+            })
+            .unwrap();
+
+        // No adapter is running, so the worker reports the failure back
+        assert_eq!(
+            completion.blocking_recv().unwrap(),
+            Err("Not connected".to_string())
+        );
+
+        // The request counted as active while it ran, and failing still releases it
+        while manager.active_requests() != 0 {
+            std::thread::yield_now();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_partial_resume_drops_only_failed_sessions() {
+        let sessions = Mutex::new(HashMap::from([
+            ("/spaces/a".to_string(), SessionId("session-a".into())),
+            ("/spaces/b".to_string(), SessionId("session-b".into())),
+            ("/spaces/c".to_string(), SessionId("session-c".into())),
+        ]));
+
+        let outcomes = resume_cached_sessions(&sessions, |_, session_id| async move {
+            if &*session_id.0 == "session-b" {
+                Err("Session not found".to_string())
+            } else {
+                Ok(())
+            }
+        })
+        .await;
+
+        let remaining = sessions.lock();
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(
+            remaining.get("/spaces/a"),
+            Some(&SessionId("session-a".into()))
+        );
+        assert_eq!(
+            remaining.get("/spaces/c"),
+            Some(&SessionId("session-c".into()))
+        );
+        assert!(!remaining.contains_key("/spaces/b"));
+
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(
+            outcomes[1],
+            SessionResumeOutcome {
+                working_directory: "/spaces/b".to_string(),
+                session_id: "session-b".to_string(),
+                error: Some("Session not found".to_string()),
+            }
+        );
+        assert!(outcomes[0].error.is_none());
+        assert!(outcomes[2].error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resume_keeps_replacement_session() {
+        let sessions = Mutex::new(HashMap::from([(
+            "/spaces/a".to_string(),
+            SessionId("old".into()),
+        )]));
+
+        // A message created a fresh session while the old one was being resumed
+        resume_cached_sessions(&sessions, |dir, _| {
+            sessions.lock().insert(dir, SessionId("new".into()));
+            async { Err("Session not found".to_string()) }
+        })
+        .await;
+
+        assert_eq!(
+            sessions.lock().get("/spaces/a"),
+            Some(&SessionId("new".into()))
+        );
+    }
+
+    /// Records notifications instead of showing them
+    struct MockNotifier {
+        focused: bool,
+        sent: Mutex<Vec<(String, String)>>,
+    }
+
+    impl MockNotifier {
+        fn new(focused: bool) -> Self {
+            Self {
+                focused,
+                sent: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl CompletionNotifier for MockNotifier {
+        fn is_window_focused(&self) -> bool {
+            self.focused
+        }
+
+        fn notify(&self, title: &str, body: &str) {
+            self.sent.lock().push((title.to_string(), body.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_notification_sent_when_window_unfocused() {
+        let notifier = MockNotifier::new(false);
+        let reply = "x".repeat(100);
+
+        assert!(notify_completion(&notifier, true, Some(&reply)));
+
+        let sent = notifier.sent.lock();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, "Thinking Space");
+        assert_eq!(sent[0].1, "x".repeat(NOTIFICATION_BODY_CHARS));
+    }
+
+    #[test]
+    fn test_no_notification_when_window_focused() {
+        let notifier = MockNotifier::new(true);
+
+        assert!(!notify_completion(&notifier, true, Some("Done")));
+        assert!(notifier.sent.lock().is_empty());
+    }
+
+    #[test]
+    fn test_no_notification_when_disabled() {
+        let notifier = MockNotifier::new(false);
+
+        assert!(!notify_completion(&notifier, false, Some("Done")));
+        assert!(notifier.sent.lock().is_empty());
+    }
+
+    #[test]
+    fn test_notification_without_agent_text() {
+        let notifier = MockNotifier::new(false);
+
+        assert!(notify_completion(&notifier, true, None));
+        assert_eq!(
+            notifier.sent.lock()[0].1,
+            "The agent has finished responding"
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_allows_exactly_max_requests() {
+        let limiter = RateLimiter::new(3, 60).unwrap();
+
+        for _ in 0..3 {
+            assert!(limiter.try_acquire().is_ok());
+        }
+        assert_eq!(limiter.try_acquire().unwrap_err(), "rate_limit_exceeded");
+    }
+
+    #[test]
+    fn test_rate_limiter_refills_after_window() {
+        let limiter = RateLimiter::new(2, 1).unwrap();
+        limiter.try_acquire().unwrap();
+        limiter.try_acquire().unwrap();
+        assert!(limiter.try_acquire().is_err());
+
+        std::thread::sleep(Duration::from_millis(1500));
+
+        assert!(limiter.try_acquire().is_ok());
+        assert!(limiter.try_acquire().is_ok());
+        assert!(limiter.try_acquire().is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter_is_shared_across_threads() {
+        let limiter = Arc::new(RateLimiter::new(50, 60).unwrap());
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let limiter = limiter.clone();
+                std::thread::spawn(move || {
+                    (0..20).filter(|_| limiter.try_acquire().is_ok()).count()
+                })
+            })
+            .collect();
+        let accepted: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+        assert_eq!(accepted, 50);
+    }
+
+    #[test]
+    fn test_set_rate_limit() {
+        let limiter = RateLimiter::new(1, 60).unwrap();
+        limiter.try_acquire().unwrap();
+        assert!(limiter.try_acquire().is_err());
+
+        limiter.set_limit(2, 60).unwrap();
+        assert!(limiter.try_acquire().is_ok());
+        assert!(limiter.try_acquire().is_ok());
+        assert!(limiter.try_acquire().is_err());
+
+        assert!(limiter.set_limit(0, 60).is_err());
+        assert!(limiter.set_limit(5, 0).is_err());
+        assert!(RateLimiter::new(0, 1).is_err());
+    }
+
+    fn default_settings() -> EffectiveSettings {
+        EffectiveSettings {
+            model: None,
+            max_tokens: None,
+            extra_system_prompt: None,
+        }
+    }
+
+    #[test]
+    fn test_working_directory_override_passed_to_new_session() {
+        let home = tempfile::tempdir().unwrap();
+        let project = home.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+
+        let cwd = session_cwd("/spaces/a", Some(project.to_str().unwrap()), home.path()).unwrap();
+        let request = new_session_request(cwd, Vec::new(), &default_settings());
+
+        assert_eq!(request.cwd, project.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_no_override_uses_space_directory() {
+        let home = tempfile::tempdir().unwrap();
+
+        let cwd = session_cwd("/spaces/a", None, home.path()).unwrap();
+        let request = new_session_request(cwd, Vec::new(), &default_settings());
+
+        assert_eq!(request.cwd, PathBuf::from("/spaces/a"));
+    }
+
+    #[test]
+    fn test_working_directory_override_must_be_inside_home() {
+        let home = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(home.path().join("notes.md"), "").unwrap();
+
+        assert!(session_cwd(
+            "/spaces/a",
+            Some(outside.path().to_str().unwrap()),
+            home.path()
+        )
+        .is_err());
+        assert!(session_cwd(
+            "/spaces/a",
+            Some(home.path().join("notes.md").to_str().unwrap()),
+            home.path()
+        )
+        .is_err());
+        assert!(session_cwd(
+            "/spaces/a",
+            Some(home.path().join("missing").to_str().unwrap()),
+            home.path()
+        )
+        .is_err());
+    }
+
+    fn init_response(protocol_version: u16) -> InitializeResponse {
+        serde_json::from_value(serde_json::json!({
+            "protocolVersion": protocol_version,
+            "agentCapabilities": {},
+            "authMethods": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_adapter_version_supported() {
+        let version = adapter_protocol_version(&init_response(MAX_TESTED_VERSION as u16)).unwrap();
+        assert_eq!(version, MAX_TESTED_VERSION);
+        assert_eq!(
+            check_adapter_version(version),
+            AdapterVersionCheck::Supported
+        );
+    }
+
+    #[test]
+    fn test_adapter_version_newer_than_tested() {
+        let version =
+            adapter_protocol_version(&init_response(MAX_TESTED_VERSION as u16 + 1)).unwrap();
+        assert_eq!(
+            check_adapter_version(version),
+            AdapterVersionCheck::Untested
+        );
+    }
+
+    #[test]
+    fn test_adapter_version_below_minimum() {
+        let version =
+            adapter_protocol_version(&init_response(MIN_SUPPORTED_VERSION as u16 - 1)).unwrap();
+        assert_eq!(
+            check_adapter_version(version),
+            AdapterVersionCheck::Unsupported
+        );
+    }
+
+    #[test]
+    fn test_adapter_version_unknown_until_started() {
+        let manager = AcpManager::with_settings(1, None);
+        assert_eq!(manager.adapter_version(), None);
+    }
+
+    #[test]
+    fn test_initialize_request_sends_client_info() {
+        let meta = initialize_request().meta.unwrap();
+        assert_eq!(meta["client"], "thinking-space");
+        assert_eq!(meta["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(meta["platform"], std::env::consts::OS);
+        assert_eq!(
+            serde_json::from_value::<ClientInfo>(meta).unwrap(),
+            client_info()
+        );
+    }
+
+    #[test]
+    fn test_protocol_metadata_across_restart() {
+        let manager = AcpManager::with_settings(1, None);
+        assert_eq!(manager.protocol_metadata().unwrap_err(), "Not connected");
+
+        let mut response = init_response(1);
+        response.agent_capabilities.load_session = true;
+        *manager.initialize_response.lock() = Some(response);
+        let metadata = manager.protocol_metadata().unwrap();
+        assert_eq!(
+            metadata,
+            ProtocolMetadata {
+                protocol_version: 1,
+                load_session: true,
+                client_info: client_info(),
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&metadata.client_info).ok(),
+            initialize_request().meta
+        );
+
+        // Shutting the adapter down forgets the old handshake...
+        manager.shutdown_adapter();
+        assert!(manager.protocol_metadata().is_err());
+
+        // ...and the next one replaces it
+        *manager.initialize_response.lock() = Some(init_response(2));
+        let metadata = manager.protocol_metadata().unwrap();
+        assert_eq!(metadata.protocol_version, 2);
+        assert!(!metadata.load_session);
+        assert_eq!(metadata.client_info, client_info());
+    }
+
+    fn commit_file(repo: &git2::Repository, name: &str, message: &str) {
+        std::fs::write(repo.workdir().unwrap().join(name), message).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parents: Vec<git2::Commit> = repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok())
+            .into_iter()
+            .collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_git_context_appended_for_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        for i in 0..7 {
+            commit_file(&repo, &format!("file{}.txt", i), &format!("Commit {}", i));
+        }
+
+        let prompt = with_ambient_context("Hello".to_string(), dir.path(), true);
+
+        assert!(prompt.starts_with("Hello\n\n<git_context>\n"));
+        assert!(prompt.ends_with("</git_context>"));
+        // Only the latest five, newest first
+        assert!(prompt.contains("Commit 6"));
+        assert!(prompt.contains("Commit 2"));
+        assert!(!prompt.contains("Commit 1"));
+        assert!(prompt.find("Commit 6").unwrap() < prompt.find("Commit 2").unwrap());
+
+        // Disabled: the prompt is left alone
+        assert_eq!(
+            with_ambient_context("Hello".to_string(), dir.path(), false),
+            "Hello"
+        );
+    }
+
+    #[test]
+    fn test_git_context_absent_outside_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            with_ambient_context("Hello".to_string(), dir.path(), true),
+            "Hello"
+        );
+
+        // A repository without commits has nothing to add
+        git2::Repository::init(dir.path()).unwrap();
+        assert_eq!(
+            with_ambient_context("Hello".to_string(), dir.path(), true),
+            "Hello"
+        );
+    }
+
+    fn test_space(name: &str, path: &str) -> crate::spaces::Space {
+        crate::spaces::Space {
+            id: name.to_lowercase(),
+            name: name.to_string(),
+            path: path.to_string(),
+            claude_md_path: String::new(),
+            created_at: 0,
+            last_accessed_at: 0,
+            template: None,
+            archived: false,
+            pinned: false,
+            pin_order: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_space_name_resolved_once_and_cached() {
+        let space_names = Mutex::new(HashMap::new());
+        let spaces = vec![
+            test_space("Research", "/spaces/research"),
+            test_space("Notes", "/spaces/notes"),
+        ];
+
+        let loaded = spaces.clone();
+        assert_eq!(
+            resolve_space_name(&space_names, "/spaces/notes", move || Ok(loaded)).await,
+            Some("Notes".to_string())
+        );
+        // Served from the cache without loading spaces again
+        assert_eq!(
+            resolve_space_name(&space_names, "/spaces/notes", || {
+                panic!("spaces should not be reloaded")
+            })
+            .await,
+            Some("Notes".to_string())
+        );
+        assert_eq!(
+            resolve_space_name(&space_names, "/elsewhere", move || Ok(spaces)).await,
+            None
+        );
+    }
+
+    #[test]
+    fn test_request_metadata_in_completion_payload() {
+        let metadata = RequestMetadata {
+            space_name: Some("Research".to_string()),
+            request_ts: 1697500000000,
+        };
+
+        let payload = completion_payload(5, "EndTurn".to_string(), None, &metadata);
+
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "requestId": 5,
+                "stopReason": "EndTurn",
+                "usage": null,
+                "spaceName": "Research",
+                "requestTs": 1697500000000i64,
+            })
+        );
+    }
+
+    #[test]
+    fn test_running_adapter_version_prefers_reported() {
+        let mut response = init_response(1);
+        assert_eq!(reported_adapter_version(&response), None);
+
+        response.meta = Some(serde_json::json!({
+            "agentInfo": { "name": ADAPTER_PACKAGE, "version": "0.6.2" },
+        }));
+        assert_eq!(
+            reported_adapter_version(&response),
+            Some("0.6.2".to_string())
+        );
+
+        let pinned = AcpConfig {
+            adapter_command: "npx".to_string(),
+            adapter_args: vec![format!("{}@0.5.1", ADAPTER_PACKAGE)],
+        };
+        assert_eq!(
+            running_adapter_version(Some(&response), &pinned),
+            Some("0.6.2".to_string())
+        );
+        // Before the adapter has answered, only the pin is known
+        assert_eq!(
+            running_adapter_version(None, &pinned),
+            Some("0.5.1".to_string())
+        );
+        assert_eq!(running_adapter_version(None, &AcpConfig::default()), None);
+    }
+
+    #[test]
+    fn test_pinned_adapter_version() {
+        assert_eq!(pinned_adapter_version(&AcpConfig::default()), None);
+
+        let pinned = AcpConfig {
+            adapter_command: "npx".to_string(),
+            adapter_args: vec!["-y".to_string(), format!("{}@0.5.1", ADAPTER_PACKAGE)],
+        };
+        assert_eq!(pinned_adapter_version(&pinned), Some("0.5.1".to_string()));
+
+        let latest = AcpConfig {
+            adapter_command: "npx".to_string(),
+            adapter_args: vec![format!("{}@latest", ADAPTER_PACKAGE)],
+        };
+        assert_eq!(pinned_adapter_version(&latest), None);
+    }
+
+    #[test]
+    fn test_is_newer_version() {
+        assert!(is_newer_version("0.6.0", "0.5.1"));
+        assert!(is_newer_version("0.10.0", "0.9.3"));
+        assert!(is_newer_version("1.0.0", "0.99.0"));
+        assert!(!is_newer_version("0.5.1", "0.5.1"));
+        assert!(!is_newer_version("0.5.0", "0.5.1"));
+        assert!(!is_newer_version("0.6.0-beta.1", "0.6.0"));
+    }
+
+    #[tokio::test]
+    async fn test_adapter_update_check_cached_for_ttl() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/latest")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"name": "@zed-industries/claude-code-acp", "version": "0.6.0"}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let registry_url = format!("{}/latest", server.url());
+        let cache = Mutex::new(None);
+        let start = Instant::now();
+
+        let result =
+            check_adapter_update_with(&cache, &registry_url, Some("0.5.1".to_string()), start)
+                .await
+                .unwrap();
+        assert_eq!(
+            result,
+            UpdateCheckResult {
+                current_version: Some("0.5.1".to_string()),
+                latest_version: "0.6.0".to_string(),
+                update_available: true,
+            }
+        );
+
+        // Within the TTL the cached result is reused
+        let cached = check_adapter_update_with(
+            &cache,
+            &registry_url,
+            Some("0.5.1".to_string()),
+            start + Duration::from_secs(60 * 60),
+        )
+        .await
+        .unwrap();
+        assert_eq!(cached, result);
+
+        // Once it expires the registry is asked again
+        check_adapter_update_with(
+            &cache,
+            &registry_url,
+            Some("0.5.1".to_string()),
+            start + UPDATE_CHECK_TTL,
+        )
+        .await
+        .unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_adapter_update_check_registry_error() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/latest")
+            .with_status(503)
+            .create_async()
+            .await;
+
+        let cache = Mutex::new(None);
+        let result = check_adapter_update_with(
+            &cache,
+            &format!("{}/latest", server.url()),
+            None,
+            Instant::now(),
+        )
+        .await;
+
+        assert!(result.is_err());
+        // Failures aren't cached
+        assert!(cache.lock().is_none());
+    }
+
+    fn attach(path: &Path, include_as: AttachmentMode) -> AttachedFile {
+        AttachedFile {
+            path: path.to_string_lossy().to_string(),
+            include_as,
+        }
+    }
+
+    #[test]
+    fn test_attached_files_inline_and_reference() {
+        let home = tempfile::tempdir().unwrap();
+        let home_dir = home.path().canonicalize().unwrap();
+        let notes = home_dir.join("notes.md");
+        let data = home_dir.join("data.csv");
+        std::fs::write(&notes, "Buy <milk> & eggs ]]> done").unwrap();
+        std::fs::write(&data, "a,b\n1,2").unwrap();
+
+        let message = with_attached_files(
+            "Summarise these",
+            &[
+                attach(&notes, AttachmentMode::Inline),
+                attach(&data, AttachmentMode::Reference),
+            ],
+            &home_dir,
+        )
+        .unwrap();
+
+        assert_eq!(
+            message,
+            format!(
+                "<file path=\"{}\"><![CDATA[Buy <milk> & eggs ]]]]><![CDATA[> done]]></file>\n\
+                 <file_reference path=\"{}\"/>\n\n\
+                 Summarise these",
+                notes.display(),
+                data.display()
+            )
+        );
+
+        // Nothing attached leaves the message alone
+        assert_eq!(with_attached_files("Hi", &[], &home_dir).unwrap(), "Hi");
+    }
+
+    #[test]
+    fn test_attached_files_inline_size_cap() {
+        let home = tempfile::tempdir().unwrap();
+        let home_dir = home.path().canonicalize().unwrap();
+        let half = "x".repeat(MAX_INLINE_ATTACHMENT_BYTES / 2);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(home_dir.join(name), &half).unwrap();
+        }
+
+        let two = [
+            attach(&home_dir.join("a.txt"), AttachmentMode::Inline),
+            attach(&home_dir.join("b.txt"), AttachmentMode::Inline),
+        ];
+        assert!(with_attached_files("Hi", &two, &home_dir).is_ok());
+
+        let three = [
+            attach(&home_dir.join("a.txt"), AttachmentMode::Inline),
+            attach(&home_dir.join("b.txt"), AttachmentMode::Inline),
+            attach(&home_dir.join("c.txt"), AttachmentMode::Inline),
+        ];
+        assert!(with_attached_files("Hi", &three, &home_dir)
+            .unwrap_err()
+            .contains("inline limit"));
+
+        // References don't count towards the cap
+        let mixed = [
+            attach(&home_dir.join("a.txt"), AttachmentMode::Inline),
+            attach(&home_dir.join("b.txt"), AttachmentMode::Inline),
+            attach(&home_dir.join("c.txt"), AttachmentMode::Reference),
+        ];
+        assert!(with_attached_files("Hi", &mixed, &home_dir).is_ok());
+    }
+
+    #[test]
+    fn test_attached_files_path_safety() {
+        let root = tempfile::tempdir().unwrap();
+        let root_dir = root.path().canonicalize().unwrap();
+        let home_dir = root_dir.join("home");
+        std::fs::create_dir_all(home_dir.join(".ssh")).unwrap();
+        std::fs::write(home_dir.join(".ssh").join("id_ed25519"), "secret").unwrap();
+        std::fs::write(home_dir.join("server.pem"), "secret").unwrap();
+        std::fs::write(root_dir.join("outside.txt"), "outside").unwrap();
+
+        for path in [
+            home_dir.join(".ssh").join("id_ed25519"),
+            home_dir.join("server.pem"),
+            home_dir.join("..").join("outside.txt"),
+            home_dir.join("missing.txt"),
+        ] {
+            for mode in [AttachmentMode::Inline, AttachmentMode::Reference] {
+                assert!(
+                    with_attached_files("Hi", &[attach(&path, mode)], &home_dir).is_err(),
+                    "{} should be rejected",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_model_override_allowlist() {
+        for model in ALLOWED_MODELS {
+            assert!(validate_model_override(model).is_ok());
+        }
+
+        let error = validate_model_override("gpt-4").unwrap_err();
+        assert!(error.contains("gpt-4"));
+        assert!(ALLOWED_MODELS.iter().all(|model| error.contains(model)));
+    }
+
+    #[test]
+    fn test_model_override_in_prompt_meta() {
+        let mut settings = EffectiveSettings {
+            model: Some("claude-sonnet-4-5".to_string()),
+            max_tokens: Some(1024),
+            extra_system_prompt: None,
+        };
+
+        apply_model_override(&mut settings, None).unwrap();
+        assert_eq!(settings.model.as_deref(), Some("claude-sonnet-4-5"));
+
+        apply_model_override(&mut settings, Some("claude-haiku-4-5".to_string())).unwrap();
+        assert_eq!(
+            prompt_meta(&settings),
+            Some(serde_json::json!({ "model": "claude-haiku-4-5", "maxTokens": 1024 }))
+        );
+
+        // An unknown model leaves the settings alone
+        assert!(apply_model_override(&mut settings, Some("claude-2".to_string())).is_err());
+        assert_eq!(settings.model.as_deref(), Some("claude-haiku-4-5"));
+    }
+
+    #[test]
+    fn test_last_used_model_unknown_space() {
+        let manager = AcpManager::with_settings(1, None);
+        assert_eq!(manager.last_used_model("/tmp/space"), None);
+
+        manager
+            .last_used_models
+            .lock()
+            .insert("/tmp/space".to_string(), "claude-opus-4-5".to_string());
+        assert_eq!(
+            manager.last_used_model("/tmp/space"),
+            Some("claude-opus-4-5".to_string())
+        );
+    }
+
+    fn stdio_server(command: &str) -> McpServerConfig {
+        McpServerConfig::Stdio {
+            command: command.to_string(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            enabled: true,
+        }
+    }
+
+    fn mcp_config(servers: &[(&str, McpServerConfig)]) -> McpConfig {
+        McpConfig {
+            mcp_servers: servers
+                .iter()
+                .map(|(name, config)| (name.to_string(), config.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff_mcp_configs() {
+        let old = mcp_config(&[
+            ("filesystem", stdio_server("mcp-fs")),
+            ("github", stdio_server("mcp-github")),
+            ("search", stdio_server("mcp-search")),
+        ]);
+        let new = mcp_config(&[
+            ("filesystem", stdio_server("mcp-fs")),
+            ("search", stdio_server("mcp-search-v2")),
+            ("memory", stdio_server("mcp-memory")),
+            ("browser", stdio_server("mcp-browser")),
+        ]);
+
+        assert_eq!(
+            diff_mcp_configs(&old, &new),
+            McpConfigDiff {
+                added: vec!["browser".to_string(), "memory".to_string()],
+                removed: vec!["github".to_string()],
+                changed: vec!["search".to_string()],
+            }
+        );
+        assert!(diff_mcp_configs(&old, &old).is_empty());
+        assert!(!diff_mcp_configs(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn test_reload_mcp_config_unchanged_space_keeps_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let working_directory = dir.path().to_string_lossy().to_string();
+        let manager = AcpManager::with_settings(1, None);
+
+        manager
+            .sessions
+            .lock()
+            .insert(working_directory.clone(), SessionId("existing".into()));
+        manager.session_mcp_configs.lock().insert(
+            working_directory.clone(),
+            load_space_mcp_config(&working_directory),
+        );
+
+        let result = manager.reload_mcp_config_for("space", &working_directory);
+        assert_eq!(result, McpReloadResult::default());
+        assert_eq!(
+            manager.sessions.lock().get(&working_directory),
+            Some(&SessionId("existing".into()))
+        );
+    }
+
+    #[test]
+    fn test_reload_mcp_config_without_session_is_noop() {
+        let manager = AcpManager::with_settings(1, None);
+
+        let result = manager.reload_mcp_config_for("space", "/spaces/none");
+        assert_eq!(result, McpReloadResult::default());
+        assert!(manager.sessions.lock().is_empty());
+    }
+
+    #[test]
+    fn test_reload_mcp_config_drops_changed_session_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let working_directory = dir.path().to_string_lossy().to_string();
+        let manager = AcpManager::with_settings(1, None);
+
+        // This space's session was started with a server that is gone now
+        let mut old_config = load_space_mcp_config(&working_directory);
+        old_config
+            .mcp_servers
+            .insert("retired".to_string(), stdio_server("mcp-retired"));
+        let other_config = mcp_config(&[("github", stdio_server("mcp-github"))]);
+        {
+            let mut sessions = manager.sessions.lock();
+            sessions.insert(working_directory.clone(), SessionId("changed".into()));
+            sessions.insert("/spaces/other".to_string(), SessionId("other".into()));
+        }
+        {
+            let mut configs = manager.session_mcp_configs.lock();
+            configs.insert(working_directory.clone(), old_config);
+            configs.insert("/spaces/other".to_string(), other_config.clone());
+        }
+
+        let result = manager.reload_mcp_config_for("space", &working_directory);
+        assert_eq!(result.removed_servers, vec!["retired".to_string()]);
+        assert!(result.session_reset);
+
+        // The next message starts a fresh session and replays the history into it
+        let sessions = manager.sessions.lock();
+        assert_eq!(sessions.get(&working_directory), None);
+        assert_eq!(
+            sessions.get("/spaces/other"),
+            Some(&SessionId("other".into()))
+        );
+        let configs = manager.session_mcp_configs.lock();
+        assert_eq!(configs.get(&working_directory), None);
+        assert_eq!(configs.get("/spaces/other"), Some(&other_config));
+    }
+
+    #[test]
+    fn test_drop_stale_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = crate::sessions::get_connection_at(&dir.path().join("sessions.db")).unwrap();
+        let now = 1_700_000_000;
+        for (session_id, last_active) in [
+            ("stale-cached", now - 7200),
+            ("stale-uncached", now - 7200),
+            ("fresh", now - 60),
+        ] {
+            db.execute(
+                "INSERT INTO sessions (session_id, space_id, created_at, last_active, is_active)
+                 VALUES (?1, 'space', ?2, ?2, 1)",
+                rusqlite::params![session_id, last_active],
+            )
+            .unwrap();
+        }
+
+        let sessions = Mutex::new(HashMap::from([
+            ("/spaces/a".to_string(), SessionId("stale-cached".into())),
+            ("/spaces/b".to_string(), SessionId("fresh".into())),
+        ]));
+
+        let dropped = drop_stale_sessions(&db, &sessions, 60, now).unwrap();
+
+        assert_eq!(dropped, vec!["stale-cached".to_string()]);
+        assert_eq!(
+            *sessions.lock(),
+            HashMap::from([("/spaces/b".to_string(), SessionId("fresh".into()))])
+        );
+        let is_active = |session_id: &str| -> bool {
+            db.query_row(
+                "SELECT is_active FROM sessions WHERE session_id = ?1",
+                rusqlite::params![session_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert!(!is_active("stale-cached"));
+        // Only sessions this app instance still holds are cleaned up
+        assert!(is_active("stale-uncached"));
+        assert!(is_active("fresh"));
+
+        // Running again finds nothing left to drop
+        assert!(drop_stale_sessions(&db, &sessions, 60, now)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[cfg(unix)]
+    fn spawn_shell(script: &str) -> tokio::process::Child {
+        tokio::process::Command::new("sh")
+            .args(["-c", script])
+            .spawn()
+            .unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_graceful_kill_sends_sigterm() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            // Exits cleanly with 143 only if it receives SIGTERM
+            let mut child = spawn_shell("trap 'exit 143' TERM; while true; do sleep 0.05; done");
+            tokio::time::sleep(Duration::from_millis(200)).await;
+
+            let started = Instant::now();
+            graceful_kill(&mut child, Duration::from_secs(3))
+                .await
+                .unwrap();
+
+            assert!(started.elapsed() < Duration::from_secs(3));
+            let status = child.wait().await.unwrap();
+            assert_eq!(status.code(), Some(143));
+            assert_eq!(status.signal(), None);
+        });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_graceful_kill_escalates_to_sigkill() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut child = spawn_shell("trap '' TERM; while true; do sleep 0.05; done");
+            tokio::time::sleep(Duration::from_millis(200)).await;
+
+            graceful_kill(&mut child, Duration::from_millis(300))
+                .await
+                .unwrap();
+
+            let status = child.wait().await.unwrap();
+            assert_eq!(status.signal(), Some(libc::SIGKILL));
+        });
+    }
+
+    #[test]
+    fn test_graceful_kill_exited_process() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let mut child =
+                tokio::process::Command::new(if cfg!(windows) { "cmd" } else { "true" })
+                    .args(if cfg!(windows) {
+                        &["/C", "exit"][..]
+                    } else {
+                        &[][..]
+                    })
+                    .spawn()
+                    .unwrap();
+            child.wait().await.unwrap();
+
+            assert!(graceful_kill(&mut child, Duration::from_secs(3))
+                .await
+                .is_ok());
+        });
+    }
+
+    fn stop_reason(reason: &str) -> StopReason {
+        let response: agent_client_protocol_schema::PromptResponse =
+            serde_json::from_value(serde_json::json!({ "stopReason": reason })).unwrap();
+        response.stop_reason
+    }
+
+    #[tokio::test]
+    async fn test_max_tokens_is_summarised() {
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let ask = |prompt: String| {
+            let asked = asked.clone();
+            async move {
+                asked.lock().push(prompt);
+                Ok::<_, String>(
+                    "  We planned a vegetable garden and picked tomatoes.\n".to_string(),
+                )
+            }
+        };
+
+        let summary = summarise_if_max_tokens(&stop_reason("max_tokens"), true, ask, 12)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(*asked.lock(), vec![SUMMARY_PROMPT.to_string()]);
+        assert_eq!(summary.role, "summary");
+        assert_eq!(
+            summary.content,
+            "We planned a vegetable garden and picked tomatoes."
+        );
+        assert_eq!(
+            summary.metadata,
+            serde_json::json!({ "auto_summarised": true, "original_message_count": 12 })
+        );
+        // The summary has to pass the checks save_conversation applies
+        assert!(crate::conversations::validate_metadata(&summary.metadata).is_ok());
+    }
+
+    async fn never_asked(_: String) -> Result<String, String> {
+        panic!("the agent shouldn't be asked")
+    }
+
+    #[tokio::test]
+    async fn test_summary_skipped_unless_max_tokens_and_enabled() {
+        assert!(matches!(
+            summarise_if_max_tokens(&stop_reason("end_turn"), true, never_asked, 4).await,
+            Ok(None)
+        ));
+        assert!(matches!(
+            summarise_if_max_tokens(&stop_reason("max_tokens"), false, never_asked, 4).await,
+            Ok(None)
+        ));
+
+        // An empty or failed reply leaves the conversation as it was
+        let ask = |_: String| async { Ok::<_, String>("   ".to_string()) };
+        assert!(
+            summarise_if_max_tokens(&stop_reason("max_tokens"), true, ask, 4)
+                .await
+                .is_err()
+        );
+        let ask = |_: String| async { Err::<String, _>("Prompt failed: too long".to_string()) };
+        assert!(
+            summarise_if_max_tokens(&stop_reason("max_tokens"), true, ask, 4)
+                .await
+                .is_err()
+        );
+    }
+}
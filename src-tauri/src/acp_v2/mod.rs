@@ -3,6 +3,7 @@
 
 mod client;
 pub mod manager;
+mod workers;
 
 pub use client::ThinkingSpaceClient;
 pub use manager::AcpManager;
@@ -0,0 +1,198 @@
+// WorkerPool - Fixed set of long-running worker threads for agent requests
+// Each worker owns a current-thread runtime and LocalSet, since ACP futures are !Send
+
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+pub struct WorkerPool<T> {
+    // Shared queue every idle worker takes from; holds at most one item per key
+    sender: mpsc::UnboundedSender<(String, T)>,
+    // Keys with an item queued or running, and the items waiting behind it
+    waiting: Arc<Mutex<HashMap<String, VecDeque<T>>>>,
+}
+
+impl<T: Send + 'static> WorkerPool<T> {
+    /// Spawn `size` workers (at least one) that run `handler` on each queued item in turn
+    pub fn new<F, Fut>(name: &str, size: usize, handler: F) -> Self
+    where
+        F: Fn(T) -> Fut + Send + Clone + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let (sender, receiver) = mpsc::unbounded_channel::<(String, T)>();
+        let receiver = Arc::new(tokio::sync::Mutex::new(receiver));
+        let waiting: Arc<Mutex<HashMap<String, VecDeque<T>>>> = Arc::default();
+
+        for index in 0..size.max(1) {
+            let receiver = receiver.clone();
+            let waiting = waiting.clone();
+            // Weak, so dropping the pool still closes the queue
+            let requeue = sender.downgrade();
+            let handler = handler.clone();
+
+            std::thread::Builder::new()
+                .name(format!("{}-{}", name, index))
+                .spawn(move || {
+                    let rt = tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .expect("Failed to create worker runtime");
+                    let local_set = tokio::task::LocalSet::new();
+
+                    // Runs until the pool (and with it the sender) is dropped
+                    local_set.block_on(&rt, async move {
+                        loop {
+                            let next = receiver.lock().await.recv().await;
+                            let Some((key, item)) = next else {
+                                break;
+                            };
+                            handler(item).await;
+
+                            // Pass the key's next item to whichever worker is free
+                            let mut waiting = waiting.lock();
+                            match waiting.get_mut(&key).and_then(VecDeque::pop_front) {
+                                Some(next) => {
+                                    if let Some(sender) = requeue.upgrade() {
+                                        let _ = sender.send((key, next));
+                                    }
+                                }
+                                None => {
+                                    waiting.remove(&key);
+                                }
+                            }
+                        }
+                    });
+                })
+                .expect("Failed to spawn worker thread");
+        }
+
+        Self { sender, waiting }
+    }
+
+    /// Queue an item for the next free worker. Items with the same key run one
+    /// at a time, in the order they were queued; other keys don't wait for them.
+    pub fn enqueue(&self, key: &str, item: T) -> Result<(), String> {
+        let mut waiting = self.waiting.lock();
+        if let Some(queue) = waiting.get_mut(key) {
+            queue.push_back(item);
+            return Ok(());
+        }
+
+        self.sender
+            .send((key.to_string(), item))
+            .map_err(|_| "Worker has shut down".to_string())?;
+        waiting.insert(key.to_string(), VecDeque::new());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::{oneshot, Barrier};
+
+    #[tokio::test]
+    async fn test_same_key_is_processed_in_order() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let recorder = order.clone();
+        let pool = WorkerPool::new(
+            "test-worker",
+            4,
+            move |(index, done): (u64, oneshot::Sender<()>)| {
+                let recorder = recorder.clone();
+                async move {
+                    // Earlier items sleep longer, so any overlap would reorder them
+                    tokio::time::sleep(Duration::from_millis(25 - index * 5)).await;
+                    recorder.lock().push(index);
+                    let _ = done.send(());
+                }
+            },
+        );
+
+        let mut receivers = Vec::new();
+        for index in 0..5 {
+            let (tx, rx) = oneshot::channel();
+            pool.enqueue("space-a", (index, tx)).unwrap();
+            receivers.push(rx);
+        }
+        for rx in receivers {
+            rx.await.unwrap();
+        }
+
+        assert_eq!(*order.lock(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_different_keys_run_in_parallel() {
+        let pool = WorkerPool::new(
+            "test-worker",
+            4,
+            |(barrier, done): (Arc<Barrier>, oneshot::Sender<bool>)| async move {
+                // Every item must be running at once to get past the barrier
+                let met = tokio::time::timeout(Duration::from_secs(5), barrier.wait())
+                    .await
+                    .is_ok();
+                let _ = done.send(met);
+            },
+        );
+
+        // Any keys will do; none of them has to wait for a particular worker
+        let keys = ["space-a", "space-b", "space-c", "space-d"];
+        let barrier = Arc::new(Barrier::new(keys.len()));
+        let mut receivers = Vec::new();
+        for key in keys {
+            let (tx, rx) = oneshot::channel();
+            pool.enqueue(key, (barrier.clone(), tx)).unwrap();
+            receivers.push(rx);
+        }
+
+        for rx in receivers {
+            assert!(rx.await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_busy_key_does_not_hold_up_others() {
+        let (release_tx, release_rx) = oneshot::channel::<()>();
+        let release_rx = Arc::new(Mutex::new(Some(release_rx)));
+        let pool = WorkerPool::new(
+            "test-worker",
+            2,
+            move |(key, done): (String, oneshot::Sender<String>)| {
+                let release_rx = release_rx.clone();
+                async move {
+                    // The first "slow" item blocks until the test releases it
+                    if key == "slow" {
+                        if let Some(rx) = release_rx.lock().take() {
+                            let _ = rx.await;
+                        }
+                    }
+                    let _ = done.send(key);
+                }
+            },
+        );
+
+        let (slow_tx, slow_rx) = oneshot::channel();
+        let (queued_tx, mut queued_rx) = oneshot::channel();
+        let (other_tx, other_rx) = oneshot::channel();
+        pool.enqueue("slow", ("slow".to_string(), slow_tx)).unwrap();
+        pool.enqueue("slow", ("slow".to_string(), queued_tx))
+            .unwrap();
+        pool.enqueue("other", ("other".to_string(), other_tx))
+            .unwrap();
+
+        // "other" finishes while "slow" is still blocked, and the second "slow" waits its turn
+        let other = tokio::time::timeout(Duration::from_secs(5), other_rx).await;
+        assert_eq!(other.unwrap().unwrap(), "other");
+        assert!(queued_rx.try_recv().is_err());
+
+        release_tx.send(()).unwrap();
+        assert_eq!(slow_rx.await.unwrap(), "slow");
+        assert_eq!(queued_rx.await.unwrap(), "slow");
+    }
+}
@@ -1,6 +1,28 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use hkdf::Hkdf;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Anthropic OAuth token endpoint used to refresh Claude Code credentials
+const OAUTH_TOKEN_URL: &str = "https://api.anthropic.com/oauth/token";
+
+/// Public OAuth client ID used by Claude Code
+const OAUTH_CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
+
+/// AES-GCM nonce length in bytes (96 bits)
+const NONCE_LEN: usize = 12;
+
+/// AES-GCM authentication tag length in bytes
+const TAG_LEN: usize = 16;
+
+/// Treat tokens as expired this many seconds early to avoid racing the real expiry
+const EXPIRY_BUFFER_SECS: i64 = 5 * 60;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OAuthCredentials {
@@ -13,6 +35,122 @@ pub struct OAuthCredentials {
     pub scopes: Vec<String>,
 }
 
+/// Token endpoint response for a refresh_token grant
+#[derive(Debug, Deserialize)]
+struct TokenRefreshResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    /// Lifetime of the new access token in seconds
+    expires_in: i64,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+/// Warn about OAuth expiry once it is this close
+const REFRESH_WARNING_SECS: i64 = 24 * 60 * 60;
+
+/// Minimum gap between credential rotation reminders
+const ROTATION_REMINDER_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
+/// Token expiry in Unix seconds
+fn expires_at_secs(creds: &OAuthCredentials) -> i64 {
+    // Claude Code stores expiresAt in milliseconds; accept seconds too
+    // Any timestamp less than 100000000000 is in seconds, not milliseconds
+    if creds.expires_at < 100_000_000_000 {
+        creds.expires_at
+    } else {
+        creds.expires_at / 1000
+    }
+}
+
+/// Check whether an access token is expired (or will be within 5 minutes)
+pub fn is_token_expired(creds: &OAuthCredentials) -> bool {
+    expires_at_secs(creds) - EXPIRY_BUFFER_SECS <= chrono::Utc::now().timestamp()
+}
+
+/// Which credentials are configured and how long the OAuth token has left
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CredentialStatus {
+    pub has_api_key: bool,
+    pub has_oauth: bool,
+    /// OAuth expiry in Unix seconds
+    pub oauth_expires_at: Option<i64>,
+    pub oauth_expires_in_hours: Option<f64>,
+    /// OAuth token expires within 24 hours (or already has)
+    pub should_refresh: bool,
+    pub is_expired: bool,
+}
+
+fn credential_status_at(
+    has_api_key: bool,
+    has_oauth: bool,
+    oauth: Option<&OAuthCredentials>,
+    now: i64,
+) -> CredentialStatus {
+    let oauth_expires_at = oauth.map(expires_at_secs);
+    let remaining = oauth_expires_at.map(|expires_at| expires_at - now);
+
+    CredentialStatus {
+        has_api_key,
+        has_oauth: has_oauth || oauth.is_some(),
+        oauth_expires_at,
+        oauth_expires_in_hours: remaining.map(|secs| secs as f64 / 3600.0),
+        should_refresh: remaining.is_some_and(|secs| secs <= REFRESH_WARNING_SECS),
+        is_expired: remaining.is_some_and(|secs| secs <= 0),
+    }
+}
+
+/// Report configured credentials and OAuth expiry
+/// Reads the credentials file rather than the keychain so it never prompts;
+/// keychain-only OAuth shows up as `has_oauth` without an expiry
+#[tauri::command]
+pub fn get_credential_status() -> Result<CredentialStatus, String> {
+    let has_api_key = load_api_key()?.is_some_and(|key| !key.is_empty())
+        || crate::settings::load_settings()?.api_key.is_some();
+    let oauth = load_claude_credentials_file()?;
+
+    Ok(credential_status_at(
+        has_api_key,
+        has_claude_code_auth()?,
+        oauth.as_ref(),
+        chrono::Utc::now().timestamp(),
+    ))
+}
+
+/// Whether a rotation reminder may be shown, given when the last one was
+fn rotation_reminder_due(last_reminder: Option<i64>, now: i64) -> bool {
+    match last_reminder {
+        Some(last) => now - last >= ROTATION_REMINDER_INTERVAL_SECS,
+        None => true,
+    }
+}
+
+/// Return the credential status if the OAuth token needs refreshing and no
+/// reminder has been shown in the last day
+/// The frontend pulls this once it has mounted, so the reminder is only
+/// recorded as shown when there is a window to show it in
+#[tauri::command]
+pub fn take_rotation_reminder(app: tauri::AppHandle) -> Result<Option<CredentialStatus>, String> {
+    let status = get_credential_status()?;
+    if !status.should_refresh {
+        return Ok(None);
+    }
+
+    let mut settings = crate::settings::load_settings()?;
+    let now = chrono::Utc::now().timestamp();
+    if !rotation_reminder_due(settings.last_rotation_reminder, now) {
+        return Ok(None);
+    }
+
+    settings.last_rotation_reminder = Some(now);
+    if let Err(e) = crate::settings::save_settings_from(Some(&app), settings) {
+        eprintln!("[AUTH] Failed to record rotation reminder: {}", e);
+    }
+
+    Ok(Some(status))
+}
+
 /// Get the path to Claude Code credentials file
 fn get_claude_credentials_path() -> PathBuf {
     let home = dirs::home_dir().expect("Could not find home directory");
@@ -20,9 +158,8 @@ fn get_claude_credentials_path() -> PathBuf {
 }
 
 /// Get the path to Thinking Space config directory
-fn get_config_dir() -> PathBuf {
-    let home = dirs::home_dir().expect("Could not find home directory");
-    home.join(".thinking-space")
+fn get_config_dir() -> Result<PathBuf, String> {
+    crate::config::get_data_dir()
 }
 
 /// Load OAuth credentials from Claude Code credentials file
@@ -34,11 +171,14 @@ pub fn load_claude_credentials_file() -> Result<Option<OAuthCredentials>, String
         return Ok(None);
     }
 
-    let content = fs::read_to_string(&creds_path)
-        .map_err(|e| format!("Failed to read credentials file: {}", e))?;
+    let content =
+        fs::read(&creds_path).map_err(|e| format!("Failed to read credentials file: {}", e))?;
 
-    let creds: OAuthCredentials = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse credentials: {}", e))?;
+    // Older versions of this app wrote the credentials without the wrapper
+    let creds = parse_claude_credentials(&content).or_else(|_| {
+        serde_json::from_slice::<OAuthCredentials>(&content)
+            .map_err(|e| format!("Failed to parse credentials: {}", e))
+    })?;
 
     Ok(Some(creds))
 }
@@ -68,100 +208,490 @@ pub fn load_claude_credentials() -> Result<Option<OAuthCredentials>, String> {
         // Claude Code stores under service "Claude Code-credentials" and account is username
 
         // Get current username
-        let username = std::env::var("USER").unwrap_or_else(|_| "unknown".to_string());
-
-        match get_generic_password("Claude Code-credentials", &username) {
-            Ok(password) => {
-                let creds_str = String::from_utf8(password.to_vec())
-                    .map_err(|e| format!("Invalid UTF-8 in keychain: {}", e))?;
-
-                // Parse the nested structure: { "claudeAiOauth": { credentials } }
-                let wrapper: serde_json::Value = serde_json::from_str(&creds_str)
-                    .map_err(|e| format!("Failed to parse keychain credentials: {}", e))?;
-
-                // Extract the claudeAiOauth object
-                if let Some(oauth_obj) = wrapper.get("claudeAiOauth") {
-                    let creds: OAuthCredentials = serde_json::from_value(oauth_obj.clone())
-                        .map_err(|e| format!("Failed to parse OAuth credentials: {}", e))?;
-
-                    Ok(Some(creds))
-                } else {
-                    Err("Keychain credentials missing 'claudeAiOauth' field".to_string())
-                }
-            }
+        let username = keyring_account();
+
+        match get_generic_password(KEYRING_SERVICE, &username) {
+            Ok(password) => parse_claude_credentials(&password).map(Some),
             Err(_) => {
                 // Not found in keychain, try file
+                eprintln!(
+                    "[AUTH] WARNING: Credentials not in Keychain, falling back to plain-text file"
+                );
                 load_claude_credentials_file()
             }
         }
     }
 
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(target_os = "linux")]
     {
-        // On Linux/Windows, fall back to file-based credentials
+        // Try the Secret Service keyring (GNOME Keyring, KWallet) using the same
+        // service/account naming convention as the macOS Keychain
+        let username = keyring_account();
+
+        match linux_keyring::load_from_keyring(&linux_keyring::SecretServiceBackend, &username) {
+            Ok(Some(creds)) => Ok(Some(creds)),
+            Ok(None) | Err(_) => {
+                // Not found in keyring (or no keyring daemon), try file
+                eprintln!(
+                    "[AUTH] WARNING: Credentials not in keyring, falling back to plain-text file"
+                );
+                load_claude_credentials_file()
+            }
+        }
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        // On Windows, fall back to file-based credentials
+        eprintln!("[AUTH] WARNING: No system keyring support, using plain-text credentials file");
         load_claude_credentials_file()
     }
 }
 
-/// Load API key from Thinking Space settings
+/// Save OAuth credentials to the system keyring (macOS Keychain / Linux Secret Service)
+/// so refreshed tokens don't have to live in a plain-text file
 #[tauri::command]
-pub fn load_api_key() -> Result<Option<String>, String> {
-    let config_path = get_config_dir().join("config.json");
+pub fn save_claude_credentials_to_keyring(creds: OAuthCredentials) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        use security_framework::passwords::*;
+
+        let username = keyring_account();
+        let existing = get_generic_password(KEYRING_SERVICE, &username).ok();
+        let secret = wrap_claude_credentials(existing.as_deref(), &creds)?;
 
+        set_generic_password(KEYRING_SERVICE, &username, &secret)
+            .map_err(|e| format!("Failed to save credentials to Keychain: {}", e))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        linux_keyring::save_to_keyring(
+            &linux_keyring::SecretServiceBackend,
+            &keyring_account(),
+            &creds,
+        )
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        let _ = creds;
+        Err("System keyring is not supported on this platform".to_string())
+    }
+}
+
+/// Service name Claude Code stores its credentials under in the system keyring
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+const KEYRING_SERVICE: &str = "Claude Code-credentials";
+
+/// Keyring account name (the current username)
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn keyring_account() -> String {
+    std::env::var("USER").unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Parse a keyring secret or credentials file with the nested structure:
+/// { "claudeAiOauth": { credentials } }
+fn parse_claude_credentials(secret: &[u8]) -> Result<OAuthCredentials, String> {
+    let creds_str = String::from_utf8(secret.to_vec())
+        .map_err(|e| format!("Invalid UTF-8 in keychain: {}", e))?;
+
+    let wrapper: serde_json::Value = serde_json::from_str(&creds_str)
+        .map_err(|e| format!("Failed to parse keychain credentials: {}", e))?;
+
+    // Extract the claudeAiOauth object
+    let oauth_obj = wrapper
+        .get("claudeAiOauth")
+        .ok_or("Keychain credentials missing 'claudeAiOauth' field")?;
+
+    serde_json::from_value(oauth_obj.clone())
+        .map_err(|e| format!("Failed to parse OAuth credentials: {}", e))
+}
+
+/// Build a keyring secret or credentials file, replacing "claudeAiOauth" while
+/// keeping any other fields Claude Code stored alongside it
+fn wrap_claude_credentials(
+    existing: Option<&[u8]>,
+    creds: &OAuthCredentials,
+) -> Result<Vec<u8>, String> {
+    let mut wrapper = existing
+        .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(bytes).ok())
+        .filter(|value| value.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+
+    let creds_value = serde_json::to_value(creds)
+        .map_err(|e| format!("Failed to serialize credentials: {}", e))?;
+
+    if let Some(obj) = wrapper.as_object_mut() {
+        obj.insert("claudeAiOauth".to_string(), creds_value);
+    }
+
+    serde_json::to_vec(&wrapper).map_err(|e| format!("Failed to serialize credentials: {}", e))
+}
+
+#[cfg(target_os = "linux")]
+mod linux_keyring {
+    use super::KEYRING_SERVICE;
+    use super::{parse_claude_credentials, wrap_claude_credentials, OAuthCredentials};
+    use secret_service::blocking::SecretService;
+    use secret_service::EncryptionType;
+    use std::collections::HashMap;
+
+    /// Minimal keyring interface so lookups can be tested without a Secret Service daemon
+    pub trait KeyringBackend {
+        fn get_secret(&self, service: &str, account: &str) -> Result<Option<Vec<u8>>, String>;
+        fn set_secret(&self, service: &str, account: &str, secret: &[u8]) -> Result<(), String>;
+    }
+
+    /// Keyring backed by the freedesktop Secret Service (GNOME Keyring, KWallet)
+    pub struct SecretServiceBackend;
+
+    impl KeyringBackend for SecretServiceBackend {
+        fn get_secret(&self, service: &str, account: &str) -> Result<Option<Vec<u8>>, String> {
+            let ss = SecretService::connect(EncryptionType::Dh)
+                .map_err(|e| format!("Failed to connect to Secret Service: {}", e))?;
+
+            let attributes = HashMap::from([("service", service), ("account", account)]);
+            let results = ss
+                .search_items(attributes)
+                .map_err(|e| format!("Failed to search keyring: {}", e))?;
+
+            let item = match results.unlocked.into_iter().next() {
+                Some(item) => item,
+                None => match results.locked.into_iter().next() {
+                    Some(item) => {
+                        item.unlock()
+                            .map_err(|e| format!("Failed to unlock keyring item: {}", e))?;
+                        item
+                    }
+                    None => return Ok(None),
+                },
+            };
+
+            item.get_secret()
+                .map(Some)
+                .map_err(|e| format!("Failed to read keyring secret: {}", e))
+        }
+
+        fn set_secret(&self, service: &str, account: &str, secret: &[u8]) -> Result<(), String> {
+            let ss = SecretService::connect(EncryptionType::Dh)
+                .map_err(|e| format!("Failed to connect to Secret Service: {}", e))?;
+
+            let collection = ss
+                .get_default_collection()
+                .map_err(|e| format!("Failed to open default keyring: {}", e))?;
+            collection
+                .unlock()
+                .map_err(|e| format!("Failed to unlock keyring: {}", e))?;
+
+            let attributes = HashMap::from([("service", service), ("account", account)]);
+            collection
+                .create_item(service, attributes, secret, true, "application/json")
+                .map(|_| ())
+                .map_err(|e| format!("Failed to save credentials to keyring: {}", e))
+        }
+    }
+
+    pub fn load_from_keyring(
+        backend: &dyn KeyringBackend,
+        account: &str,
+    ) -> Result<Option<OAuthCredentials>, String> {
+        match backend.get_secret(KEYRING_SERVICE, account)? {
+            Some(secret) => parse_claude_credentials(&secret).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save_to_keyring(
+        backend: &dyn KeyringBackend,
+        account: &str,
+        creds: &OAuthCredentials,
+    ) -> Result<(), String> {
+        let existing = backend.get_secret(KEYRING_SERVICE, account).ok().flatten();
+        let secret = wrap_claude_credentials(existing.as_deref(), creds)?;
+        backend.set_secret(KEYRING_SERVICE, account, &secret)
+    }
+}
+
+/// Derive the 256-bit key used to encrypt secrets at rest
+/// The key is bound to this machine via its hardware ID, so copied config files can't be decrypted elsewhere
+fn derive_encryption_key() -> Result<[u8; 32], String> {
+    let machine_id = machine_uid::get().map_err(|e| format!("Failed to read machine ID: {}", e))?;
+
+    let hk = Hkdf::<Sha256>::new(Some(b"thinking-space"), machine_id.as_bytes());
+    let mut key = [0u8; 32];
+    hk.expand(b"api-key-encryption", &mut key)
+        .map_err(|e| format!("Failed to derive encryption key: {}", e))?;
+
+    Ok(key)
+}
+
+/// Encrypt with AES-256-GCM, returning base64(nonce || ciphertext)
+fn encrypt_with_key(key: &[u8; 32], plaintext: &str) -> Result<String, String> {
+    let cipher = Aes256Gcm::new(key.into());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|e| format!("Failed to encrypt: {}", e))?;
+
+    let mut combined = nonce.to_vec();
+    combined.extend_from_slice(&ciphertext);
+
+    Ok(BASE64.encode(combined))
+}
+
+/// Decrypt a value produced by `encrypt_with_key`
+/// Returns Ok(None) if the value isn't in the encrypted format (legacy plaintext)
+fn decrypt_with_key(key: &[u8; 32], stored: &str) -> Result<Option<String>, String> {
+    let combined = match BASE64.decode(stored) {
+        Ok(bytes) if bytes.len() >= NONCE_LEN + TAG_LEN => bytes,
+        _ => return Ok(None),
+    };
+
+    let (nonce, ciphertext) = combined.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(key.into());
+
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt stored secret".to_string())?;
+
+    String::from_utf8(plaintext)
+        .map(Some)
+        .map_err(|e| format!("Decrypted secret is not valid UTF-8: {}", e))
+}
+
+/// Encrypt a secret (e.g. an API key) for storage on disk
+pub fn encrypt_secret(plaintext: &str) -> Result<String, String> {
+    encrypt_with_key(&derive_encryption_key()?, plaintext)
+}
+
+/// Decrypt a stored secret, passing legacy plaintext values through unchanged
+pub fn decrypt_secret(stored: &str) -> Result<String, String> {
+    Ok(decrypt_with_key(&derive_encryption_key()?, stored)?.unwrap_or_else(|| stored.to_string()))
+}
+
+/// Whether a stored secret is still in the legacy plaintext format
+pub fn is_plaintext_secret(stored: &str) -> Result<bool, String> {
+    Ok(decrypt_with_key(&derive_encryption_key()?, stored)?.is_none())
+}
+
+fn read_config(config_path: &Path) -> Result<serde_json::Value, String> {
     if !config_path.exists() {
-        return Ok(None);
+        return Ok(serde_json::json!({}));
     }
 
     let content =
-        fs::read_to_string(&config_path).map_err(|e| format!("Failed to read config: {}", e))?;
+        fs::read_to_string(config_path).map_err(|e| format!("Failed to read config: {}", e))?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))
+}
+
+fn write_config(config_path: &Path, config: &serde_json::Value) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
 
-    let config: serde_json::Value =
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?;
+    fs::write(config_path, content).map_err(|e| format!("Failed to write config: {}", e))
+}
+
+/// Re-encrypt a plaintext "apiKey" in a config file. Returns true if the file changed.
+fn migrate_config_api_key(config_path: &Path, key: &[u8; 32]) -> Result<bool, String> {
+    let mut config = read_config(config_path)?;
+
+    let Some(stored) = config.get("apiKey").and_then(|v| v.as_str()) else {
+        return Ok(false);
+    };
+
+    if decrypt_with_key(key, stored)?.is_some() {
+        // Already encrypted
+        return Ok(false);
+    }
+
+    let encrypted = encrypt_with_key(key, stored)?;
+    if let Some(obj) = config.as_object_mut() {
+        obj.insert("apiKey".to_string(), serde_json::Value::String(encrypted));
+    }
+
+    write_config(config_path, &config)?;
+    Ok(true)
+}
+
+/// Load API key from Thinking Space settings
+#[tauri::command]
+pub fn load_api_key() -> Result<Option<String>, String> {
+    let config = read_config(&get_config_dir()?.join("config.json"))?;
 
-    Ok(config
+    config
         .get("apiKey")
         .and_then(|v| v.as_str())
-        .map(|s| s.to_string()))
+        .map(decrypt_secret)
+        .transpose()
 }
 
-/// Save API key to Thinking Space settings
+/// Save API key to Thinking Space settings (encrypted at rest)
 #[tauri::command]
-pub fn save_api_key(api_key: String) -> Result<(), String> {
-    let config_dir = get_config_dir();
+pub fn save_api_key(
+    manager: tauri::State<'_, std::sync::Arc<crate::settings::SettingsManager>>,
+    api_key: String,
+) -> Result<(), String> {
+    manager.save_api_key(api_key)
+}
+
+/// Write the API key to config.json without announcing the change
+pub(crate) fn write_api_key(api_key: &str) -> Result<(), String> {
+    let config_dir = get_config_dir()?;
     fs::create_dir_all(&config_dir)
         .map_err(|e| format!("Failed to create config directory: {}", e))?;
 
     let config_path = config_dir.join("config.json");
 
     // Load existing config or create new one
-    let mut config: serde_json::Value = if config_path.exists() {
-        let content = fs::read_to_string(&config_path)
-            .map_err(|e| format!("Failed to read config: {}", e))?;
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse config: {}", e))?
-    } else {
-        serde_json::json!({})
-    };
+    let mut config = read_config(&config_path)?;
 
     // Update API key
+    let encrypted = encrypt_secret(api_key)?;
     if let Some(obj) = config.as_object_mut() {
-        obj.insert("apiKey".to_string(), serde_json::Value::String(api_key));
+        obj.insert("apiKey".to_string(), serde_json::Value::String(encrypted));
     }
 
     // Save back to file
-    let content = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    write_config(&config_path, &config)
+}
+
+/// One-time upgrade of plaintext API keys (config.json and settings.json) to the encrypted format
+/// Returns true if anything was migrated
+#[tauri::command]
+pub fn migrate_api_key_to_encrypted(
+    manager: tauri::State<'_, std::sync::Arc<crate::settings::SettingsManager>>,
+) -> Result<bool, String> {
+    let key = derive_encryption_key()?;
+    let config_migrated = migrate_config_api_key(&get_config_dir()?.join("config.json"), &key)?;
+    let settings_migrated = crate::settings::migrate_api_key_to_encrypted(&manager)?;
+
+    Ok(config_migrated || settings_migrated)
+}
+
+/// Save OAuth credentials to the Claude Code credentials file
+pub fn save_claude_credentials_file(creds: &OAuthCredentials) -> Result<(), String> {
+    write_credentials_file(&get_claude_credentials_path(), creds)
+}
+
+/// Replace the OAuth entry in the credentials file, keeping everything else the
+/// Claude CLI stores there, and swap the new file in atomically
+fn write_credentials_file(path: &Path, creds: &OAuthCredentials) -> Result<(), String> {
+    let parent = path
+        .parent()
+        .ok_or("Credentials path has no parent directory")?;
+    fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create credentials directory: {}", e))?;
 
-    fs::write(&config_path, content).map_err(|e| format!("Failed to write config: {}", e))?;
+    let existing = fs::read(path).ok();
+    let content = wrap_claude_credentials(existing.as_deref(), creds)?;
+
+    let mut file = tempfile::NamedTempFile::new_in(parent)
+        .map_err(|e| format!("Failed to create credentials file: {}", e))?;
+    file.write_all(&content)
+        .map_err(|e| format!("Failed to write credentials file: {}", e))?;
+
+    // Credentials should only be readable by the current user
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(file.path(), fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to set credentials permissions: {}", e))?;
+    }
+
+    file.persist(path)
+        .map_err(|e| format!("Failed to write credentials file: {}", e.error))?;
 
     Ok(())
 }
 
-/// Refresh OAuth token (placeholder - actual implementation would call Anthropic API)
+/// Exchange a refresh token for new credentials at the given token endpoint
+async fn request_token_refresh(
+    token_url: &str,
+    refresh_token: &str,
+    previous_scopes: Vec<String>,
+) -> Result<OAuthCredentials, String> {
+    let response = reqwest::Client::new()
+        .post(token_url)
+        .json(&serde_json::json!({
+            "grant_type": "refresh_token",
+            "refresh_token": refresh_token,
+            "client_id": OAUTH_CLIENT_ID,
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to contact token endpoint: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!(
+            "Token refresh failed with status {} - please re-authenticate with Claude Code",
+            response.status()
+        ));
+    }
+
+    let token: TokenRefreshResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse token response: {}", e))?;
+
+    Ok(OAuthCredentials {
+        access_token: token.access_token,
+        // The endpoint may not rotate the refresh token
+        refresh_token: token
+            .refresh_token
+            .unwrap_or_else(|| refresh_token.to_string()),
+        // Stored in milliseconds to match Claude Code
+        expires_at: (chrono::Utc::now().timestamp() + token.expires_in) * 1000,
+        scopes: token
+            .scope
+            .map(|s| s.split_whitespace().map(|s| s.to_string()).collect())
+            .unwrap_or(previous_scopes),
+    })
+}
+
+/// Refresh credentials and persist them to `creds_path`
+async fn refresh_and_persist(
+    token_url: &str,
+    refresh_token: &str,
+    previous_scopes: Vec<String>,
+    creds_path: &Path,
+) -> Result<OAuthCredentials, String> {
+    let creds = request_token_refresh(token_url, refresh_token, previous_scopes).await?;
+    write_credentials_file(creds_path, &creds)?;
+    Ok(creds)
+}
+
+/// Refresh OAuth token via Anthropic's OAuth endpoint and persist the result
 #[tauri::command]
-pub fn refresh_oauth_token(_refresh_token: String) -> Result<OAuthCredentials, String> {
-    // This would need to call Anthropic's OAuth refresh endpoint
-    // For now, return an error as this requires Anthropic API access
-    Err("Token refresh not yet implemented - please re-authenticate with Claude Code".to_string())
+pub async fn refresh_oauth_token(refresh_token: String) -> Result<OAuthCredentials, String> {
+    let previous_scopes = load_claude_credentials_file()?
+        .map(|creds| creds.scopes)
+        .unwrap_or_default();
+
+    refresh_and_persist(
+        OAUTH_TOKEN_URL,
+        &refresh_token,
+        previous_scopes,
+        &get_claude_credentials_path(),
+    )
+    .await
+}
+
+/// Load Claude Code credentials, refreshing them first if they're about to expire
+#[tauri::command]
+pub async fn load_and_refresh_if_needed() -> Result<Option<OAuthCredentials>, String> {
+    let Some(creds) = load_claude_credentials_file()? else {
+        return Ok(None);
+    };
+
+    if !is_token_expired(&creds) {
+        return Ok(Some(creds));
+    }
+
+    println!("[AUTH] OAuth token expired or expiring soon, refreshing...");
+    refresh_oauth_token(creds.refresh_token).await.map(Some)
 }
 
 /// Open external URL in default browser
@@ -169,3 +699,335 @@ pub fn refresh_oauth_token(_refresh_token: String) -> Result<OAuthCredentials, S
 pub fn open_external_url(url: String) -> Result<(), String> {
     opener::open(url).map_err(|e| format!("Failed to open URL: {}", e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn credentials_expiring_at(expires_at: i64) -> OAuthCredentials {
+        OAuthCredentials {
+            access_token: "old-access".to_string(),
+            refresh_token: "old-refresh".to_string(),
+            expires_at,
+            scopes: vec!["user:inference".to_string()],
+        }
+    }
+
+    const TEST_KEY: [u8; 32] = [7u8; 32];
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let encrypted = encrypt_with_key(&TEST_KEY, "sk-ant-test-key").unwrap();
+
+        assert_ne!(encrypted, "sk-ant-test-key");
+        assert_eq!(
+            decrypt_with_key(&TEST_KEY, &encrypted).unwrap(),
+            Some("sk-ant-test-key".to_string())
+        );
+
+        // Fresh nonce per encryption
+        assert_ne!(
+            encrypted,
+            encrypt_with_key(&TEST_KEY, "sk-ant-test-key").unwrap()
+        );
+
+        // Wrong key fails instead of returning garbage
+        assert!(decrypt_with_key(&[8u8; 32], &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_detects_legacy_plaintext() {
+        assert_eq!(
+            decrypt_with_key(&TEST_KEY, "sk-ant-api03-abc").unwrap(),
+            None
+        );
+        assert_eq!(decrypt_with_key(&TEST_KEY, "").unwrap(), None);
+    }
+
+    #[test]
+    fn test_migrate_config_api_key_from_plaintext() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.json");
+        fs::write(
+            &config_path,
+            r#"{"apiKey": "sk-ant-legacy", "other": true}"#,
+        )
+        .unwrap();
+
+        assert!(migrate_config_api_key(&config_path, &TEST_KEY).unwrap());
+
+        let config = read_config(&config_path).unwrap();
+        let stored = config["apiKey"].as_str().unwrap();
+        assert_ne!(stored, "sk-ant-legacy");
+        assert_eq!(
+            decrypt_with_key(&TEST_KEY, stored).unwrap(),
+            Some("sk-ant-legacy".to_string())
+        );
+        assert_eq!(config["other"], true);
+
+        // Second run is a no-op
+        assert!(!migrate_config_api_key(&config_path, &TEST_KEY).unwrap());
+    }
+
+    #[test]
+    fn test_is_token_expired() {
+        let now = chrono::Utc::now().timestamp();
+
+        // Seconds
+        assert!(is_token_expired(&credentials_expiring_at(now - 10)));
+        assert!(is_token_expired(&credentials_expiring_at(now + 60))); // inside 5 min buffer
+        assert!(!is_token_expired(&credentials_expiring_at(now + 3600)));
+
+        // Milliseconds (Claude Code format)
+        assert!(is_token_expired(&credentials_expiring_at(
+            (now + 60) * 1000
+        )));
+        assert!(!is_token_expired(&credentials_expiring_at(
+            (now + 3600) * 1000
+        )));
+    }
+
+    /// Fixed "now" so the status maths doesn't depend on the real clock
+    const NOW: i64 = 1_750_000_000;
+
+    #[test]
+    fn test_credential_status_fresh() {
+        let creds = credentials_expiring_at(NOW + 72 * 3600);
+        let status = credential_status_at(true, false, Some(&creds), NOW);
+
+        assert_eq!(
+            status,
+            CredentialStatus {
+                has_api_key: true,
+                has_oauth: true,
+                oauth_expires_at: Some(NOW + 72 * 3600),
+                oauth_expires_in_hours: Some(72.0),
+                should_refresh: false,
+                is_expired: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_credential_status_expiring_soon() {
+        // Milliseconds, as Claude Code stores it
+        let creds = credentials_expiring_at((NOW + 6 * 3600) * 1000);
+        let status = credential_status_at(false, true, Some(&creds), NOW);
+
+        assert!(!status.has_api_key);
+        assert!(status.has_oauth);
+        assert_eq!(status.oauth_expires_at, Some(NOW + 6 * 3600));
+        assert_eq!(status.oauth_expires_in_hours, Some(6.0));
+        assert!(status.should_refresh);
+        assert!(!status.is_expired);
+    }
+
+    #[test]
+    fn test_credential_status_expired() {
+        let creds = credentials_expiring_at(NOW - 1800);
+        let status = credential_status_at(false, true, Some(&creds), NOW);
+
+        assert_eq!(status.oauth_expires_in_hours, Some(-0.5));
+        assert!(status.should_refresh);
+        assert!(status.is_expired);
+    }
+
+    #[test]
+    fn test_credential_status_without_oauth_file() {
+        let status = credential_status_at(true, false, None, NOW);
+
+        assert!(!status.has_oauth);
+        assert_eq!(status.oauth_expires_at, None);
+        assert_eq!(status.oauth_expires_in_hours, None);
+        assert!(!status.should_refresh);
+        assert!(!status.is_expired);
+    }
+
+    #[test]
+    fn test_rotation_reminder_once_per_day() {
+        assert!(rotation_reminder_due(None, NOW));
+        assert!(!rotation_reminder_due(Some(NOW - 3600), NOW));
+        assert!(rotation_reminder_due(Some(NOW - 24 * 3600), NOW));
+    }
+
+    #[cfg(target_os = "linux")]
+    mod linux_keyring_tests {
+        use super::super::linux_keyring::{load_from_keyring, save_to_keyring, KeyringBackend};
+        use super::super::{OAuthCredentials, KEYRING_SERVICE};
+        use parking_lot::Mutex;
+        use std::collections::HashMap;
+
+        /// In-memory stand-in for the Secret Service
+        #[derive(Default)]
+        struct MockKeyring {
+            secrets: Mutex<HashMap<(String, String), Vec<u8>>>,
+        }
+
+        impl KeyringBackend for MockKeyring {
+            fn get_secret(&self, service: &str, account: &str) -> Result<Option<Vec<u8>>, String> {
+                Ok(self
+                    .secrets
+                    .lock()
+                    .get(&(service.to_string(), account.to_string()))
+                    .cloned())
+            }
+
+            fn set_secret(
+                &self,
+                service: &str,
+                account: &str,
+                secret: &[u8],
+            ) -> Result<(), String> {
+                self.secrets
+                    .lock()
+                    .insert((service.to_string(), account.to_string()), secret.to_vec());
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn test_load_from_keyring() {
+            let keyring = MockKeyring::default();
+            keyring
+                .set_secret(
+                    KEYRING_SERVICE,
+                    "alice",
+                    br#"{"claudeAiOauth": {"accessToken": "a", "refreshToken": "r", "expiresAt": 1, "scopes": []}}"#,
+                )
+                .unwrap();
+
+            let creds = load_from_keyring(&keyring, "alice").unwrap().unwrap();
+            assert_eq!(creds.access_token, "a");
+            assert_eq!(creds.refresh_token, "r");
+
+            // Other accounts have nothing stored
+            assert!(load_from_keyring(&keyring, "bob").unwrap().is_none());
+        }
+
+        #[test]
+        fn test_load_from_keyring_missing_oauth_field() {
+            let keyring = MockKeyring::default();
+            keyring
+                .set_secret(KEYRING_SERVICE, "alice", br#"{"other": {}}"#)
+                .unwrap();
+
+            assert!(load_from_keyring(&keyring, "alice").is_err());
+        }
+
+        #[test]
+        fn test_save_to_keyring_preserves_other_fields() {
+            let keyring = MockKeyring::default();
+            keyring
+                .set_secret(KEYRING_SERVICE, "alice", br#"{"mcpOAuth": {"x": 1}}"#)
+                .unwrap();
+
+            let creds = OAuthCredentials {
+                access_token: "new".to_string(),
+                refresh_token: "refresh".to_string(),
+                expires_at: 42,
+                scopes: vec!["user:inference".to_string()],
+            };
+            save_to_keyring(&keyring, "alice", &creds).unwrap();
+
+            let loaded = load_from_keyring(&keyring, "alice").unwrap().unwrap();
+            assert_eq!(loaded.access_token, "new");
+            assert_eq!(loaded.expires_at, 42);
+
+            let raw = keyring
+                .get_secret(KEYRING_SERVICE, "alice")
+                .unwrap()
+                .unwrap();
+            let wrapper: serde_json::Value = serde_json::from_slice(&raw).unwrap();
+            assert_eq!(wrapper["mcpOAuth"]["x"], 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_persists_new_credentials() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/oauth/token")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "grant_type": "refresh_token",
+                "refresh_token": "old-refresh",
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"access_token": "new-access", "refresh_token": "new-refresh", "expires_in": 3600}"#,
+            )
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let creds_path = temp_dir.path().join(".credentials.json");
+        let token_url = format!("{}/oauth/token", server.url());
+
+        let creds = refresh_and_persist(
+            &token_url,
+            "old-refresh",
+            vec!["user:inference".to_string()],
+            &creds_path,
+        )
+        .await
+        .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(creds.access_token, "new-access");
+        assert_eq!(creds.refresh_token, "new-refresh");
+        assert_eq!(creds.scopes, vec!["user:inference".to_string()]);
+        assert!(!is_token_expired(&creds));
+
+        // The credentials file on disk was updated
+        let saved = parse_claude_credentials(&fs::read(&creds_path).unwrap()).unwrap();
+        assert_eq!(saved.access_token, "new-access");
+        assert_eq!(saved.refresh_token, "new-refresh");
+    }
+
+    #[tokio::test]
+    async fn test_refresh_failure_leaves_file_untouched() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/oauth/token")
+            .with_status(401)
+            .create_async()
+            .await;
+
+        let temp_dir = TempDir::new().unwrap();
+        let creds_path = temp_dir.path().join(".credentials.json");
+        let token_url = format!("{}/oauth/token", server.url());
+
+        let result = refresh_and_persist(&token_url, "bad-refresh", vec![], &creds_path).await;
+
+        assert!(result.is_err());
+        assert!(!creds_path.exists());
+    }
+
+    #[test]
+    fn test_write_credentials_file_keeps_other_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let creds_path = temp_dir.path().join(".credentials.json");
+        fs::write(
+            &creds_path,
+            r#"{"claudeAiOauth": {"accessToken": "old"}, "mcpOAuth": {"x": 1}}"#,
+        )
+        .unwrap();
+
+        let creds = OAuthCredentials {
+            access_token: "new".to_string(),
+            refresh_token: "refresh".to_string(),
+            expires_at: 42,
+            scopes: vec![],
+        };
+        write_credentials_file(&creds_path, &creds).unwrap();
+
+        let raw = fs::read(&creds_path).unwrap();
+        assert_eq!(parse_claude_credentials(&raw).unwrap().access_token, "new");
+        let wrapper: serde_json::Value = serde_json::from_slice(&raw).unwrap();
+        assert_eq!(wrapper["mcpOAuth"]["x"], 1);
+
+        // Only the credentials file is left behind, no temp files
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,139 @@
+// System clipboard access for pasting into prompts and copying agent responses
+
+use crate::error::ThinkingSpaceError;
+use arboard::Clipboard;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tauri::State;
+
+/// Largest clipboard text handed to the frontend
+const MAX_CLIPBOARD_BYTES: usize = 100 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClipboardContent {
+    /// `None` when the clipboard is empty or holds something other than text
+    pub text: Option<String>,
+    /// Whether `text` was cut down to `MAX_CLIPBOARD_BYTES`
+    pub truncated: bool,
+}
+
+/// One clipboard handle for the app's lifetime
+/// On X11 the app owns what it copied only while its handle is alive, so
+/// dropping a handle right after `set_text` would lose the copied text
+#[derive(Default)]
+pub struct ClipboardState(Mutex<Option<Clipboard>>);
+
+impl ClipboardState {
+    /// Run `f` with the clipboard, opening it on first use
+    fn with_clipboard<T>(
+        &self,
+        f: impl FnOnce(&mut Clipboard) -> Result<T, ThinkingSpaceError>,
+    ) -> Result<T, ThinkingSpaceError> {
+        let mut clipboard = self.0.lock();
+        if clipboard.is_none() {
+            *clipboard = Some(Clipboard::new().map_err(unavailable)?);
+        }
+        f(clipboard.as_mut().expect("clipboard was just opened"))
+    }
+
+    fn read(&self) -> Result<ClipboardContent, ThinkingSpaceError> {
+        self.with_clipboard(|clipboard| match clipboard.get_text() {
+            Ok(text) => Ok(truncate_text(text, MAX_CLIPBOARD_BYTES)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(ClipboardContent {
+                text: None,
+                truncated: false,
+            }),
+            Err(e) => Err(unavailable(e)),
+        })
+    }
+
+    fn write(&self, text: String) -> Result<(), ThinkingSpaceError> {
+        self.with_clipboard(|clipboard| clipboard.set_text(text).map_err(unavailable))
+    }
+}
+
+fn unavailable(e: arboard::Error) -> ThinkingSpaceError {
+    ThinkingSpaceError::ClipboardUnavailable {
+        reason: e.to_string(),
+    }
+}
+
+/// Cut `text` to at most `max_bytes` without splitting a character
+fn truncate_text(mut text: String, max_bytes: usize) -> ClipboardContent {
+    if text.len() <= max_bytes {
+        return ClipboardContent {
+            text: Some(text),
+            truncated: false,
+        };
+    }
+
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    ClipboardContent {
+        text: Some(text),
+        truncated: true,
+    }
+}
+
+#[tauri::command]
+pub fn read_clipboard(
+    clipboard: State<'_, ClipboardState>,
+) -> Result<ClipboardContent, ThinkingSpaceError> {
+    clipboard.read()
+}
+
+#[tauri::command]
+pub fn write_clipboard(
+    clipboard: State<'_, ClipboardState>,
+    text: String,
+) -> Result<(), ThinkingSpaceError> {
+    clipboard.write(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_text_keeps_short_text() {
+        let content = truncate_text("hello".to_string(), 10);
+        assert_eq!(content.text.as_deref(), Some("hello"));
+        assert!(!content.truncated);
+    }
+
+    #[test]
+    fn test_truncate_text_respects_char_boundaries() {
+        // "é" is two bytes, so a cap landing inside one drops it
+        let content = truncate_text("aéé".to_string(), 4);
+        assert_eq!(content.text.as_deref(), Some("aé"));
+        assert!(content.truncated);
+
+        let content = truncate_text("aéé".to_string(), 2);
+        assert_eq!(content.text.as_deref(), Some("a"));
+        assert!(content.truncated);
+    }
+
+    #[cfg(not(headless))]
+    #[test]
+    fn test_clipboard_round_trip_and_truncation() {
+        let clipboard = ClipboardState::default();
+        let text = format!("thinking-space clipboard test {}", uuid::Uuid::new_v4());
+        clipboard.write(text.clone()).unwrap();
+
+        let content = clipboard.read().unwrap();
+        assert_eq!(content.text, Some(text));
+        assert!(!content.truncated);
+
+        // Kept in the same test since the clipboard is shared between threads
+        clipboard
+            .write("x".repeat(MAX_CLIPBOARD_BYTES + 10))
+            .unwrap();
+
+        let content = clipboard.read().unwrap();
+        assert_eq!(content.text.map(|t| t.len()), Some(MAX_CLIPBOARD_BYTES));
+        assert!(content.truncated);
+    }
+}
@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Serializes read-modify-write cycles on the usage file
+static USAGE_LOCK: Mutex<()> = Mutex::new(());
 
 /// Get the commands directory for a space
 fn get_commands_directory(space_path: &str) -> String {
@@ -11,6 +16,62 @@ fn get_commands_directory(space_path: &str) -> String {
         .to_string()
 }
 
+/// Get the directory for commands shared by every space
+fn get_global_commands_directory() -> Result<String, String> {
+    Ok(crate::config::get_data_dir()?
+        .join("commands")
+        .to_string_lossy()
+        .to_string())
+}
+
+/// Get the file that tracks how often commands are used
+fn get_usage_path() -> Result<PathBuf, String> {
+    Ok(crate::config::get_data_dir()?.join("command-usage.json"))
+}
+
+/// Where a command comes from, or which commands to list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandScope {
+    #[default]
+    SpaceOnly,
+    GlobalOnly,
+    All,
+}
+
+/// Kind of value a named argument expects
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArgType {
+    #[default]
+    Text,
+    File,
+    Number,
+}
+
+/// A named argument declared in a command's front-matter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArgSpec {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub arg_type: ArgType,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// YAML block between `---` lines at the top of a command file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FrontMatter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<ArgSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+}
+
 /// Represents a slash command loaded from a markdown file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SlashCommand {
@@ -24,6 +85,162 @@ pub struct SlashCommand {
     pub template: String,
     /// Whether this command expects arguments
     pub accepts_arguments: bool,
+    /// Named arguments declared in front-matter
+    #[serde(default)]
+    pub parsed_args: Vec<ArgSpec>,
+    /// Whether the command belongs to the space or is global
+    #[serde(default)]
+    pub scope: CommandScope,
+    /// Group shown in the command palette, from front-matter
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Usage counts, only filled in when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<CommandUsageStat>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandUsageStat {
+    pub command_name: String,
+    pub use_count: u64,
+    pub last_used_at: i64,
+}
+
+/// Stored usage entry, keyed by `space_path::command_name`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UsageEntry {
+    use_count: u64,
+    last_used_at: i64,
+}
+
+fn usage_key(space_path: &str, command_name: &str) -> String {
+    format!("{}::{}", space_path, command_name)
+}
+
+fn read_usage(usage_path: &Path) -> HashMap<String, UsageEntry> {
+    fs::read_to_string(usage_path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Increment a command's use count, writing via a temp file so the JSON is never partial
+fn record_command_use_in(
+    usage_path: &Path,
+    space_path: &str,
+    command_name: &str,
+) -> Result<(), String> {
+    let _guard = USAGE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let mut usage = read_usage(usage_path);
+    let entry = usage
+        .entry(usage_key(space_path, command_name))
+        .or_default();
+    entry.use_count += 1;
+    entry.last_used_at = chrono::Utc::now().timestamp_millis();
+
+    if let Some(parent) = usage_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(&usage)
+        .map_err(|e| format!("Failed to serialize command usage: {}", e))?;
+    let tmp_path = usage_path.with_extension("json.tmp");
+    fs::write(&tmp_path, json).map_err(|e| format!("Failed to write command usage: {}", e))?;
+    fs::rename(&tmp_path, usage_path).map_err(|e| format!("Failed to write command usage: {}", e))
+}
+
+/// Usage for every recorded command in a space, most used first
+fn command_usage_stats_in(usage_path: &Path, space_path: &str) -> Vec<CommandUsageStat> {
+    let _guard = USAGE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let prefix = usage_key(space_path, "");
+    let mut stats: Vec<CommandUsageStat> = read_usage(usage_path)
+        .into_iter()
+        .filter_map(|(key, entry)| {
+            key.strip_prefix(&prefix).map(|name| CommandUsageStat {
+                command_name: name.to_string(),
+                use_count: entry.use_count,
+                last_used_at: entry.last_used_at,
+            })
+        })
+        .collect();
+
+    stats.sort_by(|a, b| {
+        b.use_count
+            .cmp(&a.use_count)
+            .then_with(|| a.command_name.cmp(&b.command_name))
+    });
+
+    stats
+}
+
+/// Split a leading `---` front-matter block from the rest of the file
+/// Files without (or with malformed) front-matter are returned whole
+fn split_front_matter(content: &str) -> (Option<FrontMatter>, &str) {
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (None, content);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let yaml = &rest[..offset];
+            let body = &rest[offset + line.len()..];
+
+            if yaml.trim().is_empty() {
+                return (Some(FrontMatter::default()), body);
+            }
+
+            return match serde_yaml::from_str::<FrontMatter>(yaml) {
+                Ok(front_matter) => (Some(front_matter), body),
+                Err(e) => {
+                    eprintln!("[COMMANDS] Ignoring invalid front-matter: {}", e);
+                    (None, content)
+                }
+            };
+        }
+        offset += line.len();
+    }
+
+    (None, content)
+}
+
+/// First non-empty line that's not a heading marker
+fn describe(body: &str) -> String {
+    body.lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .unwrap_or("No description")
+        .to_string()
+}
+
+/// Build a SlashCommand from a command file's content
+fn parse_command(name: String, path: &Path, content: String) -> SlashCommand {
+    let (front_matter, body) = split_front_matter(&content);
+    let front_matter = front_matter.unwrap_or_default();
+
+    let description = front_matter.description.unwrap_or_else(|| describe(body));
+    let accepts_arguments = body.contains("$ARGUMENTS") || !front_matter.args.is_empty();
+    let category = front_matter
+        .category
+        .map(|category| category.trim().to_string())
+        .filter(|category| !category.is_empty());
+
+    SlashCommand {
+        name,
+        path: path.to_string_lossy().to_string(),
+        description,
+        template: content,
+        accepts_arguments,
+        parsed_args: front_matter.args,
+        scope: CommandScope::SpaceOnly,
+        category,
+        usage: None,
+    }
 }
 
 /// Load all slash commands from a directory
@@ -111,25 +328,7 @@ Feel free to edit or delete this file!"#;
             .ok_or_else(|| format!("Invalid command filename: {:?}", path))?
             .to_string();
 
-        // Extract description (first non-empty line that's not a heading marker)
-        let description = content
-            .lines()
-            .map(|line| line.trim())
-            .filter(|line| !line.is_empty() && !line.starts_with('#'))
-            .next()
-            .unwrap_or("No description")
-            .to_string();
-
-        // Check if command accepts arguments
-        let accepts_arguments = content.contains("$ARGUMENTS");
-
-        commands.push(SlashCommand {
-            name,
-            path: path.to_string_lossy().to_string(),
-            description,
-            template: content,
-            accepts_arguments,
-        });
+        commands.push(parse_command(name, &path, content));
     }
 
     // Sort commands alphabetically
@@ -140,7 +339,7 @@ Feel free to edit or delete this file!"#;
 
 /// Load a single command by name
 pub fn load_command(dir_path: &str, command_name: &str) -> Result<SlashCommand, String> {
-    let path = PathBuf::from(dir_path).join(format!("{}.md", command_name));
+    let path = command_path_in(Path::new(dir_path), command_name)?;
 
     if !path.exists() {
         return Err(format!("Command '{}' not found", command_name));
@@ -149,28 +348,44 @@ pub fn load_command(dir_path: &str, command_name: &str) -> Result<SlashCommand,
     let content =
         fs::read_to_string(&path).map_err(|e| format!("Failed to read command file: {}", e))?;
 
-    let description = content
-        .lines()
-        .map(|line| line.trim())
-        .filter(|line| !line.is_empty() && !line.starts_with('#'))
-        .next()
-        .unwrap_or("No description")
-        .to_string();
-
-    let accepts_arguments = content.contains("$ARGUMENTS");
-
-    Ok(SlashCommand {
-        name: command_name.to_string(),
-        path: path.to_string_lossy().to_string(),
-        description,
-        template: content,
-        accepts_arguments,
-    })
+    Ok(parse_command(command_name.to_string(), &path, content))
 }
 
 /// Expand a command template with arguments
-pub fn expand_command_template(template: &str, arguments: &str) -> String {
-    template.replace("$ARGUMENTS", arguments)
+/// `$ARGUMENTS` takes the positional text; `${name}` takes a named argument,
+/// falling back to its front-matter default. Front-matter is not part of the output.
+pub fn expand_command_template(
+    template: &str,
+    arguments: &str,
+    named_args: &HashMap<String, String>,
+) -> Result<String, String> {
+    let (front_matter, body) = split_front_matter(template);
+    let specs = front_matter.map(|fm| fm.args).unwrap_or_default();
+
+    let mut expanded = body.replace("$ARGUMENTS", arguments);
+
+    for spec in &specs {
+        let value = match named_args.get(&spec.name).or(spec.default.as_ref()) {
+            Some(value) => value.clone(),
+            None if spec.required => {
+                return Err(format!("Missing required argument '{}'", spec.name))
+            }
+            None => String::new(),
+        };
+
+        if spec.arg_type == ArgType::Number && !value.is_empty() && value.parse::<f64>().is_err() {
+            return Err(format!("Argument '{}' must be a number", spec.name));
+        }
+
+        expanded = expanded.replace(&format!("${{{}}}", spec.name), &value);
+    }
+
+    // Named values without a declared spec are still substituted
+    for (name, value) in named_args {
+        expanded = expanded.replace(&format!("${{{}}}", name), value);
+    }
+
+    Ok(expanded)
 }
 
 /// Create a new command file
@@ -179,6 +394,7 @@ pub fn create_command(
     command_name: &str,
     description: &str,
     template: &str,
+    args: Option<Vec<ArgSpec>>,
 ) -> Result<SlashCommand, String> {
     let path = PathBuf::from(dir_path);
 
@@ -188,7 +404,7 @@ pub fn create_command(
             .map_err(|e| format!("Failed to create commands directory: {}", e))?;
     }
 
-    let file_path = path.join(format!("{}.md", command_name));
+    let file_path = command_path_in(&path, command_name)?;
 
     // Check if command already exists
     if file_path.exists() {
@@ -196,25 +412,312 @@ pub fn create_command(
     }
 
     // Create markdown content
-    let content = format!("# {}\n\n{}\n\n{}", command_name, description, template);
+    let mut content = format!("# {}\n\n{}\n\n{}", command_name, description, template);
+
+    // Named arguments are declared in front-matter
+    if let Some(args) = args.filter(|args| !args.is_empty()) {
+        let front_matter = FrontMatter {
+            description: Some(description.to_string()),
+            args,
+            category: None,
+        };
+        let yaml = serde_yaml::to_string(&front_matter)
+            .map_err(|e| format!("Failed to serialize command arguments: {}", e))?;
+        content = format!("---\n{}---\n{}", yaml, content);
+    }
 
     // Write file
     fs::write(&file_path, &content).map_err(|e| format!("Failed to create command file: {}", e))?;
 
-    let accepts_arguments = template.contains("$ARGUMENTS");
+    Ok(parse_command(command_name.to_string(), &file_path, content))
+}
 
-    Ok(SlashCommand {
-        name: command_name.to_string(),
-        path: file_path.to_string_lossy().to_string(),
-        description: description.to_string(),
-        template: content,
-        accepts_arguments,
-    })
+/// Load commands from a global commands directory
+/// Unlike space directories, a missing global directory is not seeded with samples
+fn load_global_commands_from(dir_path: &str) -> Result<Vec<SlashCommand>, String> {
+    if !Path::new(dir_path).exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut commands = load_commands_from_directory(dir_path)?;
+    for command in &mut commands {
+        command.scope = CommandScope::GlobalOnly;
+    }
+
+    Ok(commands)
+}
+
+/// Load commands from ~/.thinking-space/commands
+pub fn load_global_commands() -> Result<Vec<SlashCommand>, String> {
+    load_global_commands_from(&get_global_commands_directory()?)
+}
+
+/// List space and/or global commands; space commands win on name clashes
+fn list_commands_in(
+    space_commands_dir: &str,
+    global_commands_dir: &str,
+    scope: CommandScope,
+) -> Result<Vec<SlashCommand>, String> {
+    let mut commands = match scope {
+        CommandScope::SpaceOnly => load_commands_from_directory(space_commands_dir)?,
+        CommandScope::GlobalOnly => load_global_commands_from(global_commands_dir)?,
+        CommandScope::All => {
+            let mut commands = load_commands_from_directory(space_commands_dir)?;
+            for global in load_global_commands_from(global_commands_dir)? {
+                if !commands.iter().any(|c| c.name == global.name) {
+                    commands.push(global);
+                }
+            }
+            commands
+        }
+    };
+
+    // Sort commands alphabetically
+    commands.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(commands)
+}
+
+/// Distinct categories used by a set of commands, sorted
+fn command_categories(commands: &[SlashCommand]) -> Vec<String> {
+    let categories: std::collections::BTreeSet<&String> = commands
+        .iter()
+        .filter_map(|command| command.category.as_ref())
+        .collect();
+    categories.into_iter().cloned().collect()
+}
+
+/// Commands in `category`; uncategorised commands never match
+fn commands_in_category(commands: Vec<SlashCommand>, category: &str) -> Vec<SlashCommand> {
+    commands
+        .into_iter()
+        .filter(|command| command.category.as_deref() == Some(category))
+        .collect()
+}
+
+/// Longest command name accepted for renames and copies
+const MAX_COMMAND_NAME_LEN: usize = 64;
+
+/// Command names become filenames: ASCII letters, digits, '-' and '_' only
+fn validate_command_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_COMMAND_NAME_LEN {
+        return Err(format!(
+            "Command name must be 1-{} characters",
+            MAX_COMMAND_NAME_LEN
+        ));
+    }
+
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid command name '{}': use letters, numbers, hyphens and underscores",
+            name
+        ));
+    }
+
+    Ok(())
+}
+
+/// Path of a command file, refusing names that would resolve outside `dir`
+fn command_path_in(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    validate_command_name(name)?;
+
+    let path = dir.join(format!("{}.md", name));
+    let canonical_dir = dir
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve commands directory: {}", e))?;
+    // A command file may be a symlink; the link itself has to live in the directory
+    let resolved_parent = path
+        .parent()
+        .and_then(|parent| parent.canonicalize().ok())
+        .ok_or_else(|| format!("Invalid command path for '{}'", name))?;
+
+    if resolved_parent != canonical_dir {
+        return Err(format!(
+            "Command '{}' is outside the commands directory",
+            name
+        ));
+    }
+
+    Ok(path)
+}
+
+/// Rename a command file, refusing to replace an existing command unless `overwrite` is set
+pub fn rename_command(
+    dir_path: &str,
+    old_name: &str,
+    new_name: &str,
+    overwrite: bool,
+) -> Result<SlashCommand, String> {
+    let dir = PathBuf::from(dir_path);
+    let old_path = command_path_in(&dir, old_name)?;
+    let new_path = command_path_in(&dir, new_name)?;
+
+    if !old_path.exists() {
+        return Err(format!("Command '{}' not found", old_name));
+    }
+
+    if new_path.exists() && !overwrite {
+        return Err(format!("Command '{}' already exists", new_name));
+    }
+
+    fs::rename(&old_path, &new_path).map_err(|e| format!("Failed to rename command: {}", e))?;
+
+    load_command(dir_path, new_name)
+}
+
+/// Copy a command into another directory, adding a numeric suffix if the name is taken
+pub fn copy_command(
+    source_dir: &str,
+    dest_dir: &str,
+    command_name: &str,
+) -> Result<SlashCommand, String> {
+    validate_command_name(command_name)?;
+
+    let source_path = PathBuf::from(source_dir).join(format!("{}.md", command_name));
+    if !source_path.exists() {
+        return Err(format!("Command '{}' not found", command_name));
+    }
+
+    let dest = PathBuf::from(dest_dir);
+    fs::create_dir_all(&dest).map_err(|e| format!("Failed to create commands directory: {}", e))?;
+
+    let mut dest_name = command_name.to_string();
+    let mut suffix = 1;
+    while dest.join(format!("{}.md", dest_name)).exists() {
+        let suffix_text = format!("-{}", suffix);
+        // Keep suffixed names within the length limit
+        let base_len = command_name
+            .len()
+            .min(MAX_COMMAND_NAME_LEN - suffix_text.len());
+        dest_name = format!("{}{}", &command_name[..base_len], suffix_text);
+        suffix += 1;
+    }
+
+    fs::copy(&source_path, dest.join(format!("{}.md", dest_name)))
+        .map_err(|e| format!("Failed to copy command: {}", e))?;
+
+    load_command(dest_dir, &dest_name)
+}
+
+/// Byte ranges of a command body's description paragraph and the template after it
+struct BodySections {
+    description_start: usize,
+    description_end: usize,
+    template_start: usize,
+    has_description: bool,
+}
+
+/// Locate the first non-heading paragraph (the description) and what follows it
+fn body_sections(body: &str) -> BodySections {
+    let mut offset = 0;
+    let mut description_start = None;
+    let mut description_end = body.len();
+
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim();
+        match description_start {
+            None if !trimmed.is_empty() && !trimmed.starts_with('#') => {
+                description_start = Some(offset);
+            }
+            Some(_) if trimmed.is_empty() => {
+                description_end = offset;
+                break;
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+
+    let Some(description_start) = description_start else {
+        return BodySections {
+            description_start: body.len(),
+            description_end: body.len(),
+            template_start: body.len(),
+            has_description: false,
+        };
+    };
+
+    // The template starts at the first non-blank line after the description
+    let mut template_start = description_end;
+    for line in body[description_end..].split_inclusive('\n') {
+        if !line.trim().is_empty() {
+            break;
+        }
+        template_start += line.len();
+    }
+
+    BodySections {
+        description_start,
+        description_end,
+        template_start,
+        has_description: true,
+    }
+}
+
+/// Rewrite a command's description and/or template, leaving everything else as it was
+pub fn update_command(
+    dir_path: &str,
+    command_name: &str,
+    new_description: Option<&str>,
+    new_template: Option<&str>,
+) -> Result<SlashCommand, String> {
+    validate_command_name(command_name)?;
+
+    let path = PathBuf::from(dir_path).join(format!("{}.md", command_name));
+    if !path.exists() {
+        return Err(format!("Command '{}' not found", command_name));
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read command file: {}", e))?;
+    let (front_matter, body) = split_front_matter(&content);
+    let raw_front_matter = &content[..content.len() - body.len()];
+
+    let sections = body_sections(body);
+    let description = new_description
+        .map(str::trim)
+        .unwrap_or_else(|| body[sections.description_start..sections.description_end].trim_end());
+    let template = new_template.unwrap_or(&body[sections.template_start..]);
+
+    let mut updated_body = body[..sections.description_start].to_string();
+    if !sections.has_description && !updated_body.is_empty() {
+        // Heading-only file: leave a blank line before the new description
+        while !updated_body.ends_with("\n\n") {
+            updated_body.push('\n');
+        }
+    }
+    updated_body.push_str(description);
+    updated_body.push('\n');
+    if !template.is_empty() {
+        match &body[sections.description_end..sections.template_start] {
+            "" => updated_body.push('\n'),
+            separator => updated_body.push_str(separator),
+        }
+        updated_body.push_str(template);
+    }
+
+    // A front-matter description takes precedence when loading, so keep it in step
+    let updated = match (front_matter, new_description) {
+        (Some(mut front_matter), Some(description)) if front_matter.description.is_some() => {
+            front_matter.description = Some(description.trim().to_string());
+            let yaml = serde_yaml::to_string(&front_matter)
+                .map_err(|e| format!("Failed to serialize command arguments: {}", e))?;
+            format!("---\n{}---\n{}", yaml, updated_body)
+        }
+        _ => format!("{}{}", raw_front_matter, updated_body),
+    };
+
+    fs::write(&path, &updated).map_err(|e| format!("Failed to update command file: {}", e))?;
+
+    Ok(parse_command(command_name.to_string(), &path, updated))
 }
 
 /// Delete a command file
 pub fn delete_command(dir_path: &str, command_name: &str) -> Result<(), String> {
-    let path = PathBuf::from(dir_path).join(format!("{}.md", command_name));
+    let path = command_path_in(Path::new(dir_path), command_name)?;
 
     if !path.exists() {
         return Err(format!("Command '{}' not found", command_name));
@@ -227,10 +730,66 @@ pub fn delete_command(dir_path: &str, command_name: &str) -> Result<(), String>
 // Tauri Commands
 // =============================================================================
 
+/// List commands for a space (default scope: space and global commands)
 #[tauri::command]
-pub fn list_slash_commands(space_path: String) -> Result<Vec<SlashCommand>, String> {
+pub fn list_slash_commands(
+    space_path: String,
+    scope: Option<CommandScope>,
+    include_stats: Option<bool>,
+) -> Result<Vec<SlashCommand>, String> {
     let commands_dir = get_commands_directory(&space_path);
-    load_commands_from_directory(&commands_dir)
+    let mut commands = list_commands_in(
+        &commands_dir,
+        &get_global_commands_directory()?,
+        scope.unwrap_or(CommandScope::All),
+    )?;
+
+    if include_stats.unwrap_or(false) {
+        let stats = command_usage_stats_in(&get_usage_path()?, &space_path);
+        for command in &mut commands {
+            command.usage = stats
+                .iter()
+                .find(|stat| stat.command_name == command.name)
+                .cloned();
+        }
+    }
+
+    Ok(commands)
+}
+
+/// Categories used by the space's and global commands
+#[tauri::command]
+pub fn list_slash_command_categories(space_path: String) -> Result<Vec<String>, String> {
+    let commands = list_commands_in(
+        &get_commands_directory(&space_path),
+        &get_global_commands_directory()?,
+        CommandScope::All,
+    )?;
+    Ok(command_categories(&commands))
+}
+
+/// Space and global commands in one category
+#[tauri::command]
+pub fn list_slash_commands_by_category(
+    space_path: String,
+    category: String,
+) -> Result<Vec<SlashCommand>, String> {
+    let commands = list_commands_in(
+        &get_commands_directory(&space_path),
+        &get_global_commands_directory()?,
+        CommandScope::All,
+    )?;
+    Ok(commands_in_category(commands, &category))
+}
+
+#[tauri::command]
+pub fn record_command_use(space_path: String, command_name: String) -> Result<(), String> {
+    record_command_use_in(&get_usage_path()?, &space_path, &command_name)
+}
+
+#[tauri::command]
+pub fn get_command_usage_stats(space_path: String) -> Result<Vec<CommandUsageStat>, String> {
+    Ok(command_usage_stats_in(&get_usage_path()?, &space_path))
 }
 
 #[tauri::command]
@@ -243,8 +802,12 @@ pub fn load_slash_command(
 }
 
 #[tauri::command]
-pub fn expand_slash_command(template: String, arguments: String) -> Result<String, String> {
-    Ok(expand_command_template(&template, &arguments))
+pub fn expand_slash_command(
+    template: String,
+    arguments: String,
+    named_args: Option<HashMap<String, String>>,
+) -> Result<String, String> {
+    expand_command_template(&template, &arguments, &named_args.unwrap_or_default())
 }
 
 #[tauri::command]
@@ -253,9 +816,32 @@ pub fn create_slash_command(
     command_name: String,
     description: String,
     template: String,
+    args: Option<Vec<ArgSpec>>,
+) -> Result<SlashCommand, String> {
+    let commands_dir = get_commands_directory(&space_path);
+    create_command(&commands_dir, &command_name, &description, &template, args)
+}
+
+#[tauri::command]
+pub fn update_slash_command(
+    space_path: String,
+    command_name: String,
+    new_description: Option<String>,
+    new_template: Option<String>,
 ) -> Result<SlashCommand, String> {
     let commands_dir = get_commands_directory(&space_path);
-    create_command(&commands_dir, &command_name, &description, &template)
+    update_command(
+        &commands_dir,
+        &command_name,
+        new_description.as_deref(),
+        new_template.as_deref(),
+    )
+}
+
+/// Where a space's command files live (for debugging)
+#[tauri::command]
+pub fn get_commands_directory_path(space_path: String) -> Result<String, String> {
+    Ok(get_commands_directory(&space_path))
 }
 
 #[tauri::command]
@@ -264,6 +850,58 @@ pub fn delete_slash_command(space_path: String, command_name: String) -> Result<
     delete_command(&commands_dir, &command_name)
 }
 
+#[tauri::command]
+pub fn rename_slash_command(
+    space_path: String,
+    old_name: String,
+    new_name: String,
+    overwrite: bool,
+) -> Result<SlashCommand, String> {
+    let commands_dir = get_commands_directory(&space_path);
+    rename_command(&commands_dir, &old_name, &new_name, overwrite)
+}
+
+#[tauri::command]
+pub fn copy_slash_command(
+    source_space_path: String,
+    dest_space_path: String,
+    command_name: String,
+) -> Result<SlashCommand, String> {
+    copy_command(
+        &get_commands_directory(&source_space_path),
+        &get_commands_directory(&dest_space_path),
+        &command_name,
+    )
+}
+
+#[tauri::command]
+pub fn list_global_slash_commands() -> Result<Vec<SlashCommand>, String> {
+    load_global_commands()
+}
+
+#[tauri::command]
+pub fn create_global_slash_command(
+    command_name: String,
+    description: String,
+    template: String,
+    args: Option<Vec<ArgSpec>>,
+) -> Result<SlashCommand, String> {
+    let mut command = create_command(
+        &get_global_commands_directory()?,
+        &command_name,
+        &description,
+        &template,
+        args,
+    )?;
+    command.scope = CommandScope::GlobalOnly;
+    Ok(command)
+}
+
+#[tauri::command]
+pub fn delete_global_slash_command(command_name: String) -> Result<(), String> {
+    delete_command(&get_global_commands_directory()?, &command_name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -310,7 +948,8 @@ mod tests {
     #[test]
     fn test_expand_command_template() {
         let template = "Write a summary of $ARGUMENTS in 3 sentences.";
-        let expanded = expand_command_template(template, "quantum computing");
+        let expanded =
+            expand_command_template(template, "quantum computing", &HashMap::new()).unwrap();
 
         assert_eq!(
             expanded,
@@ -328,6 +967,7 @@ mod tests {
             "review",
             "Review code changes",
             "Please review the following code:\n\n$ARGUMENTS",
+            None,
         )
         .unwrap();
 
@@ -339,6 +979,226 @@ mod tests {
         assert!(file_path.exists());
     }
 
+    #[test]
+    fn test_parse_front_matter_args() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path();
+
+        let content = "---\ndescription: Translate text\nargs:\n  - name: text\n    type: text\n    required: true\n  - name: language\n    default: French\n  - name: count\n    type: number\n---\n# Translate\n\nTranslate ${text} into ${language}";
+        fs::write(dir_path.join("translate.md"), content).unwrap();
+
+        let command = load_command(dir_path.to_str().unwrap(), "translate").unwrap();
+
+        assert_eq!(command.description, "Translate text");
+        assert!(command.accepts_arguments);
+        assert_eq!(command.parsed_args.len(), 3);
+        assert_eq!(command.parsed_args[0].name, "text");
+        assert_eq!(command.parsed_args[0].arg_type, ArgType::Text);
+        assert!(command.parsed_args[0].required);
+        assert_eq!(command.parsed_args[1].default.as_deref(), Some("French"));
+        assert!(!command.parsed_args[1].required);
+        assert_eq!(command.parsed_args[2].arg_type, ArgType::Number);
+    }
+
+    #[test]
+    fn test_named_substitution_and_defaults() {
+        let template = "---\nargs:\n  - name: topic\n    required: true\n  - name: tone\n    default: friendly\n---\nWrite about ${topic} in a ${tone} tone. $ARGUMENTS";
+
+        let mut named = HashMap::new();
+        named.insert("topic".to_string(), "rust".to_string());
+
+        // Omitted argument uses its default; front-matter is stripped
+        let expanded = expand_command_template(template, "Be brief.", &named).unwrap();
+        assert_eq!(expanded, "Write about rust in a friendly tone. Be brief.");
+
+        named.insert("tone".to_string(), "formal".to_string());
+        let expanded = expand_command_template(template, "", &named).unwrap();
+        assert_eq!(expanded, "Write about rust in a formal tone. ");
+
+        // Missing required argument
+        assert!(expand_command_template(template, "", &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_create_command_with_args_round_trips() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+
+        let args = vec![ArgSpec {
+            name: "file".to_string(),
+            arg_type: ArgType::File,
+            required: true,
+            default: None,
+        }];
+        let created = create_command(
+            dir_path,
+            "outline",
+            "Outline a file",
+            "Outline ${file}",
+            Some(args.clone()),
+        )
+        .unwrap();
+        assert_eq!(created.parsed_args, args);
+
+        let loaded = load_command(dir_path, "outline").unwrap();
+        assert_eq!(loaded.description, "Outline a file");
+        assert_eq!(loaded.parsed_args, args);
+    }
+
+    #[test]
+    fn test_list_commands_merges_global_with_space_precedence() {
+        let temp_dir = TempDir::new().unwrap();
+        let space_dir = temp_dir.path().join("space");
+        let global_dir = temp_dir.path().join("global");
+        fs::create_dir_all(&space_dir).unwrap();
+        fs::create_dir_all(&global_dir).unwrap();
+
+        fs::write(space_dir.join("review.md"), "# Review\n\nSpace review").unwrap();
+        fs::write(global_dir.join("review.md"), "# Review\n\nGlobal review").unwrap();
+        fs::write(global_dir.join("standup.md"), "# Standup\n\nDaily standup").unwrap();
+
+        let space_dir = space_dir.to_str().unwrap();
+        let global_dir = global_dir.to_str().unwrap();
+
+        let all = list_commands_in(space_dir, global_dir, CommandScope::All).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].name, "review");
+        assert_eq!(all[0].description, "Space review");
+        assert_eq!(all[0].scope, CommandScope::SpaceOnly);
+        assert_eq!(all[1].name, "standup");
+        assert_eq!(all[1].scope, CommandScope::GlobalOnly);
+
+        let global = list_commands_in(space_dir, global_dir, CommandScope::GlobalOnly).unwrap();
+        assert_eq!(global.len(), 2);
+        assert!(global.iter().all(|c| c.scope == CommandScope::GlobalOnly));
+
+        // Global commands never show up under the space alone
+        let space = list_commands_in(space_dir, global_dir, CommandScope::SpaceOnly).unwrap();
+        assert_eq!(space.len(), 1);
+        assert!(!space.iter().any(|c| c.name == "standup"));
+    }
+
+    #[test]
+    fn test_missing_global_directory_is_empty() {
+        let temp_dir = TempDir::new().unwrap();
+        let global_dir = temp_dir.path().join("global");
+
+        let commands = load_global_commands_from(global_dir.to_str().unwrap()).unwrap();
+
+        // No sample commands are seeded for the global directory
+        assert!(commands.is_empty());
+        assert!(!global_dir.exists());
+    }
+
+    #[test]
+    fn test_rename_command() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path();
+        fs::write(dir_path.join("draft.md"), "# Draft\n\nDraft command").unwrap();
+        fs::write(dir_path.join("final.md"), "# Final\n\nFinal command").unwrap();
+        let dir = dir_path.to_str().unwrap();
+
+        let renamed = rename_command(dir, "draft", "first_draft", false).unwrap();
+        assert_eq!(renamed.name, "first_draft");
+        assert_eq!(renamed.description, "Draft command");
+        assert!(!dir_path.join("draft.md").exists());
+
+        // Existing names need overwrite
+        assert!(rename_command(dir, "first_draft", "final", false).is_err());
+        let overwritten = rename_command(dir, "first_draft", "final", true).unwrap();
+        assert_eq!(overwritten.description, "Draft command");
+        assert!(!dir_path.join("first_draft.md").exists());
+
+        // Invalid names
+        assert!(rename_command(dir, "final", "../escape", false).is_err());
+        assert!(rename_command(dir, "final", "has space", false).is_err());
+        assert!(rename_command(dir, "final", &"a".repeat(65), false).is_err());
+
+        // The old name can't reach outside the commands directory either
+        let commands_dir = dir_path.join("commands");
+        fs::create_dir(&commands_dir).unwrap();
+        fs::write(dir_path.join("outside.md"), "# Outside").unwrap();
+        let commands = commands_dir.to_str().unwrap();
+        assert!(rename_command(commands, "../outside", "inside", false).is_err());
+        assert!(dir_path.join("outside.md").exists());
+        assert!(!commands_dir.join("inside.md").exists());
+    }
+
+    #[test]
+    fn test_copy_command_adds_suffix() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("source");
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("review.md"), "# Review\n\nReview code").unwrap();
+        let (source, dest) = (source.to_str().unwrap(), dest.to_str().unwrap());
+
+        let first = copy_command(source, dest, "review").unwrap();
+        assert_eq!(first.name, "review");
+
+        let second = copy_command(source, dest, "review").unwrap();
+        assert_eq!(second.name, "review-1");
+
+        let third = copy_command(source, dest, "review").unwrap();
+        assert_eq!(third.name, "review-2");
+        assert_eq!(third.description, "Review code");
+
+        // Copying within the same directory also gets a suffix
+        let same = copy_command(source, source, "review").unwrap();
+        assert_eq!(same.name, "review-1");
+    }
+
+    #[test]
+    fn test_record_command_use_increments() {
+        let temp_dir = TempDir::new().unwrap();
+        let usage_path = temp_dir.path().join("command-usage.json");
+
+        record_command_use_in(&usage_path, "/spaces/a", "review").unwrap();
+        record_command_use_in(&usage_path, "/spaces/a", "review").unwrap();
+        record_command_use_in(&usage_path, "/spaces/a", "explain").unwrap();
+        record_command_use_in(&usage_path, "/spaces/b", "review").unwrap();
+
+        let stats = command_usage_stats_in(&usage_path, "/spaces/a");
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].command_name, "review");
+        assert_eq!(stats[0].use_count, 2);
+        assert!(stats[0].last_used_at > 0);
+        assert_eq!(stats[1].command_name, "explain");
+        assert_eq!(stats[1].use_count, 1);
+
+        let other = command_usage_stats_in(&usage_path, "/spaces/b");
+        assert_eq!(other.len(), 1);
+        assert_eq!(other[0].use_count, 1);
+
+        assert!(command_usage_stats_in(&usage_path, "/spaces/c").is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_command_use_does_not_corrupt() {
+        let temp_dir = TempDir::new().unwrap();
+        let usage_path = temp_dir.path().join("command-usage.json");
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let usage_path = usage_path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        record_command_use_in(&usage_path, "/spaces/a", "review").unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // File is still valid JSON and no increments were lost
+        let contents = fs::read_to_string(&usage_path).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&contents).is_ok());
+        let stats = command_usage_stats_in(&usage_path, "/spaces/a");
+        assert_eq!(stats[0].use_count, 200);
+    }
+
     #[test]
     fn test_delete_command() {
         let temp_dir = TempDir::new().unwrap();
@@ -362,6 +1222,22 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_command_names_cannot_escape_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let commands_dir = temp_dir.path().join("commands");
+        fs::create_dir(&commands_dir).unwrap();
+        fs::write(temp_dir.path().join("outside.md"), "# Outside").unwrap();
+        let commands = commands_dir.to_str().unwrap();
+
+        assert!(load_command(commands, "../outside").is_err());
+        assert!(create_command(commands, "../created", "Escape", "Body", None).is_err());
+        assert!(delete_command(commands, "../outside").is_err());
+
+        assert!(temp_dir.path().join("outside.md").exists());
+        assert!(!temp_dir.path().join("created.md").exists());
+    }
+
     #[test]
     fn test_load_multiple_commands() {
         let temp_dir = TempDir::new().unwrap();
@@ -380,4 +1256,159 @@ mod tests {
         assert_eq!(commands[1].name, "cmd2");
         assert_eq!(commands[2].name, "cmd3");
     }
+
+    #[test]
+    fn test_update_command_description_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        create_command(
+            dir_path,
+            "review",
+            "Review code changes",
+            "Please review:\n\n$ARGUMENTS\n",
+            None,
+        )
+        .unwrap();
+
+        let command =
+            update_command(dir_path, "review", Some("Review a pull request"), None).unwrap();
+
+        assert_eq!(command.description, "Review a pull request");
+        assert_eq!(
+            command.template,
+            "# review\n\nReview a pull request\n\nPlease review:\n\n$ARGUMENTS\n"
+        );
+        assert!(command.accepts_arguments);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("review.md")).unwrap(),
+            command.template
+        );
+    }
+
+    #[test]
+    fn test_update_command_template_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let content = "# Review\n\nReview code changes\nacross several lines\n\n\nOld template\n\nSecond part";
+        fs::write(temp_dir.path().join("review.md"), content).unwrap();
+
+        let command = update_command(dir_path, "review", None, Some("New template")).unwrap();
+
+        assert_eq!(
+            command.template,
+            "# Review\n\nReview code changes\nacross several lines\n\n\nNew template"
+        );
+        assert_eq!(command.description, "Review code changes");
+        assert!(!command.accepts_arguments);
+    }
+
+    #[test]
+    fn test_update_command_keeps_front_matter_args() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        let args = vec![ArgSpec {
+            name: "language".to_string(),
+            arg_type: ArgType::Text,
+            required: false,
+            default: Some("French".to_string()),
+        }];
+        create_command(
+            dir_path,
+            "translate",
+            "Translate text",
+            "Into ${language}",
+            Some(args.clone()),
+        )
+        .unwrap();
+
+        let command =
+            update_command(dir_path, "translate", Some("Translate anything"), None).unwrap();
+        assert_eq!(command.description, "Translate anything");
+        assert_eq!(command.parsed_args, args);
+        assert!(command.template.contains("Into ${language}"));
+
+        let command = update_command(dir_path, "translate", None, Some("To ${language}")).unwrap();
+        assert_eq!(command.description, "Translate anything");
+        assert_eq!(command.parsed_args, args);
+        assert!(command
+            .template
+            .ends_with("Translate anything\n\nTo ${language}"));
+    }
+
+    #[test]
+    fn test_update_command_heading_only_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        fs::write(temp_dir.path().join("bare.md"), "# Bare\n").unwrap();
+
+        let command =
+            update_command(dir_path, "bare", Some("Now described"), Some("Body")).unwrap();
+        assert_eq!(command.template, "# Bare\n\nNow described\n\nBody");
+
+        assert!(update_command(dir_path, "missing", Some("x"), None).is_err());
+        assert!(update_command(dir_path, "../bare", Some("x"), None).is_err());
+    }
+
+    #[test]
+    fn test_command_categories() {
+        let temp_dir = TempDir::new().unwrap();
+        let space_dir = temp_dir.path().join("space");
+        let global_dir = temp_dir.path().join("global");
+        fs::create_dir_all(&space_dir).unwrap();
+        fs::create_dir_all(&global_dir).unwrap();
+
+        fs::write(
+            space_dir.join("review.md"),
+            "---\ncategory: Writing\n---\n# Review\n\nReview a draft",
+        )
+        .unwrap();
+        fs::write(
+            space_dir.join("outline.md"),
+            "---\ncategory: \"  Writing  \"\n---\n# Outline\n\nOutline an essay",
+        )
+        .unwrap();
+        fs::write(space_dir.join("notes.md"), "# Notes\n\nTidy my notes").unwrap();
+        fs::write(
+            global_dir.join("standup.md"),
+            "---\ncategory: Meetings\n---\n# Standup\n\nDaily standup",
+        )
+        .unwrap();
+
+        let all = list_commands_in(
+            space_dir.to_str().unwrap(),
+            global_dir.to_str().unwrap(),
+            CommandScope::All,
+        )
+        .unwrap();
+
+        // Uncategorised commands are still listed
+        assert_eq!(all.len(), 4);
+        let notes = all.iter().find(|c| c.name == "notes").unwrap();
+        assert_eq!(notes.category, None);
+
+        assert_eq!(command_categories(&all), vec!["Meetings", "Writing"]);
+
+        let writing = commands_in_category(all.clone(), "Writing");
+        let names: Vec<&str> = writing.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["outline", "review"]);
+        assert!(commands_in_category(all, "Meetings")
+            .iter()
+            .all(|c| c.name != "notes"));
+    }
+
+    #[test]
+    fn test_update_command_keeps_category() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir_path = temp_dir.path().to_str().unwrap();
+        fs::write(
+            temp_dir.path().join("review.md"),
+            "---\ndescription: Review a draft\ncategory: Writing\n---\n# Review\n\nReview a draft",
+        )
+        .unwrap();
+
+        let updated = update_command(dir_path, "review", Some("Critique a draft"), None).unwrap();
+
+        assert_eq!(updated.description, "Critique a draft");
+        assert_eq!(updated.category.as_deref(), Some("Writing"));
+    }
 }
@@ -0,0 +1,107 @@
+// Where Thinking Space keeps its data, and configuration shared between modules
+
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// How to launch the ACP adapter process
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AcpConfig {
+    pub adapter_command: String,
+    pub adapter_args: Vec<String>,
+}
+
+impl Default for AcpConfig {
+    fn default() -> Self {
+        Self {
+            adapter_command: "npx".to_string(),
+            adapter_args: vec!["@zed-industries/claude-code-acp".to_string()],
+        }
+    }
+}
+
+/// Moves the data directory, e.g. to run a second instance, in CI,
+/// or inside a snap/flatpak sandbox
+pub const DATA_DIR_ENV_VAR: &str = "THINKING_SPACE_DATA_DIR";
+
+/// The data directory: $THINKING_SPACE_DATA_DIR if set, else ~/.thinking-space
+pub fn get_data_dir() -> Result<PathBuf, String> {
+    data_dir_from(std::env::var_os(DATA_DIR_ENV_VAR), dirs::home_dir())
+}
+
+fn data_dir_from(override_dir: Option<OsString>, home: Option<PathBuf>) -> Result<PathBuf, String> {
+    match override_dir {
+        Some(dir) if !dir.is_empty() => Ok(PathBuf::from(dir)),
+        _ => home
+            .map(|home| home.join(".thinking-space"))
+            .ok_or_else(|| "Could not find home directory".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Set in the child process test_data_dir_override_used_by_every_module starts
+    const CHILD_TEST_ENV_VAR: &str = "THINKING_SPACE_DATA_DIR_TEST_CHILD";
+
+    #[test]
+    fn test_data_dir_from() {
+        let home = Some(PathBuf::from("/home/user"));
+
+        assert_eq!(
+            data_dir_from(None, home.clone()).unwrap(),
+            PathBuf::from("/home/user/.thinking-space")
+        );
+        assert_eq!(
+            data_dir_from(Some("/data/ts".into()), home.clone()).unwrap(),
+            PathBuf::from("/data/ts")
+        );
+        // An empty override is ignored
+        assert_eq!(
+            data_dir_from(Some("".into()), home).unwrap(),
+            PathBuf::from("/home/user/.thinking-space")
+        );
+        assert!(data_dir_from(None, None).is_err());
+    }
+
+    #[test]
+    fn test_data_dir_override_used_by_every_module() {
+        if std::env::var_os(CHILD_TEST_ENV_VAR).is_some() {
+            crate::spaces::get_spaces_dir().unwrap();
+            let conversations_db = crate::conversations::get_db_path().unwrap();
+            crate::conversations::get_connection_at(&conversations_db).unwrap();
+            let sessions_db = crate::sessions::get_db_path().unwrap();
+            crate::sessions::get_connection_at(&sessions_db).unwrap();
+            crate::settings::save_settings_from(None, crate::settings::Settings::default())
+                .unwrap();
+            crate::mcp_config::McpConfig::load_global()
+                .unwrap()
+                .save_global()
+                .unwrap();
+            return;
+        }
+
+        // The override is process-wide, so the modules are exercised in a child
+        // test process instead of racing the tests that use the real directory
+        let data_dir = tempfile::tempdir().unwrap();
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "config::tests::test_data_dir_override_used_by_every_module",
+                "--quiet",
+            ])
+            .env(DATA_DIR_ENV_VAR, data_dir.path())
+            .env(CHILD_TEST_ENV_VAR, "1")
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let data_dir = data_dir.path();
+        assert!(data_dir.join("spaces").is_dir());
+        assert!(data_dir.join("conversations.db").is_file());
+        assert!(data_dir.join("sessions.db").is_file());
+        assert!(data_dir.join("settings.json").is_file());
+        assert!(data_dir.join("global-mcp.json").is_file());
+    }
+}
@@ -1,18 +1,132 @@
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::error::ThinkingSpaceError;
 
 /// A single message in a conversation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub id: String,
-    pub role: String, // "user" or "assistant"
+    pub role: String, // "user", "assistant" or "summary"
     pub content: String,
     pub timestamp: i64,
-    #[serde(default)]
+    #[serde(default = "empty_metadata", deserialize_with = "metadata_or_empty")]
     pub metadata: serde_json::Value,
 }
 
+/// Messages sent without metadata get an empty object rather than null
+fn empty_metadata() -> serde_json::Value {
+    serde_json::Value::Object(serde_json::Map::new())
+}
+
+/// Older conversations stored `"metadata": null`; read it as an empty object
+fn metadata_or_empty<'de, D>(deserializer: D) -> Result<serde_json::Value, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(if value.is_null() {
+        empty_metadata()
+    } else {
+        value
+    })
+}
+
+/// Conversations need this many messages before a title is generated
+const MIN_MESSAGES_FOR_TITLE: usize = 5;
+
+/// Generated titles are cut down to this many words
+const MAX_TITLE_WORDS: usize = 8;
+
+/// Orphaned conversations are cleaned up at startup at most this often
+const ORPHAN_CLEANUP_INTERVAL_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Longest allowed conversation name, in characters
+const MAX_CONVERSATION_NAME_CHARS: usize = 256;
+
+/// Snapshots kept per space; the oldest are deleted beyond this
+const MAX_SNAPSHOTS_PER_SPACE: i64 = 10;
+
+/// Message content in CSV exports is cut to this many characters
+const MAX_CSV_CONTENT_CHARS: usize = 10_000;
+
+/// Columns written for each message in a CSV export
+const CSV_COLUMNS: &[&str] = &[
+    "message_id",
+    "role",
+    "timestamp_iso",
+    "content_length",
+    "content",
+    "tool_call_count",
+    "model",
+];
+
+/// Top-level metadata keys the backend knows about; others are kept but logged
+const KNOWN_METADATA_KEYS: &[&str] = &[
+    "toolCalls",
+    "files",
+    "duration_ms",
+    "model",
+    "usage",
+    "auto_summarised",
+    "original_message_count",
+];
+
+/// Message metadata, guaranteed to be a JSON object
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MessageMetadata(serde_json::Value);
+
+impl MessageMetadata {
+    pub fn as_value(&self) -> &serde_json::Value {
+        &self.0
+    }
+}
+
+impl TryFrom<serde_json::Value> for MessageMetadata {
+    type Error = String;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        if value.is_null() {
+            Ok(Self(empty_metadata()))
+        } else if value.is_object() {
+            Ok(Self(value))
+        } else {
+            Err(format!(
+                "metadata must be an object, got {}",
+                json_type_name(&value)
+            ))
+        }
+    }
+}
+
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+/// Check that message metadata is an object (or null, read as an empty object)
+/// Keys the backend doesn't know are allowed, so newer frontends can add their own
+pub fn validate_metadata(value: &serde_json::Value) -> Result<(), Vec<String>> {
+    let metadata = MessageMetadata::try_from(value.clone()).map_err(|e| vec![e])?;
+
+    if let Some(object) = metadata.as_value().as_object() {
+        for key in object.keys() {
+            if !KNOWN_METADATA_KEYS.contains(&key.as_str()) {
+                println!("[CONVERSATIONS] Unknown metadata key '{}'", key);
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// A complete conversation for a Space
 #[derive(Debug, Serialize, Deserialize)]
 struct Conversation {
@@ -34,19 +148,19 @@ impl Conversation {
 }
 
 /// Get the path to the conversations database
-fn get_db_path() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let thinking_space_dir = home.join(".thinking-space");
+pub(crate) fn get_db_path() -> Result<PathBuf, ThinkingSpaceError> {
+    let thinking_space_dir = crate::config::get_data_dir()
+        .map_err(|_| ThinkingSpaceError::not_found("Home directory"))?;
 
     // Create directory if it doesn't exist
     std::fs::create_dir_all(&thinking_space_dir)
-        .map_err(|e| format!("Failed to create .thinking-space directory: {}", e))?;
+        .map_err(|e| ThinkingSpaceError::io(&thinking_space_dir, e))?;
 
     Ok(thinking_space_dir.join("conversations.db"))
 }
 
 /// Initialize the database with the conversations table
-fn init_database(conn: &Connection) -> Result<(), String> {
+fn init_database(conn: &Connection) -> Result<(), ThinkingSpaceError> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS conversations (
             space_id TEXT PRIMARY KEY,
@@ -57,7 +171,9 @@ fn init_database(conn: &Connection) -> Result<(), String> {
         ) STRICT",
         [],
     )
-    .map_err(|e| format!("Failed to create conversations table: {}", e))?;
+    .map_err(|e| {
+        ThinkingSpaceError::Database(format!("Failed to create conversations table: {}", e))
+    })?;
 
     // Create index on updated_at for efficient sorting in list_conversations
     // This improves performance when displaying conversation history
@@ -66,66 +182,273 @@ fn init_database(conn: &Connection) -> Result<(), String> {
          ON conversations(updated_at DESC)",
         [],
     )
-    .map_err(|e| format!("Failed to create index: {}", e))?;
+    .map_err(|e| ThinkingSpaceError::Database(format!("Failed to create index: {}", e)))?;
+
+    // Generated titles were added later; older databases need the column
+    let has_title: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('conversations') WHERE name = 'title'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| {
+            ThinkingSpaceError::Database(format!("Failed to inspect conversations table: {}", e))
+        })?;
+    if has_title == 0 {
+        conn.execute("ALTER TABLE conversations ADD COLUMN title TEXT", [])
+            .map_err(|e| {
+                ThinkingSpaceError::Database(format!("Failed to add title column: {}", e))
+            })?;
+    }
+
+    // Earlier versions of each conversation, for undo
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS conversation_snapshots (
+            snapshot_id INTEGER PRIMARY KEY,
+            space_id TEXT NOT NULL,
+            snapshot_at INTEGER NOT NULL,
+            data BLOB NOT NULL
+        ) STRICT",
+        [],
+    )
+    .map_err(|e| {
+        ThinkingSpaceError::Database(format!(
+            "Failed to create conversation_snapshots table: {}",
+            e
+        ))
+    })?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_conversation_snapshots_space_id
+         ON conversation_snapshots(space_id, snapshot_id DESC)",
+        [],
+    )
+    .map_err(|e| ThinkingSpaceError::Database(format!("Failed to create index: {}", e)))?;
 
     Ok(())
 }
 
-/// Get a connection to the database
-fn get_connection() -> Result<Connection, String> {
-    let db_path = get_db_path()?;
-    let conn = Connection::open(&db_path).map_err(|e| format!("Failed to open database: {}", e))?;
-
-    init_database(&conn)?;
+/// Get a connection to the database, for callers outside the async commands
+pub(crate) fn get_connection() -> Result<Connection, ThinkingSpaceError> {
+    get_connection_at(&get_db_path()?)
+}
 
+/// Get a connection to the database at `db_path`
+pub(crate) fn get_connection_at(db_path: &Path) -> Result<Connection, ThinkingSpaceError> {
+    let conn = Connection::open(db_path)
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to open database: {}", e)))?;
+    prepare_connection(&conn)?;
     Ok(conn)
 }
 
+/// Connection shared by the async commands, opened and initialized on first use
+static SHARED_CONNECTION: tokio::sync::OnceCell<tokio_rusqlite::Connection> =
+    tokio::sync::OnceCell::const_new();
+
+/// The database connection for async commands
+/// It runs on its own thread, so commands don't block while SQLite works
+async fn shared_connection() -> Result<&'static tokio_rusqlite::Connection, ThinkingSpaceError> {
+    SHARED_CONNECTION
+        .get_or_try_init(|| async {
+            let db_path = get_db_path()?;
+            let conn = tokio_rusqlite::Connection::open(&db_path)
+                .await
+                .map_err(|e| {
+                    ThinkingSpaceError::Database(format!("Failed to open database: {}", e))
+                })?;
+            conn.call(|conn| Ok(prepare_connection(conn))).await??;
+            Ok(conn)
+        })
+        .await
+}
+
+fn prepare_connection(conn: &Connection) -> Result<(), ThinkingSpaceError> {
+    crate::db::configure_connection(conn).map_err(ThinkingSpaceError::Database)?;
+    init_database(conn)
+}
+
 /// Save a conversation to the database
+///
+/// Assistant messages may carry the tokens they consumed as
+/// `metadata.usage = { "input_tokens": N, "output_tokens": N }`, which
+/// `get_conversation_token_summary` totals up.
 #[tauri::command]
-pub fn save_conversation(
+pub async fn save_conversation(
     space_id: String,
     space_name: String,
     messages: Vec<Message>,
-) -> Result<(), String> {
-    let conn = get_connection()?;
+) -> Result<(), ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(move |conn| {
+        Ok(save_conversation_internal(
+            conn,
+            &space_id,
+            &space_name,
+            &messages,
+        ))
+    })
+    .await?
+}
+
+/// Save several conversations in one transaction; none are saved if any fails
+/// Returns the number of conversation rows written
+#[tauri::command]
+pub async fn save_conversation_batch(
+    entries: Vec<(String, String, Vec<Message>)>,
+) -> Result<usize, ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(move |conn| Ok(save_conversation_batch_internal(conn, &entries)))
+        .await?
+}
+
+fn save_conversation_batch_internal(
+    conn: &mut Connection,
+    entries: &[(String, String, Vec<Message>)],
+) -> Result<usize, ThinkingSpaceError> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to start transaction: {}", e)))?;
+
+    let mut rows = 0;
+    for (space_id, space_name, messages) in entries {
+        rows += store_conversation(&tx, space_id, space_name, messages)?;
+    }
+
+    tx.commit().map_err(|e| {
+        ThinkingSpaceError::Database(format!("Failed to commit conversations: {}", e))
+    })?;
+    Ok(rows)
+}
+
+/// Internal helper to save a conversation using an existing connection
+pub(crate) fn save_conversation_internal(
+    conn: &Connection,
+    space_id: &str,
+    space_name: &str,
+    messages: &[Message],
+) -> Result<(), ThinkingSpaceError> {
+    store_conversation(conn, space_id, space_name, messages).map(|_| ())
+}
+
+/// Validate, snapshot and upsert one conversation, returning the rows written
+fn store_conversation(
+    conn: &Connection,
+    space_id: &str,
+    space_name: &str,
+    messages: &[Message],
+) -> Result<usize, ThinkingSpaceError> {
+    let violations: Vec<String> = messages
+        .iter()
+        .filter_map(|message| {
+            validate_metadata(&message.metadata)
+                .err()
+                .map(|errors| format!("{}: {}", message.id, errors.join(", ")))
+        })
+        .collect();
+
+    if !violations.is_empty() {
+        return Err(ThinkingSpaceError::validation(
+            "metadata",
+            format!("Invalid message metadata: {}", violations.join("; ")),
+        ));
+    }
 
     // Create conversation structure
-    let conversation = Conversation::new(space_id.clone(), messages.clone());
+    let conversation = Conversation::new(space_id.to_string(), messages.to_vec());
 
     // Serialize to JSON
-    let data = serde_json::to_vec(&conversation)
-        .map_err(|e| format!("Failed to serialize conversation: {}", e))?;
+    let data = serde_json::to_vec(&conversation).map_err(|e| {
+        ThinkingSpaceError::Serialization(format!("Failed to serialize conversation: {}", e))
+    })?;
+
+    snapshot_conversation(conn, space_id, &data)?;
 
     // Get current timestamp
     let now = chrono::Utc::now().to_rfc3339();
 
-    // Upsert (insert or replace)
+    // Upsert, keeping columns that save doesn't manage (e.g. title)
+    let rows = conn
+        .execute(
+            "INSERT INTO conversations (space_id, space_name, updated_at, message_count, data)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(space_id) DO UPDATE SET
+            space_name = excluded.space_name,
+            updated_at = excluded.updated_at,
+            message_count = excluded.message_count,
+            data = excluded.data",
+            params![space_id, space_name, now, messages.len() as i64, data,],
+        )
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to save conversation: {}", e)))?;
+
+    Ok(rows)
+}
+
+/// Keep a copy of the stored conversation before it's replaced by `new_data`
+/// Empty or unchanged conversations aren't snapshotted
+fn snapshot_conversation(
+    conn: &Connection,
+    space_id: &str,
+    new_data: &[u8],
+) -> Result<(), ThinkingSpaceError> {
+    let existing = conn.query_row(
+        "SELECT data, message_count FROM conversations WHERE space_id = ?1",
+        params![space_id],
+        |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, i64>(1)?)),
+    );
+
+    let data = match existing {
+        Ok((data, message_count)) if message_count >= 1 && data != new_data => data,
+        Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(()),
+        Err(e) => {
+            return Err(ThinkingSpaceError::Database(format!(
+                "Failed to read conversation: {}",
+                e
+            )))
+        }
+    };
+
     conn.execute(
-        "INSERT OR REPLACE INTO conversations (space_id, space_name, updated_at, message_count, data)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![
-            space_id,
-            space_name,
-            now,
-            messages.len() as i64,
-            data,
-        ],
+        "INSERT INTO conversation_snapshots (space_id, snapshot_at, data) VALUES (?1, ?2, ?3)",
+        params![space_id, chrono::Utc::now().timestamp_millis(), data],
+    )
+    .map_err(|e| {
+        ThinkingSpaceError::Database(format!("Failed to save conversation snapshot: {}", e))
+    })?;
+
+    conn.execute(
+        "DELETE FROM conversation_snapshots
+         WHERE space_id = ?1 AND snapshot_id NOT IN (
+            SELECT snapshot_id FROM conversation_snapshots
+            WHERE space_id = ?1
+            ORDER BY snapshot_id DESC
+            LIMIT ?2
+         )",
+        params![space_id, MAX_SNAPSHOTS_PER_SPACE],
     )
-    .map_err(|e| format!("Failed to save conversation: {}", e))?;
+    .map_err(|e| {
+        ThinkingSpaceError::Database(format!("Failed to prune conversation snapshots: {}", e))
+    })?;
 
     Ok(())
 }
 
 /// Load a conversation from the database
 #[tauri::command]
-pub fn load_conversation(space_id: String) -> Result<Vec<Message>, String> {
-    let conn = get_connection()?;
+pub async fn load_conversation(space_id: String) -> Result<Vec<Message>, ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(move |conn| Ok(load_conversation_internal(conn, &space_id)))
+        .await?
+}
 
+/// Internal helper to load a conversation using an existing connection
+fn load_conversation_internal(
+    conn: &Connection,
+    space_id: &str,
+) -> Result<Vec<Message>, ThinkingSpaceError> {
     // Query for the conversation
     let mut stmt = conn
         .prepare("SELECT data FROM conversations WHERE space_id = ?1")
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to prepare query: {}", e)))?;
 
     let result = stmt.query_row(params![space_id], |row| {
         let data: Vec<u8> = row.get(0)?;
@@ -135,8 +458,12 @@ pub fn load_conversation(space_id: String) -> Result<Vec<Message>, String> {
     match result {
         Ok(data) => {
             // Deserialize the conversation
-            let conversation: Conversation = serde_json::from_slice(&data)
-                .map_err(|e| format!("Failed to deserialize conversation: {}", e))?;
+            let conversation: Conversation = serde_json::from_slice(&data).map_err(|e| {
+                ThinkingSpaceError::Serialization(format!(
+                    "Failed to deserialize conversation: {}",
+                    e
+                ))
+            })?;
 
             Ok(conversation.messages)
         }
@@ -144,286 +471,2611 @@ pub fn load_conversation(space_id: String) -> Result<Vec<Message>, String> {
             // No conversation yet, return empty
             Ok(Vec::new())
         }
-        Err(e) => Err(format!("Failed to load conversation: {}", e)),
+        Err(e) => Err(ThinkingSpaceError::Database(format!(
+            "Failed to load conversation: {}",
+            e
+        ))),
     }
 }
 
 /// Delete a conversation from the database
 #[tauri::command]
-pub fn delete_conversation(space_id: String) -> Result<(), String> {
-    let conn = get_connection()?;
+pub async fn delete_conversation(space_id: String) -> Result<(), ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(move |conn| Ok(delete_conversation_internal(conn, &space_id)))
+        .await?
+}
 
+pub(crate) fn delete_conversation_internal(
+    conn: &Connection,
+    space_id: &str,
+) -> Result<(), ThinkingSpaceError> {
     conn.execute(
         "DELETE FROM conversations WHERE space_id = ?1",
         params![space_id],
     )
-    .map_err(|e| format!("Failed to delete conversation: {}", e))?;
+    .map_err(|e| ThinkingSpaceError::Database(format!("Failed to delete conversation: {}", e)))?;
+
+    conn.execute(
+        "DELETE FROM conversation_snapshots WHERE space_id = ?1",
+        params![space_id],
+    )
+    .map_err(|e| {
+        ThinkingSpaceError::Database(format!("Failed to delete conversation snapshots: {}", e))
+    })?;
 
     Ok(())
 }
 
-/// Get metadata about all conversations (for future use)
+/// Remove one message from a conversation, returning how many are left
+/// Removing the last message deletes the whole conversation unless `allow_empty`
+#[tauri::command]
+pub async fn delete_conversation_message(
+    space_id: String,
+    message_id: String,
+    allow_empty: bool,
+) -> Result<usize, ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(move |conn| {
+        Ok(delete_conversation_message_internal(
+            conn,
+            &space_id,
+            &message_id,
+            allow_empty,
+        ))
+    })
+    .await?
+}
+
+fn delete_conversation_message_internal(
+    conn: &Connection,
+    space_id: &str,
+    message_id: &str,
+    allow_empty: bool,
+) -> Result<usize, ThinkingSpaceError> {
+    let mut messages = load_conversation_internal(conn, space_id)?;
+    let index = messages
+        .iter()
+        .position(|message| message.id == message_id)
+        .ok_or_else(|| {
+            ThinkingSpaceError::not_found(format!(
+                "Message {} in conversation for space {}",
+                message_id, space_id
+            ))
+        })?;
+    messages.remove(index);
+
+    if messages.is_empty() && !allow_empty {
+        delete_conversation_internal(conn, space_id)?;
+        return Ok(0);
+    }
+
+    let space_name: String = conn
+        .query_row(
+            "SELECT space_name FROM conversations WHERE space_id = ?1",
+            params![space_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| {
+            ThinkingSpaceError::Database(format!("Failed to look up conversation: {}", e))
+        })?;
+
+    save_conversation_internal(conn, space_id, &space_name, &messages)?;
+    Ok(messages.len())
+}
+
+/// A saved earlier version of a conversation
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub id: i64,
+    /// When the snapshot was taken (Unix ms)
+    pub timestamp: i64,
+    pub message_count: usize,
+}
+
+/// Earlier versions of a conversation, newest first
 #[tauri::command]
-pub fn list_conversations() -> Result<Vec<ConversationMetadata>, String> {
-    let conn = get_connection()?;
+pub async fn list_conversation_snapshots(
+    space_id: String,
+) -> Result<Vec<SnapshotInfo>, ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(move |conn| Ok(list_conversation_snapshots_internal(conn, &space_id)))
+        .await?
+}
 
+fn list_conversation_snapshots_internal(
+    conn: &Connection,
+    space_id: &str,
+) -> Result<Vec<SnapshotInfo>, ThinkingSpaceError> {
     let mut stmt = conn
-        .prepare("SELECT space_id, space_name, updated_at, message_count FROM conversations ORDER BY updated_at DESC")
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .prepare(
+            "SELECT snapshot_id, snapshot_at, data FROM conversation_snapshots
+             WHERE space_id = ?1
+             ORDER BY snapshot_id DESC",
+        )
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to prepare query: {}", e)))?;
 
     let rows = stmt
-        .query_map([], |row| {
-            Ok(ConversationMetadata {
-                space_id: row.get(0)?,
-                space_name: row.get(1)?,
-                updated_at: row.get(2)?,
-                message_count: row.get(3)?,
+        .query_map(params![space_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Vec<u8>>(2)?,
+            ))
+        })
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to query snapshots: {}", e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to read row: {}", e)))?;
+
+    rows.into_iter()
+        .map(|(id, timestamp, data)| {
+            let conversation: Conversation = serde_json::from_slice(&data).map_err(|e| {
+                ThinkingSpaceError::Serialization(format!("Failed to deserialize snapshot: {}", e))
+            })?;
+            Ok(SnapshotInfo {
+                id,
+                timestamp,
+                message_count: conversation.messages.len(),
             })
         })
-        .map_err(|e| format!("Failed to query conversations: {}", e))?;
+        .collect()
+}
 
-    let mut conversations = Vec::new();
-    for conversation in rows {
-        conversations.push(conversation.map_err(|e| format!("Failed to read row: {}", e))?);
-    }
+/// Put a snapshot's messages back as the conversation
+/// The conversation being replaced is itself snapshotted, so a restore can be undone
+#[tauri::command]
+pub async fn restore_conversation_snapshot(
+    space_id: String,
+    snapshot_id: i64,
+) -> Result<Vec<Message>, ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(move |conn| {
+        Ok(restore_conversation_snapshot_internal(
+            conn,
+            &space_id,
+            snapshot_id,
+        ))
+    })
+    .await?
+}
 
-    Ok(conversations)
+fn restore_conversation_snapshot_internal(
+    conn: &Connection,
+    space_id: &str,
+    snapshot_id: i64,
+) -> Result<Vec<Message>, ThinkingSpaceError> {
+    let data: Vec<u8> = conn
+        .query_row(
+            "SELECT data FROM conversation_snapshots WHERE space_id = ?1 AND snapshot_id = ?2",
+            params![space_id, snapshot_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => ThinkingSpaceError::not_found(format!(
+                "Snapshot {} for space {}",
+                snapshot_id, space_id
+            )),
+            e => ThinkingSpaceError::Database(format!("Failed to load snapshot: {}", e)),
+        })?;
+
+    let conversation: Conversation = serde_json::from_slice(&data).map_err(|e| {
+        ThinkingSpaceError::Serialization(format!("Failed to deserialize snapshot: {}", e))
+    })?;
+
+    let space_name: String = conn
+        .query_row(
+            "SELECT space_name FROM conversations WHERE space_id = ?1",
+            params![space_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| {
+            ThinkingSpaceError::Database(format!("Failed to look up conversation: {}", e))
+        })?;
+
+    save_conversation_internal(conn, space_id, &space_name, &conversation.messages)?;
+    Ok(conversation.messages)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ConversationMetadata {
-    pub space_id: String,
-    pub space_name: String,
-    pub updated_at: String,
-    pub message_count: i64,
+/// Rename a conversation, keeping the space's own name in sync
+#[tauri::command]
+pub async fn rename_conversation(
+    space_id: String,
+    new_name: String,
+) -> Result<(), ThinkingSpaceError> {
+    let spaces_dir = crate::spaces::get_spaces_dir()?;
+    let conn = shared_connection().await?;
+    conn.call(move |conn| {
+        Ok(rename_conversation_internal(
+            conn,
+            &spaces_dir,
+            &space_id,
+            &new_name,
+        ))
+    })
+    .await?
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::NamedTempFile;
+pub(crate) fn conversation_exists_internal(
+    conn: &Connection,
+    space_id: &str,
+) -> Result<bool, ThinkingSpaceError> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM conversations WHERE space_id = ?1)",
+        params![space_id],
+        |row| row.get::<_, bool>(0),
+    )
+    .map_err(|e| ThinkingSpaceError::Database(format!("Failed to look up conversation: {}", e)))
+}
 
-    fn setup_test_db() -> (Connection, tempfile::TempPath) {
-        let temp_file = NamedTempFile::new().unwrap();
-        let temp_path = temp_file.into_temp_path();
-        let conn = Connection::open(&temp_path).unwrap();
-        init_database(&conn).unwrap();
-        (conn, temp_path)
+/// Internal helper to rename a conversation using an existing connection
+pub(crate) fn rename_conversation_internal(
+    conn: &Connection,
+    spaces_dir: &Path,
+    space_id: &str,
+    new_name: &str,
+) -> Result<(), ThinkingSpaceError> {
+    crate::spaces::validate_space_id(space_id)?;
+    if new_name.trim().is_empty() {
+        return Err(ThinkingSpaceError::validation(
+            "name",
+            "Conversation name cannot be empty",
+        ));
+    }
+    if new_name.chars().count() > MAX_CONVERSATION_NAME_CHARS {
+        return Err(ThinkingSpaceError::validation(
+            "name",
+            format!(
+                "Conversation name cannot be longer than {} characters",
+                MAX_CONVERSATION_NAME_CHARS
+            ),
+        ));
     }
 
-    #[test]
-    fn test_database_initialization() {
-        let (conn, _temp) = setup_test_db();
-
-        // Check that the table exists
-        let table_exists: bool = conn
-            .query_row(
-                "SELECT 1 FROM sqlite_master WHERE type='table' AND name='conversations'",
-                [],
-                |_| Ok(true),
-            )
-            .unwrap_or(false);
+    let updated = conn
+        .execute(
+            "UPDATE conversations SET space_name = ?1 WHERE space_id = ?2",
+            params![new_name, space_id],
+        )
+        .map_err(|e| {
+            ThinkingSpaceError::Database(format!("Failed to rename conversation: {}", e))
+        })?;
+
+    if updated == 0 {
+        return Err(ThinkingSpaceError::not_found(format!(
+            "Conversation for space {}",
+            space_id
+        )));
+    }
 
-        assert!(table_exists);
+    // The conversation can outlive its space, so only update metadata that exists
+    if spaces_dir
+        .join(space_id)
+        .join(".space-metadata.json")
+        .exists()
+    {
+        crate::spaces::update_space_metadata_in(spaces_dir, space_id, |space| {
+            space.name = new_name.to_string()
+        })?;
     }
 
-    #[test]
-    fn test_database_index_created() {
-        let (conn, _temp) = setup_test_db();
+    Ok(())
+}
 
-        // Check that the index exists
-        let index_exists: bool = conn
-            .query_row(
-                "SELECT 1 FROM sqlite_master WHERE type='index' AND name='idx_conversations_updated_at'",
-                [],
-                |_| Ok(true),
-            )
-            .unwrap_or(false);
+/// Set a conversation's title by hand
+#[tauri::command]
+pub async fn set_conversation_title(
+    space_id: String,
+    title: String,
+) -> Result<(), ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(move |conn| Ok(set_conversation_title_internal(conn, &space_id, &title)))
+        .await?
+}
 
-        assert!(index_exists, "Database index should be created");
+/// Internal helper to store a title using an existing connection
+fn set_conversation_title_internal(
+    conn: &Connection,
+    space_id: &str,
+    title: &str,
+) -> Result<(), ThinkingSpaceError> {
+    if title.trim().is_empty() {
+        return Err(ThinkingSpaceError::validation(
+            "title",
+            "Conversation title cannot be empty",
+        ));
     }
 
-    #[test]
-    fn test_save_and_load_conversation() {
-        let (_conn, _temp) = setup_test_db();
+    let updated = conn
+        .execute(
+            "UPDATE conversations SET title = ?1 WHERE space_id = ?2",
+            params![title.trim(), space_id],
+        )
+        .map_err(|e| {
+            ThinkingSpaceError::Database(format!("Failed to set conversation title: {}", e))
+        })?;
+
+    if updated == 0 {
+        return Err(ThinkingSpaceError::not_found(format!(
+            "Conversation for space {}",
+            space_id
+        )));
+    }
 
-        let messages = vec![
-            Message {
-                id: "msg-1".to_string(),
-                role: "user".to_string(),
-                content: "Hello".to_string(),
-                timestamp: 1234567890,
-                metadata: serde_json::json!({}),
-            },
-            Message {
-                id: "msg-2".to_string(),
-                role: "assistant".to_string(),
-                content: "Hi there!".to_string(),
-                timestamp: 1234567891,
-                metadata: serde_json::json!({}),
-            },
-        ];
+    Ok(())
+}
 
-        // Save conversation
-        let save_result =
-            save_conversation("test-space".to_string(), "Test Space".to_string(), messages.clone());
+/// The title stored for a conversation, if it has one
+pub(crate) async fn conversation_title(
+    space_id: &str,
+) -> Result<Option<String>, ThinkingSpaceError> {
+    let space_id = space_id.to_string();
+    let conn = shared_connection().await?;
+    conn.call(move |conn| Ok(conversation_title_internal(conn, &space_id)))
+        .await?
+}
 
-        assert!(save_result.is_ok());
+fn conversation_title_internal(
+    conn: &Connection,
+    space_id: &str,
+) -> Result<Option<String>, ThinkingSpaceError> {
+    match conn.query_row(
+        "SELECT title FROM conversations WHERE space_id = ?1",
+        params![space_id],
+        |row| row.get(0),
+    ) {
+        Ok(title) => Ok(title),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(ThinkingSpaceError::Database(format!(
+            "Failed to load conversation title: {}",
+            e
+        ))),
+    }
+}
 
-        // Load conversation
-        let loaded = load_conversation("test-space".to_string()).unwrap();
+/// The prompt used to summarise a conversation into a title
+pub(crate) async fn conversation_title_prompt(
+    space_id: &str,
+) -> Result<String, ThinkingSpaceError> {
+    let space_id = space_id.to_string();
+    let conn = shared_connection().await?;
+    conn.call(move |conn| Ok(conversation_title_prompt_internal(conn, &space_id)))
+        .await?
+}
 
-        assert_eq!(loaded.len(), 2);
-        assert_eq!(loaded[0].id, "msg-1");
-        assert_eq!(loaded[0].role, "user");
-        assert_eq!(loaded[0].content, "Hello");
-        assert_eq!(loaded[1].id, "msg-2");
-        assert_eq!(loaded[1].role, "assistant");
+fn conversation_title_prompt_internal(
+    conn: &Connection,
+    space_id: &str,
+) -> Result<String, ThinkingSpaceError> {
+    let messages = load_conversation_internal(conn, space_id)?;
+    if messages.len() < MIN_MESSAGES_FOR_TITLE {
+        return Err(ThinkingSpaceError::validation(
+            "messages",
+            format!("A title needs at least {} messages", MIN_MESSAGES_FOR_TITLE),
+        ));
     }
 
-    #[test]
-    fn test_load_nonexistent_conversation() {
-        let (_conn, _temp) = setup_test_db();
+    let first_prompt = messages
+        .iter()
+        .find(|message| message.role == "user" && !message.content.trim().is_empty())
+        .ok_or_else(|| {
+            ThinkingSpaceError::validation("messages", "Conversation has no user messages")
+        })?;
+
+    Ok(format!(
+        "Summarise this prompt in ≤8 words: {}",
+        first_prompt.content.trim()
+    ))
+}
 
-        let loaded = load_conversation("nonexistent-space".to_string()).unwrap();
+/// Clean up the agent's answer and save it as the conversation's title
+pub(crate) async fn store_generated_title(
+    space_id: &str,
+    answer: &str,
+) -> Result<String, ThinkingSpaceError> {
+    let (space_id, answer) = (space_id.to_string(), answer.to_string());
+    let conn = shared_connection().await?;
+    conn.call(move |conn| Ok(store_generated_title_internal(conn, &space_id, &answer)))
+        .await?
+}
 
-        assert_eq!(loaded.len(), 0);
-    }
+fn store_generated_title_internal(
+    conn: &Connection,
+    space_id: &str,
+    answer: &str,
+) -> Result<String, ThinkingSpaceError> {
+    let title = clean_generated_title(answer)
+        .ok_or_else(|| ThinkingSpaceError::validation("title", "Agent returned an empty title"))?;
+    set_conversation_title_internal(conn, space_id, &title)?;
+    Ok(title)
+}
 
-    #[test]
-    fn test_update_conversation() {
-        let (_conn, _temp) = setup_test_db();
+/// First non-empty line, without wrapping quotes or a trailing full stop,
+/// and at most MAX_TITLE_WORDS words
+fn clean_generated_title(answer: &str) -> Option<String> {
+    let line = answer
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())?;
+    let line = line
+        .trim_matches(|c| c == '"' || c == '\'' || c == '*')
+        .trim_end_matches('.')
+        .trim();
+
+    let title = line
+        .split_whitespace()
+        .take(MAX_TITLE_WORDS)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (!title.is_empty()).then_some(title)
+}
 
-        let messages_v1 = vec![Message {
-            id: "msg-1".to_string(),
-            role: "user".to_string(),
-            content: "First message".to_string(),
-            timestamp: 1234567890,
-            metadata: serde_json::json!({}),
-        }];
+/// How merge_conversations orders the combined messages
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MergeStrategy {
+    /// Source messages follow the target's last message
+    AppendToEnd,
+    /// All messages ordered by timestamp
+    InterleavedByTimestamp,
+}
 
-        save_conversation(
-            "test-space".to_string(),
-            "Test Space".to_string(),
-            messages_v1,
-        )
-        .unwrap();
+/// Copy another space's messages into a conversation, leaving the source untouched
+/// Returns the target's new message count
+#[tauri::command]
+pub async fn merge_conversations(
+    source_space_id: String,
+    target_space_id: String,
+    strategy: MergeStrategy,
+) -> Result<usize, ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(move |conn| {
+        let result = crate::sessions::get_db_path()
+            .and_then(|db_path| crate::sessions::get_connection_at(&db_path))
+            .map_err(ThinkingSpaceError::Database)
+            .and_then(|sessions| {
+                merge_conversations_internal(
+                    conn,
+                    &sessions,
+                    &source_space_id,
+                    &target_space_id,
+                    strategy,
+                )
+            });
+        Ok(result)
+    })
+    .await?
+}
 
-        // Update with more messages
-        let messages_v2 = vec![
-            Message {
-                id: "msg-1".to_string(),
-                role: "user".to_string(),
-                content: "First message".to_string(),
-                timestamp: 1234567890,
-                metadata: serde_json::json!({}),
-            },
-            Message {
-                id: "msg-2".to_string(),
-                role: "assistant".to_string(),
-                content: "Second message".to_string(),
-                timestamp: 1234567891,
-                metadata: serde_json::json!({}),
-            },
-        ];
+fn merge_conversations_internal(
+    conn: &Connection,
+    sessions: &Connection,
+    source_space_id: &str,
+    target_space_id: &str,
+    strategy: MergeStrategy,
+) -> Result<usize, ThinkingSpaceError> {
+    if source_space_id == target_space_id {
+        return Err(ThinkingSpaceError::validation(
+            "target_space_id",
+            "Cannot merge a conversation into itself",
+        ));
+    }
 
-        save_conversation(
-            "test-space".to_string(),
-            "Test Space".to_string(),
-            messages_v2,
-        )
-        .unwrap();
+    // A running agent would keep writing to a conversation mid-merge
+    for space_id in [source_space_id, target_space_id] {
+        if crate::sessions::has_active_session_internal(sessions, space_id)
+            .map_err(ThinkingSpaceError::Database)?
+        {
+            return Err(ThinkingSpaceError::validation(
+                "space_id",
+                format!(
+                    "Space {} has an active session; end it before merging",
+                    space_id
+                ),
+            ));
+        }
+    }
 
-        let loaded = load_conversation("test-space".to_string()).unwrap();
-        assert_eq!(loaded.len(), 2);
+    for space_id in [source_space_id, target_space_id] {
+        if !conversation_exists_internal(conn, space_id)? {
+            return Err(ThinkingSpaceError::not_found(format!(
+                "Conversation for space {}",
+                space_id
+            )));
+        }
+    }
+
+    let mut messages = load_conversation_internal(conn, target_space_id)?;
+    let source = load_conversation_internal(conn, source_space_id)?;
+
+    // Messages already in the target (e.g. from an earlier merge) aren't copied twice
+    let existing: std::collections::HashSet<String> =
+        messages.iter().map(|m| m.id.clone()).collect();
+    messages.extend(source.into_iter().filter(|m| !existing.contains(&m.id)));
+
+    if strategy == MergeStrategy::InterleavedByTimestamp {
+        // Stable, so messages with equal timestamps keep their relative order
+        messages.sort_by_key(|m| m.timestamp);
+    }
+
+    let total = messages.len();
+    let data = serde_json::to_vec(&Conversation::new(target_space_id.to_string(), messages))
+        .map_err(|e| {
+            ThinkingSpaceError::Serialization(format!("Failed to serialize conversation: {}", e))
+        })?;
+
+    conn.execute(
+        "UPDATE conversations SET data = ?1, message_count = ?2, updated_at = ?3
+         WHERE space_id = ?4",
+        params![
+            data,
+            total as i64,
+            chrono::Utc::now().to_rfc3339(),
+            target_space_id
+        ],
+    )
+    .map_err(|e| {
+        ThinkingSpaceError::Database(format!("Failed to save merged conversation: {}", e))
+    })?;
+
+    Ok(total)
+}
+
+/// How two conversations differ, compared by message ID
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConversationDiff {
+    /// Messages only in the second conversation, in its order
+    pub added: Vec<Message>,
+    /// Messages only in the first conversation, in its order
+    pub removed: Vec<Message>,
+    /// Messages present in both
+    pub common_count: usize,
+}
+
+/// Compare two conversations (e.g. a fork and its original)
+#[tauri::command]
+pub async fn diff_conversations(
+    space_id_a: String,
+    space_id_b: String,
+) -> Result<ConversationDiff, ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(move |conn| Ok(diff_conversations_internal(conn, &space_id_a, &space_id_b)))
+        .await?
+}
+
+fn diff_conversations_internal(
+    conn: &Connection,
+    space_id_a: &str,
+    space_id_b: &str,
+) -> Result<ConversationDiff, ThinkingSpaceError> {
+    let a = load_conversation_internal(conn, space_id_a)?;
+    let b = load_conversation_internal(conn, space_id_b)?;
+    Ok(diff_messages(a, b))
+}
+
+fn diff_messages(a: Vec<Message>, b: Vec<Message>) -> ConversationDiff {
+    let ids_a: std::collections::HashSet<String> = a.iter().map(|m| m.id.clone()).collect();
+    let ids_b: std::collections::HashSet<String> = b.iter().map(|m| m.id.clone()).collect();
+
+    let common_count = ids_a.intersection(&ids_b).count();
+    let removed = a.into_iter().filter(|m| !ids_b.contains(&m.id)).collect();
+    let added = b.into_iter().filter(|m| !ids_a.contains(&m.id)).collect();
+
+    ConversationDiff {
+        added,
+        removed,
+        common_count,
+    }
+}
+
+/// Counts from a Claude.ai import
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportStats {
+    pub conversations_processed: usize,
+    pub messages_imported: usize,
+    /// Malformed messages, and messages already in the conversation
+    pub skipped: usize,
+}
+
+/// A message as it appears in a Claude.ai export
+#[derive(Debug, Deserialize)]
+struct ClaudeAiMessage {
+    uuid: String,
+    sender: String,
+    text: String,
+    created_at: String,
+}
+
+/// Convert a Claude.ai export message, or None if it can't be represented
+fn claude_ai_message(value: serde_json::Value) -> Option<Message> {
+    let message: ClaudeAiMessage = serde_json::from_value(value).ok()?;
+    let role = match message.sender.as_str() {
+        "human" => "user",
+        "assistant" => "assistant",
+        _ => return None,
+    };
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&message.created_at)
+        .ok()?
+        .timestamp_millis();
+
+    Some(Message {
+        id: message.uuid,
+        role: role.to_string(),
+        content: message.text,
+        timestamp,
+        metadata: empty_metadata(),
+    })
+}
+
+/// Import conversations from a Claude.ai data export into a space's conversation
+#[tauri::command]
+pub async fn import_from_claude_ai_export(
+    json_bytes: Vec<u8>,
+    target_space_id: String,
+) -> Result<ImportStats, ThinkingSpaceError> {
+    let space = crate::spaces::list_spaces(Some(true))?
+        .into_iter()
+        .find(|space| space.id == target_space_id)
+        .ok_or_else(|| ThinkingSpaceError::not_found(format!("Space {}", target_space_id)))?;
+
+    let conn = shared_connection().await?;
+    let stats = conn
+        .call(move |conn| {
+            Ok(import_from_claude_ai_export_internal(
+                conn,
+                &json_bytes,
+                &target_space_id,
+                &space.name,
+            ))
+        })
+        .await??;
+    println!(
+        "[CONVERSATIONS] Imported {} messages from {} Claude.ai conversations ({} skipped)",
+        stats.messages_imported, stats.conversations_processed, stats.skipped
+    );
+    Ok(stats)
+}
+
+fn import_from_claude_ai_export_internal(
+    conn: &Connection,
+    json_bytes: &[u8],
+    space_id: &str,
+    space_name: &str,
+) -> Result<ImportStats, ThinkingSpaceError> {
+    let conversations: Vec<serde_json::Value> =
+        serde_json::from_slice(json_bytes).map_err(|e| {
+            ThinkingSpaceError::Serialization(format!("Failed to parse Claude.ai export: {}", e))
+        })?;
+
+    let mut messages = load_conversation_internal(conn, space_id)?;
+    let mut seen: std::collections::HashSet<String> =
+        messages.iter().map(|m| m.id.clone()).collect();
+    let mut stats = ImportStats::default();
+
+    for conversation in conversations {
+        let chat_messages = match conversation.get("chat_messages") {
+            Some(serde_json::Value::Array(chat_messages)) => chat_messages.clone(),
+            _ => continue,
+        };
+        stats.conversations_processed += 1;
+
+        for value in chat_messages {
+            match claude_ai_message(value) {
+                // Re-importing the same export doesn't duplicate messages
+                Some(message) if seen.insert(message.id.clone()) => {
+                    messages.push(message);
+                    stats.messages_imported += 1;
+                }
+                _ => stats.skipped += 1,
+            }
+        }
+    }
+
+    if stats.messages_imported > 0 {
+        // Stable, so messages with equal timestamps keep their export order
+        messages.sort_by_key(|m| m.timestamp);
+        save_conversation_internal(conn, space_id, space_name, &messages)?;
+    }
+
+    Ok(stats)
+}
+
+/// One CSV row for a message (see CSV_COLUMNS)
+fn csv_record(message: &Message) -> Vec<String> {
+    // Timestamps come from Date.now(), so they're in milliseconds
+    let timestamp_iso = chrono::DateTime::from_timestamp_millis(message.timestamp)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default();
+
+    let content = if message.content.chars().count() > MAX_CSV_CONTENT_CHARS {
+        let truncated: String = message
+            .content
+            .chars()
+            .take(MAX_CSV_CONTENT_CHARS)
+            .collect();
+        format!("{}…", truncated)
+    } else {
+        message.content.clone()
+    };
+
+    let tool_call_count = message
+        .metadata
+        .get("toolCalls")
+        .and_then(|calls| calls.as_array())
+        .map(|calls| calls.len())
+        .unwrap_or(0);
+    let model = message
+        .metadata
+        .get("model")
+        .and_then(|model| model.as_str())
+        .unwrap_or_default();
+
+    vec![
+        message.id.clone(),
+        message.role.clone(),
+        timestamp_iso,
+        message.content.chars().count().to_string(),
+        content,
+        tool_call_count.to_string(),
+        model.to_string(),
+    ]
+}
+
+fn create_csv_writer(output_path: &Path) -> Result<csv::Writer<std::fs::File>, ThinkingSpaceError> {
+    csv::Writer::from_path(output_path).map_err(|e| ThinkingSpaceError::io(output_path, e))
+}
+
+/// Write a conversation's messages to a CSV file for spreadsheet analysis
+/// Returns the number of message rows written
+#[tauri::command]
+pub async fn export_conversation_csv(
+    space_id: String,
+    output_path: String,
+) -> Result<usize, ThinkingSpaceError> {
+    let home_dir =
+        dirs::home_dir().ok_or_else(|| ThinkingSpaceError::not_found("Home directory"))?;
+    let output_path = crate::spaces::resolve_within(Path::new(&output_path), &home_dir)?;
+
+    let conn = shared_connection().await?;
+    let (rows, space_id, output_path) = conn
+        .call(move |conn| {
+            let rows = export_conversation_csv_internal(conn, &space_id, &output_path);
+            Ok(rows.map(|rows| (rows, space_id, output_path)))
+        })
+        .await??;
+
+    println!(
+        "[CONVERSATIONS] Exported {} message(s) from {} to {}",
+        rows,
+        space_id,
+        output_path.display()
+    );
+    Ok(rows)
+}
+
+fn export_conversation_csv_internal(
+    conn: &Connection,
+    space_id: &str,
+    output_path: &Path,
+) -> Result<usize, ThinkingSpaceError> {
+    let messages = load_conversation_internal(conn, space_id)?;
+
+    let mut writer = create_csv_writer(output_path)?;
+    writer
+        .write_record(CSV_COLUMNS)
+        .map_err(|e| ThinkingSpaceError::io(output_path, e))?;
+    for message in &messages {
+        writer
+            .write_record(csv_record(message))
+            .map_err(|e| ThinkingSpaceError::io(output_path, e))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| ThinkingSpaceError::io(output_path, e))?;
+
+    Ok(messages.len())
+}
+
+/// Write every conversation to one CSV file, with a leading space_id column
+/// Returns the number of message rows written
+#[tauri::command]
+pub async fn export_all_conversations_csv(
+    output_path: String,
+) -> Result<usize, ThinkingSpaceError> {
+    let home_dir =
+        dirs::home_dir().ok_or_else(|| ThinkingSpaceError::not_found("Home directory"))?;
+    let output_path = crate::spaces::resolve_within(Path::new(&output_path), &home_dir)?;
+
+    let conn = shared_connection().await?;
+    let (rows, output_path) = conn
+        .call(move |conn| {
+            let rows = export_all_conversations_csv_internal(conn, &output_path);
+            Ok(rows.map(|rows| (rows, output_path)))
+        })
+        .await??;
+
+    println!(
+        "[CONVERSATIONS] Exported {} message(s) from all conversations to {}",
+        rows,
+        output_path.display()
+    );
+    Ok(rows)
+}
+
+fn export_all_conversations_csv_internal(
+    conn: &Connection,
+    output_path: &Path,
+) -> Result<usize, ThinkingSpaceError> {
+    let mut stmt = conn
+        .prepare("SELECT space_id FROM conversations ORDER BY updated_at DESC")
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to prepare query: {}", e)))?;
+    let space_ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to query conversations: {}", e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to read row: {}", e)))?;
+
+    let mut writer = create_csv_writer(output_path)?;
+    writer
+        .write_record(std::iter::once(&"space_id").chain(CSV_COLUMNS))
+        .map_err(|e| ThinkingSpaceError::io(output_path, e))?;
+
+    let mut rows = 0;
+    for space_id in &space_ids {
+        for message in load_conversation_internal(conn, space_id)? {
+            let mut record = csv_record(&message);
+            record.insert(0, space_id.clone());
+            writer
+                .write_record(record)
+                .map_err(|e| ThinkingSpaceError::io(output_path, e))?;
+            rows += 1;
+        }
+    }
+    writer
+        .flush()
+        .map_err(|e| ThinkingSpaceError::io(output_path, e))?;
+
+    Ok(rows)
+}
+
+/// Delete conversations whose space directory no longer exists
+#[tauri::command]
+pub async fn cleanup_orphaned_conversations() -> Result<usize, ThinkingSpaceError> {
+    let spaces_dir = crate::spaces::get_spaces_dir()?;
+    let conn = shared_connection().await?;
+    let removed = conn
+        .call(move |conn| Ok(cleanup_orphaned_conversations_internal(conn, &spaces_dir)))
+        .await??;
+
+    println!(
+        "[CONVERSATIONS] Removed {} orphaned conversation(s)",
+        removed
+    );
+    Ok(removed)
+}
+
+/// Internal helper to remove orphaned conversations using an existing connection
+fn cleanup_orphaned_conversations_internal(
+    conn: &Connection,
+    spaces_dir: &Path,
+) -> Result<usize, ThinkingSpaceError> {
+    let mut stmt = conn
+        .prepare("SELECT space_id FROM conversations")
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to prepare query: {}", e)))?;
+
+    let space_ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to query conversations: {}", e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to read row: {}", e)))?;
+
+    // A missing, unreadable or empty spaces directory more likely means it isn't
+    // mounted or the data dir moved than that every space was deleted
+    if !space_ids.is_empty() && !has_any_space(spaces_dir) {
+        eprintln!(
+            "[CONVERSATIONS] Skipping orphan cleanup: no spaces found in {} but {} conversation(s) stored",
+            spaces_dir.display(),
+            space_ids.len()
+        );
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+    for space_id in space_ids {
+        if crate::spaces::space_exists_in(spaces_dir, &space_id) {
+            continue;
+        }
+
+        println!(
+            "[CONVERSATIONS] Removing orphaned conversation for missing space {}",
+            space_id
+        );
+        removed += conn
+            .execute(
+                "DELETE FROM conversations WHERE space_id = ?1",
+                params![space_id],
+            )
+            .map_err(|e| {
+                ThinkingSpaceError::Database(format!("Failed to delete conversation: {}", e))
+            })?;
+        conn.execute(
+            "DELETE FROM conversation_snapshots WHERE space_id = ?1",
+            params![space_id],
+        )
+        .map_err(|e| {
+            ThinkingSpaceError::Database(format!("Failed to delete conversation snapshots: {}", e))
+        })?;
+    }
+
+    Ok(removed)
+}
+
+/// Whether `spaces_dir` exists and contains at least one space directory
+fn has_any_space(spaces_dir: &Path) -> bool {
+    std::fs::read_dir(spaces_dir).is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+    })
+}
+
+/// Whether the startup cleanup should run, given when it last ran
+fn orphan_cleanup_due(last_cleaned_at: Option<i64>, now: i64) -> bool {
+    match last_cleaned_at {
+        Some(last) => now - last >= ORPHAN_CLEANUP_INTERVAL_SECS,
+        None => true,
+    }
+}
+
+/// Run the orphaned conversation cleanup if it hasn't run in the last week
+pub fn cleanup_orphaned_conversations_if_due(app: &tauri::AppHandle) {
+    let mut settings = match crate::settings::load_settings() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!(
+                "[CONVERSATIONS] Skipping cleanup, could not load settings: {}",
+                e
+            );
+            return;
+        }
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    if !orphan_cleanup_due(settings.last_cleaned_at, now) {
+        return;
+    }
+
+    // Runs on its own thread at startup, so it can wait for the async command
+    if let Err(e) = tauri::async_runtime::block_on(cleanup_orphaned_conversations()) {
+        eprintln!(
+            "[CONVERSATIONS] Orphaned conversation cleanup failed: {}",
+            e
+        );
+        return;
+    }
+
+    settings.last_cleaned_at = Some(now);
+    if let Err(e) = crate::settings::save_settings_from(Some(app), settings) {
+        eprintln!("[CONVERSATIONS] Failed to record cleanup time: {}", e);
+    }
+}
+
+/// Message counts and time range for one conversation
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct MessageStats {
+    pub total: usize,
+    pub user: usize,
+    pub assistant: usize,
+    pub first_at: Option<i64>,
+    pub last_at: Option<i64>,
+}
+
+pub(crate) fn message_stats_internal(
+    conn: &Connection,
+    space_id: &str,
+) -> Result<MessageStats, ThinkingSpaceError> {
+    let messages = load_conversation_internal(conn, space_id)?;
+
+    Ok(MessageStats {
+        total: messages.len(),
+        user: messages.iter().filter(|m| m.role == "user").count(),
+        assistant: messages.iter().filter(|m| m.role == "assistant").count(),
+        first_at: messages.iter().map(|m| m.timestamp).min(),
+        last_at: messages.iter().map(|m| m.timestamp).max(),
+    })
+}
+
+/// Get metadata about all conversations (for future use)
+#[tauri::command]
+pub async fn list_conversations() -> Result<Vec<ConversationMetadata>, ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(|conn| Ok(list_conversations_internal(conn)))
+        .await?
+}
+
+fn list_conversations_internal(
+    conn: &Connection,
+) -> Result<Vec<ConversationMetadata>, ThinkingSpaceError> {
+    let mut stmt = conn
+        .prepare("SELECT space_id, space_name, updated_at, message_count, title FROM conversations ORDER BY updated_at DESC")
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to prepare query: {}", e)))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(ConversationMetadata {
+                space_id: row.get(0)?,
+                space_name: row.get(1)?,
+                updated_at: row.get(2)?,
+                message_count: row.get(3)?,
+                title: row.get(4)?,
+            })
+        })
+        .map_err(|e| {
+            ThinkingSpaceError::Database(format!("Failed to query conversations: {}", e))
+        })?;
+
+    let mut conversations = Vec::new();
+    for conversation in rows {
+        conversations.push(
+            conversation
+                .map_err(|e| ThinkingSpaceError::Database(format!("Failed to read row: {}", e)))?,
+        );
+    }
+
+    Ok(conversations)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConversationMetadata {
+    pub space_id: String,
+    pub space_name: String,
+    pub updated_at: String,
+    pub message_count: i64,
+    /// Short generated (or user-set) title, if any
+    pub title: Option<String>,
+}
+
+/// Token totals across all messages in a conversation
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenSummary {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    /// How many messages carried usage metadata
+    pub messages_with_usage: usize,
+}
+
+/// Sum the token usage recorded on a conversation's messages
+#[tauri::command]
+pub async fn get_conversation_token_summary(
+    space_id: String,
+) -> Result<TokenSummary, ThinkingSpaceError> {
+    let messages = load_conversation(space_id).await?;
+    Ok(summarize_token_usage(&messages))
+}
+
+fn summarize_token_usage(messages: &[Message]) -> TokenSummary {
+    let mut summary = TokenSummary::default();
+
+    for message in messages {
+        let Some(usage) = message.metadata.get("usage") else {
+            continue;
+        };
+        let count = |key: &str| usage.get(key).and_then(serde_json::Value::as_u64);
+        let input = count("input_tokens");
+        let output = count("output_tokens");
+        if input.is_none() && output.is_none() {
+            continue;
+        }
+
+        summary.input_tokens += input.unwrap_or(0);
+        summary.output_tokens += output.unwrap_or(0);
+        summary.messages_with_usage += 1;
+    }
+
+    summary.total_tokens = summary.input_tokens + summary.output_tokens;
+    summary
+}
+
+/// Result of a conversations database integrity check
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatabaseIntegrityReport {
+    pub ok: bool,
+    pub issues: Vec<String>,
+    pub conversation_count: usize,
+    pub total_message_count: usize,
+}
+
+/// Database size before and after a VACUUM
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VacuumResult {
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+    pub freed_bytes: u64,
+}
+
+/// Check the conversations database for corruption
+#[tauri::command]
+pub async fn check_database_integrity() -> Result<DatabaseIntegrityReport, ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    conn.call(|conn| Ok(check_database_integrity_internal(conn)))
+        .await?
+}
+
+/// Internal helper to run an integrity check using an existing connection
+fn check_database_integrity_internal(
+    conn: &Connection,
+) -> Result<DatabaseIntegrityReport, ThinkingSpaceError> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check").map_err(|e| {
+        ThinkingSpaceError::Database(format!("Failed to prepare integrity check: {}", e))
+    })?;
+
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| {
+            ThinkingSpaceError::Database(format!("Failed to run integrity check: {}", e))
+        })?;
+
+    // A healthy database reports a single "ok" row
+    let mut issues = Vec::new();
+    for row in rows {
+        let line = row.map_err(|e| {
+            ThinkingSpaceError::Database(format!("Failed to read integrity check row: {}", e))
+        })?;
+        if line != "ok" {
+            issues.push(line);
+        }
+    }
+
+    let (conversation_count, total_message_count): (i64, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(message_count), 0) FROM conversations",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| {
+            ThinkingSpaceError::Database(format!("Failed to count conversations: {}", e))
+        })?;
+
+    Ok(DatabaseIntegrityReport {
+        ok: issues.is_empty(),
+        issues,
+        conversation_count: conversation_count as usize,
+        total_message_count: total_message_count as usize,
+    })
+}
+
+/// Reclaim unused space in the conversations database
+#[tauri::command]
+pub async fn vacuum_database() -> Result<VacuumResult, ThinkingSpaceError> {
+    let conn = shared_connection().await?;
+    let result = conn
+        .call(|conn| Ok(vacuum_database_internal(conn)))
+        .await??;
+
+    println!(
+        "[CONVERSATIONS] Vacuumed database, freed {} bytes",
+        result.freed_bytes
+    );
+
+    Ok(result)
+}
+
+/// Internal helper to VACUUM using an existing connection
+fn vacuum_database_internal(conn: &Connection) -> Result<VacuumResult, ThinkingSpaceError> {
+    let before_bytes = database_size_bytes(conn)?;
+
+    conn.execute_batch("VACUUM")
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to vacuum database: {}", e)))?;
+
+    let after_bytes = database_size_bytes(conn)?;
+
+    Ok(VacuumResult {
+        before_bytes,
+        after_bytes,
+        freed_bytes: before_bytes.saturating_sub(after_bytes),
+    })
+}
+
+/// Size of the database file as SQLite sees it (page_count * page_size)
+fn database_size_bytes(conn: &Connection) -> Result<u64, ThinkingSpaceError> {
+    let page_count: i64 = conn
+        .pragma_query_value(None, "page_count", |row| row.get(0))
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to read page count: {}", e)))?;
+    let page_size: i64 = conn
+        .pragma_query_value(None, "page_size", |row| row.get(0))
+        .map_err(|e| ThinkingSpaceError::Database(format!("Failed to read page size: {}", e)))?;
+
+    Ok((page_count * page_size) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn setup_test_db() -> (Connection, tempfile::TempPath) {
+        let temp_file = NamedTempFile::new().unwrap();
+        let temp_path = temp_file.into_temp_path();
+        let conn = Connection::open(&temp_path).unwrap();
+        init_database(&conn).unwrap();
+        (conn, temp_path)
+    }
+
+    #[test]
+    fn test_database_initialization() {
+        let (conn, _temp) = setup_test_db();
+
+        // Check that the table exists
+        let table_exists: bool = conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type='table' AND name='conversations'",
+                [],
+                |_| Ok(true),
+            )
+            .unwrap_or(false);
+
+        assert!(table_exists);
+    }
+
+    #[test]
+    fn test_database_index_created() {
+        let (conn, _temp) = setup_test_db();
+
+        // Check that the index exists
+        let index_exists: bool = conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type='index' AND name='idx_conversations_updated_at'",
+                [],
+                |_| Ok(true),
+            )
+            .unwrap_or(false);
+
+        assert!(index_exists, "Database index should be created");
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_conversation() {
+        let (_conn, _temp) = setup_test_db();
+
+        let messages = vec![
+            Message {
+                id: "msg-1".to_string(),
+                role: "user".to_string(),
+                content: "Hello".to_string(),
+                timestamp: 1234567890,
+                metadata: serde_json::json!({}),
+            },
+            Message {
+                id: "msg-2".to_string(),
+                role: "assistant".to_string(),
+                content: "Hi there!".to_string(),
+                timestamp: 1234567891,
+                metadata: serde_json::json!({}),
+            },
+        ];
+
+        // Save conversation
+        let save_result =
+            save_conversation("test-space".to_string(), "Test Space".to_string(), messages.clone())
+                .await;
+
+        assert!(save_result.is_ok());
+
+        // Load conversation
+        let loaded = load_conversation("test-space".to_string()).await.unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].id, "msg-1");
+        assert_eq!(loaded[0].role, "user");
+        assert_eq!(loaded[0].content, "Hello");
+        assert_eq!(loaded[1].id, "msg-2");
+        assert_eq!(loaded[1].role, "assistant");
+    }
+
+    #[tokio::test]
+    async fn test_load_nonexistent_conversation() {
+        let (_conn, _temp) = setup_test_db();
+
+        let loaded = load_conversation("nonexistent-space".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(loaded.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_update_conversation() {
+        let (_conn, _temp) = setup_test_db();
+
+        let messages_v1 = vec![Message {
+            id: "msg-1".to_string(),
+            role: "user".to_string(),
+            content: "First message".to_string(),
+            timestamp: 1234567890,
+            metadata: serde_json::json!({}),
+        }];
+
+        save_conversation(
+            "test-space".to_string(),
+            "Test Space".to_string(),
+            messages_v1,
+        )
+        .await
+        .unwrap();
+
+        // Update with more messages
+        let messages_v2 = vec![
+            Message {
+                id: "msg-1".to_string(),
+                role: "user".to_string(),
+                content: "First message".to_string(),
+                timestamp: 1234567890,
+                metadata: serde_json::json!({}),
+            },
+            Message {
+                id: "msg-2".to_string(),
+                role: "assistant".to_string(),
+                content: "Second message".to_string(),
+                timestamp: 1234567891,
+                metadata: serde_json::json!({}),
+            },
+        ];
+
+        save_conversation(
+            "test-space".to_string(),
+            "Test Space".to_string(),
+            messages_v2,
+        )
+        .await
+        .unwrap();
+
+        let loaded = load_conversation("test-space".to_string()).await.unwrap();
+        assert_eq!(loaded.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_delete_conversation() {
+        let (_conn, _temp) = setup_test_db();
+
+        let messages = vec![Message {
+            id: "msg-1".to_string(),
+            role: "user".to_string(),
+            content: "Test".to_string(),
+            timestamp: 1234567890,
+            metadata: serde_json::json!({}),
+        }];
+
+        save_conversation("test-space".to_string(), "Test".to_string(), messages)
+            .await
+            .unwrap();
+
+        // Verify it exists
+        let loaded_before = load_conversation("test-space".to_string()).await.unwrap();
+        assert_eq!(loaded_before.len(), 1);
+
+        // Delete
+        delete_conversation("test-space".to_string()).await.unwrap();
+
+        // Verify it's gone
+        let loaded_after = load_conversation("test-space".to_string()).await.unwrap();
+        assert_eq!(loaded_after.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_list_conversations() {
+        let (_conn, _temp) = setup_test_db();
+
+        // Create multiple conversations
+        save_conversation(
+            "space-1".to_string(),
+            "Space 1".to_string(),
+            vec![Message {
+                id: "msg-1".to_string(),
+                role: "user".to_string(),
+                content: "Test".to_string(),
+                timestamp: 1234567890,
+                metadata: serde_json::json!({}),
+            }],
+        )
+        .await
+        .unwrap();
+
+        save_conversation(
+            "space-2".to_string(),
+            "Space 2".to_string(),
+            vec![Message {
+                id: "msg-2".to_string(),
+                role: "user".to_string(),
+                content: "Test 2".to_string(),
+                timestamp: 1234567891,
+                metadata: serde_json::json!({}),
+            }],
+        )
+        .await
+        .unwrap();
+
+        let conversations = list_conversations().await.unwrap();
+
+        assert_eq!(conversations.len(), 2);
+        // Should be sorted by updated_at DESC (most recent first)
+        assert_eq!(conversations[0].space_id, "space-2");
+        assert_eq!(conversations[1].space_id, "space-1");
+    }
+
+    #[tokio::test]
+    async fn test_message_metadata_preservation() {
+        let (_conn, _temp) = setup_test_db();
+
+        let metadata = serde_json::json!({
+            "toolCalls": ["ls", "cat file.txt"],
+            "files": ["/path/to/file.txt"]
+        });
+
+        let messages = vec![Message {
+            id: "msg-1".to_string(),
+            role: "user".to_string(),
+            content: "Test with metadata".to_string(),
+            timestamp: 1234567890,
+            metadata: metadata.clone(),
+        }];
+
+        save_conversation("test-space".to_string(), "Test".to_string(), messages)
+            .await
+            .unwrap();
+
+        let loaded = load_conversation("test-space".to_string()).await.unwrap();
+
+        assert_eq!(loaded[0].metadata, metadata);
+    }
+
+    fn test_message(id: &str, content: &str) -> Message {
+        Message {
+            id: id.to_string(),
+            role: "user".to_string(),
+            content: content.to_string(),
+            timestamp: 1234567890,
+            metadata: serde_json::json!({}),
+        }
+    }
+
+    #[test]
+    fn test_integrity_check_counts_conversations() {
+        let (conn, _temp) = setup_test_db();
+
+        save_conversation_internal(
+            &conn,
+            "space-1",
+            "Space 1",
+            &[test_message("msg-1", "a"), test_message("msg-2", "b")],
+        )
+        .unwrap();
+        save_conversation_internal(&conn, "space-2", "Space 2", &[test_message("msg-3", "c")])
+            .unwrap();
+
+        let report = check_database_integrity_internal(&conn).unwrap();
+
+        assert!(report.ok);
+        assert!(report.issues.is_empty());
+        assert_eq!(report.conversation_count, 2);
+        assert_eq!(report.total_message_count, 3);
+    }
+
+    #[test]
+    fn test_integrity_check_empty_database() {
+        let (conn, _temp) = setup_test_db();
+
+        let report = check_database_integrity_internal(&conn).unwrap();
+
+        assert!(report.ok);
+        assert_eq!(report.conversation_count, 0);
+        assert_eq!(report.total_message_count, 0);
+    }
+
+    #[test]
+    fn test_vacuum_frees_space_after_delete() {
+        let (conn, _temp) = setup_test_db();
+
+        let large = "x".repeat(64 * 1024);
+        for i in 0..20 {
+            let space_id = format!("space-{}", i);
+            save_conversation_internal(&conn, &space_id, "Space", &[test_message("msg", &large)])
+                .unwrap();
+        }
+
+        conn.execute("DELETE FROM conversations", []).unwrap();
+
+        let result = vacuum_database_internal(&conn).unwrap();
+
+        assert!(result.freed_bytes > 0);
+        assert_eq!(result.freed_bytes, result.before_bytes - result.after_bytes);
+    }
+
+    #[test]
+    fn test_validate_metadata_accepts_known_keys() {
+        let metadata = serde_json::json!({
+            "toolCalls": [],
+            "files": ["notes.md"],
+            "duration_ms": 1200,
+            "model": "claude-sonnet",
+        });
+
+        assert!(validate_metadata(&metadata).is_ok());
+        assert!(validate_metadata(&serde_json::json!({})).is_ok());
+    }
+
+    #[test]
+    fn test_validate_metadata_accepts_null_as_empty() {
+        assert!(validate_metadata(&serde_json::Value::Null).is_ok());
+        assert_eq!(
+            MessageMetadata::try_from(serde_json::Value::Null)
+                .unwrap()
+                .as_value(),
+            &serde_json::json!({})
+        );
+    }
+
+    #[test]
+    fn test_validate_metadata_rejects_array() {
+        let errors = validate_metadata(&serde_json::json!(["files"])).unwrap_err();
+
+        assert_eq!(errors, vec!["metadata must be an object, got array"]);
+    }
+
+    #[test]
+    fn test_validate_metadata_allows_unknown_keys() {
+        let metadata = serde_json::json!({ "files": [], "color": "red", "mood": 1 });
+
+        assert!(validate_metadata(&metadata).is_ok());
+    }
+
+    #[test]
+    fn test_save_rejects_invalid_metadata() {
+        let (conn, _temp) = setup_test_db();
+
+        let mut message = test_message("msg-1", "Hello");
+        message.metadata = serde_json::json!(["unexpected"]);
+
+        let err = save_conversation_internal(&conn, "space-1", "Space", &[message]).unwrap_err();
+
+        assert!(
+            matches!(&err, ThinkingSpaceError::ValidationFailed { field, .. } if field == "metadata")
+        );
+        assert!(err.to_string().contains("msg-1"));
+        assert!(err.to_string().contains("got array"));
+
+        let report = check_database_integrity_internal(&conn).unwrap();
+        assert_eq!(report.conversation_count, 0);
+    }
+
+    #[test]
+    fn test_missing_metadata_defaults_to_empty_object() {
+        let message: Message = serde_json::from_value(serde_json::json!({
+            "id": "msg-1",
+            "role": "user",
+            "content": "Hello",
+            "timestamp": 1234567890,
+        }))
+        .unwrap();
+
+        assert_eq!(message.metadata, serde_json::json!({}));
+        assert!(validate_metadata(&message.metadata).is_ok());
+    }
+
+    #[test]
+    fn test_null_metadata_from_older_conversations_still_saves() {
+        let (conn, _temp) = setup_test_db();
+        let message: Message = serde_json::from_value(serde_json::json!({
+            "id": "msg-1",
+            "role": "user",
+            "content": "Hello",
+            "timestamp": 1234567890,
+            "metadata": null,
+        }))
+        .unwrap();
+
+        assert_eq!(message.metadata, serde_json::json!({}));
+        save_conversation_internal(&conn, "space-1", "Space", &[message]).unwrap();
+        assert_eq!(
+            load_conversation_internal(&conn, "space-1").unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_token_summary_sums_usage() {
+        let mut first = test_message("msg-1", "Hello");
+        first.metadata =
+            serde_json::json!({ "usage": { "input_tokens": 100, "output_tokens": 20 } });
+        let mut second = test_message("msg-2", "Again");
+        second.metadata =
+            serde_json::json!({ "usage": { "input_tokens": 150, "output_tokens": 30 } });
+
+        let summary = summarize_token_usage(&[first, second]);
+
+        assert_eq!(summary.input_tokens, 250);
+        assert_eq!(summary.output_tokens, 50);
+        assert_eq!(summary.total_tokens, 300);
+        assert_eq!(summary.messages_with_usage, 2);
+    }
+
+    #[test]
+    fn test_token_summary_skips_messages_without_usage() {
+        let mut with_usage = test_message("msg-1", "Hello");
+        with_usage.metadata =
+            serde_json::json!({ "usage": { "input_tokens": 10, "output_tokens": 5 } });
+        let mut malformed = test_message("msg-2", "Odd");
+        malformed.metadata = serde_json::json!({ "usage": "lots" });
+        let mut null_metadata = test_message("msg-3", "Legacy");
+        null_metadata.metadata = serde_json::Value::Null;
+
+        let summary = summarize_token_usage(&[
+            test_message("msg-0", "No usage"),
+            with_usage,
+            malformed,
+            null_metadata,
+        ]);
+
+        assert_eq!(summary.input_tokens, 10);
+        assert_eq!(summary.output_tokens, 5);
+        assert_eq!(summary.total_tokens, 15);
+        assert_eq!(summary.messages_with_usage, 1);
+
+        assert_eq!(summarize_token_usage(&[]), TokenSummary::default());
+    }
+
+    fn conversation_name(conn: &Connection, space_id: &str) -> String {
+        conn.query_row(
+            "SELECT space_name FROM conversations WHERE space_id = ?1",
+            params![space_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn write_test_space(spaces_dir: &Path, id: &str, name: &str) {
+        let space_dir = spaces_dir.join(id);
+        std::fs::create_dir_all(&space_dir).unwrap();
+        let space = crate::spaces::Space {
+            id: id.to_string(),
+            name: name.to_string(),
+            path: space_dir.to_string_lossy().to_string(),
+            claude_md_path: space_dir.join("CLAUDE.md").to_string_lossy().to_string(),
+            created_at: 0,
+            last_accessed_at: 0,
+            template: None,
+            archived: false,
+            pinned: false,
+            pin_order: None,
+        };
+        std::fs::write(
+            space_dir.join(".space-metadata.json"),
+            serde_json::to_string(&space).unwrap(),
+        )
+        .unwrap();
     }
 
     #[test]
-    fn test_delete_conversation() {
-        let (_conn, _temp) = setup_test_db();
+    fn test_rename_conversation_updates_name_and_space() {
+        let (conn, _temp) = setup_test_db();
+        let spaces_dir = tempfile::TempDir::new().unwrap();
+        write_test_space(spaces_dir.path(), "space-1", "Old Name");
+        save_conversation_internal(&conn, "space-1", "Old Name", &[test_message("msg-1", "Hi")])
+            .unwrap();
+
+        rename_conversation_internal(&conn, spaces_dir.path(), "space-1", "New Name").unwrap();
+
+        assert_eq!(conversation_name(&conn, "space-1"), "New Name");
+        let metadata =
+            std::fs::read_to_string(spaces_dir.path().join("space-1/.space-metadata.json"))
+                .unwrap();
+        let space: crate::spaces::Space = serde_json::from_str(&metadata).unwrap();
+        assert_eq!(space.name, "New Name");
+    }
 
-        let messages = vec![Message {
-            id: "msg-1".to_string(),
-            role: "user".to_string(),
-            content: "Test".to_string(),
-            timestamp: 1234567890,
-            metadata: serde_json::json!({}),
-        }];
+    #[test]
+    fn test_rename_conversation_without_space_directory() {
+        let (conn, _temp) = setup_test_db();
+        let spaces_dir = tempfile::TempDir::new().unwrap();
+        save_conversation_internal(&conn, "space-1", "Old Name", &[test_message("msg-1", "Hi")])
+            .unwrap();
 
-        save_conversation("test-space".to_string(), "Test".to_string(), messages).unwrap();
+        rename_conversation_internal(&conn, spaces_dir.path(), "space-1", "New Name").unwrap();
 
-        // Verify it exists
-        let loaded_before = load_conversation("test-space".to_string()).unwrap();
-        assert_eq!(loaded_before.len(), 1);
+        assert_eq!(conversation_name(&conn, "space-1"), "New Name");
+    }
 
-        // Delete
-        delete_conversation("test-space".to_string()).unwrap();
+    #[test]
+    fn test_rename_missing_conversation_fails() {
+        let (conn, _temp) = setup_test_db();
+        let spaces_dir = tempfile::TempDir::new().unwrap();
+        write_test_space(spaces_dir.path(), "space-1", "Old Name");
+
+        let err = rename_conversation_internal(&conn, spaces_dir.path(), "space-1", "New Name")
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            ThinkingSpaceError::not_found("Conversation for space space-1")
+        );
+        // The space is left untouched when there is nothing to rename
+        let metadata =
+            std::fs::read_to_string(spaces_dir.path().join("space-1/.space-metadata.json"))
+                .unwrap();
+        assert!(metadata.contains("Old Name"));
+    }
 
-        // Verify it's gone
-        let loaded_after = load_conversation("test-space".to_string()).unwrap();
-        assert_eq!(loaded_after.len(), 0);
+    #[test]
+    fn test_rename_conversation_validates_name() {
+        let (conn, _temp) = setup_test_db();
+        let spaces_dir = tempfile::TempDir::new().unwrap();
+        save_conversation_internal(&conn, "space-1", "Old Name", &[test_message("msg-1", "Hi")])
+            .unwrap();
+
+        assert!(rename_conversation_internal(&conn, spaces_dir.path(), "space-1", "  ").is_err());
+        let too_long = "a".repeat(MAX_CONVERSATION_NAME_CHARS + 1);
+        assert!(
+            rename_conversation_internal(&conn, spaces_dir.path(), "space-1", &too_long).is_err()
+        );
+        let longest = "a".repeat(MAX_CONVERSATION_NAME_CHARS);
+        assert!(
+            rename_conversation_internal(&conn, spaces_dir.path(), "space-1", &longest).is_ok()
+        );
+    }
+
+    fn seed_conversation(conn: &Connection, space_id: &str, count: usize) {
+        let messages: Vec<Message> = (0..count)
+            .map(|i| {
+                let mut message = test_message(&format!("msg-{}", i), &format!("Message {}", i));
+                if i % 2 == 1 {
+                    message.role = "assistant".to_string();
+                }
+                message
+            })
+            .collect();
+        save_conversation_internal(conn, space_id, "Space", &messages).unwrap();
+    }
+
+    fn stored_title(conn: &Connection, space_id: &str) -> Option<String> {
+        conn.query_row(
+            "SELECT title FROM conversations WHERE space_id = ?1",
+            params![space_id],
+            |row| row.get(0),
+        )
+        .unwrap()
     }
 
     #[test]
-    fn test_list_conversations() {
-        let (_conn, _temp) = setup_test_db();
+    fn test_generated_title_is_persisted() {
+        let (conn, _temp) = setup_test_db();
+        seed_conversation(&conn, "space-1", 5);
 
-        // Create multiple conversations
-        save_conversation(
-            "space-1".to_string(),
-            "Space 1".to_string(),
-            vec![Message {
+        assert_eq!(conversation_title_internal(&conn, "space-1").unwrap(), None);
+        let prompt = conversation_title_prompt_internal(&conn, "space-1").unwrap();
+        assert_eq!(prompt, "Summarise this prompt in ≤8 words: Message 0");
+
+        let title = store_generated_title_internal(
+            &conn,
+            "space-1",
+            "\n\"Planning a vegetable garden.\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(title, "Planning a vegetable garden");
+        assert_eq!(
+            stored_title(&conn, "space-1").as_deref(),
+            Some("Planning a vegetable garden")
+        );
+        assert_eq!(
+            conversation_title_internal(&conn, "space-1").unwrap(),
+            Some(title)
+        );
+
+        // Saving the conversation again keeps the title
+        seed_conversation(&conn, "space-1", 6);
+        assert_eq!(
+            stored_title(&conn, "space-1").as_deref(),
+            Some("Planning a vegetable garden")
+        );
+    }
+
+    #[test]
+    fn test_title_requires_enough_messages() {
+        let (conn, _temp) = setup_test_db();
+        seed_conversation(&conn, "space-1", 4);
+
+        assert!(matches!(
+            conversation_title_prompt_internal(&conn, "space-1"),
+            Err(ThinkingSpaceError::ValidationFailed { .. })
+        ));
+        assert_eq!(stored_title(&conn, "space-1"), None);
+    }
+
+    #[test]
+    fn test_clean_generated_title() {
+        assert_eq!(
+            clean_generated_title("one two three four five six seven eight nine ten").as_deref(),
+            Some("one two three four five six seven eight")
+        );
+        assert_eq!(clean_generated_title("  \n  "), None);
+        assert_eq!(clean_generated_title("'Quoted'").as_deref(), Some("Quoted"));
+    }
+
+    #[test]
+    fn test_title_column_added_to_existing_database() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let conn = Connection::open(temp_file.path()).unwrap();
+        conn.execute(
+            "CREATE TABLE conversations (
+                space_id TEXT PRIMARY KEY,
+                space_name TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                message_count INTEGER NOT NULL DEFAULT 0,
+                data BLOB NOT NULL
+            ) STRICT",
+            [],
+        )
+        .unwrap();
+
+        init_database(&conn).unwrap();
+        // Running again must not try to add the column twice
+        init_database(&conn).unwrap();
+
+        seed_conversation(&conn, "space-1", 1);
+        set_conversation_title_internal(&conn, "space-1", "Garden").unwrap();
+        assert_eq!(stored_title(&conn, "space-1").as_deref(), Some("Garden"));
+    }
+
+    #[test]
+    fn test_cleanup_removes_only_orphaned_conversations() {
+        let (conn, _temp) = setup_test_db();
+        let spaces_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(spaces_dir.path().join("live-space")).unwrap();
+
+        seed_conversation(&conn, "live-space", 2);
+        seed_conversation(&conn, "deleted-space", 2);
+
+        let removed = cleanup_orphaned_conversations_internal(&conn, spaces_dir.path()).unwrap();
+
+        assert_eq!(removed, 1);
+        assert_eq!(
+            load_conversation_internal(&conn, "live-space")
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(load_conversation_internal(&conn, "deleted-space")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_cleanup_skipped_without_any_spaces() {
+        let (conn, _temp) = setup_test_db();
+        let spaces_dir = tempfile::TempDir::new().unwrap();
+        seed_conversation(&conn, "space-1", 2);
+
+        // Empty spaces directory
+        assert_eq!(
+            cleanup_orphaned_conversations_internal(&conn, spaces_dir.path()).unwrap(),
+            0
+        );
+        // Missing spaces directory
+        let missing = spaces_dir.path().join("missing");
+        assert_eq!(
+            cleanup_orphaned_conversations_internal(&conn, &missing).unwrap(),
+            0
+        );
+
+        assert_eq!(
+            load_conversation_internal(&conn, "space-1").unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_orphan_cleanup_due() {
+        let now = 1_700_000_000;
+
+        assert!(orphan_cleanup_due(None, now));
+        assert!(!orphan_cleanup_due(Some(now - 60), now));
+        assert!(orphan_cleanup_due(
+            Some(now - ORPHAN_CLEANUP_INTERVAL_SECS),
+            now
+        ));
+    }
+
+    fn timed_message(id: &str, timestamp: i64) -> Message {
+        Message {
+            timestamp,
+            ..test_message(id, id)
+        }
+    }
+
+    fn message_ids(conn: &Connection, space_id: &str) -> Vec<String> {
+        load_conversation_internal(conn, space_id)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect()
+    }
+
+    fn setup_merge() -> (
+        Connection,
+        tempfile::TempPath,
+        Connection,
+        tempfile::TempDir,
+    ) {
+        let (conn, temp) = setup_test_db();
+        let sessions_dir = tempfile::tempdir().unwrap();
+        let sessions =
+            crate::sessions::get_connection_at(&sessions_dir.path().join("sessions.db")).unwrap();
+
+        save_conversation_internal(
+            &conn,
+            "target",
+            "Target",
+            &[timed_message("t1", 100), timed_message("t2", 300)],
+        )
+        .unwrap();
+        save_conversation_internal(
+            &conn,
+            "source",
+            "Source",
+            &[timed_message("s1", 200), timed_message("s2", 400)],
+        )
+        .unwrap();
+
+        (conn, temp, sessions, sessions_dir)
+    }
+
+    #[test]
+    fn test_merge_append_to_end() {
+        let (conn, _temp, sessions, _sessions_dir) = setup_merge();
+
+        let total = merge_conversations_internal(
+            &conn,
+            &sessions,
+            "source",
+            "target",
+            MergeStrategy::AppendToEnd,
+        )
+        .unwrap();
+
+        assert_eq!(total, 4);
+        assert_eq!(message_ids(&conn, "target"), vec!["t1", "t2", "s1", "s2"]);
+        let count: i64 = conn
+            .query_row(
+                "SELECT message_count FROM conversations WHERE space_id = 'target'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 4);
+
+        // Source is left as it was
+        assert_eq!(message_ids(&conn, "source"), vec!["s1", "s2"]);
+    }
+
+    #[test]
+    fn test_merge_interleaved_by_timestamp() {
+        let (conn, _temp, sessions, _sessions_dir) = setup_merge();
+
+        let total = merge_conversations_internal(
+            &conn,
+            &sessions,
+            "source",
+            "target",
+            MergeStrategy::InterleavedByTimestamp,
+        )
+        .unwrap();
+
+        assert_eq!(total, 4);
+        assert_eq!(message_ids(&conn, "target"), vec!["t1", "s1", "t2", "s2"]);
+
+        // Merging again doesn't duplicate messages
+        let total = merge_conversations_internal(
+            &conn,
+            &sessions,
+            "source",
+            "target",
+            MergeStrategy::InterleavedByTimestamp,
+        )
+        .unwrap();
+        assert_eq!(total, 4);
+    }
+
+    #[test]
+    fn test_merge_refused_while_session_active() {
+        let (conn, _temp, sessions, _sessions_dir) = setup_merge();
+        sessions
+            .execute(
+                "INSERT INTO sessions (session_id, space_id, created_at, last_active, is_active)
+                 VALUES ('live', 'source', 1, 2, 1)",
+                [],
+            )
+            .unwrap();
+
+        let err = merge_conversations_internal(
+            &conn,
+            &sessions,
+            "source",
+            "target",
+            MergeStrategy::AppendToEnd,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ThinkingSpaceError::ValidationFailed { .. }));
+        assert!(err.to_string().contains("active session"));
+        assert_eq!(message_ids(&conn, "target"), vec!["t1", "t2"]);
+
+        // Same space and missing conversations are rejected too
+        assert!(merge_conversations_internal(
+            &conn,
+            &sessions,
+            "target",
+            "target",
+            MergeStrategy::AppendToEnd
+        )
+        .is_err());
+        assert!(merge_conversations_internal(
+            &conn,
+            &sessions,
+            "target",
+            "missing",
+            MergeStrategy::AppendToEnd
+        )
+        .is_err());
+    }
+
+    fn read_csv(path: &Path) -> (csv::StringRecord, Vec<csv::StringRecord>) {
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        let rows = reader.records().map(|r| r.unwrap()).collect();
+        (headers, rows)
+    }
+
+    #[test]
+    fn test_export_conversation_csv() {
+        let (conn, _temp) = setup_test_db();
+        let out_dir = tempfile::tempdir().unwrap();
+        let output = out_dir.path().join("conversation.csv");
+
+        let messages = vec![
+            Message {
                 id: "msg-1".to_string(),
                 role: "user".to_string(),
-                content: "Test".to_string(),
-                timestamp: 1234567890,
+                content: "Hello, world\nsecond \"line\"".to_string(),
+                timestamp: 1697500000000,
                 metadata: serde_json::json!({}),
-            }],
+            },
+            Message {
+                id: "msg-2".to_string(),
+                role: "assistant".to_string(),
+                content: "Done".to_string(),
+                timestamp: 1697500001000,
+                metadata: serde_json::json!({
+                    "toolCalls": [{"name": "Read"}, {"name": "Edit"}],
+                    "model": "claude-sonnet",
+                }),
+            },
+        ];
+        save_conversation_internal(&conn, "space-1", "Space", &messages).unwrap();
+
+        let rows = export_conversation_csv_internal(&conn, "space-1", &output).unwrap();
+        assert_eq!(rows, 2);
+
+        let (headers, records) = read_csv(&output);
+        assert_eq!(headers.len(), 7);
+        assert_eq!(&headers[0], "message_id");
+        assert_eq!(records.len(), 2);
+
+        // Commas, quotes and newlines survive the round trip
+        assert_eq!(&records[0][4], "Hello, world\nsecond \"line\"");
+        assert_eq!(&records[0][2], "2023-10-16T23:46:40+00:00");
+        assert_eq!(&records[0][5], "0");
+        assert_eq!(&records[0][6], "");
+
+        assert_eq!(&records[1][3], "4");
+        assert_eq!(&records[1][5], "2");
+        assert_eq!(&records[1][6], "claude-sonnet");
+    }
+
+    #[test]
+    fn test_export_csv_truncates_long_content() {
+        let (conn, _temp) = setup_test_db();
+        let out_dir = tempfile::tempdir().unwrap();
+        let output = out_dir.path().join("long.csv");
+
+        let mut message = timed_message("msg-1", 0);
+        message.content = "x".repeat(MAX_CSV_CONTENT_CHARS + 5);
+        save_conversation_internal(&conn, "space-1", "Space", &[message]).unwrap();
+
+        export_conversation_csv_internal(&conn, "space-1", &output).unwrap();
+
+        let (_, records) = read_csv(&output);
+        assert_eq!(
+            &records[0][3],
+            (MAX_CSV_CONTENT_CHARS + 5).to_string().as_str()
+        );
+        assert_eq!(
+            records[0][4],
+            format!("{}…", "x".repeat(MAX_CSV_CONTENT_CHARS))
+        );
+    }
+
+    #[test]
+    fn test_export_all_conversations_csv() {
+        let (conn, _temp) = setup_test_db();
+        let out_dir = tempfile::tempdir().unwrap();
+        let output = out_dir.path().join("all.csv");
+
+        save_conversation_internal(
+            &conn,
+            "space-a",
+            "A",
+            &[timed_message("a-1", 1), timed_message("a-2", 2)],
         )
         .unwrap();
+        save_conversation_internal(&conn, "space-b", "B", &[timed_message("b-1", 3)]).unwrap();
+
+        let rows = export_all_conversations_csv_internal(&conn, &output).unwrap();
+        assert_eq!(rows, 3);
+
+        let (headers, records) = read_csv(&output);
+        assert_eq!(headers.len(), 8);
+        assert_eq!(&headers[0], "space_id");
+        assert_eq!(records.len(), 3);
+
+        let mut pairs: Vec<(String, String)> = records
+            .iter()
+            .map(|r| (r[0].to_string(), r[1].to_string()))
+            .collect();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            vec![
+                ("space-a".to_string(), "a-1".to_string()),
+                ("space-a".to_string(), "a-2".to_string()),
+                ("space-b".to_string(), "b-1".to_string()),
+            ]
+        );
+    }
 
-        save_conversation(
-            "space-2".to_string(),
-            "Space 2".to_string(),
-            vec![Message {
-                id: "msg-2".to_string(),
-                role: "user".to_string(),
-                content: "Test 2".to_string(),
-                timestamp: 1234567891,
-                metadata: serde_json::json!({}),
-            }],
+    fn message_ids(messages: &[Message]) -> Vec<&str> {
+        messages.iter().map(|m| m.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_diff_disjoint_conversations() {
+        let (conn, _temp) = setup_test_db();
+        save_conversation_internal(
+            &conn,
+            "a",
+            "A",
+            &[timed_message("a-1", 1), timed_message("a-2", 2)],
         )
         .unwrap();
+        save_conversation_internal(&conn, "b", "B", &[timed_message("b-1", 1)]).unwrap();
 
-        let conversations = list_conversations().unwrap();
+        let diff = diff_conversations_internal(&conn, "a", "b").unwrap();
 
-        assert_eq!(conversations.len(), 2);
-        // Should be sorted by updated_at DESC (most recent first)
-        assert_eq!(conversations[0].space_id, "space-2");
-        assert_eq!(conversations[1].space_id, "space-1");
+        assert_eq!(message_ids(&diff.removed), vec!["a-1", "a-2"]);
+        assert_eq!(message_ids(&diff.added), vec!["b-1"]);
+        assert_eq!(diff.common_count, 0);
     }
 
     #[test]
-    fn test_message_metadata_preservation() {
-        let (_conn, _temp) = setup_test_db();
+    fn test_diff_identical_conversations() {
+        let (conn, _temp) = setup_test_db();
+        let messages = [timed_message("m-1", 1), timed_message("m-2", 2)];
+        save_conversation_internal(&conn, "a", "A", &messages).unwrap();
+        save_conversation_internal(&conn, "b", "B", &messages).unwrap();
 
-        let metadata = serde_json::json!({
-            "toolCalls": ["ls", "cat file.txt"],
-            "files": ["/path/to/file.txt"]
-        });
+        let diff = diff_conversations_internal(&conn, "a", "b").unwrap();
 
-        let messages = vec![Message {
-            id: "msg-1".to_string(),
-            role: "user".to_string(),
-            content: "Test with metadata".to_string(),
-            timestamp: 1234567890,
-            metadata: metadata.clone(),
-        }];
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.common_count, 2);
+    }
+
+    #[test]
+    fn test_diff_overlapping_conversations_preserves_order() {
+        let (conn, _temp) = setup_test_db();
+        save_conversation_internal(
+            &conn,
+            "original",
+            "Original",
+            &[
+                timed_message("m-1", 1),
+                timed_message("m-2", 2),
+                timed_message("m-3", 3),
+                timed_message("m-4", 4),
+            ],
+        )
+        .unwrap();
+        save_conversation_internal(
+            &conn,
+            "fork",
+            "Fork",
+            &[
+                timed_message("m-1", 1),
+                timed_message("f-2", 5),
+                timed_message("m-3", 3),
+                timed_message("f-1", 6),
+            ],
+        )
+        .unwrap();
 
-        save_conversation("test-space".to_string(), "Test".to_string(), messages).unwrap();
+        let diff = diff_conversations_internal(&conn, "original", "fork").unwrap();
 
-        let loaded = load_conversation("test-space".to_string()).unwrap();
+        assert_eq!(message_ids(&diff.removed), vec!["m-2", "m-4"]);
+        assert_eq!(message_ids(&diff.added), vec!["f-2", "f-1"]);
+        assert_eq!(diff.common_count, 2);
 
-        assert_eq!(loaded[0].metadata, metadata);
+        // A missing conversation is treated as empty
+        let diff = diff_conversations_internal(&conn, "original", "missing").unwrap();
+        assert_eq!(diff.removed.len(), 4);
+        assert_eq!(diff.common_count, 0);
+    }
+
+    const CLAUDE_AI_EXPORT: &str = r#"[
+        {
+            "uuid": "6f1c2a9e-0d3b-4c1e-9a57-1b2f3c4d5e6f",
+            "name": "Planning the garden",
+            "created_at": "2024-03-01T09:15:00.000000+00:00",
+            "updated_at": "2024-03-01T09:20:12.000000+00:00",
+            "account": { "uuid": "a1b2c3d4-0000-0000-0000-000000000000" },
+            "chat_messages": [
+                {
+                    "uuid": "c0a80101-0001-4000-8000-000000000001",
+                    "text": "What should I plant in early spring?",
+                    "sender": "human",
+                    "created_at": "2024-03-01T09:15:00.000000+00:00",
+                    "updated_at": "2024-03-01T09:15:00.000000+00:00",
+                    "attachments": [],
+                    "files": []
+                },
+                {
+                    "uuid": "c0a80101-0002-4000-8000-000000000002",
+                    "text": "Peas, spinach and radishes all tolerate cool soil.",
+                    "sender": "assistant",
+                    "created_at": "2024-03-01T09:15:08.250000+00:00",
+                    "updated_at": "2024-03-01T09:15:08.250000+00:00",
+                    "attachments": [],
+                    "files": []
+                },
+                {
+                    "uuid": "c0a80101-0003-4000-8000-000000000003",
+                    "sender": "human",
+                    "created_at": "2024-03-01T09:16:00.000000+00:00"
+                },
+                {
+                    "uuid": "c0a80101-0004-4000-8000-000000000004",
+                    "text": "Thanks!",
+                    "sender": "human",
+                    "created_at": "yesterday"
+                }
+            ]
+        },
+        {
+            "uuid": "7a2d3b8f-1e4c-4d2f-8b68-2c3d4e5f6a7b",
+            "name": "Sourdough",
+            "created_at": "2024-02-10T18:00:00.000000+00:00",
+            "updated_at": "2024-02-10T18:01:00.000000+00:00",
+            "chat_messages": [
+                {
+                    "uuid": "c0a80101-0005-4000-8000-000000000005",
+                    "text": "Why is my starter sluggish?",
+                    "sender": "human",
+                    "created_at": "2024-02-10T18:00:00Z"
+                },
+                {
+                    "uuid": "c0a80101-0006-4000-8000-000000000006",
+                    "text": "It may need warmer water.",
+                    "sender": "system",
+                    "created_at": "2024-02-10T18:00:30Z"
+                }
+            ]
+        }
+    ]"#;
+
+    #[test]
+    fn test_import_from_claude_ai_export() {
+        let (conn, _temp) = setup_test_db();
+
+        let stats = import_from_claude_ai_export_internal(
+            &conn,
+            CLAUDE_AI_EXPORT.as_bytes(),
+            "space-1",
+            "Garden",
+        )
+        .unwrap();
+
+        assert_eq!(
+            stats,
+            ImportStats {
+                conversations_processed: 2,
+                messages_imported: 3,
+                skipped: 3, // missing text, bad date, unknown sender
+            }
+        );
+
+        // Sorted by timestamp, so the older conversation comes first
+        let messages = load_conversation_internal(&conn, "space-1").unwrap();
+        let ids: Vec<&str> = messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "c0a80101-0005-4000-8000-000000000005",
+                "c0a80101-0001-4000-8000-000000000001",
+                "c0a80101-0002-4000-8000-000000000002",
+            ]
+        );
+        assert_eq!(messages[0].role, "user");
+        assert_eq!(messages[2].role, "assistant");
+        assert_eq!(messages[2].timestamp, 1709284508250);
+        assert_eq!(
+            messages[2].content,
+            "Peas, spinach and radishes all tolerate cool soil."
+        );
+    }
+
+    #[test]
+    fn test_import_from_claude_ai_export_twice_skips_existing() {
+        let (conn, _temp) = setup_test_db();
+        import_from_claude_ai_export_internal(
+            &conn,
+            CLAUDE_AI_EXPORT.as_bytes(),
+            "space-1",
+            "Garden",
+        )
+        .unwrap();
+
+        let stats = import_from_claude_ai_export_internal(
+            &conn,
+            CLAUDE_AI_EXPORT.as_bytes(),
+            "space-1",
+            "Garden",
+        )
+        .unwrap();
+
+        assert_eq!(stats.messages_imported, 0);
+        assert_eq!(stats.skipped, 6);
+        assert_eq!(
+            load_conversation_internal(&conn, "space-1").unwrap().len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_import_from_claude_ai_export_rejects_non_array() {
+        let (conn, _temp) = setup_test_db();
+
+        assert!(matches!(
+            import_from_claude_ai_export_internal(&conn, b"{}", "space-1", "Garden"),
+            Err(ThinkingSpaceError::Serialization(_))
+        ));
+        assert!(load_conversation_internal(&conn, "space-1")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_snapshots_capped_per_space() {
+        let (conn, _temp) = setup_test_db();
+
+        // The first save has nothing to snapshot
+        let mut messages = vec![timed_message("msg-0", 0)];
+        save_conversation_internal(&conn, "space-1", "Space 1", &messages).unwrap();
+        assert!(list_conversation_snapshots_internal(&conn, "space-1")
+            .unwrap()
+            .is_empty());
+
+        for i in 1..=15 {
+            messages.push(timed_message(&format!("msg-{}", i), i));
+            save_conversation_internal(&conn, "space-1", "Space 1", &messages).unwrap();
+        }
+        // Saving the same messages again doesn't add a snapshot
+        save_conversation_internal(&conn, "space-1", "Space 1", &messages).unwrap();
+        save_conversation_internal(&conn, "space-2", "Space 2", &messages).unwrap();
+
+        let snapshots = list_conversation_snapshots_internal(&conn, "space-1").unwrap();
+        assert_eq!(snapshots.len(), MAX_SNAPSHOTS_PER_SPACE as usize);
+        // Newest first; the oldest five were dropped
+        let counts: Vec<usize> = snapshots.iter().map(|s| s.message_count).collect();
+        assert_eq!(counts, (6..=15).rev().collect::<Vec<_>>());
+        assert!(list_conversation_snapshots_internal(&conn, "space-2")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_restore_conversation_snapshot() {
+        let (conn, _temp) = setup_test_db();
+        let original = vec![timed_message("msg-1", 1), timed_message("msg-2", 2)];
+        save_conversation_internal(&conn, "space-1", "Space 1", &original).unwrap();
+        save_conversation_internal(&conn, "space-1", "Space 1", &[timed_message("msg-3", 3)])
+            .unwrap();
+
+        let snapshot = &list_conversation_snapshots_internal(&conn, "space-1").unwrap()[0];
+        assert_eq!(snapshot.message_count, 2);
+
+        let restored =
+            restore_conversation_snapshot_internal(&conn, "space-1", snapshot.id).unwrap();
+        let ids: Vec<&str> = restored.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["msg-1", "msg-2"]);
+        let loaded = load_conversation_internal(&conn, "space-1").unwrap();
+        assert_eq!(loaded.len(), 2);
+
+        // The replaced version was snapshotted, so the restore can be undone
+        let snapshots = list_conversation_snapshots_internal(&conn, "space-1").unwrap();
+        assert_eq!(snapshots[0].message_count, 1);
+
+        // Snapshots can't be restored into another space
+        assert!(matches!(
+            restore_conversation_snapshot_internal(&conn, "space-2", snapshot.id),
+            Err(ThinkingSpaceError::NotFound { .. })
+        ));
+    }
+
+    fn stored_message_count(conn: &Connection, space_id: &str) -> Option<i64> {
+        conn.query_row(
+            "SELECT message_count FROM conversations WHERE space_id = ?1",
+            params![space_id],
+            |row| row.get(0),
+        )
+        .ok()
+    }
+
+    #[test]
+    fn test_delete_conversation_message() {
+        let (conn, _temp) = setup_test_db();
+        save_conversation_internal(
+            &conn,
+            "space-1",
+            "Space",
+            &[
+                test_message("msg-1", "Hi"),
+                test_message("msg-2", "Hello"),
+                test_message("msg-3", "Bye"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            delete_conversation_message_internal(&conn, "space-1", "msg-2", false).unwrap(),
+            2
+        );
+
+        let ids: Vec<String> = load_conversation_internal(&conn, "space-1")
+            .unwrap()
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+        assert_eq!(ids, vec!["msg-1", "msg-3"]);
+        assert_eq!(stored_message_count(&conn, "space-1"), Some(2));
+        assert_eq!(conversation_name(&conn, "space-1"), "Space");
+    }
+
+    #[test]
+    fn test_delete_missing_conversation_message_fails() {
+        let (conn, _temp) = setup_test_db();
+        save_conversation_internal(&conn, "space-1", "Space", &[test_message("msg-1", "Hi")])
+            .unwrap();
+
+        let err =
+            delete_conversation_message_internal(&conn, "space-1", "msg-404", false).unwrap_err();
+
+        assert!(
+            matches!(&err, ThinkingSpaceError::NotFound { resource } if resource.contains("msg-404"))
+        );
+        assert_eq!(stored_message_count(&conn, "space-1"), Some(1));
+        assert!(delete_conversation_message_internal(&conn, "space-2", "msg-1", false).is_err());
+    }
+
+    #[test]
+    fn test_delete_last_conversation_message() {
+        let (conn, _temp) = setup_test_db();
+        for space_id in ["space-1", "space-2"] {
+            save_conversation_internal(&conn, space_id, "Space", &[test_message("msg-1", "Hi")])
+                .unwrap();
+        }
+
+        // By default the emptied conversation goes away entirely
+        assert_eq!(
+            delete_conversation_message_internal(&conn, "space-1", "msg-1", false).unwrap(),
+            0
+        );
+        assert_eq!(stored_message_count(&conn, "space-1"), None);
+
+        // With allow_empty it stays, with no messages
+        assert_eq!(
+            delete_conversation_message_internal(&conn, "space-2", "msg-1", true).unwrap(),
+            0
+        );
+        assert_eq!(stored_message_count(&conn, "space-2"), Some(0));
+        assert!(load_conversation_internal(&conn, "space-2")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_failures_report_their_variant() {
+        let (conn, _temp) = setup_test_db();
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let output = output_dir.path().join("missing").join("export.csv");
+
+        let err = export_conversation_csv_internal(&conn, "space-1", &output).unwrap_err();
+        assert!(
+            matches!(&err, ThinkingSpaceError::Io { path, .. } if path == &output.display().to_string())
+        );
+
+        save_conversation_internal(&conn, "space-1", "Garden", &[test_message("msg-1", "Hi")])
+            .unwrap();
+        conn.execute(
+            "UPDATE conversations SET data = ?1",
+            params![b"not json".to_vec()],
+        )
+        .unwrap();
+        assert!(matches!(
+            load_conversation_internal(&conn, "space-1"),
+            Err(ThinkingSpaceError::Serialization(_))
+        ));
+
+        conn.execute_batch("DROP TABLE conversations").unwrap();
+        assert!(matches!(
+            load_conversation_internal(&conn, "space-1"),
+            Err(ThinkingSpaceError::Database(_))
+        ));
+    }
+
+    #[test]
+    fn test_save_conversation_batch() {
+        let (mut conn, _temp) = setup_test_db();
+        let message = |id: &str, metadata: serde_json::Value| Message {
+            id: id.to_string(),
+            role: "user".to_string(),
+            content: "Hello".to_string(),
+            timestamp: 1,
+            metadata,
+        };
+
+        let entries = vec![
+            (
+                "space-a".to_string(),
+                "A".to_string(),
+                vec![message("a1", serde_json::json!({}))],
+            ),
+            (
+                "space-b".to_string(),
+                "B".to_string(),
+                vec![message("b1", serde_json::json!({}))],
+            ),
+        ];
+        assert_eq!(
+            save_conversation_batch_internal(&mut conn, &entries).unwrap(),
+            2
+        );
+        assert_eq!(list_conversations_internal(&conn).unwrap().len(), 2);
+
+        // One bad entry rolls back the whole batch
+        let entries = vec![
+            (
+                "space-c".to_string(),
+                "C".to_string(),
+                vec![message("c1", serde_json::json!({}))],
+            ),
+            (
+                "space-d".to_string(),
+                "D".to_string(),
+                vec![message("d1", serde_json::json!({ "unknown": true }))],
+            ),
+        ];
+        assert!(matches!(
+            save_conversation_batch_internal(&mut conn, &entries),
+            Err(ThinkingSpaceError::ValidationFailed { .. })
+        ));
+        assert!(load_conversation_internal(&conn, "space-c")
+            .unwrap()
+            .is_empty());
     }
 }
@@ -0,0 +1,60 @@
+use rusqlite::Connection;
+
+/// Apply the connection-level PRAGMAs shared by every SQLite database
+///
+/// WAL lets readers proceed while a write is in flight, and NORMAL
+/// synchronous mode is durable under WAL without an fsync per commit.
+pub fn configure_connection(conn: &Connection) -> Result<(), String> {
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to enable WAL mode: {}", e))?;
+
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(|e| format!("Failed to set synchronous mode: {}", e))?;
+
+    // Negative values are in KiB, so this is a 64 MB page cache
+    conn.pragma_update(None, "cache_size", -64000)
+        .map_err(|e| format!("Failed to set cache size: {}", e))?;
+
+    conn.pragma_update(None, "foreign_keys", "ON")
+        .map_err(|e| format!("Failed to enable foreign keys: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn pragma<T: rusqlite::types::FromSql>(conn: &Connection, name: &str) -> T {
+        conn.pragma_query_value(None, name, |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_configure_connection_applies_pragmas() {
+        let temp_dir = TempDir::new().unwrap();
+        let conn = Connection::open(temp_dir.path().join("test.db")).unwrap();
+
+        configure_connection(&conn).unwrap();
+
+        assert_eq!(pragma::<String>(&conn, "journal_mode"), "wal");
+        assert_eq!(pragma::<i64>(&conn, "synchronous"), 1);
+        assert_eq!(pragma::<i64>(&conn, "cache_size"), -64000);
+        assert_eq!(pragma::<i64>(&conn, "foreign_keys"), 1);
+    }
+
+    #[test]
+    fn test_configure_connection_is_idempotent() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+
+        let first = Connection::open(&db_path).unwrap();
+        configure_connection(&first).unwrap();
+        drop(first);
+
+        let second = Connection::open(&db_path).unwrap();
+        configure_connection(&second).unwrap();
+        assert_eq!(pragma::<String>(&second, "journal_mode"), "wal");
+    }
+}
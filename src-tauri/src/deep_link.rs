@@ -0,0 +1,207 @@
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+/// URL scheme the app registers, as in `thinkingspace://open?space_id=abc123`
+const DEEP_LINK_SCHEME: &str = "thinkingspace";
+
+/// The link the app was launched with, held until the frontend asks for it
+/// since nothing is listening for events while the app is still starting
+#[derive(Default)]
+pub struct PendingDeepLink(Mutex<Option<Value>>);
+
+/// What a deep link asks the app to do
+#[derive(Debug, PartialEq)]
+pub enum DeepLinkAction {
+    OpenSpace { space_id: String },
+}
+
+/// Parse a `thinkingspace://` URL into the action it requests
+pub fn parse_deep_link(url: &str) -> Result<DeepLinkAction, String> {
+    let url = Url::parse(url).map_err(|e| format!("Invalid deep link: {}", e))?;
+
+    if url.scheme() != DEEP_LINK_SCHEME {
+        return Err(format!("Unsupported deep link scheme: {}", url.scheme()));
+    }
+
+    match url.host_str() {
+        Some("open") => {
+            let space_id = url
+                .query_pairs()
+                .find(|(key, _)| key == "space_id")
+                .map(|(_, value)| value.into_owned())
+                .ok_or("Deep link is missing space_id")?;
+
+            // Space IDs are UUIDs; anything path-like must never reach the filesystem
+            if space_id.is_empty()
+                || !space_id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!("Invalid space_id in deep link: {}", space_id));
+            }
+
+            Ok(DeepLinkAction::OpenSpace { space_id })
+        }
+        Some(other) => Err(format!("Unknown deep link action: {}", other)),
+        None => Err("Deep link has no action".to_string()),
+    }
+}
+
+/// The event to emit for a deep link, or an error if the link is malformed
+fn deep_link_event(spaces_dir: &Path, url: &str) -> Result<(&'static str, Value), String> {
+    match parse_deep_link(url)? {
+        DeepLinkAction::OpenSpace { space_id } => {
+            let exists = crate::spaces::list_spaces_in(spaces_dir, true)
+                .map_err(|e| e.to_string())?
+                .iter()
+                .any(|space| space.id == space_id);
+            let event = if exists {
+                "deep-link-open-space"
+            } else {
+                "deep-link-not-found"
+            };
+            Ok((event, json!({ "spaceId": space_id })))
+        }
+    }
+}
+
+/// Resolve a deep link against the spaces on disk, logging anything unusable
+fn resolve_deep_link(url: &Url) -> Option<(&'static str, Value)> {
+    let spaces_dir = match crate::spaces::get_spaces_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("[DEEP LINK] Cannot resolve spaces directory: {}", e);
+            return None;
+        }
+    };
+
+    match deep_link_event(&spaces_dir, url.as_str()) {
+        Ok((event, payload)) => {
+            println!("[DEEP LINK] {} -> {}", url, event);
+            Some((event, payload))
+        }
+        Err(e) => {
+            eprintln!("[DEEP LINK] Ignoring {}: {}", url, e);
+            None
+        }
+    }
+}
+
+fn handle_deep_link(app: &AppHandle, url: &Url) {
+    if let Some((event, payload)) = resolve_deep_link(url) {
+        if let Err(e) = app.emit(event, payload) {
+            eprintln!("[DEEP LINK] Failed to emit {}: {}", event, e);
+        }
+    }
+}
+
+/// Hand the launch deep link to the frontend once it has mounted
+/// Returns `{ event, spaceId }` or null when the app wasn't opened from a link
+#[tauri::command]
+pub fn take_pending_deep_link(pending: State<'_, PendingDeepLink>) -> Option<Value> {
+    pending.0.lock().take()
+}
+
+/// Register the `thinkingspace://` scheme with the OS and route incoming links
+/// to the frontend; the one the app was launched with waits in `PendingDeepLink`
+pub fn register_deep_link_protocol(app: &AppHandle) -> Result<(), String> {
+    let deep_link = app.deep_link();
+
+    // macOS registers schemes from the bundle; Linux and Windows need it at runtime
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    deep_link
+        .register(DEEP_LINK_SCHEME)
+        .map_err(|e| format!("Failed to register deep link scheme: {}", e))?;
+
+    let handle = app.clone();
+    deep_link.on_open_url(move |event| {
+        for url in event.urls() {
+            handle_deep_link(&handle, &url);
+        }
+    });
+
+    if let Some(urls) = deep_link
+        .get_current()
+        .map_err(|e| format!("Failed to read launch deep link: {}", e))?
+    {
+        // Only the last link matters if several arrive at launch
+        if let Some((event, payload)) = urls.iter().filter_map(resolve_deep_link).last() {
+            let mut pending = payload;
+            pending["event"] = json!(event);
+            *app.state::<PendingDeepLink>().0.lock() = Some(pending);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_open_space_link() {
+        assert_eq!(
+            parse_deep_link("thinkingspace://open?space_id=abc123").unwrap(),
+            DeepLinkAction::OpenSpace {
+                space_id: "abc123".to_string()
+            }
+        );
+        assert_eq!(
+            parse_deep_link("thinkingspace://open?foo=bar&space_id=0b1c-2d3e").unwrap(),
+            DeepLinkAction::OpenSpace {
+                space_id: "0b1c-2d3e".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed_links() {
+        for url in [
+            "not a url",
+            "https://open?space_id=abc123",
+            "thinkingspace://delete?space_id=abc123",
+            "thinkingspace://open",
+            "thinkingspace://open?space_id=",
+            "thinkingspace://open?space_id=..%2F..%2Fetc",
+        ] {
+            assert!(parse_deep_link(url).is_err(), "{} should be rejected", url);
+        }
+    }
+
+    #[test]
+    fn test_deep_link_event_choice() {
+        let spaces_dir = TempDir::new().unwrap();
+        let space = crate::spaces::create_space_in(
+            spaces_dir.path(),
+            crate::spaces::CreateSpaceRequest {
+                name: "Linked".to_string(),
+                template: "quick-start".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+        // A stray directory without space metadata is not a space
+        std::fs::create_dir(spaces_dir.path().join("stray")).unwrap();
+
+        let url = format!("thinkingspace://open?space_id={}", space.id);
+        let (event, payload) = deep_link_event(spaces_dir.path(), &url).unwrap();
+        assert_eq!(event, "deep-link-open-space");
+        assert_eq!(payload, json!({ "spaceId": space.id }));
+
+        let (event, _) =
+            deep_link_event(spaces_dir.path(), "thinkingspace://open?space_id=stray").unwrap();
+        assert_eq!(event, "deep-link-not-found");
+
+        let (event, payload) =
+            deep_link_event(spaces_dir.path(), "thinkingspace://open?space_id=missing").unwrap();
+        assert_eq!(event, "deep-link-not-found");
+        assert_eq!(payload, json!({ "spaceId": "missing" }));
+
+        assert!(deep_link_event(spaces_dir.path(), "thinkingspace://open").is_err());
+    }
+}
@@ -0,0 +1,231 @@
+// Errors returned to the frontend
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+use std::path::Path;
+
+/// A command error the frontend can tell apart by `error.type`
+///
+/// Serialized as `{ "type": "notFound", "message": "...", ...fields }`, where
+/// `message` is the same text `to_string()` gives.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThinkingSpaceError {
+    /// Reading or writing a file failed
+    Io {
+        path: String,
+        source: String,
+    },
+    Database(String),
+    Serialization(String),
+    /// A path or file was refused because it's outside what the app may touch
+    Security {
+        reason: String,
+    },
+    AgentNotConnected,
+    /// There's no system clipboard to use, e.g. when running headless
+    ClipboardUnavailable {
+        reason: String,
+    },
+    NotFound {
+        resource: String,
+    },
+    ValidationFailed {
+        field: String,
+        message: String,
+    },
+}
+
+impl ThinkingSpaceError {
+    pub fn io(path: impl AsRef<Path>, source: impl fmt::Display) -> Self {
+        Self::Io {
+            path: path.as_ref().display().to_string(),
+            source: source.to_string(),
+        }
+    }
+
+    pub fn security(reason: impl Into<String>) -> Self {
+        Self::Security {
+            reason: reason.into(),
+        }
+    }
+
+    pub fn not_found(resource: impl Into<String>) -> Self {
+        Self::NotFound {
+            resource: resource.into(),
+        }
+    }
+
+    pub fn validation(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::ValidationFailed {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    /// The `type` the frontend matches on
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Io { .. } => "io",
+            Self::Database(_) => "database",
+            Self::Serialization(_) => "serialization",
+            Self::Security { .. } => "security",
+            Self::AgentNotConnected => "agentNotConnected",
+            Self::ClipboardUnavailable { .. } => "clipboardUnavailable",
+            Self::NotFound { .. } => "notFound",
+            Self::ValidationFailed { .. } => "validationFailed",
+        }
+    }
+}
+
+impl fmt::Display for ThinkingSpaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // io::Error carries no path, so errors converted with `?` have none
+            Self::Io { path, source } if path.is_empty() => write!(f, "{}", source),
+            Self::Io { path, source } => write!(f, "Failed to access {}: {}", path, source),
+            Self::Database(message) | Self::Serialization(message) => write!(f, "{}", message),
+            Self::Security { reason } => write!(f, "{}", reason),
+            Self::AgentNotConnected => write!(f, "Not connected"),
+            Self::ClipboardUnavailable { reason } => {
+                write!(f, "Clipboard is not available: {}", reason)
+            }
+            Self::NotFound { resource } => write!(f, "{} not found", resource),
+            Self::ValidationFailed { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ThinkingSpaceError {}
+
+impl Serialize for ThinkingSpaceError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", self.type_name())?;
+        map.serialize_entry("message", &self.to_string())?;
+        match self {
+            Self::Io { path, source } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("source", source)?;
+            }
+            Self::Security { reason } | Self::ClipboardUnavailable { reason } => {
+                map.serialize_entry("reason", reason)?
+            }
+            Self::NotFound { resource } => map.serialize_entry("resource", resource)?,
+            Self::ValidationFailed { field, .. } => map.serialize_entry("field", field)?,
+            Self::Database(_) | Self::Serialization(_) | Self::AgentNotConnected => {}
+        }
+        map.end()
+    }
+}
+
+impl From<rusqlite::Error> for ThinkingSpaceError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Database(e.to_string())
+    }
+}
+
+impl From<tokio_rusqlite::Error> for ThinkingSpaceError {
+    fn from(e: tokio_rusqlite::Error) -> Self {
+        Self::Database(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ThinkingSpaceError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Serialization(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for ThinkingSpaceError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io {
+            path: String::new(),
+            source: e.to_string(),
+        }
+    }
+}
+
+/// Lets modules still returning `Result<_, String>` call migrated ones with `?`
+impl From<ThinkingSpaceError> for String {
+    fn from(e: ThinkingSpaceError) -> Self {
+        e.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_type_message_and_fields() {
+        let error = ThinkingSpaceError::not_found("Space abc");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "type": "notFound",
+                "message": "Space abc not found",
+                "resource": "Space abc",
+            })
+        );
+
+        let error = ThinkingSpaceError::io("/tmp/a.txt", "permission denied");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "type": "io",
+                "message": "Failed to access /tmp/a.txt: permission denied",
+                "path": "/tmp/a.txt",
+                "source": "permission denied",
+            })
+        );
+
+        let error = ThinkingSpaceError::validation("name", "Space name cannot be empty");
+        let value = serde_json::to_value(&error).unwrap();
+        assert_eq!(value["type"], "validationFailed");
+        assert_eq!(value["field"], "name");
+        assert_eq!(value["message"], "Space name cannot be empty");
+
+        let value = serde_json::to_value(ThinkingSpaceError::AgentNotConnected).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({ "type": "agentNotConnected", "message": "Not connected" })
+        );
+    }
+
+    #[test]
+    fn test_from_library_errors() {
+        let error: ThinkingSpaceError = rusqlite::Error::QueryReturnedNoRows.into();
+        assert!(matches!(error, ThinkingSpaceError::Database(_)));
+
+        let error: ThinkingSpaceError = serde_json::from_str::<serde_json::Value>("{")
+            .unwrap_err()
+            .into();
+        assert!(matches!(error, ThinkingSpaceError::Serialization(_)));
+
+        let error: ThinkingSpaceError =
+            std::io::Error::new(std::io::ErrorKind::NotFound, "gone").into();
+        assert_eq!(
+            error,
+            ThinkingSpaceError::Io {
+                path: String::new(),
+                source: "gone".to_string()
+            }
+        );
+        assert_eq!(error.to_string(), "gone");
+    }
+
+    #[test]
+    fn test_converts_to_string_for_unmigrated_callers() {
+        fn caller() -> Result<(), String> {
+            Err(ThinkingSpaceError::security(
+                "Path is outside the allowed directory",
+            ))?;
+            Ok(())
+        }
+
+        assert_eq!(
+            caller().unwrap_err(),
+            "Path is outside the allowed directory"
+        );
+    }
+}
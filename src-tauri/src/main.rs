@@ -3,73 +3,260 @@
 
 mod acp_v2;
 mod auth;
+mod clipboard;
 mod commands;
+mod config;
 mod conversations;
+mod db;
+mod deep_link;
+mod error;
 mod mcp_config;
 mod sessions;
 mod settings;
 mod spaces;
 mod terminal;
+mod tray;
 
 use acp_v2::AcpManager;
+use settings::SettingsManager;
 use std::sync::Arc;
+use tauri::Manager;
 
 fn main() {
     let acp_manager = Arc::new(AcpManager::new());
     let acp_manager_clone = acp_manager.clone();
+    let settings_manager = Arc::new(SettingsManager::new());
+    let settings_manager_clone = settings_manager.clone();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_deep_link::init())
         .setup(move |app| {
             // Set app handle for event emission
             acp_manager_clone.set_app_handle(app.handle().clone());
+            settings_manager_clone.set_app_handle(app.handle().clone());
+
+            // A broken scheme registration shouldn't stop the app from starting
+            if let Err(e) = deep_link::register_deep_link_protocol(app.handle()) {
+                eprintln!("[DEEP LINK] {}", e);
+            }
+
+            // Restore the main window where the user left it, and remember it on close
+            if let Some(window) = app.get_webview_window("main") {
+                match settings::load_window_state() {
+                    Ok(Some(state)) => {
+                        if let Err(e) = settings::apply_window_state(&window, &state) {
+                            eprintln!("[SETTINGS] {}", e);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("[SETTINGS] Failed to load window state: {}", e),
+                }
+
+                let tracked = window.clone();
+                window.on_window_event(move |event| {
+                    if let tauri::WindowEvent::CloseRequested { .. } = event {
+                        let saved = settings::capture_window_state(&tracked)
+                            .and_then(settings::save_window_state);
+                        if let Err(e) = saved {
+                            eprintln!("[SETTINGS] Failed to save window state: {}", e);
+                        }
+                    }
+                });
+            }
+
+            if let Err(e) = tray::create_tray(app.handle()) {
+                eprintln!("[TRAY] {}", e);
+            }
+
+            std::thread::spawn(|| match sessions::apply_session_retention_policy() {
+                Ok(report) => println!(
+                    "[SESSIONS] Retention: {} deleted, {} deactivated",
+                    report.inactive_deleted, report.active_deactivated
+                ),
+                Err(e) => eprintln!("[SESSIONS] Retention policy failed: {}", e),
+            });
+
+            // Weekly housekeeping, off the startup path
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                conversations::cleanup_orphaned_conversations_if_due(&handle)
+            });
 
             Ok(())
         })
         .manage(acp_manager)
+        .manage(settings_manager)
+        .manage(deep_link::PendingDeepLink::default())
+        .manage(clipboard::ClipboardState::default())
         .invoke_handler(tauri::generate_handler![
             spaces::list_spaces,
             spaces::create_space,
+            spaces::fetch_community_templates,
+            spaces::clone_space,
+            spaces::export_space,
+            spaces::import_space,
+            spaces::archive_space,
+            spaces::unarchive_space,
+            spaces::rename_space,
+            spaces::pin_space,
+            spaces::unpin_space,
+            spaces::reorder_pinned_spaces,
+            spaces::check_space_health,
+            spaces::repair_space,
+            spaces::get_space_statistics,
+            spaces::list_archived_spaces,
+            spaces::search_spaces,
+            spaces::filter_spaces_by_template,
             spaces::delete_space,
+            spaces::delete_spaces_batch,
             spaces::update_last_accessed,
             spaces::read_claude_md,
             spaces::write_claude_md,
+            spaces::list_claude_md_history,
+            spaces::restore_claude_md,
+            spaces::list_prompt_templates,
+            spaces::apply_prompt_template,
+            spaces::estimate_claude_md_tokens,
             spaces::list_space_files,
+            spaces::list_space_files_recursive,
+            spaces::preview_file_write,
+            spaces::write_space_file,
+            spaces::delete_space_file,
+            spaces::rename_space_file,
+            spaces::search_space_files,
+            spaces::get_git_status,
+            spaces::get_file_diff,
             spaces::open_file,
+            spaces::open_terminal_at_space,
             spaces::read_file_content,
+            spaces::read_file_binary,
+            spaces::get_file_info,
+            spaces::get_path_security_policy,
             conversations::save_conversation,
+            conversations::save_conversation_batch,
             conversations::load_conversation,
             conversations::delete_conversation,
+            conversations::delete_conversation_message,
+            conversations::rename_conversation,
+            conversations::merge_conversations,
+            conversations::diff_conversations,
+            conversations::import_from_claude_ai_export,
+            conversations::list_conversation_snapshots,
+            conversations::restore_conversation_snapshot,
+            conversations::export_conversation_csv,
+            conversations::export_all_conversations_csv,
+            conversations::set_conversation_title,
+            conversations::cleanup_orphaned_conversations,
             conversations::list_conversations,
+            conversations::get_conversation_token_summary,
+            conversations::check_database_integrity,
+            conversations::vacuum_database,
             settings::load_settings,
             settings::save_settings,
+            settings::save_window_state,
+            settings::load_window_state,
+            settings::reset_settings,
+            settings::export_settings,
+            settings::import_settings,
+            settings::get_keyboard_shortcut,
+            settings::set_keyboard_shortcut,
+            settings::validate_settings,
+            settings::load_space_settings,
+            settings::save_space_settings,
+            settings::get_effective_settings,
             settings::get_data_location,
             settings::open_data_folder,
             auth::has_claude_code_auth,
+            auth::get_credential_status,
+            auth::take_rotation_reminder,
             auth::load_claude_credentials,
             auth::load_claude_credentials_file,
+            auth::save_claude_credentials_to_keyring,
             auth::load_api_key,
             auth::save_api_key,
+            auth::migrate_api_key_to_encrypted,
             auth::refresh_oauth_token,
+            auth::load_and_refresh_if_needed,
             auth::open_external_url,
+            clipboard::read_clipboard,
+            clipboard::write_clipboard,
             // ACP (Agent Client Protocol) commands
             acp_v2::manager::agent_v2_send_message,
             acp_v2::manager::agent_v2_start,
             acp_v2::manager::agent_v2_stop,
+            acp_v2::manager::agent_v2_restart,
+            acp_v2::manager::generate_conversation_title,
+            acp_v2::manager::reload_mcp_config,
+            acp_v2::manager::agent_v2_set_adapter_config,
+            acp_v2::manager::agent_v2_get_adapter_logs,
+            acp_v2::manager::agent_v2_get_adapter_version,
+            acp_v2::manager::agent_v2_get_protocol_metadata,
+            acp_v2::manager::agent_v2_check_adapter_update,
+            acp_v2::manager::get_last_used_model,
             acp_v2::manager::agent_v2_send_permission_response,
+            acp_v2::manager::agent_v2_set_permission_timeout,
+            acp_v2::manager::agent_v2_set_permission_rules,
+            acp_v2::manager::agent_v2_set_show_thoughts,
+            acp_v2::manager::agent_v2_set_notifications,
+            acp_v2::manager::agent_v2_set_ambient_context,
+            acp_v2::manager::agent_v2_set_auto_summarise,
+            acp_v2::manager::agent_v2_set_event_batching,
+            acp_v2::manager::agent_v2_set_rate_limit,
+            acp_v2::manager::agent_v2_get_available_commands,
+            acp_v2::manager::get_context_usage,
+            acp_v2::manager::agent_v2_list_terminals,
+            acp_v2::manager::agent_v2_set_terminal_policy,
+            acp_v2::manager::create_terminal_group,
+            acp_v2::manager::add_terminal_to_group,
+            acp_v2::manager::get_group_output,
+            acp_v2::manager::get_terminal_output_range,
+            acp_v2::manager::start_space_file_watcher,
+            acp_v2::manager::stop_space_file_watcher,
             // Slash commands
             commands::list_slash_commands,
+            commands::list_slash_command_categories,
+            commands::list_slash_commands_by_category,
             commands::load_slash_command,
             commands::expand_slash_command,
             commands::create_slash_command,
+            commands::update_slash_command,
             commands::delete_slash_command,
+            commands::get_commands_directory_path,
+            commands::rename_slash_command,
+            commands::copy_slash_command,
+            commands::record_command_use,
+            commands::get_command_usage_stats,
+            commands::list_global_slash_commands,
+            commands::create_global_slash_command,
+            commands::delete_global_slash_command,
             // Session persistence
             sessions::save_session,
             sessions::load_session,
             sessions::get_active_session_for_space,
             sessions::deactivate_session,
+            sessions::detect_stale_sessions,
             sessions::cleanup_old_sessions,
+            sessions::update_session_metadata,
+            sessions::get_session_mode,
+            sessions::get_session_statistics,
+            sessions::apply_session_retention_policy,
+            sessions::get_permission_log,
+            sessions::clear_permission_log,
+            // MCP configuration
+            mcp_config::get_mcp_config,
+            mcp_config::save_mcp_config,
+            mcp_config::validate_mcp_config,
+            mcp_config::load_global_mcp_config,
+            mcp_config::save_global_mcp_config,
+            mcp_config::get_effective_mcp_config,
+            mcp_config::toggle_mcp_server,
+            mcp_config::get_mcp_server_status,
+            mcp_config::check_mcp_server,
+            // Deep links
+            deep_link::take_pending_deep_link,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,27 +1,309 @@
 // MCP Server Configuration
 // Simple file-based configuration for MCP servers
 
-use agent_client_protocol_schema::{EnvVariable, McpServer};
-use serde::{Deserialize, Serialize};
+use agent_client_protocol_schema::{EnvVariable, HttpHeader, McpServer};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Configuration file format for MCP servers
 /// Stored as .mcp.json in the Space directory
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct McpConfig {
+    #[serde(deserialize_with = "deserialize_servers")]
     pub mcp_servers: HashMap<String, McpServerConfig>,
 }
 
 /// Individual MCP server configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum McpServerConfig {
+    /// Local process speaking MCP over stdin/stdout
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: HashMap<String, String>,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+    },
+    /// Remote server over streamable HTTP
+    Http {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+    },
+    /// Remote server over Server-Sent Events
+    Sse {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+    },
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl McpServerConfig {
+    /// Disabled servers stay in the config but aren't started
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            McpServerConfig::Stdio { enabled, .. }
+            | McpServerConfig::Http { enabled, .. }
+            | McpServerConfig::Sse { enabled, .. } => *enabled,
+        }
+    }
+
+    pub fn set_enabled(&mut self, value: bool) {
+        match self {
+            McpServerConfig::Stdio { enabled, .. }
+            | McpServerConfig::Http { enabled, .. }
+            | McpServerConfig::Sse { enabled, .. } => *enabled = value,
+        }
+    }
+}
+
+/// Deserialize the server map, treating entries without a "type" as stdio
+/// so .mcp.json files written before HTTP/SSE support keep working
+fn deserialize_servers<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<String, McpServerConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = HashMap::<String, serde_json::Value>::deserialize(deserializer)?;
+
+    raw.into_iter()
+        .map(|(name, mut value)| {
+            if let Some(obj) = value.as_object_mut() {
+                obj.entry("type")
+                    .or_insert_with(|| serde_json::Value::String("stdio".to_string()));
+            }
+            serde_json::from_value(value)
+                .map(|config| (name, config))
+                .map_err(serde::de::Error::custom)
+        })
+        .collect()
+}
+
+/// Result of checking a Space's MCP configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct McpServerConfig {
-    pub command: String,
-    #[serde(default)]
-    pub args: Vec<String>,
-    #[serde(default)]
-    pub env: HashMap<String, String>,
+pub struct McpConfigValidation {
+    /// `${VAR}` references with no matching process environment variable
+    pub unresolved_vars: Vec<String>,
+    pub server_count: usize,
+}
+
+/// Result of checking that a single MCP server can be reached
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerValidation {
+    /// Stdio: the command exists and is executable. Http/Sse: the host accepted a TCP connection
+    pub command_found: bool,
+    /// Resolved executable path (Stdio) or host:port (Http/Sse)
+    pub command_path: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Check that an MCP server's command exists (Stdio) or its host is reachable (Http/Sse)
+pub async fn validate_mcp_server(config: &McpServerConfig) -> Result<McpServerValidation, String> {
+    match config {
+        McpServerConfig::Stdio { command, .. } => {
+            let path_var = std::env::var_os("PATH").unwrap_or_default();
+            Ok(validate_command(command, &path_var))
+        }
+        McpServerConfig::Http { url, .. } | McpServerConfig::Sse { url, .. } => {
+            Ok(validate_url(url).await)
+        }
+    }
+}
+
+fn validate_command(command: &str, path_var: &OsStr) -> McpServerValidation {
+    match find_in_path(command, path_var) {
+        Some(path) => McpServerValidation {
+            command_found: true,
+            command_path: Some(path.to_string_lossy().to_string()),
+            warnings: Vec::new(),
+        },
+        None => McpServerValidation {
+            command_found: false,
+            command_path: None,
+            warnings: vec![format!(
+                "Command '{}' was not found or is not executable",
+                command
+            )],
+        },
+    }
+}
+
+/// Locate an executable, walking `path_var` unless the command already contains a path
+fn find_in_path(command: &str, path_var: &OsStr) -> Option<PathBuf> {
+    let command_path = Path::new(command);
+
+    if command_path.components().count() > 1 {
+        return is_executable(command_path).then(|| command_path.to_path_buf());
+    }
+
+    std::env::split_paths(path_var).find_map(|dir| {
+        let candidate = dir.join(command);
+        if is_executable(&candidate) {
+            return Some(candidate);
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            for ext in ["exe", "cmd", "bat"] {
+                let candidate = candidate.with_extension(ext);
+                if is_executable(&candidate) {
+                    return Some(candidate);
+                }
+            }
+        }
+
+        None
+    })
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+/// How long a remote MCP server gets to accept a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+async fn validate_url(url: &str) -> McpServerValidation {
+    let Some(address) = host_and_port(url) else {
+        return McpServerValidation {
+            command_found: false,
+            command_path: None,
+            warnings: vec![format!("Invalid server URL '{}'", url)],
+        };
+    };
+
+    // Name lookup and connecting both count against the timeout
+    let reachable = tokio::time::timeout(CONNECT_TIMEOUT, async {
+        let Ok(addrs) = tokio::net::lookup_host(address.as_str()).await else {
+            return false;
+        };
+        for addr in addrs {
+            if tokio::net::TcpStream::connect(addr).await.is_ok() {
+                return true;
+            }
+        }
+        false
+    })
+    .await
+    .unwrap_or(false);
+
+    let warnings = if reachable {
+        Vec::new()
+    } else {
+        vec![format!("Could not connect to {}", address)]
+    };
+
+    McpServerValidation {
+        command_found: reachable,
+        command_path: Some(address),
+        warnings,
+    }
+}
+
+/// Extract "host:port" from an http(s) URL, filling in the default port
+fn host_and_port(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let default_port = match scheme {
+        "http" => 80,
+        "https" => 443,
+        _ => return None,
+    };
+
+    let authority = rest.split(['/', '?', '#']).next()?;
+    // Drop any userinfo
+    let host_port = authority.rsplit('@').next()?;
+    if host_port.is_empty() {
+        return None;
+    }
+
+    // Bracketed IPv6 literals contain ':' themselves
+    let has_port = match host_port.rfind(']') {
+        Some(bracket) => host_port[bracket..].contains(':'),
+        None => host_port.contains(':'),
+    };
+
+    if has_port {
+        Some(host_port.to_string())
+    } else {
+        Some(format!("{}:{}", host_port, default_port))
+    }
+}
+
+/// Replace `${VAR_NAME}` patterns with values from the process environment
+/// Unset variables are left as-is and their names pushed to `unresolved`
+fn expand_env_vars(value: &str, unresolved: &mut Vec<String>) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        let Some(end) = after.find('}') else {
+            // Unterminated reference - keep the remainder verbatim
+            result.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+
+        let name = &after[..end];
+        match std::env::var(name) {
+            Ok(resolved) if !name.is_empty() => result.push_str(&resolved),
+            _ => {
+                result.push_str(&rest[start..start + end + 3]);
+                if !unresolved.iter().any(|v| v == name) {
+                    unresolved.push(name.to_string());
+                }
+            }
+        }
+
+        rest = &after[end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Convert a header map to the ACP library's HttpHeader list
+fn to_http_headers(headers: &HashMap<String, String>) -> Vec<HttpHeader> {
+    headers
+        .iter()
+        .map(|(k, v)| HttpHeader {
+            name: k.clone(),
+            value: v.clone(),
+            meta: None,
+        })
+        .collect()
 }
 
 impl McpConfig {
@@ -33,9 +315,17 @@ impl McpConfig {
         serde_json::from_str(&contents).map_err(|e| format!("Failed to parse MCP config: {}", e))
     }
 
-    /// Load MCP configuration from a Space directory
-    /// Looks for .mcp.json in the space path
+    /// Load the effective MCP configuration for a Space
+    /// Global servers are merged in, with the Space's .mcp.json taking precedence
     pub fn load_from_space(space_path: &Path) -> Result<Self, String> {
+        let global = Self::load_global()?;
+        let space = Self::load_space_config(space_path)?;
+
+        Ok(Self::merge(&global, &space))
+    }
+
+    /// Load only the Space-level configuration (.mcp.json in the space path)
+    pub fn load_space_config(space_path: &Path) -> Result<Self, String> {
         let config_path = space_path.join(".mcp.json");
 
         if !config_path.exists() {
@@ -48,26 +338,237 @@ impl McpConfig {
         Self::load_from_file(&config_path)
     }
 
-    /// Convert to ACP library's McpServer format
+    /// Load the global configuration that applies to every Space
+    /// Stored as global-mcp.json in the data directory (~/.thinking-space by default)
+    pub fn load_global() -> Result<Self, String> {
+        let config_path = get_global_config_path()?;
+
+        if !config_path.exists() {
+            return Ok(McpConfig {
+                mcp_servers: HashMap::new(),
+            });
+        }
+
+        Self::load_from_file(&config_path)
+    }
+
+    /// Save this configuration as the global configuration
+    pub fn save_global(&self) -> Result<(), String> {
+        let config_path = get_global_config_path()?;
+
+        if let Some(parent) = config_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize MCP config: {}", e))?;
+
+        std::fs::write(&config_path, json).map_err(|e| format!("Failed to write MCP config: {}", e))
+    }
+
+    /// Merge two configurations; servers in `override_config` replace
+    /// servers with the same name in `base`
+    pub fn merge(base: &McpConfig, override_config: &McpConfig) -> McpConfig {
+        let mut mcp_servers = base.mcp_servers.clone();
+        mcp_servers.extend(
+            override_config
+                .mcp_servers
+                .iter()
+                .map(|(name, config)| (name.clone(), config.clone())),
+        );
+
+        McpConfig { mcp_servers }
+    }
+
+    /// Convert to ACP library's McpServer format, leaving out disabled servers
     pub fn to_acp_servers(&self) -> Vec<McpServer> {
         self.mcp_servers
             .iter()
-            .map(|(name, config)| McpServer::Stdio {
-                name: name.clone(),
-                command: PathBuf::from(&config.command),
-                args: config.args.clone(),
-                env: config
-                    .env
-                    .iter()
-                    .map(|(k, v)| EnvVariable {
-                        name: k.clone(),
-                        value: v.clone(),
-                        meta: None,
-                    })
-                    .collect(),
+            .filter(|(_, config)| config.is_enabled())
+            .map(|(name, config)| match config {
+                McpServerConfig::Stdio {
+                    command, args, env, ..
+                } => McpServer::Stdio {
+                    name: name.clone(),
+                    command: PathBuf::from(command),
+                    args: args.clone(),
+                    env: env
+                        .iter()
+                        .map(|(k, v)| EnvVariable {
+                            name: k.clone(),
+                            value: v.clone(),
+                            meta: None,
+                        })
+                        .collect(),
+                },
+                McpServerConfig::Http { url, headers, .. } => McpServer::Http {
+                    name: name.clone(),
+                    url: url.clone(),
+                    headers: to_http_headers(headers),
+                },
+                McpServerConfig::Sse { url, headers, .. } => McpServer::Sse {
+                    name: name.clone(),
+                    url: url.clone(),
+                    headers: to_http_headers(headers),
+                },
             })
             .collect()
     }
+
+    /// Expand `${VAR_NAME}` references in every server's env map in place
+    /// Returns the names of variables that aren't set (left unexpanded)
+    pub fn resolve_env_vars(&mut self) -> Vec<String> {
+        let mut unresolved = Vec::new();
+
+        for config in self.mcp_servers.values_mut() {
+            if let McpServerConfig::Stdio { env, .. } = config {
+                for value in env.values_mut() {
+                    *value = expand_env_vars(value, &mut unresolved);
+                }
+            }
+        }
+
+        unresolved.sort();
+        unresolved
+    }
+
+    /// Like `resolve_env_vars`, but returns a resolved copy and leaves `self` untouched
+    pub fn with_resolved_env_vars(&self) -> (McpConfig, Vec<String>) {
+        let mut resolved = self.clone();
+        let unresolved = resolved.resolve_env_vars();
+        (resolved, unresolved)
+    }
+
+    /// Whether each server is enabled, by name
+    pub fn server_status(&self) -> HashMap<String, bool> {
+        self.mcp_servers
+            .iter()
+            .map(|(name, config)| (name.clone(), config.is_enabled()))
+            .collect()
+    }
+
+    /// Save MCP configuration to a .mcp.json file in a Space directory
+    pub fn save_to_space(&self, space_path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize MCP config: {}", e))?;
+
+        std::fs::write(space_path.join(".mcp.json"), json)
+            .map_err(|e| format!("Failed to write MCP config: {}", e))
+    }
+}
+
+/// Enable or disable a server in a Space's .mcp.json
+/// A global server is copied into the Space's config first, so other Spaces keep it as is
+fn toggle_mcp_server_in(
+    space_path: &Path,
+    global: &McpConfig,
+    server_name: &str,
+    enabled: bool,
+) -> Result<(), String> {
+    let mut config = McpConfig::load_space_config(space_path)?;
+
+    let server = match config.mcp_servers.entry(server_name.to_string()) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let global_server = global
+                .mcp_servers
+                .get(server_name)
+                .cloned()
+                .ok_or_else(|| format!("MCP server not found: {}", server_name))?;
+            entry.insert(global_server)
+        }
+    };
+    server.set_enabled(enabled);
+
+    config.save_to_space(space_path)
+}
+
+/// Get the path to the global MCP config file
+fn get_global_config_path() -> Result<PathBuf, String> {
+    Ok(crate::config::get_data_dir()?.join("global-mcp.json"))
+}
+
+// =============================================================================
+// Tauri Commands
+// =============================================================================
+
+#[tauri::command]
+pub fn get_mcp_config(space_id: String) -> Result<McpConfig, String> {
+    McpConfig::load_space_config(&crate::spaces::get_space_dir(&space_id)?)
+}
+
+/// Save a Space's .mcp.json, returning warnings for servers that can't be reached
+#[tauri::command]
+pub async fn save_mcp_config(space_id: String, config: McpConfig) -> Result<Vec<String>, String> {
+    config.save_to_space(&crate::spaces::get_space_dir(&space_id)?)?;
+
+    let mut names: Vec<&String> = config.mcp_servers.keys().collect();
+    names.sort();
+
+    let mut warnings = Vec::new();
+    for name in names {
+        let validation = validate_mcp_server(&config.mcp_servers[name]).await?;
+        warnings.extend(
+            validation
+                .warnings
+                .into_iter()
+                .map(|warning| format!("{}: {}", name, warning)),
+        );
+    }
+
+    Ok(warnings)
+}
+
+#[tauri::command]
+pub fn toggle_mcp_server(
+    space_id: String,
+    server_name: String,
+    enabled: bool,
+) -> Result<(), String> {
+    toggle_mcp_server_in(
+        &crate::spaces::get_space_dir(&space_id)?,
+        &McpConfig::load_global()?,
+        &server_name,
+        enabled,
+    )
+}
+
+/// Enabled state of every server the Space would start (global and its own)
+#[tauri::command]
+pub fn get_mcp_server_status(space_id: String) -> Result<HashMap<String, bool>, String> {
+    Ok(McpConfig::load_from_space(&crate::spaces::get_space_dir(&space_id)?)?.server_status())
+}
+
+#[tauri::command]
+pub async fn check_mcp_server(config: McpServerConfig) -> Result<McpServerValidation, String> {
+    validate_mcp_server(&config).await
+}
+
+#[tauri::command]
+pub fn load_global_mcp_config() -> Result<McpConfig, String> {
+    McpConfig::load_global()
+}
+
+#[tauri::command]
+pub fn save_global_mcp_config(config: McpConfig) -> Result<(), String> {
+    config.save_global()
+}
+
+#[tauri::command]
+pub fn get_effective_mcp_config(space_id: String) -> Result<McpConfig, String> {
+    McpConfig::load_from_space(&crate::spaces::get_space_dir(&space_id)?)
+}
+
+#[tauri::command]
+pub fn validate_mcp_config(space_id: String) -> Result<McpConfigValidation, String> {
+    let config = McpConfig::load_from_space(&crate::spaces::get_space_dir(&space_id)?)?;
+    let (_, unresolved_vars) = config.with_resolved_env_vars();
+
+    Ok(McpConfigValidation {
+        unresolved_vars,
+        server_count: config.mcp_servers.len(),
+    })
 }
 
 #[cfg(test)]
@@ -98,12 +599,94 @@ mod tests {
         let config: McpConfig = serde_json::from_str(json).unwrap();
         assert_eq!(config.mcp_servers.len(), 2);
 
-        let filesystem = config.mcp_servers.get("filesystem").unwrap();
-        assert_eq!(filesystem.command, "npx");
-        assert_eq!(filesystem.args.len(), 3);
+        // Entries without a "type" are treated as stdio servers
+        match config.mcp_servers.get("filesystem").unwrap() {
+            McpServerConfig::Stdio { command, args, .. } => {
+                assert_eq!(command, "npx");
+                assert_eq!(args.len(), 3);
+            }
+            other => panic!("Expected Stdio config, got {:?}", other),
+        }
+
+        match config.mcp_servers.get("github").unwrap() {
+            McpServerConfig::Stdio { env, .. } => {
+                assert_eq!(env.get("GITHUB_TOKEN").unwrap(), "test-token");
+            }
+            other => panic!("Expected Stdio config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_all_server_types() {
+        let json = r#"
+        {
+            "mcpServers": {
+                "local": {
+                    "type": "stdio",
+                    "command": "npx",
+                    "args": ["-y", "@modelcontextprotocol/server-memory"]
+                },
+                "remote": {
+                    "type": "http",
+                    "url": "https://example.com/mcp",
+                    "headers": {
+                        "Authorization": "Bearer abc"
+                    }
+                },
+                "events": {
+                    "type": "sse",
+                    "url": "https://example.com/sse"
+                }
+            }
+        }
+        "#;
+
+        let config: McpConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.mcp_servers.len(), 3);
+
+        let mut acp_servers = config.to_acp_servers();
+        acp_servers.sort_by_key(|server| match server {
+            McpServer::Stdio { name, .. } => name.clone(),
+            McpServer::Http { name, .. } => name.clone(),
+            McpServer::Sse { name, .. } => name.clone(),
+        });
+
+        match &acp_servers[0] {
+            McpServer::Sse { name, url, headers } => {
+                assert_eq!(name, "events");
+                assert_eq!(url, "https://example.com/sse");
+                assert!(headers.is_empty());
+            }
+            other => panic!("Expected Sse variant, got {:?}", other),
+        }
 
-        let github = config.mcp_servers.get("github").unwrap();
-        assert_eq!(github.env.get("GITHUB_TOKEN").unwrap(), "test-token");
+        match &acp_servers[1] {
+            McpServer::Stdio { name, command, .. } => {
+                assert_eq!(name, "local");
+                assert_eq!(command, &PathBuf::from("npx"));
+            }
+            other => panic!("Expected Stdio variant, got {:?}", other),
+        }
+
+        match &acp_servers[2] {
+            McpServer::Http { name, url, headers } => {
+                assert_eq!(name, "remote");
+                assert_eq!(url, "https://example.com/mcp");
+                assert_eq!(headers.len(), 1);
+                assert_eq!(headers[0].name, "Authorization");
+                assert_eq!(headers[0].value, "Bearer abc");
+            }
+            other => panic!("Expected Http variant, got {:?}", other),
+        }
+
+        // Serializing writes an explicit "type" tag that parses back identically
+        let round_tripped: McpConfig =
+            serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(round_tripped.to_acp_servers().len(), 3);
+        assert!(matches!(
+            round_tripped.mcp_servers.get("remote").unwrap(),
+            McpServerConfig::Http { .. }
+        ));
     }
 
     #[test]
@@ -111,12 +694,13 @@ mod tests {
         let config = McpConfig {
             mcp_servers: [(
                 "test".to_string(),
-                McpServerConfig {
+                McpServerConfig::Stdio {
                     command: "echo".to_string(),
                     args: vec!["hello".to_string()],
                     env: [("KEY".to_string(), "value".to_string())]
                         .into_iter()
                         .collect(),
+                    enabled: true,
                 },
             )]
             .into_iter()
@@ -144,10 +728,306 @@ mod tests {
         }
     }
 
+    fn stdio_config_with_env(env: &[(&str, &str)]) -> McpConfig {
+        McpConfig {
+            mcp_servers: [(
+                "test".to_string(),
+                McpServerConfig::Stdio {
+                    command: "echo".to_string(),
+                    args: vec![],
+                    env: env
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                    enabled: true,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    fn env_value(config: &McpConfig, key: &str) -> String {
+        match config.mcp_servers.get("test").unwrap() {
+            McpServerConfig::Stdio { env, .. } => env.get(key).unwrap().clone(),
+            other => panic!("Expected Stdio config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_resolve_env_vars() {
+        std::env::set_var("TS_MCP_TEST_TOKEN", "secret");
+
+        let mut config = stdio_config_with_env(&[
+            ("TOKEN", "${TS_MCP_TEST_TOKEN}"),
+            ("HEADER", "Bearer ${TS_MCP_TEST_TOKEN}!"),
+            ("PLAIN", "unchanged"),
+        ]);
+
+        let unresolved = config.resolve_env_vars();
+
+        assert!(unresolved.is_empty());
+        assert_eq!(env_value(&config, "TOKEN"), "secret");
+        assert_eq!(env_value(&config, "HEADER"), "Bearer secret!");
+        assert_eq!(env_value(&config, "PLAIN"), "unchanged");
+    }
+
+    #[test]
+    fn test_resolve_env_vars_reports_missing() {
+        std::env::remove_var("TS_MCP_TEST_MISSING");
+
+        let mut config = stdio_config_with_env(&[
+            ("A", "${TS_MCP_TEST_MISSING}"),
+            ("B", "prefix-${TS_MCP_TEST_MISSING}"),
+        ]);
+
+        let unresolved = config.resolve_env_vars();
+
+        // Reported once, and left verbatim in the config
+        assert_eq!(unresolved, vec!["TS_MCP_TEST_MISSING".to_string()]);
+        assert_eq!(env_value(&config, "A"), "${TS_MCP_TEST_MISSING}");
+        assert_eq!(env_value(&config, "B"), "prefix-${TS_MCP_TEST_MISSING}");
+    }
+
+    #[test]
+    fn test_with_resolved_env_vars_leaves_original_untouched() {
+        std::env::set_var("TS_MCP_TEST_PRESENT", "value");
+        std::env::remove_var("TS_MCP_TEST_ABSENT");
+
+        let config = stdio_config_with_env(&[
+            ("PRESENT", "${TS_MCP_TEST_PRESENT}"),
+            ("ABSENT", "${TS_MCP_TEST_ABSENT}"),
+        ]);
+
+        let (resolved, unresolved) = config.with_resolved_env_vars();
+
+        assert_eq!(unresolved, vec!["TS_MCP_TEST_ABSENT".to_string()]);
+        assert_eq!(env_value(&resolved, "PRESENT"), "value");
+        assert_eq!(env_value(&config, "PRESENT"), "${TS_MCP_TEST_PRESENT}");
+        assert_eq!(env_value(&config, "ABSENT"), "${TS_MCP_TEST_ABSENT}");
+    }
+
+    #[test]
+    fn test_merge_space_shadows_global() {
+        let global = McpConfig {
+            mcp_servers: [
+                (
+                    "shared".to_string(),
+                    McpServerConfig::Http {
+                        url: "https://global.example.com".to_string(),
+                        headers: HashMap::new(),
+                        enabled: true,
+                    },
+                ),
+                (
+                    "global-only".to_string(),
+                    McpServerConfig::Sse {
+                        url: "https://sse.example.com".to_string(),
+                        headers: HashMap::new(),
+                        enabled: true,
+                    },
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        let space = McpConfig {
+            mcp_servers: [(
+                "shared".to_string(),
+                McpServerConfig::Stdio {
+                    command: "space-server".to_string(),
+                    args: vec![],
+                    env: HashMap::new(),
+                    enabled: true,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+
+        let merged = McpConfig::merge(&global, &space);
+
+        assert_eq!(merged.mcp_servers.len(), 2);
+
+        // Space-level definition wins for the shared name
+        match merged.mcp_servers.get("shared").unwrap() {
+            McpServerConfig::Stdio { command, .. } => assert_eq!(command, "space-server"),
+            other => panic!("Expected space-level Stdio config, got {:?}", other),
+        }
+
+        // Unique global servers are kept
+        assert!(matches!(
+            merged.mcp_servers.get("global-only").unwrap(),
+            McpServerConfig::Sse { .. }
+        ));
+    }
+
+    #[test]
+    fn test_load_space_config_ignores_global() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join(".mcp.json"),
+            r#"{"mcpServers": {"local": {"command": "echo"}}}"#,
+        )
+        .unwrap();
+
+        let config = McpConfig::load_space_config(temp_dir.path()).unwrap();
+        assert_eq!(config.mcp_servers.len(), 1);
+        assert!(config.mcp_servers.contains_key("local"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_command_with_mock_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let bin_dir = tempfile::TempDir::new().unwrap();
+        let empty_dir = tempfile::TempDir::new().unwrap();
+
+        let tool = bin_dir.path().join("mcp-tool");
+        std::fs::write(&tool, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let not_executable = bin_dir.path().join("mcp-data");
+        std::fs::write(&not_executable, "data").unwrap();
+        std::fs::set_permissions(&not_executable, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let path_var = std::env::join_paths([empty_dir.path(), bin_dir.path()]).unwrap();
+
+        let found = validate_command("mcp-tool", &path_var);
+        assert!(found.command_found);
+        assert_eq!(found.command_path, Some(tool.to_string_lossy().to_string()));
+        assert!(found.warnings.is_empty());
+
+        let missing = validate_command("not-a-real-mcp-tool", &path_var);
+        assert!(!missing.command_found);
+        assert!(missing.command_path.is_none());
+        assert_eq!(missing.warnings.len(), 1);
+
+        // Present but not executable doesn't count
+        assert!(!validate_command("mcp-data", &path_var).command_found);
+
+        // Absolute paths are checked directly, without consulting PATH
+        let absolute = validate_command(tool.to_str().unwrap(), OsStr::new(""));
+        assert!(absolute.command_found);
+    }
+
+    #[test]
+    fn test_host_and_port() {
+        assert_eq!(
+            host_and_port("https://example.com/mcp"),
+            Some("example.com:443".to_string())
+        );
+        assert_eq!(
+            host_and_port("http://localhost:3000/sse?x=1"),
+            Some("localhost:3000".to_string())
+        );
+        assert_eq!(
+            host_and_port("http://[::1]/mcp"),
+            Some("[::1]:80".to_string())
+        );
+        assert_eq!(host_and_port("ftp://example.com"), None);
+        assert_eq!(host_and_port("not a url"), None);
+    }
+
+    #[tokio::test]
+    async fn test_validate_unreachable_http_server() {
+        // Bind then drop a listener to get a port that refuses connections
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let validation = validate_mcp_server(&McpServerConfig::Http {
+            url: format!("http://127.0.0.1:{}/mcp", port),
+            headers: HashMap::new(),
+            enabled: true,
+        })
+        .await
+        .unwrap();
+
+        assert!(!validation.command_found);
+        assert_eq!(validation.warnings.len(), 1);
+    }
+
     #[test]
     fn test_load_missing_config() {
         // Loading from a non-existent directory should return empty config
-        let config = McpConfig::load_from_space(Path::new("/nonexistent/path")).unwrap();
+        let config = McpConfig::load_space_config(Path::new("/nonexistent/path")).unwrap();
         assert_eq!(config.mcp_servers.len(), 0);
     }
+
+    #[test]
+    fn test_disabled_server_not_started() {
+        let config: McpConfig = serde_json::from_str(
+            r#"{"mcpServers": {
+                "on": {"command": "echo"},
+                "off": {"command": "echo", "enabled": false},
+                "remote-off": {"type": "http", "url": "https://example.com", "enabled": false}
+            }}"#,
+        )
+        .unwrap();
+
+        let acp_servers = config.to_acp_servers();
+        assert_eq!(acp_servers.len(), 1);
+        assert!(matches!(&acp_servers[0], McpServer::Stdio { name, .. } if name == "on"));
+
+        assert_eq!(
+            config.server_status(),
+            HashMap::from([
+                ("on".to_string(), true),
+                ("off".to_string(), false),
+                ("remote-off".to_string(), false),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_toggle_mcp_server_persists() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join(".mcp.json"),
+            r#"{"mcpServers": {"local": {"command": "echo", "args": ["hi"]}}}"#,
+        )
+        .unwrap();
+        let no_global = McpConfig {
+            mcp_servers: HashMap::new(),
+        };
+
+        toggle_mcp_server_in(temp_dir.path(), &no_global, "local", false).unwrap();
+        let config = McpConfig::load_space_config(temp_dir.path()).unwrap();
+        assert!(!config.mcp_servers["local"].is_enabled());
+        assert!(config.to_acp_servers().is_empty());
+        // The rest of the server's settings survive the round-trip
+        assert!(matches!(
+            &config.mcp_servers["local"],
+            McpServerConfig::Stdio { args, .. } if args == &vec!["hi".to_string()]
+        ));
+
+        toggle_mcp_server_in(temp_dir.path(), &no_global, "local", true).unwrap();
+        let config = McpConfig::load_space_config(temp_dir.path()).unwrap();
+        assert!(config.mcp_servers["local"].is_enabled());
+        assert_eq!(config.to_acp_servers().len(), 1);
+
+        assert!(toggle_mcp_server_in(temp_dir.path(), &no_global, "missing", false).is_err());
+    }
+
+    #[test]
+    fn test_toggle_global_mcp_server_only_affects_space() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let global: McpConfig =
+            serde_json::from_str(r#"{"mcpServers": {"shared": {"command": "echo"}}}"#).unwrap();
+
+        toggle_mcp_server_in(temp_dir.path(), &global, "shared", false).unwrap();
+
+        let space = McpConfig::load_space_config(temp_dir.path()).unwrap();
+        let effective = McpConfig::merge(&global, &space);
+        assert_eq!(
+            effective.server_status(),
+            HashMap::from([("shared".to_string(), false)])
+        );
+        assert!(global.mcp_servers["shared"].is_enabled());
+    }
 }
@@ -1,6 +1,7 @@
+use crate::settings::SessionRetentionPolicy;
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// ACP Session state for a Space
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,14 +21,39 @@ pub struct SessionState {
     pub metadata: serde_json::Value,
 }
 
+/// A permission decision made for a tool call, either by the user or a rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionLogEntry {
+    pub id: i64,
+    pub session_id: String,
+    pub request_id: String,
+    pub tool_call_id: String,
+    pub kind: String,
+    pub outcome: String,
+    pub decided_at: i64,
+}
+
+/// Aggregate session figures, for one space or across all of them
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionStatistics {
+    pub total_sessions: usize,
+    pub active_sessions: usize,
+    pub average_session_duration_seconds: f64,
+    /// Creation time of the longest-running active session
+    pub oldest_active_session_at: Option<i64>,
+    pub sessions_created_last_7d: usize,
+}
+
+/// Rows returned by get_permission_log when no limit is given
+const DEFAULT_PERMISSION_LOG_LIMIT: usize = 100;
+
 /// Get the path to the sessions database
-fn get_db_path() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let thinking_space_dir = home.join(".thinking-space");
+pub(crate) fn get_db_path() -> Result<PathBuf, String> {
+    let thinking_space_dir = crate::config::get_data_dir()?;
 
     // Create directory if it doesn't exist
     std::fs::create_dir_all(&thinking_space_dir)
-        .map_err(|e| format!("Failed to create .thinking-space directory: {}", e))?;
+        .map_err(|e| format!("Failed to create data directory: {}", e))?;
 
     Ok(thinking_space_dir.join("sessions.db"))
 }
@@ -63,14 +89,40 @@ fn init_database(conn: &Connection) -> Result<(), String> {
     )
     .map_err(|e| format!("Failed to create is_active index: {}", e))?;
 
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS permission_log (
+            id INTEGER PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            request_id TEXT NOT NULL,
+            tool_call_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            outcome TEXT NOT NULL,
+            decided_at INTEGER NOT NULL
+        ) STRICT",
+        [],
+    )
+    .map_err(|e| format!("Failed to create permission_log table: {}", e))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_permission_log_session
+         ON permission_log(session_id, decided_at DESC)",
+        [],
+    )
+    .map_err(|e| format!("Failed to create permission_log index: {}", e))?;
+
     Ok(())
 }
 
 /// Get a connection to the database
 fn get_connection() -> Result<Connection, String> {
-    let db_path = get_db_path()?;
-    let conn = Connection::open(&db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+    get_connection_at(&get_db_path()?)
+}
 
+/// Get a connection to the database at a specific path
+pub(crate) fn get_connection_at(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+
+    crate::db::configure_connection(&conn)?;
     init_database(&conn)?;
 
     Ok(conn)
@@ -79,14 +131,31 @@ fn get_connection() -> Result<Connection, String> {
 /// Save a session state to the database
 fn save_session_internal(session: &SessionState) -> Result<(), String> {
     let conn = get_connection()?;
+    upsert_session(&conn, session)
+}
 
-    let metadata_json = serde_json::to_string(&session.metadata)
+/// Insert or update a session
+/// Metadata is merged into what's stored, so keys recorded by the backend
+/// (e.g. currentMode) survive the frontend saving its own copy
+pub(crate) fn upsert_session(conn: &Connection, session: &SessionState) -> Result<(), String> {
+    let metadata = if session.metadata.is_object() {
+        session.metadata.clone()
+    } else {
+        serde_json::json!({})
+    };
+    let metadata_json = serde_json::to_string(&metadata)
         .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
 
     conn.execute(
-        "INSERT OR REPLACE INTO sessions
+        "INSERT INTO sessions
          (session_id, space_id, created_at, last_active, is_active, metadata)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(session_id) DO UPDATE SET
+            space_id = excluded.space_id,
+            created_at = excluded.created_at,
+            last_active = excluded.last_active,
+            is_active = excluded.is_active,
+            metadata = json_patch(sessions.metadata, excluded.metadata)",
         params![
             &session.session_id,
             &session.space_id,
@@ -183,20 +252,302 @@ fn deactivate_session_internal(session_id: &str) -> Result<(), String> {
     Ok(())
 }
 
-/// Delete old inactive sessions (older than 30 days)
+/// Sessions removed or deactivated by a retention pass
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionCleanupReport {
+    pub inactive_deleted: usize,
+    pub active_deactivated: usize,
+}
+
+fn days_before(now: i64, days: u32) -> i64 {
+    now - i64::from(days) * 24 * 60 * 60
+}
+
+/// Delete inactive sessions idle for more than `days`
+fn delete_inactive_sessions(conn: &Connection, days: u32, now: i64) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM sessions WHERE is_active = 0 AND last_active < ?1",
+        params![days_before(now, days)],
+    )
+    .map_err(|e| format!("Failed to cleanup old sessions: {}", e))
+}
+
+/// Sessions still marked active that haven't been used for `threshold_minutes`
+/// (usually left behind by a crash)
+pub(crate) fn stale_session_ids_internal(
+    conn: &Connection,
+    threshold_minutes: u32,
+    now: i64,
+) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT session_id FROM sessions
+             WHERE is_active = 1 AND last_active < ?1
+             ORDER BY session_id",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let ids = stmt
+        .query_map(params![now - i64::from(threshold_minutes) * 60], |row| {
+            row.get(0)
+        })
+        .map_err(|e| format!("Failed to query stale sessions: {}", e))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| format!("Failed to read stale sessions: {}", e))?;
+
+    Ok(ids)
+}
+
+pub(crate) fn deactivate_sessions_internal(
+    conn: &Connection,
+    session_ids: &[String],
+) -> Result<usize, String> {
+    let mut deactivated = 0;
+    for session_id in session_ids {
+        deactivated += conn
+            .execute(
+                "UPDATE sessions SET is_active = 0 WHERE session_id = ?1",
+                params![session_id],
+            )
+            .map_err(|e| format!("Failed to deactivate session: {}", e))?;
+    }
+    Ok(deactivated)
+}
+
+/// Delete old inactive sessions (older than the configured retention period)
 fn cleanup_old_sessions_internal() -> Result<usize, String> {
     let conn = get_connection()?;
+    let days = crate::settings::load_settings()?
+        .session_retention
+        .inactive_session_days;
+
+    delete_inactive_sessions(&conn, days, chrono::Utc::now().timestamp())
+}
 
-    let thirty_days_ago = chrono::Utc::now().timestamp() - (30 * 24 * 60 * 60);
+fn apply_session_retention_policy_internal(
+    conn: &Connection,
+    policy: &SessionRetentionPolicy,
+    now: i64,
+) -> Result<SessionCleanupReport, String> {
+    let inactive_deleted = delete_inactive_sessions(conn, policy.inactive_session_days, now)?;
 
-    let deleted = conn
+    // Long-lived "active" sessions are usually leftovers from a crash; keep
+    // their history but stop treating them as live
+    let active_deactivated = conn
         .execute(
-            "DELETE FROM sessions WHERE is_active = 0 AND last_active < ?1",
-            params![thirty_days_ago],
+            "UPDATE sessions SET is_active = 0 WHERE is_active = 1 AND created_at < ?1",
+            params![days_before(now, policy.active_session_max_days)],
         )
-        .map_err(|e| format!("Failed to cleanup old sessions: {}", e))?;
+        .map_err(|e| format!("Failed to deactivate old sessions: {}", e))?;
+
+    Ok(SessionCleanupReport {
+        inactive_deleted,
+        active_deactivated,
+    })
+}
+
+/// Number of sessions ever recorded for a space
+pub(crate) fn count_sessions_for_space_internal(
+    conn: &Connection,
+    space_id: &str,
+) -> Result<usize, String> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sessions WHERE space_id = ?1",
+        params![space_id],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count as usize)
+    .map_err(|e| format!("Failed to count sessions: {}", e))
+}
+
+fn delete_sessions_for_space_internal(conn: &Connection, space_id: &str) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM sessions WHERE space_id = ?1",
+        params![space_id],
+    )
+    .map_err(|e| format!("Failed to delete sessions: {}", e))
+}
 
-    Ok(deleted)
+/// Forget every session recorded for a deleted space
+pub(crate) fn delete_sessions_for_space(space_id: &str) -> Result<usize, String> {
+    delete_sessions_for_space_internal(&get_connection()?, space_id)
+}
+
+fn session_statistics_internal(
+    conn: &Connection,
+    space_id: Option<&str>,
+    now: i64,
+) -> Result<SessionStatistics, String> {
+    let week_ago = now - 7 * 24 * 60 * 60;
+
+    conn.query_row(
+        "SELECT COUNT(*),
+                COALESCE(SUM(is_active), 0),
+                COALESCE(AVG(last_active - created_at), 0.0),
+                MIN(CASE WHEN is_active = 1 THEN created_at END),
+                COALESCE(SUM(created_at >= ?2), 0)
+         FROM sessions
+         WHERE ?1 IS NULL OR space_id = ?1",
+        params![space_id, week_ago],
+        |row| {
+            Ok(SessionStatistics {
+                total_sessions: row.get::<_, i64>(0)? as usize,
+                active_sessions: row.get::<_, i64>(1)? as usize,
+                average_session_duration_seconds: row.get(2)?,
+                oldest_active_session_at: row.get(3)?,
+                sessions_created_last_7d: row.get::<_, i64>(4)? as usize,
+            })
+        },
+    )
+    .map_err(|e| format!("Failed to compute session statistics: {}", e))
+}
+
+/// Whether any session for the space is still marked active
+pub(crate) fn has_active_session_internal(
+    conn: &Connection,
+    space_id: &str,
+) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sessions WHERE space_id = ?1 AND is_active = 1)",
+        params![space_id],
+        |row| row.get::<_, bool>(0),
+    )
+    .map_err(|e| format!("Failed to check for active sessions: {}", e))
+}
+
+/// Merge `patch`'s keys into a session's metadata
+/// Sessions the frontend hasn't saved yet get a placeholder row so the data isn't lost
+pub(crate) fn update_session_metadata_internal(
+    conn: &Connection,
+    session_id: &str,
+    patch: &serde_json::Value,
+) -> Result<(), String> {
+    let existing: Option<String> = match conn.query_row(
+        "SELECT metadata FROM sessions WHERE session_id = ?1",
+        params![session_id],
+        |row| row.get(0),
+    ) {
+        Ok(metadata) => Some(metadata),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(format!("Failed to load session metadata: {}", e)),
+    };
+
+    let mut metadata: serde_json::Value = existing
+        .and_then(|m| serde_json::from_str(&m).ok())
+        .filter(|m: &serde_json::Value| m.is_object())
+        .unwrap_or_else(|| serde_json::Value::Object(Default::default()));
+
+    if let (Some(target), Some(updates)) = (metadata.as_object_mut(), patch.as_object()) {
+        for (key, value) in updates {
+            target.insert(key.clone(), value.clone());
+        }
+    }
+
+    let metadata_json = serde_json::to_string(&metadata)
+        .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+    let now = chrono::Utc::now().timestamp();
+
+    conn.execute(
+        "INSERT INTO sessions (session_id, space_id, created_at, last_active, is_active, metadata)
+         VALUES (?1, '', ?2, ?2, 1, ?3)
+         ON CONFLICT(session_id) DO UPDATE SET metadata = excluded.metadata",
+        params![session_id, now, metadata_json],
+    )
+    .map_err(|e| format!("Failed to update session metadata: {}", e))?;
+
+    Ok(())
+}
+
+/// The agent mode last recorded for a session (metadata.currentMode)
+pub(crate) fn get_session_mode_internal(
+    conn: &Connection,
+    session_id: &str,
+) -> Result<Option<String>, String> {
+    let metadata: Option<String> = match conn.query_row(
+        "SELECT metadata FROM sessions WHERE session_id = ?1",
+        params![session_id],
+        |row| row.get(0),
+    ) {
+        Ok(metadata) => Some(metadata),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(format!("Failed to load session metadata: {}", e)),
+    };
+
+    Ok(metadata
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(&m).ok())
+        .and_then(|m| m.get("currentMode")?.as_str().map(str::to_string)))
+}
+
+/// Record a permission decision (decided_at is set to now, in seconds)
+pub(crate) fn insert_permission_log(
+    conn: &Connection,
+    session_id: &str,
+    request_id: &str,
+    tool_call_id: &str,
+    kind: &str,
+    outcome: &str,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO permission_log
+         (session_id, request_id, tool_call_id, kind, outcome, decided_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            session_id,
+            request_id,
+            tool_call_id,
+            kind,
+            outcome,
+            chrono::Utc::now().timestamp(),
+        ],
+    )
+    .map_err(|e| format!("Failed to record permission decision: {}", e))?;
+
+    Ok(())
+}
+
+/// Most recent permission decisions first, optionally for a single session
+fn query_permission_log(
+    conn: &Connection,
+    session_id: Option<&str>,
+    limit: usize,
+) -> Result<Vec<PermissionLogEntry>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, session_id, request_id, tool_call_id, kind, outcome, decided_at
+             FROM permission_log
+             WHERE ?1 IS NULL OR session_id = ?1
+             ORDER BY decided_at DESC, id DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let rows = stmt
+        .query_map(params![session_id, limit as i64], |row| {
+            Ok(PermissionLogEntry {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                request_id: row.get(2)?,
+                tool_call_id: row.get(3)?,
+                kind: row.get(4)?,
+                outcome: row.get(5)?,
+                decided_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| format!("Failed to load permission log: {}", e))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load permission log: {}", e))
+}
+
+/// Delete permission decisions older than `older_than_days`
+fn clear_permission_log_internal(conn: &Connection, older_than_days: u32) -> Result<usize, String> {
+    let cutoff = chrono::Utc::now().timestamp() - (older_than_days as i64 * 24 * 60 * 60);
+
+    conn.execute(
+        "DELETE FROM permission_log WHERE decided_at < ?1",
+        params![cutoff],
+    )
+    .map_err(|e| format!("Failed to clear permission log: {}", e))
 }
 
 // =============================================================================
@@ -223,11 +574,70 @@ pub fn deactivate_session(session_id: String) -> Result<(), String> {
     deactivate_session_internal(&session_id)
 }
 
+/// Active sessions idle for longer than the threshold
+#[tauri::command]
+pub fn detect_stale_sessions(inactivity_threshold_minutes: u32) -> Result<Vec<String>, String> {
+    stale_session_ids_internal(
+        &get_connection()?,
+        inactivity_threshold_minutes,
+        chrono::Utc::now().timestamp(),
+    )
+}
+
 #[tauri::command]
 pub fn cleanup_old_sessions() -> Result<usize, String> {
     cleanup_old_sessions_internal()
 }
 
+/// Run both retention passes using the policy from settings
+#[tauri::command]
+pub fn apply_session_retention_policy() -> Result<SessionCleanupReport, String> {
+    let conn = get_connection()?;
+    let policy = crate::settings::load_settings()?.session_retention;
+    apply_session_retention_policy_internal(&conn, &policy, chrono::Utc::now().timestamp())
+}
+
+#[tauri::command]
+pub fn update_session_metadata(
+    session_id: String,
+    metadata: serde_json::Value,
+) -> Result<(), String> {
+    let conn = get_connection()?;
+    update_session_metadata_internal(&conn, &session_id, &metadata)
+}
+
+#[tauri::command]
+pub fn get_session_mode(session_id: String) -> Result<Option<String>, String> {
+    let conn = get_connection()?;
+    get_session_mode_internal(&conn, &session_id)
+}
+
+/// Session counts and durations, for one space or (with no space_id) globally
+#[tauri::command]
+pub fn get_session_statistics(space_id: Option<String>) -> Result<SessionStatistics, String> {
+    let conn = get_connection()?;
+    session_statistics_internal(&conn, space_id.as_deref(), chrono::Utc::now().timestamp())
+}
+
+#[tauri::command]
+pub fn get_permission_log(
+    session_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<PermissionLogEntry>, String> {
+    let conn = get_connection()?;
+    query_permission_log(
+        &conn,
+        session_id.as_deref(),
+        limit.unwrap_or(DEFAULT_PERMISSION_LOG_LIMIT),
+    )
+}
+
+#[tauri::command]
+pub fn clear_permission_log(older_than_days: u32) -> Result<usize, String> {
+    let conn = get_connection()?;
+    clear_permission_log_internal(&conn, older_than_days)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -357,4 +767,312 @@ mod tests {
         // Recent session should still exist
         assert!(load_session_internal("recent-session").unwrap().is_some());
     }
+
+    #[test]
+    fn test_permission_log_query() {
+        let (conn, _temp) = setup_test_db();
+
+        insert_permission_log(&conn, "session-1", "req-1", "tool-1", "Read", "allow_once").unwrap();
+        insert_permission_log(&conn, "session-1", "req-2", "tool-2", "Edit", "reject_once")
+            .unwrap();
+        insert_permission_log(&conn, "session-2", "req-3", "tool-3", "Read", "cancelled").unwrap();
+
+        let all = query_permission_log(&conn, None, 10).unwrap();
+        assert_eq!(all.len(), 3);
+        // Newest first
+        assert_eq!(all[0].request_id, "req-3");
+
+        let session = query_permission_log(&conn, Some("session-1"), 10).unwrap();
+        assert_eq!(session.len(), 2);
+        assert!(session.iter().all(|e| e.session_id == "session-1"));
+
+        let limited = query_permission_log(&conn, None, 1).unwrap();
+        assert_eq!(limited.len(), 1);
+    }
+
+    #[test]
+    fn test_clear_permission_log_retention() {
+        let (conn, _temp) = setup_test_db();
+
+        insert_permission_log(&conn, "session-1", "old", "tool-1", "Read", "allow_once").unwrap();
+        insert_permission_log(&conn, "session-1", "new", "tool-2", "Read", "allow_once").unwrap();
+
+        let ten_days_ago = chrono::Utc::now().timestamp() - (10 * 24 * 60 * 60);
+        conn.execute(
+            "UPDATE permission_log SET decided_at = ?1 WHERE request_id = 'old'",
+            params![ten_days_ago],
+        )
+        .unwrap();
+
+        // Nothing is older than 30 days
+        assert_eq!(clear_permission_log_internal(&conn, 30).unwrap(), 0);
+
+        assert_eq!(clear_permission_log_internal(&conn, 7).unwrap(), 1);
+        let remaining = query_permission_log(&conn, None, 10).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].request_id, "new");
+    }
+
+    #[test]
+    fn test_update_session_metadata_merges() {
+        let (conn, _temp) = setup_test_db();
+
+        conn.execute(
+            "INSERT INTO sessions (session_id, space_id, created_at, last_active, is_active, metadata)
+             VALUES ('session-1', 'space-1', 1000, 2000, 1, '{\"foo\":\"bar\"}')",
+            [],
+        )
+        .unwrap();
+
+        update_session_metadata_internal(
+            &conn,
+            "session-1",
+            &serde_json::json!({ "currentMode": "plan" }),
+        )
+        .unwrap();
+
+        let metadata: String = conn
+            .query_row(
+                "SELECT metadata FROM sessions WHERE session_id = 'session-1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let metadata: serde_json::Value = serde_json::from_str(&metadata).unwrap();
+        assert_eq!(metadata["foo"], "bar");
+        assert_eq!(metadata["currentMode"], "plan");
+
+        assert_eq!(
+            get_session_mode_internal(&conn, "session-1").unwrap(),
+            Some("plan".to_string())
+        );
+    }
+
+    #[test]
+    fn test_session_mode_for_unsaved_session() {
+        let (conn, _temp) = setup_test_db();
+
+        assert_eq!(
+            get_session_mode_internal(&conn, "new-session").unwrap(),
+            None
+        );
+
+        update_session_metadata_internal(
+            &conn,
+            "new-session",
+            &serde_json::json!({ "currentMode": "default" }),
+        )
+        .unwrap();
+
+        assert_eq!(
+            get_session_mode_internal(&conn, "new-session").unwrap(),
+            Some("default".to_string())
+        );
+    }
+
+    fn insert_session(
+        conn: &Connection,
+        id: &str,
+        space_id: &str,
+        created_at: i64,
+        last_active: i64,
+        is_active: bool,
+    ) {
+        conn.execute(
+            "INSERT INTO sessions (session_id, space_id, created_at, last_active, is_active)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, space_id, created_at, last_active, is_active as i64],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_session_statistics() {
+        let (conn, _temp) = setup_test_db();
+        let now = 1_700_000_000;
+        let day = 24 * 60 * 60;
+
+        insert_session(
+            &conn,
+            "a",
+            "space-1",
+            now - 30 * day,
+            now - 30 * day + 600,
+            false,
+        );
+        insert_session(
+            &conn,
+            "b",
+            "space-1",
+            now - 3 * day,
+            now - 3 * day + 1200,
+            true,
+        );
+        insert_session(&conn, "c", "space-2", now - day, now - day + 300, true);
+
+        let global = session_statistics_internal(&conn, None, now).unwrap();
+        assert_eq!(
+            global,
+            SessionStatistics {
+                total_sessions: 3,
+                active_sessions: 2,
+                average_session_duration_seconds: 700.0,
+                oldest_active_session_at: Some(now - 3 * day),
+                sessions_created_last_7d: 2,
+            }
+        );
+
+        let space_1 = session_statistics_internal(&conn, Some("space-1"), now).unwrap();
+        assert_eq!(
+            space_1,
+            SessionStatistics {
+                total_sessions: 2,
+                active_sessions: 1,
+                average_session_duration_seconds: 900.0,
+                oldest_active_session_at: Some(now - 3 * day),
+                sessions_created_last_7d: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_session_statistics_empty() {
+        let (conn, _temp) = setup_test_db();
+
+        assert_eq!(
+            session_statistics_internal(&conn, Some("nothing"), 1_700_000_000).unwrap(),
+            SessionStatistics::default()
+        );
+    }
+
+    #[test]
+    fn test_retention_policy_one_day() {
+        let (conn, _temp) = setup_test_db();
+        let now = 1_700_000_000;
+        let hour = 60 * 60;
+        let policy = SessionRetentionPolicy {
+            inactive_session_days: 1,
+            active_session_max_days: 1,
+        };
+
+        insert_session(
+            &conn,
+            "old-inactive",
+            "s",
+            now - 50 * hour,
+            now - 30 * hour,
+            false,
+        );
+        insert_session(
+            &conn,
+            "new-inactive",
+            "s",
+            now - 50 * hour,
+            now - 2 * hour,
+            false,
+        );
+        insert_session(&conn, "old-active", "s", now - 30 * hour, now - hour, true);
+        insert_session(&conn, "new-active", "s", now - 2 * hour, now - hour, true);
+
+        let report = apply_session_retention_policy_internal(&conn, &policy, now).unwrap();
+        assert_eq!(
+            report,
+            SessionCleanupReport {
+                inactive_deleted: 1,
+                active_deactivated: 1,
+            }
+        );
+
+        let remaining: Vec<(String, bool)> = conn
+            .prepare("SELECT session_id, is_active FROM sessions ORDER BY session_id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            remaining,
+            vec![
+                ("new-active".to_string(), true),
+                ("new-inactive".to_string(), false),
+                // Deactivated, not deleted
+                ("old-active".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delete_sessions_for_space() {
+        let (conn, _temp) = setup_test_db();
+        insert_session(&conn, "a", "space-1", 100, 200, true);
+        insert_session(&conn, "b", "space-1", 100, 200, false);
+        insert_session(&conn, "c", "space-2", 100, 200, true);
+
+        assert_eq!(
+            delete_sessions_for_space_internal(&conn, "space-1").unwrap(),
+            2
+        );
+        assert_eq!(
+            count_sessions_for_space_internal(&conn, "space-1").unwrap(),
+            0
+        );
+        assert_eq!(
+            count_sessions_for_space_internal(&conn, "space-2").unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_stale_session_ids() {
+        let (conn, _temp) = setup_test_db();
+        let now = 1_700_000_000;
+
+        insert_session(&conn, "stale", "space-1", now - 7200, now - 7200, true);
+        insert_session(&conn, "fresh", "space-1", now - 7200, now - 60, true);
+        insert_session(&conn, "ended", "space-2", now - 7200, now - 7200, false);
+
+        assert_eq!(
+            stale_session_ids_internal(&conn, 30, now).unwrap(),
+            vec!["stale".to_string()]
+        );
+        assert!(stale_session_ids_internal(&conn, 180, now)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_saving_session_keeps_recorded_mode() {
+        let (conn, _temp) = setup_test_db();
+
+        // The mode notification can arrive before the frontend saves the session
+        update_session_metadata_internal(
+            &conn,
+            "session-1",
+            &serde_json::json!({ "currentMode": "plan" }),
+        )
+        .unwrap();
+
+        let session = SessionState {
+            session_id: "session-1".to_string(),
+            space_id: "space-1".to_string(),
+            created_at: 1000,
+            last_active: 2000,
+            is_active: true,
+            metadata: serde_json::json!({}),
+        };
+        upsert_session(&conn, &session).unwrap();
+
+        assert_eq!(
+            get_session_mode_internal(&conn, "session-1").unwrap(),
+            Some("plan".to_string())
+        );
+        let space_id: String = conn
+            .query_row(
+                "SELECT space_id FROM sessions WHERE session_id = 'session-1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(space_id, "space-1");
+    }
 }
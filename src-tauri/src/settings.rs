@@ -1,11 +1,65 @@
+use crate::config::AcpConfig;
+use crate::terminal::TerminalPolicy;
 use dirs::home_dir;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Current settings schema version (bump when a migration step is added)
+pub const CURRENT_SETTINGS_VERSION: u32 = 1;
+
+fn default_settings_version() -> u32 {
+    CURRENT_SETTINGS_VERSION
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Shortcuts a fresh install starts with (action name -> key combo)
+pub fn default_keyboard_shortcuts() -> HashMap<String, String> {
+    [
+        ("sendMessage", "Ctrl+Enter"),
+        ("newSpace", "Ctrl+N"),
+        ("searchSpaces", "Ctrl+K"),
+        ("toggleSidebar", "Ctrl+B"),
+        ("focusInput", "Ctrl+L"),
+    ]
+    .into_iter()
+    .map(|(action, combo)| (action.to_string(), combo.to_string()))
+    .collect()
+}
+
+/// One or more modifiers followed by a letter, digit, or named key, e.g. "Ctrl+Shift+K"
+static KEY_COMBO_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(Ctrl\+|Alt\+|Shift\+|Meta\+)+([A-Z0-9]|Enter|Escape|Tab|Space|Backspace|F([1-9]|1[0-2]))$")
+        .expect("key combo pattern is valid")
+});
+
+fn validate_key_combo(combo: &str) -> Result<(), String> {
+    if KEY_COMBO_PATTERN.is_match(combo) {
+        Ok(())
+    } else {
+        Err(format!("Invalid key combo: {}", combo))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Settings {
+    /// Schema version, used by migrate_settings to upgrade old files
+    #[serde(default = "default_settings_version")]
+    pub version: u32,
     pub api_key: Option<String>,
+    /// The stored API key couldn't be decrypted (e.g. the machine key changed)
+    /// Set on load only; the ciphertext stays on disk until a new key is saved
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub api_key_undecryptable: bool,
     pub theme: String, // "light" | "dark" | "system"
     #[serde(default)]
     pub has_completed_onboarding: bool,
@@ -13,22 +67,188 @@ pub struct Settings {
     /// When true, uses "AllowOnce" (not "AllowAlways") so toggling this off immediately affects new requests
     #[serde(default)]
     pub always_allow_tool_actions: bool,
+    /// Allow/deny rules for commands the agent may run in terminals
+    #[serde(default)]
+    pub terminal_policy: Option<TerminalPolicy>,
+    /// Model to request from the agent (None = adapter default)
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Maximum tokens per response (None = adapter default)
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Tools the agent may use (empty = all tools allowed)
+    #[serde(default)]
+    pub allow_tools: Vec<String>,
+    /// Token budget CLAUDE.md is measured against (None = 8000)
+    #[serde(default)]
+    pub claude_md_token_budget: Option<usize>,
+    /// Spaces whose metadata is cached in memory (None = 50), read at startup;
+    /// the cache grows past this when there are more spaces than that
+    #[serde(default)]
+    pub space_cache_capacity: Option<usize>,
+    /// How to launch the ACP adapter (None = npx @zed-industries/claude-code-acp)
+    #[serde(default)]
+    pub acp_config: Option<AcpConfig>,
+    /// When orphaned conversations were last cleaned up (Unix seconds)
+    #[serde(default)]
+    pub last_cleaned_at: Option<i64>,
+    /// When the last OAuth rotation reminder was shown (Unix seconds)
+    #[serde(default)]
+    pub last_rotation_reminder: Option<i64>,
+    /// Show a desktop notification when the agent finishes while the window is in the background
+    #[serde(default = "default_true")]
+    pub notify_on_completion: bool,
+    /// Append the space's recent git commits to each prompt
+    #[serde(default)]
+    pub ambient_context_enabled: bool,
+    /// Summarise the conversation when a prompt stops at the context limit
+    #[serde(default = "default_true")]
+    pub auto_summarise_enabled: bool,
+    /// Key combo for each app action, e.g. "sendMessage" -> "Ctrl+Enter"
+    #[serde(default = "default_keyboard_shortcuts")]
+    pub keyboard_shortcuts: HashMap<String, String>,
+    /// Main window geometry from the last session (None = tauri.conf.json defaults)
+    #[serde(default)]
+    pub window_state: Option<WindowState>,
+    /// How long ACP sessions are kept around
+    #[serde(default)]
+    pub session_retention: SessionRetentionPolicy,
+    /// Terminal opened by open_terminal_at_space (None = the platform default)
+    /// A program name on Linux and Windows, an application name on macOS
+    #[serde(default)]
+    pub preferred_terminal: Option<String>,
+}
+
+/// When old ACP sessions are cleaned out of sessions.db
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRetentionPolicy {
+    /// Inactive sessions idle for longer than this are deleted
+    pub inactive_session_days: u32,
+    /// Sessions still marked active after this long are deactivated
+    pub active_session_max_days: u32,
 }
 
+impl Default for SessionRetentionPolicy {
+    fn default() -> Self {
+        Self {
+            inactive_session_days: 30,
+            active_session_max_days: 90,
+        }
+    }
+}
+
+/// Position and size of the main window, in physical pixels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+/// Upper bound accepted for `max_tokens`
+const MAX_TOKENS_LIMIT: u32 = 200_000;
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
+            version: CURRENT_SETTINGS_VERSION,
             api_key: None,
+            api_key_undecryptable: false,
             theme: "system".to_string(),
             has_completed_onboarding: false,
             always_allow_tool_actions: false, // Default to requiring approval
+            terminal_policy: None,
+            model: None,
+            max_tokens: None,
+            allow_tools: Vec::new(),
+            claude_md_token_budget: None,
+            space_cache_capacity: None,
+            acp_config: None,
+            last_cleaned_at: None,
+            last_rotation_reminder: None,
+            notify_on_completion: true,
+            ambient_context_enabled: false,
+            auto_summarise_enabled: true,
+            keyboard_shortcuts: default_keyboard_shortcuts(),
+            window_state: None,
+            session_retention: SessionRetentionPolicy::default(),
+            preferred_terminal: None,
         }
     }
 }
 
+/// Per-space overrides, stored as .space-settings.json in the space directory
+/// Unset fields fall back to the global settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpaceSettings {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Appended to the agent's system prompt for this space
+    #[serde(default)]
+    pub extra_system_prompt: Option<String>,
+}
+
+/// Settings after applying a space's overrides on top of the global settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectiveSettings {
+    pub model: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub extra_system_prompt: Option<String>,
+}
+
+impl SpaceSettings {
+    const FILE_NAME: &'static str = ".space-settings.json";
+
+    /// Load overrides from a space directory (defaults if the file doesn't exist)
+    pub fn load_from_space(space_path: &Path) -> Result<Self, String> {
+        let path = space_path.join(Self::FILE_NAME);
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read space settings: {}", e))?;
+
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse space settings: {}", e))
+    }
+
+    pub fn save_to_space(&self, space_path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize space settings: {}", e))?;
+
+        fs::write(space_path.join(Self::FILE_NAME), json)
+            .map_err(|e| format!("Failed to write space settings: {}", e))
+    }
+}
+
+impl EffectiveSettings {
+    /// Merge space overrides over global settings
+    pub fn resolve(global: &Settings, space: &SpaceSettings) -> Self {
+        Self {
+            model: space.model.clone().or_else(|| global.model.clone()),
+            max_tokens: space.max_tokens.or(global.max_tokens),
+            extra_system_prompt: space.extra_system_prompt.clone(),
+        }
+    }
+
+    /// Effective settings for the space at `space_path`
+    pub fn for_space_path(space_path: &Path) -> Result<Self, String> {
+        let global = load_settings()?;
+        let space = SpaceSettings::load_from_space(space_path)?;
+
+        Ok(Self::resolve(&global, &space))
+    }
+}
+
 fn get_settings_path() -> Result<PathBuf, String> {
-    let home = home_dir().ok_or("Could not determine home directory")?;
-    let settings_dir = home.join(".thinking-space");
+    let settings_dir = crate::config::get_data_dir()?;
 
     // Create directory if it doesn't exist
     if !settings_dir.exists() {
@@ -39,48 +259,642 @@ fn get_settings_path() -> Result<PathBuf, String> {
     Ok(settings_dir.join("settings.json"))
 }
 
-#[tauri::command]
-pub fn load_settings() -> Result<Settings, String> {
-    let settings_path = get_settings_path()?;
+/// Check user-editable fields, returning a list of problems (empty = valid)
+pub fn validation_errors(settings: &Settings) -> Vec<String> {
+    field_validation_errors(settings)
+        .into_iter()
+        .map(|(_, message)| message)
+        .collect()
+}
+
+/// Like validation_errors, but paired with the field each problem is in
+fn field_validation_errors(settings: &Settings) -> Vec<(&'static str, String)> {
+    let mut errors = Vec::new();
+
+    if let Some(ref model) = settings.model {
+        if model.trim().is_empty() {
+            errors.push(("model", "Model name cannot be empty".to_string()));
+        }
+    }
+
+    if let Some(max_tokens) = settings.max_tokens {
+        if !(1..=MAX_TOKENS_LIMIT).contains(&max_tokens) {
+            errors.push((
+                "max_tokens",
+                format!("Max tokens must be between 1 and {}", MAX_TOKENS_LIMIT),
+            ));
+        }
+    }
+
+    if settings
+        .allow_tools
+        .iter()
+        .any(|tool| tool.trim().is_empty())
+    {
+        errors.push((
+            "allow_tools",
+            "Allowed tool names cannot be empty".to_string(),
+        ));
+    }
 
+    if settings.claude_md_token_budget == Some(0) {
+        errors.push((
+            "claude_md_token_budget",
+            "CLAUDE.md token budget must be greater than 0".to_string(),
+        ));
+    }
+
+    if settings.space_cache_capacity == Some(0) {
+        errors.push((
+            "space_cache_capacity",
+            "Space cache capacity must be greater than 0".to_string(),
+        ));
+    }
+
+    if settings.session_retention.inactive_session_days == 0
+        || settings.session_retention.active_session_max_days == 0
+    {
+        errors.push((
+            "session_retention",
+            "Session retention periods must be at least 1 day".to_string(),
+        ));
+    }
+
+    if let Some(ref acp_config) = settings.acp_config {
+        if acp_config.adapter_command.trim().is_empty() {
+            errors.push(("acp_config", "Adapter command cannot be empty".to_string()));
+        }
+    }
+
+    errors
+}
+
+/// Upgrade a raw settings blob to the current schema version
+/// Files without a "version" field are treated as version 0
+pub fn migrate_settings(raw: serde_json::Value) -> Result<Settings, String> {
+    let mut raw = match raw {
+        serde_json::Value::Object(map) => map,
+        _ => return Err("Settings file is not a JSON object".to_string()),
+    };
+
+    let version = raw.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+
+    if version > CURRENT_SETTINGS_VERSION {
+        return Err(format!(
+            "Settings file version {} is newer than this app supports ({})",
+            version, CURRENT_SETTINGS_VERSION
+        ));
+    }
+
+    // Version 0 -> 1: no renames; missing fields are seeded from defaults below
+    // (Add version 1 -> 2 steps here when new fields need transforming)
+
+    // Seed defaults for any field the stored file doesn't have
+    let mut migrated = match serde_json::to_value(Settings::default()) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => return Err("Failed to serialize default settings".to_string()),
+    };
+    migrated.append(&mut raw);
+    migrated.insert(
+        "version".to_string(),
+        serde_json::Value::from(CURRENT_SETTINGS_VERSION),
+    );
+
+    serde_json::from_value(serde_json::Value::Object(migrated))
+        .map_err(|e| format!("Failed to migrate settings: {}", e))
+}
+
+/// Read settings as stored on disk (API key still encrypted)
+/// Older files are migrated to the current version and re-saved
+fn read_stored_settings(settings_path: &Path) -> Result<Settings, String> {
     if !settings_path.exists() {
         // Return default settings if file doesn't exist
         return Ok(Settings::default());
     }
 
-    let contents = fs::read_to_string(&settings_path)
+    let contents = fs::read_to_string(settings_path)
         .map_err(|e| format!("Failed to read settings file: {}", e))?;
 
-    let settings: Settings = serde_json::from_str(&contents)
+    let raw: serde_json::Value = serde_json::from_str(&contents)
         .map_err(|e| format!("Failed to parse settings file: {}", e))?;
 
+    let is_current =
+        raw.get("version").and_then(|v| v.as_u64()) == Some(CURRENT_SETTINGS_VERSION as u64);
+
+    if is_current {
+        if let Ok(settings) = serde_json::from_value::<Settings>(raw.clone()) {
+            return Ok(settings);
+        }
+    }
+
+    let settings = migrate_settings(raw)?;
+    write_stored_settings(settings_path, &settings)?;
+
     Ok(settings)
 }
 
+fn write_stored_settings(settings_path: &Path, settings: &Settings) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    fs::write(settings_path, json).map_err(|e| format!("Failed to write settings file: {}", e))
+}
+
+#[tauri::command]
+pub fn load_settings() -> Result<Settings, String> {
+    load_settings_at(&get_settings_path()?)
+}
+
+fn load_settings_at(settings_path: &Path) -> Result<Settings, String> {
+    let mut settings = read_stored_settings(settings_path)?;
+
+    // API key is encrypted at rest; legacy plaintext values pass through unchanged
+    settings.api_key = match settings.api_key {
+        Some(stored) => match crate::auth::decrypt_secret(&stored) {
+            Ok(api_key) => Some(api_key),
+            Err(e) => {
+                eprintln!("[SETTINGS] Could not decrypt stored API key: {}", e);
+                settings.api_key_undecryptable = true;
+                None
+            }
+        },
+        None => None,
+    };
+
+    // Shortcuts are validated when set; drop any hand-edited ones that don't parse
+    // so one bad combo can't block every other settings save
+    settings.keyboard_shortcuts.retain(|action, combo| {
+        let valid = validate_key_combo(combo).is_ok();
+        if !valid {
+            eprintln!(
+                "[SETTINGS] Ignoring invalid shortcut for {}: {:?}",
+                action, combo
+            );
+        }
+        valid
+    });
+
+    Ok(settings)
+}
+
+/// Validate settings and write them with the API key encrypted
+fn write_settings_at(settings_path: &Path, settings: Settings) -> Result<(), String> {
+    let errors = validation_errors(&settings);
+    if !errors.is_empty() {
+        return Err(format!("Invalid settings: {}", errors.join("; ")));
+    }
+
+    let mut settings = settings;
+    settings.api_key_undecryptable = false;
+    settings.api_key = match settings.api_key.as_deref() {
+        Some(api_key) => Some(crate::auth::encrypt_secret(api_key)?),
+        // Keep a key we can't decrypt rather than erasing it on an unrelated save
+        None => read_stored_settings(settings_path)
+            .ok()
+            .and_then(|stored| stored.api_key)
+            .filter(|stored| crate::auth::decrypt_secret(stored).is_err()),
+    };
+
+    write_stored_settings(settings_path, &settings)
+}
+
+/// Settings as sent to the frontend in "settings-changed" events
+fn without_api_key(settings: &Settings) -> Settings {
+    Settings {
+        api_key: None,
+        ..settings.clone()
+    }
+}
+
+/// Saves settings and emits "settings-changed" so open windows can update live
+#[derive(Default)]
+pub struct SettingsManager {
+    app_handle: Arc<Mutex<Option<AppHandle>>>,
+    #[cfg(test)]
+    emitted_events: Mutex<Vec<Settings>>,
+}
+
+impl SettingsManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_app_handle(&self, handle: AppHandle) {
+        *self.app_handle.lock() = Some(handle);
+    }
+
+    pub fn save(&self, settings: Settings) -> Result<(), String> {
+        self.save_at(&get_settings_path()?, settings)
+    }
+
+    /// Save settings, emitting "settings-changed" unless nothing but the API key changed
+    fn save_at(&self, settings_path: &Path, settings: Settings) -> Result<(), String> {
+        // An unreadable file (e.g. before a reset) counts as a change
+        let previous = read_stored_settings(settings_path).ok();
+        write_settings_at(settings_path, settings.clone())?;
+
+        let settings = without_api_key(&settings);
+        if previous.map(|previous| without_api_key(&previous)) != Some(settings.clone()) {
+            self.emit_changed(settings);
+        }
+        Ok(())
+    }
+
+    /// Save the API key to config.json, emitting "settings-changed" if it changed
+    pub fn save_api_key(&self, api_key: String) -> Result<(), String> {
+        let previous = crate::auth::load_api_key().ok().flatten();
+        crate::auth::write_api_key(&api_key)?;
+
+        if previous.as_deref() != Some(api_key.as_str()) {
+            self.emit_changed(without_api_key(&load_settings()?));
+        }
+        Ok(())
+    }
+
+    fn emit_changed(&self, settings: Settings) {
+        if let Some(handle) = self.app_handle.lock().as_ref() {
+            if let Err(e) = handle.emit("settings-changed", &settings) {
+                eprintln!("[SETTINGS] Failed to emit settings change: {}", e);
+            }
+        }
+
+        #[cfg(test)]
+        self.emitted_events.lock().push(settings);
+    }
+}
+
+/// Save settings through the app's SettingsManager so the change is announced
+/// Without an app handle (e.g. in tests) the settings are saved silently
+pub fn save_settings_from(app: Option<&AppHandle>, settings: Settings) -> Result<(), String> {
+    match app {
+        Some(app) => app.state::<Arc<SettingsManager>>().save(settings),
+        None => SettingsManager::new().save(settings),
+    }
+}
+
 #[tauri::command]
-pub fn save_settings(settings: Settings) -> Result<(), String> {
+pub fn save_settings(
+    manager: tauri::State<'_, Arc<SettingsManager>>,
+    settings: Settings,
+) -> Result<(), String> {
+    manager.save(settings)
+}
+
+/// Re-save settings.json if it still holds a plaintext API key
+/// Returns true if the file was migrated
+pub fn migrate_api_key_to_encrypted(manager: &SettingsManager) -> Result<bool, String> {
     let settings_path = get_settings_path()?;
+    let stored = read_stored_settings(&settings_path)?;
+
+    match stored.api_key {
+        Some(ref api_key) if crate::auth::is_plaintext_secret(api_key)? => {
+            manager.save(stored)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+#[tauri::command]
+pub fn validate_settings(settings: Settings) -> Result<Vec<String>, String> {
+    Ok(validation_errors(&settings))
+}
+
+#[tauri::command]
+pub fn load_space_settings(space_id: String) -> Result<SpaceSettings, String> {
+    SpaceSettings::load_from_space(&crate::spaces::get_space_dir(&space_id)?)
+}
+
+#[tauri::command]
+pub fn save_space_settings(space_id: String, settings: SpaceSettings) -> Result<(), String> {
+    settings.save_to_space(&crate::spaces::get_space_dir(&space_id)?)
+}
 
+#[tauri::command]
+pub fn get_effective_settings(space_id: String) -> Result<EffectiveSettings, String> {
+    EffectiveSettings::for_space_path(&crate::spaces::get_space_dir(&space_id)?)
+}
+
+/// Machine-specific state left out of exported settings and ignored on import
+const NON_PORTABLE_SETTINGS: &[&str] =
+    &["window_state", "last_cleaned_at", "last_rotation_reminder"];
+
+/// A problem with one field of an imported settings file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingsImportError {
+    pub field: String,
+    pub message: String,
+    pub received_value: serde_json::Value,
+}
+
+impl SettingsImportError {
+    fn new(field: &str, message: impl Into<String>, received_value: serde_json::Value) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+            received_value,
+        }
+    }
+}
+
+/// Settings as a portable JSON object: API key redacted, machine state removed
+fn portable_settings(settings: &Settings) -> Result<serde_json::Value, String> {
+    let mut value = serde_json::to_value(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    if let Some(object) = value.as_object_mut() {
+        object.insert("api_key".to_string(), serde_json::Value::Null);
+        for key in NON_PORTABLE_SETTINGS {
+            object.remove(*key);
+        }
+    }
+
+    Ok(value)
+}
+
+fn export_settings_at(settings_path: &Path, output_path: &Path) -> Result<(), String> {
+    let settings = portable_settings(&read_stored_settings(settings_path)?)?;
     let json = serde_json::to_string_pretty(&settings)
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
-    fs::write(&settings_path, json).map_err(|e| format!("Failed to write settings file: {}", e))?;
+    fs::write(output_path, json).map_err(|e| format!("Failed to write settings export: {}", e))
+}
+
+/// Apply an exported settings object over `current`
+/// Fields the import doesn't mention keep their current values. The API key is only
+/// taken from the import when `overwrite_api_key` is set and the key isn't redacted.
+fn merge_imported_settings(
+    current: &Settings,
+    imported: serde_json::Value,
+    overwrite_api_key: bool,
+) -> Result<Settings, Vec<SettingsImportError>> {
+    let imported = match imported {
+        serde_json::Value::Object(map) => map,
+        other => {
+            return Err(vec![SettingsImportError::new(
+                "",
+                "Settings file is not a JSON object",
+                other,
+            )])
+        }
+    };
+
+    let current_value = match serde_json::to_value(current) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => {
+            return Err(vec![SettingsImportError::new(
+                "",
+                "Failed to serialize current settings",
+                serde_json::Value::Null,
+            )])
+        }
+    };
+
+    let mut errors = Vec::new();
+    let mut merged = current_value.clone();
+
+    for (field, value) in &imported {
+        if !current_value.contains_key(field) {
+            errors.push(SettingsImportError::new(
+                field,
+                "Unknown setting",
+                value.clone(),
+            ));
+            continue;
+        }
+
+        match field.as_str() {
+            "version" => {
+                if !value
+                    .as_u64()
+                    .is_some_and(|v| v <= CURRENT_SETTINGS_VERSION as u64)
+                {
+                    errors.push(SettingsImportError::new(
+                        field,
+                        format!(
+                            "Settings version must be at most {}",
+                            CURRENT_SETTINGS_VERSION
+                        ),
+                        value.clone(),
+                    ));
+                }
+                continue;
+            }
+            "api_key" if !overwrite_api_key || value.is_null() => continue,
+            _ if NON_PORTABLE_SETTINGS.contains(&field.as_str()) => continue,
+            _ => {}
+        }
+
+        // Check each field on its own so type errors can be attributed to it
+        let mut candidate = current_value.clone();
+        candidate.insert(field.clone(), value.clone());
+        if let Err(e) = serde_json::from_value::<Settings>(serde_json::Value::Object(candidate)) {
+            errors.push(SettingsImportError::new(
+                field,
+                e.to_string(),
+                value.clone(),
+            ));
+            continue;
+        }
+
+        merged.insert(field.clone(), value.clone());
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let settings: Settings = serde_json::from_value(serde_json::Value::Object(merged.clone()))
+        .map_err(|e| {
+            vec![SettingsImportError::new(
+                "",
+                e.to_string(),
+                serde_json::Value::Null,
+            )]
+        })?;
+
+    let errors: Vec<SettingsImportError> = field_validation_errors(&settings)
+        .into_iter()
+        .map(|(field, message)| {
+            let received_value = merged.get(field).cloned().unwrap_or_default();
+            SettingsImportError::new(field, message, received_value)
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(settings)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Write the current settings to a file that can be imported on another machine
+/// The API key is never exported
+#[tauri::command]
+pub fn export_settings(output_path: String) -> Result<(), String> {
+    let home_dir = home_dir().ok_or("Could not determine home directory")?;
+    let output_path = crate::spaces::resolve_within(Path::new(&output_path), &home_dir)?;
+
+    export_settings_at(&get_settings_path()?, &output_path)?;
+    println!("[SETTINGS] Exported settings to {}", output_path.display());
+    Ok(())
+}
+
+/// Merge settings from an exported file into the current settings and save them
+/// Each problem found is reported against its field; nothing is saved unless all pass
+#[tauri::command]
+pub fn import_settings(
+    manager: tauri::State<'_, Arc<SettingsManager>>,
+    input_path: String,
+    overwrite_api_key: bool,
+) -> Result<Settings, Vec<SettingsImportError>> {
+    let file_error = |message: String| {
+        vec![SettingsImportError::new(
+            "",
+            message,
+            serde_json::Value::Null,
+        )]
+    };
+
+    let home_dir =
+        home_dir().ok_or_else(|| file_error("Could not determine home directory".to_string()))?;
+    let input_path = crate::spaces::resolve_within(Path::new(&input_path), &home_dir)
+        .map_err(|e| file_error(e.to_string()))?;
+
+    let contents = fs::read_to_string(&input_path)
+        .map_err(|e| file_error(format!("Failed to read settings file: {}", e)))?;
+    let imported: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| file_error(format!("Failed to parse settings file: {}", e)))?;
+
+    let current = load_settings().map_err(file_error)?;
+    let settings = merge_imported_settings(&current, imported, overwrite_api_key)?;
+    manager.save(settings.clone()).map_err(file_error)?;
+
+    println!("[SETTINGS] Imported settings from {}", input_path.display());
+    Ok(settings)
+}
+
+/// Overwrite the settings file with defaults
+#[tauri::command]
+pub fn reset_settings(manager: tauri::State<'_, Arc<SettingsManager>>) -> Result<(), String> {
+    manager.save(Settings::default())
+}
+
+fn get_keyboard_shortcut_at(settings_path: &Path, action: &str) -> Result<Option<String>, String> {
+    Ok(load_settings_at(settings_path)?
+        .keyboard_shortcuts
+        .get(action)
+        .cloned())
+}
+
+fn set_keyboard_shortcut_at(
+    manager: &SettingsManager,
+    settings_path: &Path,
+    action: &str,
+    combo: &str,
+) -> Result<(), String> {
+    if action.trim().is_empty() {
+        return Err("Shortcut action cannot be empty".to_string());
+    }
+    validate_key_combo(combo)?;
+
+    let mut settings = load_settings_at(settings_path)?;
+    settings
+        .keyboard_shortcuts
+        .insert(action.to_string(), combo.to_string());
+    manager.save_at(settings_path, settings)
+}
+
+/// Key combo bound to an action (None if the action has no shortcut)
+#[tauri::command]
+pub fn get_keyboard_shortcut(action: String) -> Result<Option<String>, String> {
+    get_keyboard_shortcut_at(&get_settings_path()?, &action)
+}
+
+#[tauri::command]
+pub fn set_keyboard_shortcut(
+    manager: tauri::State<'_, Arc<SettingsManager>>,
+    action: String,
+    combo: String,
+) -> Result<(), String> {
+    set_keyboard_shortcut_at(&manager, &get_settings_path()?, &action, &combo)
+}
+
+fn save_window_state_at(settings_path: &Path, state: WindowState) -> Result<(), String> {
+    let mut settings = read_stored_settings(settings_path)?;
+    settings.window_state = Some(state);
+    write_stored_settings(settings_path, &settings)
+}
+
+fn load_window_state_at(settings_path: &Path) -> Result<Option<WindowState>, String> {
+    Ok(read_stored_settings(settings_path)?.window_state)
+}
+
+#[tauri::command]
+pub fn save_window_state(state: WindowState) -> Result<(), String> {
+    save_window_state_at(&get_settings_path()?, state)
+}
+
+#[tauri::command]
+pub fn load_window_state() -> Result<Option<WindowState>, String> {
+    load_window_state_at(&get_settings_path()?)
+}
+
+/// Current geometry of `window`, as it should be restored next launch
+pub fn capture_window_state(window: &tauri::WebviewWindow) -> Result<WindowState, String> {
+    let position = window
+        .outer_position()
+        .map_err(|e| format!("Failed to read window position: {}", e))?;
+    let size = window
+        .inner_size()
+        .map_err(|e| format!("Failed to read window size: {}", e))?;
+    let maximized = window
+        .is_maximized()
+        .map_err(|e| format!("Failed to read window state: {}", e))?;
+
+    Ok(WindowState {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized,
+    })
+}
+
+/// Move and resize `window` to a saved state
+pub fn apply_window_state(
+    window: &tauri::WebviewWindow,
+    state: &WindowState,
+) -> Result<(), String> {
+    // A zero-sized window would be invisible; keep the configured default instead
+    if state.width > 0 && state.height > 0 {
+        window
+            .set_size(tauri::PhysicalSize::new(state.width, state.height))
+            .map_err(|e| format!("Failed to restore window size: {}", e))?;
+        window
+            .set_position(tauri::PhysicalPosition::new(state.x, state.y))
+            .map_err(|e| format!("Failed to restore window position: {}", e))?;
+    }
+
+    if state.maximized {
+        window
+            .maximize()
+            .map_err(|e| format!("Failed to maximize window: {}", e))?;
+    }
 
     Ok(())
 }
 
 #[tauri::command]
 pub fn get_data_location() -> Result<String, String> {
-    let home = home_dir().ok_or("Could not determine home directory")?;
-    let data_path = home.join(".thinking-space");
+    let data_path = crate::config::get_data_dir()?;
 
     Ok(data_path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
 pub fn open_data_folder() -> Result<(), String> {
-    let home = home_dir().ok_or("Could not determine home directory")?;
-    let data_path = home.join(".thinking-space");
+    let data_path = crate::config::get_data_dir()?;
 
     // Create directory if it doesn't exist
     if !data_path.exists() {
@@ -115,3 +929,457 @@ pub fn open_data_folder() -> Result<(), String> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_migrate_settings_without_version() {
+        let raw = serde_json::json!({
+            "api_key": null,
+            "has_completed_onboarding": true
+        });
+
+        let settings = migrate_settings(raw).unwrap();
+
+        assert_eq!(settings.version, CURRENT_SETTINGS_VERSION);
+        // Stored values are kept
+        assert!(settings.has_completed_onboarding);
+        // Missing fields (including ones without serde defaults) are seeded
+        assert_eq!(settings.theme, "system");
+        assert!(!settings.always_allow_tool_actions);
+        assert!(settings.terminal_policy.is_none());
+    }
+
+    #[test]
+    fn test_space_settings_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // Missing file means no overrides
+        let empty = SpaceSettings::load_from_space(temp_dir.path()).unwrap();
+        assert!(empty.model.is_none());
+
+        let space = SpaceSettings {
+            model: Some("claude-sonnet".to_string()),
+            max_tokens: Some(4096),
+            extra_system_prompt: Some("Be brief.".to_string()),
+        };
+        space.save_to_space(temp_dir.path()).unwrap();
+
+        let loaded = SpaceSettings::load_from_space(temp_dir.path()).unwrap();
+        assert_eq!(loaded.model.as_deref(), Some("claude-sonnet"));
+        assert_eq!(loaded.max_tokens, Some(4096));
+
+        let effective = EffectiveSettings::resolve(&Settings::default(), &loaded);
+        assert_eq!(effective.model.as_deref(), Some("claude-sonnet"));
+        assert_eq!(effective.extra_system_prompt.as_deref(), Some("Be brief."));
+    }
+
+    #[test]
+    fn test_effective_settings_fall_back_to_global() {
+        let global = Settings {
+            model: Some("global-model".to_string()),
+            max_tokens: Some(1000),
+            ..Settings::default()
+        };
+        let space = SpaceSettings {
+            max_tokens: Some(2000),
+            ..SpaceSettings::default()
+        };
+
+        let effective = EffectiveSettings::resolve(&global, &space);
+        assert_eq!(effective.model.as_deref(), Some("global-model"));
+        assert_eq!(effective.max_tokens, Some(2000));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        assert!(validation_errors(&Settings::default()).is_empty());
+
+        let valid = Settings {
+            model: Some("claude-sonnet".to_string()),
+            max_tokens: Some(200_000),
+            allow_tools: vec!["Read".to_string()],
+            ..Settings::default()
+        };
+        assert!(validation_errors(&valid).is_empty());
+
+        let invalid = Settings {
+            model: Some("  ".to_string()),
+            max_tokens: Some(0),
+            ..Settings::default()
+        };
+        assert_eq!(validation_errors(&invalid).len(), 2);
+
+        let too_many = Settings {
+            max_tokens: Some(200_001),
+            ..Settings::default()
+        };
+        assert_eq!(validation_errors(&too_many).len(), 1);
+    }
+
+    #[test]
+    fn test_migrate_settings_rejects_newer_version() {
+        let raw = serde_json::json!({ "version": CURRENT_SETTINGS_VERSION + 1 });
+        assert!(migrate_settings(raw).is_err());
+    }
+
+    #[test]
+    fn test_read_stored_settings_migrates_and_saves() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_path = temp_dir.path().join("settings.json");
+        fs::write(&settings_path, r#"{"api_key": null, "theme": "dark"}"#).unwrap();
+
+        let settings = read_stored_settings(&settings_path).unwrap();
+        assert_eq!(settings.version, CURRENT_SETTINGS_VERSION);
+        assert_eq!(settings.theme, "dark");
+
+        // The migrated file now records its version
+        let saved: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&settings_path).unwrap()).unwrap();
+        assert_eq!(saved["version"], CURRENT_SETTINGS_VERSION);
+    }
+
+    #[test]
+    fn test_window_state_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_path = temp_dir.path().join("settings.json");
+        fs::write(
+            &settings_path,
+            r#"{"version": 1, "api_key": null, "theme": "dark"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(load_window_state_at(&settings_path).unwrap(), None);
+
+        let state = WindowState {
+            x: -40,
+            y: 120,
+            width: 1440,
+            height: 900,
+            maximized: true,
+        };
+        save_window_state_at(&settings_path, state.clone()).unwrap();
+
+        assert_eq!(load_window_state_at(&settings_path).unwrap(), Some(state));
+        // Other settings are left alone
+        assert_eq!(read_stored_settings(&settings_path).unwrap().theme, "dark");
+    }
+
+    #[test]
+    fn test_window_state_from_older_settings_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_path = temp_dir.path().join("settings.json");
+        // Written before `maximized` was tracked
+        fs::write(
+            &settings_path,
+            r#"{
+                "version": 1,
+                "api_key": null,
+                "theme": "system",
+                "window_state": { "x": 10, "y": 20, "width": 1000, "height": 700 }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            load_window_state_at(&settings_path).unwrap(),
+            Some(WindowState {
+                x: 10,
+                y: 20,
+                width: 1000,
+                height: 700,
+                maximized: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_export_settings_redacts_api_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_path = temp_dir.path().join("settings.json");
+        let output_path = temp_dir.path().join("exported.json");
+        let stored = Settings {
+            api_key: Some("sk-ant-secret".to_string()),
+            theme: "dark".to_string(),
+            window_state: Some(WindowState {
+                x: 0,
+                y: 0,
+                width: 800,
+                height: 600,
+                maximized: false,
+            }),
+            ..Settings::default()
+        };
+        write_stored_settings(&settings_path, &stored).unwrap();
+
+        export_settings_at(&settings_path, &output_path).unwrap();
+
+        let contents = fs::read_to_string(&output_path).unwrap();
+        assert!(!contents.contains("sk-ant-secret"));
+        let exported: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!(exported["api_key"], serde_json::Value::Null);
+        assert_eq!(exported["theme"], "dark");
+        assert!(exported.get("window_state").is_none());
+
+        // An export imports cleanly and keeps the existing key
+        let current = Settings {
+            api_key: Some("sk-ant-local".to_string()),
+            ..Settings::default()
+        };
+        let imported = merge_imported_settings(&current, exported, true).unwrap();
+        assert_eq!(imported.api_key.as_deref(), Some("sk-ant-local"));
+        assert_eq!(imported.theme, "dark");
+    }
+
+    #[test]
+    fn test_import_settings_partial() {
+        let current = Settings {
+            theme: "light".to_string(),
+            model: Some("claude-sonnet".to_string()),
+            ..Settings::default()
+        };
+
+        let imported = merge_imported_settings(
+            &current,
+            serde_json::json!({ "max_tokens": 4096, "allow_tools": ["Read"] }),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(imported.max_tokens, Some(4096));
+        assert_eq!(imported.allow_tools, vec!["Read".to_string()]);
+        // Fields missing from the import are left alone
+        assert_eq!(imported.theme, "light");
+        assert_eq!(imported.model.as_deref(), Some("claude-sonnet"));
+    }
+
+    #[test]
+    fn test_import_settings_api_key_only_when_requested() {
+        let current = Settings {
+            api_key: Some("sk-ant-local".to_string()),
+            ..Settings::default()
+        };
+        let file = serde_json::json!({ "api_key": "sk-ant-imported" });
+
+        let kept = merge_imported_settings(&current, file.clone(), false).unwrap();
+        assert_eq!(kept.api_key.as_deref(), Some("sk-ant-local"));
+
+        let overwritten = merge_imported_settings(&current, file, true).unwrap();
+        assert_eq!(overwritten.api_key.as_deref(), Some("sk-ant-imported"));
+    }
+
+    #[test]
+    fn test_import_settings_validation_failures() {
+        let errors = merge_imported_settings(
+            &Settings::default(),
+            serde_json::json!({
+                "theme": "dark",
+                "max_tokens": 500_000,
+                "space_cache_capacity": 0,
+            }),
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                SettingsImportError::new(
+                    "max_tokens",
+                    format!("Max tokens must be between 1 and {}", MAX_TOKENS_LIMIT),
+                    serde_json::json!(500_000),
+                ),
+                SettingsImportError::new(
+                    "space_cache_capacity",
+                    "Space cache capacity must be greater than 0",
+                    serde_json::json!(0),
+                ),
+            ]
+        );
+
+        // Unknown keys and wrong types are rejected field by field
+        let errors = merge_imported_settings(
+            &Settings::default(),
+            serde_json::json!({
+                "colour_scheme": "dark",
+                "notify_on_completion": "yes",
+                "version": CURRENT_SETTINGS_VERSION + 1,
+            }),
+            false,
+        )
+        .unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec!["colour_scheme", "notify_on_completion", "version"]
+        );
+        assert_eq!(errors[1].received_value, "yes");
+
+        assert!(
+            merge_imported_settings(&Settings::default(), serde_json::json!([]), false).is_err()
+        );
+    }
+
+    #[test]
+    fn test_settings_changed_emitted_on_save() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_path = temp_dir.path().join("settings.json");
+        let manager = SettingsManager::new();
+
+        let settings = Settings {
+            theme: "dark".to_string(),
+            ..Settings::default()
+        };
+        manager.save_at(&settings_path, settings.clone()).unwrap();
+
+        assert_eq!(*manager.emitted_events.lock(), vec![settings]);
+        assert_eq!(read_stored_settings(&settings_path).unwrap().theme, "dark");
+    }
+
+    #[test]
+    fn test_settings_changed_not_emitted_when_unchanged() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_path = temp_dir.path().join("settings.json");
+        let manager = SettingsManager::new();
+        let settings = Settings {
+            model: Some("claude-sonnet".to_string()),
+            ..Settings::default()
+        };
+
+        manager.save_at(&settings_path, settings.clone()).unwrap();
+        manager.save_at(&settings_path, settings.clone()).unwrap();
+        assert_eq!(manager.emitted_events.lock().len(), 1);
+
+        // Invalid settings aren't saved or announced
+        let invalid = Settings {
+            max_tokens: Some(0),
+            ..settings
+        };
+        assert!(manager.save_at(&settings_path, invalid).is_err());
+        assert_eq!(manager.emitted_events.lock().len(), 1);
+    }
+
+    #[test]
+    fn test_default_keyboard_shortcuts() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_path = temp_dir.path().join("settings.json");
+
+        assert_eq!(
+            get_keyboard_shortcut_at(&settings_path, "sendMessage").unwrap(),
+            Some("Ctrl+Enter".to_string())
+        );
+        assert_eq!(
+            get_keyboard_shortcut_at(&settings_path, "unknown").unwrap(),
+            None
+        );
+
+        // Files written before shortcuts existed get the defaults
+        let migrated = migrate_settings(serde_json::json!({ "theme": "dark" })).unwrap();
+        assert_eq!(migrated.keyboard_shortcuts, default_keyboard_shortcuts());
+        assert!(validation_errors(&Settings::default()).is_empty());
+    }
+
+    #[test]
+    fn test_set_keyboard_shortcut_persists() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_path = temp_dir.path().join("settings.json");
+        let manager = SettingsManager::new();
+
+        set_keyboard_shortcut_at(&manager, &settings_path, "sendMessage", "Meta+Enter").unwrap();
+        set_keyboard_shortcut_at(&manager, &settings_path, "openTerminal", "Ctrl+Shift+T").unwrap();
+
+        assert_eq!(
+            get_keyboard_shortcut_at(&settings_path, "sendMessage").unwrap(),
+            Some("Meta+Enter".to_string())
+        );
+        assert_eq!(
+            get_keyboard_shortcut_at(&settings_path, "openTerminal").unwrap(),
+            Some("Ctrl+Shift+T".to_string())
+        );
+        // Other defaults are kept
+        assert_eq!(
+            get_keyboard_shortcut_at(&settings_path, "newSpace").unwrap(),
+            Some("Ctrl+N".to_string())
+        );
+    }
+
+    #[test]
+    fn test_set_keyboard_shortcut_rejects_invalid_combo() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_path = temp_dir.path().join("settings.json");
+        let manager = SettingsManager::new();
+
+        for combo in ["K", "Ctrl+", "Ctrl+k", "Hyper+K", "Ctrl+KK", ""] {
+            assert!(
+                set_keyboard_shortcut_at(&manager, &settings_path, "newSpace", combo).is_err(),
+                "{:?} should be rejected",
+                combo
+            );
+        }
+        assert!(set_keyboard_shortcut_at(&manager, &settings_path, " ", "Ctrl+K").is_err());
+        assert!(!settings_path.exists());
+    }
+
+    #[test]
+    fn test_invalid_stored_shortcut_is_dropped_on_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_path = temp_dir.path().join("settings.json");
+        let manager = SettingsManager::new();
+        let stored = Settings {
+            keyboard_shortcuts: HashMap::from([
+                ("newSpace".to_string(), "N".to_string()),
+                ("sendMessage".to_string(), "Meta+Enter".to_string()),
+            ]),
+            ..Settings::default()
+        };
+        write_stored_settings(&settings_path, &stored).unwrap();
+
+        let loaded = load_settings_at(&settings_path).unwrap();
+        assert_eq!(loaded.keyboard_shortcuts.get("newSpace"), None);
+        assert_eq!(
+            loaded
+                .keyboard_shortcuts
+                .get("sendMessage")
+                .map(String::as_str),
+            Some("Meta+Enter")
+        );
+
+        // Other settings still save
+        let updated = Settings {
+            max_tokens: Some(1024),
+            ..loaded
+        };
+        manager.save_at(&settings_path, updated).unwrap();
+        assert_eq!(
+            load_settings_at(&settings_path).unwrap().max_tokens,
+            Some(1024)
+        );
+    }
+
+    #[test]
+    fn test_undecryptable_api_key_survives_load_then_save() {
+        let temp_dir = TempDir::new().unwrap();
+        let settings_path = temp_dir.path().join("settings.json");
+        // Looks like nonce + ciphertext but wasn't encrypted with this machine's key
+        let ciphertext = format!("{}==", "A".repeat(54));
+        let stored = Settings {
+            api_key: Some(ciphertext.clone()),
+            ..Settings::default()
+        };
+        write_stored_settings(&settings_path, &stored).unwrap();
+
+        let mut settings = load_settings_at(&settings_path).unwrap();
+        assert_eq!(settings.api_key, None);
+        assert!(settings.api_key_undecryptable);
+
+        settings.theme = "dark".to_string();
+        write_settings_at(&settings_path, settings).unwrap();
+
+        let saved = read_stored_settings(&settings_path).unwrap();
+        assert_eq!(saved.api_key, Some(ciphertext));
+        assert_eq!(saved.theme, "dark");
+        assert!(!saved.api_key_undecryptable);
+    }
+}
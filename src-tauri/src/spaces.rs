@@ -1,8 +1,74 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use lru::LruCache;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, DiffOp, TextDiff};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use std::time::SystemTime;
+use std::io::{BufRead, BufReader, Read, Seek, Write};
+use std::num::NonZeroUsize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter};
+use tempfile::NamedTempFile;
 use uuid::Uuid;
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::ThinkingSpaceError;
+
+/// Metadata filename stored in each space directory
+const METADATA_FILE: &str = ".space-metadata.json";
+
+/// Hidden entries that are part of a space's configuration and get copied when cloning
+const CLONED_HIDDEN_ENTRIES: [&str; 3] = [".mcp.json", ".space-settings.json", ".claude"];
+
+/// Files larger than this are skipped when cloning
+const MAX_CLONED_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Directory inside each space holding previous CLAUDE.md versions
+const CLAUDE_MD_HISTORY_DIR: &str = ".claude-md-history";
+
+/// Oldest CLAUDE.md versions are deleted beyond this many
+const MAX_CLAUDE_MD_VERSIONS: usize = 20;
+
+/// CLAUDE.md token budget when none is configured in settings
+const DEFAULT_CLAUDE_MD_TOKEN_BUDGET: usize = 8000;
+
+/// Rough characters-per-token ratio used for estimates
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Default depth for recursive file listings (1 = top level only)
+const DEFAULT_LISTING_DEPTH: u32 = 3;
+
+/// Recursive file listings stop after this many entries
+const MAX_LISTED_FILES: usize = 10_000;
+
+/// Largest file read_file_binary will return
+const MAX_BINARY_READ_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Bytes sniffed to detect a file's type without reading all of it
+const FILE_TYPE_SAMPLE_BYTES: usize = 8192;
+
+/// Files larger than this are skipped by content search
+const MAX_SEARCHED_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Upper bound on content search results, whatever the caller asks for
+const MAX_SEARCH_RESULTS: usize = 500;
+
+/// File changes are reported once a path has been quiet this long
+const FILE_WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Spaces whose metadata is kept in memory when no capacity is configured
+const DEFAULT_SPACE_CACHE_CAPACITY: usize = 50;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Space {
@@ -13,26 +79,54 @@ pub struct Space {
     pub created_at: i64,
     pub last_accessed_at: i64,
     pub template: Option<String>,
+    /// Archived spaces are hidden from the main list but not deleted
+    #[serde(default)]
+    pub archived: bool,
+    /// Pinned spaces are listed before all others
+    #[serde(default)]
+    pub pinned: bool,
+    /// Position among pinned spaces, lowest first
+    #[serde(default)]
+    pub pin_order: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateSpaceRequest {
     pub name: String,
+    /// A built-in template or the id of a cached community template
+    #[serde(alias = "template_id")]
     pub template: String,
 }
 
-pub fn get_spaces_dir() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let spaces_dir = home.join(".thinking-space").join("spaces");
+pub fn get_spaces_dir() -> Result<PathBuf, ThinkingSpaceError> {
+    let spaces_dir = crate::config::get_data_dir()
+        .map_err(|_| ThinkingSpaceError::not_found("Home directory"))?
+        .join("spaces");
 
     if !spaces_dir.exists() {
-        fs::create_dir_all(&spaces_dir)
-            .map_err(|e| format!("Failed to create spaces directory: {}", e))?;
+        fs::create_dir_all(&spaces_dir).map_err(|e| ThinkingSpaceError::io(&spaces_dir, e))?;
     }
 
     Ok(spaces_dir)
 }
 
+/// Whether a space directory for `space_id` exists under `spaces_dir`
+pub(crate) fn space_exists_in(spaces_dir: &Path, space_id: &str) -> bool {
+    validate_space_id(space_id).is_ok() && spaces_dir.join(space_id).is_dir()
+}
+
+/// Get the directory for an existing space
+pub fn get_space_dir(space_id: &str) -> Result<PathBuf, ThinkingSpaceError> {
+    validate_space_id(space_id)?;
+    let space_dir = get_spaces_dir()?.join(space_id);
+
+    if !space_dir.exists() {
+        return Err(ThinkingSpaceError::not_found("Space directory"));
+    }
+
+    Ok(space_dir)
+}
+
 pub fn get_template_content(template: &str) -> String {
     match template {
         "quick-start" => r#"# {name}
@@ -56,15 +150,258 @@ This is a workspace for [brief description].
     }
 }
 
+/// Templates compiled into the app; community templates can't reuse these ids
+const BUILT_IN_TEMPLATES: [&str; 2] = ["quick-start", "custom"];
+
+/// Community templates from the last fetch, in the data directory
+const TEMPLATE_CACHE_FILE: &str = "template-cache.json";
+
+/// How long fetched community templates are reused before fetching again (ms)
+const TEMPLATE_CACHE_TTL_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// A space template shared by the community
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpaceTemplate {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub author: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// CLAUDE.md contents, with `{name}` replaced by the space name
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TemplateCache {
+    url: String,
+    fetched_at: i64,
+    templates: Vec<SpaceTemplate>,
+}
+
+fn template_cache_path() -> Result<PathBuf, ThinkingSpaceError> {
+    Ok(crate::config::get_data_dir()
+        .map_err(|_| ThinkingSpaceError::not_found("Home directory"))?
+        .join(TEMPLATE_CACHE_FILE))
+}
+
+/// The cached community templates, if there is a readable cache
+fn read_template_cache(cache_path: &Path) -> Option<TemplateCache> {
+    let contents = fs::read_to_string(cache_path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn validate_space_template(value: serde_json::Value) -> Result<SpaceTemplate, ThinkingSpaceError> {
+    let template: SpaceTemplate = serde_json::from_value(value)
+        .map_err(|e| ThinkingSpaceError::Serialization(format!("Invalid template: {}", e)))?;
+
+    if template.id.is_empty()
+        || !template
+            .id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(ThinkingSpaceError::validation(
+            "id",
+            format!("Invalid template id: {:?}", template.id),
+        ));
+    }
+    if BUILT_IN_TEMPLATES.contains(&template.id.as_str()) {
+        return Err(ThinkingSpaceError::validation(
+            "id",
+            format!(
+                "Template id {} is reserved for a built-in template",
+                template.id
+            ),
+        ));
+    }
+    if template.name.trim().is_empty() {
+        return Err(ThinkingSpaceError::validation(
+            "name",
+            format!("Template {} has no name", template.id),
+        ));
+    }
+    if template.content.trim().is_empty() {
+        return Err(ThinkingSpaceError::validation(
+            "content",
+            format!("Template {} has no content", template.id),
+        ));
+    }
+
+    Ok(template)
+}
+
+/// Fetch the community templates at `url`, reusing a cache younger than
+/// TEMPLATE_CACHE_TTL_MS. Malformed entries are skipped
+async fn fetch_community_templates_with(
+    cache_path: &Path,
+    url: &str,
+    now: i64,
+) -> Result<Vec<SpaceTemplate>, ThinkingSpaceError> {
+    if let Some(cache) = read_template_cache(cache_path) {
+        if cache.url == url && now - cache.fetched_at < TEMPLATE_CACHE_TTL_MS {
+            return Ok(cache.templates);
+        }
+    }
+
+    let response = reqwest::Client::new()
+        .get(url)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| ThinkingSpaceError::io(url, e))?;
+
+    if !response.status().is_success() {
+        return Err(ThinkingSpaceError::io(
+            url,
+            format!("Template server returned status {}", response.status()),
+        ));
+    }
+
+    let entries: Vec<serde_json::Value> = response.json().await.map_err(|e| {
+        ThinkingSpaceError::Serialization(format!("Failed to parse community templates: {}", e))
+    })?;
+
+    let mut templates: Vec<SpaceTemplate> = Vec::new();
+    for entry in entries {
+        match validate_space_template(entry) {
+            Ok(template) if templates.iter().any(|t| t.id == template.id) => {
+                println!(
+                    "[SPACES] Skipping duplicate community template {}",
+                    template.id
+                );
+            }
+            Ok(template) => templates.push(template),
+            Err(e) => println!("[SPACES] Skipping community template: {}", e),
+        }
+    }
+
+    let cache = TemplateCache {
+        url: url.to_string(),
+        fetched_at: now,
+        templates,
+    };
+    if let Some(parent) = cache_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| ThinkingSpaceError::io(parent, e))?;
+    }
+    let cache_json = serde_json::to_string_pretty(&cache).map_err(|e| {
+        ThinkingSpaceError::Serialization(format!("Failed to serialize template cache: {}", e))
+    })?;
+    atomic_write(cache_path, cache_json.as_bytes())?;
+
+    Ok(cache.templates)
+}
+
+/// CLAUDE.md template for `template_id`: a built-in, else a cached community
+/// template, else the default built-in
+fn resolve_template_content(template_id: &str, community_templates: &[SpaceTemplate]) -> String {
+    if !BUILT_IN_TEMPLATES.contains(&template_id) {
+        if let Some(template) = community_templates.iter().find(|t| t.id == template_id) {
+            return template.content.clone();
+        }
+    }
+    get_template_content(template_id)
+}
+
 #[tauri::command]
-pub fn list_spaces() -> Result<Vec<Space>, String> {
-    let spaces_dir = get_spaces_dir()?;
+pub async fn fetch_community_templates(
+    url: String,
+) -> Result<Vec<SpaceTemplate>, ThinkingSpaceError> {
+    fetch_community_templates_with(
+        &template_cache_path()?,
+        &url,
+        chrono::Utc::now().timestamp_millis(),
+    )
+    .await
+}
+
+/// Parsed space metadata, most recently used kept, so listing spaces
+/// doesn't re-read every .space-metadata.json
+pub struct SpaceMetadataCache {
+    entries: LruCache<String, Space>,
+    /// Lookups answered from memory
+    hits: usize,
+}
+
+impl SpaceMetadataCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: LruCache::new(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN)),
+            hits: 0,
+        }
+    }
+
+    /// Grow to hold `count` spaces; a listing walks every space in turn, so a
+    /// cache smaller than that evicts each entry before it is asked for again
+    fn fit(&mut self, count: usize) {
+        if let Some(count) = NonZeroUsize::new(count) {
+            if count > self.entries.cap() {
+                self.entries.resize(count);
+            }
+        }
+    }
+
+    /// The cached metadata for a space, if it was loaded from `spaces_dir`
+    fn get(&mut self, spaces_dir: &Path, id: &str) -> Option<Space> {
+        let space = self
+            .entries
+            .get(id)
+            .filter(|space| Path::new(&space.path) == spaces_dir.join(id))
+            .cloned();
+        if space.is_some() {
+            self.hits += 1;
+        }
+        space
+    }
+
+    fn put(&mut self, space: Space) {
+        self.entries.put(space.id.clone(), space);
+    }
+
+    fn invalidate(&mut self, id: &str) {
+        self.entries.pop(id);
+    }
+}
+
+static SPACE_METADATA_CACHE: Lazy<Arc<Mutex<SpaceMetadataCache>>> = Lazy::new(|| {
+    let capacity = crate::settings::load_settings()
+        .ok()
+        .and_then(|settings| settings.space_cache_capacity)
+        .unwrap_or(DEFAULT_SPACE_CACHE_CAPACITY);
+    Arc::new(Mutex::new(SpaceMetadataCache::new(capacity)))
+});
+
+/// Drop a space's cached metadata so the next listing reads it from disk
+pub(crate) fn invalidate_space_cache(space_id: &str) {
+    SPACE_METADATA_CACHE.lock().invalidate(space_id);
+}
+
+/// Scan the spaces directory and load every space's metadata (including archived)
+/// Spaces with unreadable metadata are skipped
+fn load_all_spaces_in(spaces_dir: &Path) -> Result<Vec<Space>, ThinkingSpaceError> {
+    load_all_spaces_cached(spaces_dir, &SPACE_METADATA_CACHE)
+}
+
+/// Like load_all_spaces_in, but only reads metadata missing from `cache`
+fn load_all_spaces_cached(
+    spaces_dir: &Path,
+    cache: &Mutex<SpaceMetadataCache>,
+) -> Result<Vec<Space>, ThinkingSpaceError> {
     let mut spaces = Vec::new();
 
     if let Ok(entries) = fs::read_dir(spaces_dir) {
-        for entry in entries.flatten() {
+        let entries: Vec<_> = entries.flatten().collect();
+        cache.lock().fit(entries.len());
+
+        for entry in entries {
+            let id = entry.file_name().to_string_lossy().to_string();
+            if let Some(space) = cache.lock().get(spaces_dir, &id) {
+                spaces.push(space);
+                continue;
+            }
+
             if entry.path().is_dir() {
-                let metadata_path = entry.path().join(".space-metadata.json");
+                let metadata_path = entry.path().join(METADATA_FILE);
                 if let Ok(contents) = fs::read_to_string(&metadata_path) {
                     if let Ok(mut space) = serde_json::from_str::<Space>(&contents) {
                         // Migration: Fix old timestamps in seconds (< year 2100 in milliseconds)
@@ -85,11 +422,10 @@ pub fn list_spaces() -> Result<Vec<Space>, String> {
 
                         // Save the migrated metadata
                         if needs_update {
-                            if let Ok(metadata_json) = serde_json::to_string_pretty(&space) {
-                                let _ = fs::write(&metadata_path, metadata_json);
-                            }
+                            let _ = write_space_metadata(&entry.path(), &space);
                         }
 
+                        cache.lock().put(space.clone());
                         spaces.push(space);
                     }
                 }
@@ -97,297 +433,4523 @@ pub fn list_spaces() -> Result<Vec<Space>, String> {
         }
     }
 
-    // Sort by last accessed (most recent first)
-    spaces.sort_by(|a, b| b.last_accessed_at.cmp(&a.last_accessed_at));
-
     Ok(spaces)
 }
 
-#[tauri::command]
-pub fn create_space(request: CreateSpaceRequest) -> Result<Space, String> {
-    let spaces_dir = get_spaces_dir()?;
-    let id = Uuid::new_v4().to_string();
-    let space_dir = spaces_dir.join(&id);
+fn load_all_spaces() -> Result<Vec<Space>, ThinkingSpaceError> {
+    load_all_spaces_in(&get_spaces_dir()?)
+}
 
-    // Create space directory
-    fs::create_dir_all(&space_dir)
-        .map_err(|e| format!("Failed to create space directory: {}", e))?;
+/// Pinned spaces first (by pin order), then everything else most recently accessed first
+fn compare_spaces(a: &Space, b: &Space) -> std::cmp::Ordering {
+    b.pinned
+        .cmp(&a.pinned)
+        .then_with(|| match (a.pin_order, b.pin_order) {
+            (Some(a_order), Some(b_order)) => a_order.cmp(&b_order),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        })
+        .then_with(|| b.last_accessed_at.cmp(&a.last_accessed_at))
+}
 
-    // Create CLAUDE.md from template
-    let template_content = get_template_content(&request.template);
-    let claude_md_content = template_content.replace("{name}", &request.name);
-    let claude_md_path = space_dir.join("CLAUDE.md");
+/// Keep spaces matching `predicate` (and not archived, unless asked), pinned first
+fn select_spaces(
+    spaces: Vec<Space>,
+    include_archived: bool,
+    predicate: impl Fn(&Space) -> bool,
+) -> Vec<Space> {
+    let mut spaces: Vec<Space> = spaces
+        .into_iter()
+        .filter(|space| include_archived || !space.archived)
+        .filter(|space| predicate(space))
+        .collect();
 
-    fs::write(&claude_md_path, claude_md_content)
-        .map_err(|e| format!("Failed to create CLAUDE.md: {}", e))?;
+    spaces.sort_by(compare_spaces);
 
-    // Create space metadata
-    // Use timestamp_millis() to match JavaScript Date expectations
-    let now = chrono::Utc::now().timestamp_millis();
-    let space = Space {
-        id: id.clone(),
-        name: request.name,
-        path: space_dir.to_string_lossy().to_string(),
-        claude_md_path: claude_md_path.to_string_lossy().to_string(),
-        created_at: now,
-        last_accessed_at: now,
-        template: Some(request.template),
-    };
+    spaces
+}
+
+pub(crate) fn list_spaces_in(
+    spaces_dir: &Path,
+    include_archived: bool,
+) -> Result<Vec<Space>, ThinkingSpaceError> {
+    Ok(select_spaces(
+        load_all_spaces_in(spaces_dir)?,
+        include_archived,
+        |_| true,
+    ))
+}
+
+/// Case-insensitive substring match on space names
+fn search_spaces_in(spaces_dir: &Path, query: &str) -> Result<Vec<Space>, ThinkingSpaceError> {
+    let query = query.to_lowercase();
+    Ok(select_spaces(
+        load_all_spaces_in(spaces_dir)?,
+        false,
+        |space| space.name.to_lowercase().contains(&query),
+    ))
+}
+
+/// Exact match on the template a space was created from
+fn filter_spaces_by_template_in(
+    spaces_dir: &Path,
+    template: &str,
+) -> Result<Vec<Space>, ThinkingSpaceError> {
+    Ok(select_spaces(
+        load_all_spaces_in(spaces_dir)?,
+        false,
+        |space| space.template.as_deref() == Some(template),
+    ))
+}
+
+/// List spaces: pinned spaces first, then the rest most recently accessed first
+/// Archived spaces are only included when `include_archived` is true
+#[tauri::command]
+pub fn list_spaces(include_archived: Option<bool>) -> Result<Vec<Space>, ThinkingSpaceError> {
+    list_spaces_in(&get_spaces_dir()?, include_archived.unwrap_or(false))
+}
+
+#[tauri::command]
+pub fn list_archived_spaces() -> Result<Vec<Space>, ThinkingSpaceError> {
+    Ok(select_spaces(load_all_spaces()?, true, |space| {
+        space.archived
+    }))
+}
+
+#[tauri::command]
+pub fn search_spaces(query: String) -> Result<Vec<Space>, ThinkingSpaceError> {
+    search_spaces_in(&get_spaces_dir()?, &query)
+}
+
+#[tauri::command]
+pub fn filter_spaces_by_template(template: String) -> Result<Vec<Space>, ThinkingSpaceError> {
+    filter_spaces_by_template_in(&get_spaces_dir()?, &template)
+}
 
-    // Save metadata
-    let metadata_path = space_dir.join(".space-metadata.json");
-    let metadata_json = serde_json::to_string_pretty(&space)
-        .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+/// Read a space's metadata, apply `update`, and save it back
+pub(crate) fn update_space_metadata_in(
+    spaces_dir: &Path,
+    id: &str,
+    update: impl FnOnce(&mut Space),
+) -> Result<Space, ThinkingSpaceError> {
+    validate_space_id(id)?;
+    let space_dir = spaces_dir.join(id);
+    let metadata_path = space_dir.join(METADATA_FILE);
+    let contents = fs::read_to_string(&metadata_path)
+        .map_err(|e| ThinkingSpaceError::io(&metadata_path, e))?;
+    let mut space: Space = serde_json::from_str(&contents).map_err(|e| {
+        ThinkingSpaceError::Serialization(format!("Failed to parse metadata: {}", e))
+    })?;
 
-    fs::write(metadata_path, metadata_json)
-        .map_err(|e| format!("Failed to write metadata: {}", e))?;
+    update(&mut space);
+    write_space_metadata(&space_dir, &space)?;
 
     Ok(space)
 }
 
 #[tauri::command]
-pub fn delete_space(id: String) -> Result<(), String> {
-    let spaces_dir = get_spaces_dir()?;
-    let space_dir = spaces_dir.join(&id);
+pub fn archive_space(id: String) -> Result<(), ThinkingSpaceError> {
+    update_space_metadata_in(&get_spaces_dir()?, &id, |space| space.archived = true).map(|_| ())
+}
 
-    if space_dir.exists() {
-        fs::remove_dir_all(space_dir).map_err(|e| format!("Failed to delete space: {}", e))?;
-    }
+#[tauri::command]
+pub fn unarchive_space(id: String) -> Result<(), ThinkingSpaceError> {
+    update_space_metadata_in(&get_spaces_dir()?, &id, |space| space.archived = false).map(|_| ())
+}
 
-    // Also delete the conversation for this space
-    // Note: We don't fail if conversation deletion fails, since the space is already deleted
-    let _ = crate::conversations::delete_conversation(id);
+/// Longest allowed space name, in characters
+const MAX_SPACE_NAME_CHARS: usize = 256;
 
+fn validate_space_name(name: &str) -> Result<(), ThinkingSpaceError> {
+    if name.trim().is_empty() {
+        return Err(ThinkingSpaceError::validation(
+            "name",
+            "Space name cannot be empty",
+        ));
+    }
+    if name.chars().count() > MAX_SPACE_NAME_CHARS {
+        return Err(ThinkingSpaceError::validation(
+            "name",
+            format!(
+                "Space name cannot be longer than {} characters",
+                MAX_SPACE_NAME_CHARS
+            ),
+        ));
+    }
+    if name.contains(['/', '\\']) {
+        return Err(ThinkingSpaceError::validation(
+            "name",
+            "Space name cannot contain path separators",
+        ));
+    }
     Ok(())
 }
 
-#[tauri::command]
-pub fn update_last_accessed(id: String) -> Result<(), String> {
-    let spaces_dir = get_spaces_dir()?;
-    let metadata_path = spaces_dir.join(&id).join(".space-metadata.json");
+/// CLAUDE.md with its first heading renamed, if that heading is still the
+/// `# {name}` the template rendered (None = the user has changed it)
+fn rename_claude_md_heading(content: &str, old_name: &str, new_name: &str) -> Option<String> {
+    let heading = content.lines().find(|line| line.starts_with('#'))?;
+    if heading.trim_end() != format!("# {}", old_name) {
+        return None;
+    }
+    Some(content.replacen(heading, &format!("# {}", new_name), 1))
+}
 
-    if let Ok(contents) = fs::read_to_string(&metadata_path) {
-        if let Ok(mut space) = serde_json::from_str::<Space>(&contents) {
-            // Use timestamp_millis() to match JavaScript Date expectations
-            space.last_accessed_at = chrono::Utc::now().timestamp_millis();
+/// Rename a space: its metadata, the CLAUDE.md heading and its conversation's name
+fn rename_space_in(
+    spaces_dir: &Path,
+    conversations: &rusqlite::Connection,
+    space_id: &str,
+    new_name: &str,
+) -> Result<Space, ThinkingSpaceError> {
+    validate_space_name(new_name)?;
+    let space_dir = existing_space_dir(spaces_dir, space_id)?;
 
-            let metadata_json = serde_json::to_string_pretty(&space)
-                .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+    let mut old_name = String::new();
+    let space = update_space_metadata_in(spaces_dir, space_id, |space| {
+        old_name = std::mem::replace(&mut space.name, new_name.to_string());
+    })?;
 
-            fs::write(metadata_path, metadata_json)
-                .map_err(|e| format!("Failed to write metadata: {}", e))?;
+    if let Ok(content) = fs::read_to_string(space_dir.join("CLAUDE.md")) {
+        if let Some(renamed) = rename_claude_md_heading(&content, &old_name, new_name) {
+            write_claude_md_in(&space_dir, &renamed)?;
         }
     }
 
-    Ok(())
+    // A space that hasn't been chatted in yet has no conversation to rename
+    if crate::conversations::conversation_exists_internal(conversations, space_id)? {
+        crate::conversations::rename_conversation_internal(
+            conversations,
+            spaces_dir,
+            space_id,
+            new_name,
+        )?;
+    }
+
+    println!(
+        "[SPACES] Renamed space {} from '{}' to '{}'",
+        space_id, old_name, new_name
+    );
+    Ok(space)
 }
 
 #[tauri::command]
-pub fn read_claude_md(space_id: String) -> Result<String, String> {
-    let spaces_dir = get_spaces_dir()?;
-    let claude_md_path = spaces_dir.join(&space_id).join("CLAUDE.md");
+pub fn rename_space(
+    acp_manager: tauri::State<'_, Arc<crate::acp_v2::manager::AcpManager>>,
+    space_id: String,
+    new_name: String,
+) -> Result<Space, ThinkingSpaceError> {
+    let conversations =
+        crate::conversations::get_connection_at(&crate::conversations::get_db_path()?)?;
+    let space = rename_space_in(&get_spaces_dir()?, &conversations, &space_id, &new_name)?;
 
-    fs::read_to_string(claude_md_path).map_err(|e| format!("Failed to read CLAUDE.md: {}", e))
+    // Agent events carry the space name, so drop the cached old one
+    acp_manager.forget_space_name(&space.path);
+    Ok(space)
 }
 
-#[tauri::command]
-pub fn write_claude_md(space_id: String, content: String) -> Result<(), String> {
-    let spaces_dir = get_spaces_dir()?;
-    let claude_md_path = spaces_dir.join(&space_id).join("CLAUDE.md");
+fn pin_space_in(spaces_dir: &Path, id: &str) -> Result<(), ThinkingSpaceError> {
+    // New pins go to the end of the pinned list
+    let next_order = load_all_spaces_in(spaces_dir)?
+        .iter()
+        .filter(|space| space.pinned && space.id != id)
+        .filter_map(|space| space.pin_order)
+        .max()
+        .map_or(0, |order| order + 1);
 
-    fs::write(claude_md_path, content).map_err(|e| format!("Failed to write CLAUDE.md: {}", e))
+    update_space_metadata_in(spaces_dir, id, |space| {
+        if !space.pinned {
+            space.pinned = true;
+            space.pin_order = Some(next_order);
+        }
+    })
+    .map(|_| ())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SpaceFile {
-    pub name: String,
-    pub path: String,
-    pub size: u64,
-    pub modified: i64,
-    pub is_directory: bool,
+fn unpin_space_in(spaces_dir: &Path, id: &str) -> Result<(), ThinkingSpaceError> {
+    update_space_metadata_in(spaces_dir, id, |space| {
+        space.pinned = false;
+        space.pin_order = None;
+    })
+    .map(|_| ())
 }
 
-#[tauri::command]
-pub fn list_space_files(space_id: String) -> Result<Vec<SpaceFile>, String> {
-    let spaces_dir = get_spaces_dir()?;
-    let space_dir = spaces_dir.join(&space_id);
-
-    if !space_dir.exists() {
-        return Err("Space directory not found".to_string());
-    }
+fn reorder_pinned_spaces_in(
+    spaces_dir: &Path,
+    ordered_ids: &[String],
+) -> Result<(), ThinkingSpaceError> {
+    let mut updated = Vec::with_capacity(ordered_ids.len());
 
-    let mut files = Vec::new();
+    for (index, id) in ordered_ids.iter().enumerate() {
+        validate_space_id(id)?;
+        let space_dir = spaces_dir.join(id);
+        let metadata_path = space_dir.join(METADATA_FILE);
+        let contents = fs::read_to_string(&metadata_path)
+            .map_err(|e| ThinkingSpaceError::io(&metadata_path, e))?;
+        let mut space: Space = serde_json::from_str(&contents).map_err(|e| {
+            ThinkingSpaceError::Serialization(format!("Failed to parse metadata for {}: {}", id, e))
+        })?;
 
-    if let Ok(entries) = fs::read_dir(&space_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
+        if !space.pinned {
+            return Err(ThinkingSpaceError::validation(
+                "ordered_ids",
+                format!("Space {} is not pinned", id),
+            ));
+        }
 
-            // Skip hidden files and metadata
-            let file_name = entry.file_name().to_string_lossy().to_string();
-            if file_name.starts_with('.') {
-                continue;
-            }
+        space.pin_order = Some(index as u32);
+        updated.push((space_dir, space));
+    }
 
-            if let Ok(metadata) = entry.metadata() {
-                let modified = metadata
-                    .modified()
-                    .ok()
-                    .and_then(|time| {
-                        time.duration_since(SystemTime::UNIX_EPOCH)
-                            .ok()
-                            .map(|d| d.as_millis() as i64)
-                    })
-                    .unwrap_or(0);
-
-                files.push(SpaceFile {
-                    name: file_name,
-                    path: path.to_string_lossy().to_string(),
-                    size: metadata.len(),
-                    modified,
-                    is_directory: metadata.is_dir(),
-                });
-            }
-        }
+    // Stage every file before renaming any, so a failure leaves the old order intact
+    // (staged temp files are removed when dropped)
+    let mut staged = Vec::with_capacity(updated.len());
+    for (space_dir, space) in &updated {
+        let file = stage_space_metadata(space_dir, space)?;
+        staged.push((file, space_dir.join(METADATA_FILE)));
     }
 
-    // Sort by name
-    files.sort_by(|a, b| a.name.cmp(&b.name));
+    for (file, metadata_path) in staged {
+        file.persist(&metadata_path)
+            .map_err(|e| ThinkingSpaceError::io(&metadata_path, e.error))?;
+    }
+    for (_, space) in updated {
+        SPACE_METADATA_CACHE.lock().put(space);
+    }
 
-    Ok(files)
+    Ok(())
 }
 
 #[tauri::command]
-pub fn open_file(path: String) -> Result<(), String> {
-    opener::open(&path).map_err(|e| format!("Failed to open file: {}", e))
+pub fn pin_space(id: String) -> Result<(), ThinkingSpaceError> {
+    pin_space_in(&get_spaces_dir()?, &id)
 }
 
 #[tauri::command]
-pub fn read_file_content(path: String) -> Result<String, String> {
-    // Security: Validate path to prevent path traversal attacks
-    let path_buf = PathBuf::from(&path);
+pub fn unpin_space(id: String) -> Result<(), ThinkingSpaceError> {
+    unpin_space_in(&get_spaces_dir()?, &id)
+}
 
-    // Canonicalize to resolve symlinks and relative paths
-    let canonical = path_buf
-        .canonicalize()
-        .map_err(|e| format!("Invalid path: {}", e))?;
+/// Set the pinned order to match `ordered_ids`
+#[tauri::command]
+pub fn reorder_pinned_spaces(ordered_ids: Vec<String>) -> Result<(), ThinkingSpaceError> {
+    reorder_pinned_spaces_in(&get_spaces_dir()?, &ordered_ids)
+}
 
-    // Only allow reads from user's home directory
-    let home_dir = dirs::home_dir().ok_or("Cannot determine home directory")?;
+/// Build fresh metadata for a space living in `space_dir`
+fn new_space_metadata(
+    id: String,
+    space_dir: &Path,
+    name: String,
+    template: Option<String>,
+) -> Space {
+    // Use timestamp_millis() to match JavaScript Date expectations
+    let now = chrono::Utc::now().timestamp_millis();
 
-    if !canonical.starts_with(&home_dir) {
-        return Err("Access denied: path outside allowed directory".to_string());
+    Space {
+        id,
+        name,
+        path: space_dir.to_string_lossy().to_string(),
+        claude_md_path: space_dir.join("CLAUDE.md").to_string_lossy().to_string(),
+        created_at: now,
+        last_accessed_at: now,
+        template,
+        archived: false,
+        pinned: false,
+        pin_order: None,
     }
+}
 
-    // Additional check: Don't allow reading sensitive files
-    let file_name = canonical.file_name().and_then(|n| n.to_str()).unwrap_or("");
-
-    // Block common sensitive files
-    let blocked_files = [
-        ".env",
-        ".aws",
-        ".ssh",
-        "id_rsa",
-        "id_ed25519",
-        "credentials",
-        "config",
-        ".netrc",
-        ".git-credentials",
-    ];
-
-    if blocked_files
-        .iter()
-        .any(|&blocked| file_name.contains(blocked))
-    {
-        return Err("Access denied: cannot read sensitive files".to_string());
-    }
+/// Write `content` to a new uniquely named temp file next to `path` and flush
+/// it to disk; the file is removed again if it is dropped without being persisted
+fn write_synced_temp(path: &Path, content: &[u8]) -> std::io::Result<NamedTempFile> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut file = NamedTempFile::new_in(parent)?;
+    file.write_all(content)?;
+    file.as_file().sync_all()?;
+    Ok(file)
+}
 
-    fs::read_to_string(&canonical).map_err(|e| format!("Failed to read file: {}", e))
+/// Write to a temp file in the same directory, sync, then rename it over `path`,
+/// so a crash or failed write never leaves a half-written file behind
+fn atomic_write(path: &Path, content: &[u8]) -> Result<(), ThinkingSpaceError> {
+    write_synced_temp(path, content)
+        .and_then(|file| file.persist(path).map(|_| ()).map_err(|e| e.error))
+        .map_err(|e| ThinkingSpaceError::io(path, e))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
+/// Write a space's metadata to a temp file next to .space-metadata.json
+fn stage_space_metadata(
+    space_dir: &Path,
+    space: &Space,
+) -> Result<NamedTempFile, ThinkingSpaceError> {
+    let metadata_json = serde_json::to_string_pretty(space).map_err(|e| {
+        ThinkingSpaceError::Serialization(format!("Failed to serialize metadata: {}", e))
+    })?;
 
-    #[test]
-    fn test_path_traversal_prevention() {
-        // Test various path traversal attack vectors
-        let attacks = vec![
-            "../../../etc/passwd",
-            "..\\..\\..\\Windows\\System32",
-            "/etc/shadow",
-            "/root/.ssh/id_rsa",
-        ];
+    let metadata_path = space_dir.join(METADATA_FILE);
+    write_synced_temp(&metadata_path, metadata_json.as_bytes())
+        .map_err(|e| ThinkingSpaceError::io(&metadata_path, e))
+}
 
-        for attack in attacks {
-            let result = read_file_content(attack.to_string());
-            assert!(result.is_err(), "Failed to block path traversal: {}", attack);
-            assert!(
-                result.as_ref().unwrap_err().contains("Invalid path")
-                    || result.as_ref().unwrap_err().contains("Access denied"),
-                "Expected security error for: {}",
-                attack
-            );
-        }
+/// Save a space's metadata to .space-metadata.json in its directory
+/// (and the metadata cache, so listings see the change)
+fn write_space_metadata(space_dir: &Path, space: &Space) -> Result<(), ThinkingSpaceError> {
+    let metadata_json = serde_json::to_string_pretty(space).map_err(|e| {
+        ThinkingSpaceError::Serialization(format!("Failed to serialize metadata: {}", e))
+    })?;
+
+    let result = atomic_write(&space_dir.join(METADATA_FILE), metadata_json.as_bytes());
+    match result {
+        Ok(()) => SPACE_METADATA_CACHE.lock().put(space.clone()),
+        Err(_) => invalidate_space_cache(&space.id),
     }
+    result
+}
 
-    #[test]
-    fn test_sensitive_file_blocking() {
-        // Create a temp file with a blocked name
-        let temp_dir = tempfile::tempdir().unwrap();
-        let sensitive_path = temp_dir.path().join("id_rsa");
-        std::fs::write(&sensitive_path, "sensitive data").unwrap();
+pub(crate) fn create_space_in(
+    spaces_dir: &Path,
+    request: CreateSpaceRequest,
+    community_templates: &[SpaceTemplate],
+) -> Result<Space, ThinkingSpaceError> {
+    let id = Uuid::new_v4().to_string();
+    let space_dir = spaces_dir.join(&id);
 
-        let result = read_file_content(sensitive_path.to_string_lossy().to_string());
+    // Create space directory
+    fs::create_dir_all(&space_dir).map_err(|e| ThinkingSpaceError::io(&space_dir, e))?;
 
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("sensitive files"));
-    }
+    // Create CLAUDE.md from template
+    let template_content = resolve_template_content(&request.template, community_templates);
+    let claude_md_content = template_content.replace("{name}", &request.name);
+    let claude_md_path = space_dir.join("CLAUDE.md");
 
-    #[test]
-    fn test_allowed_file_read() {
+    atomic_write(&claude_md_path, claude_md_content.as_bytes())?;
+
+    // Create and save space metadata
+    let space = new_space_metadata(id, &space_dir, request.name, Some(request.template));
+    write_space_metadata(&space_dir, &space)?;
+
+    Ok(space)
+}
+
+#[tauri::command]
+pub fn create_space(request: CreateSpaceRequest) -> Result<Space, ThinkingSpaceError> {
+    let community_templates = if BUILT_IN_TEMPLATES.contains(&request.template.as_str()) {
+        Vec::new()
+    } else {
+        read_template_cache(&template_cache_path()?)
+            .map(|cache| cache.templates)
+            .unwrap_or_default()
+    };
+    create_space_in(&get_spaces_dir()?, request, &community_templates)
+}
+
+/// Recursively copy a space's files, skipping metadata and oversized files
+fn copy_space_contents(
+    source: &Path,
+    dest: &Path,
+    top_level: bool,
+) -> Result<(), ThinkingSpaceError> {
+    fs::create_dir_all(dest).map_err(|e| ThinkingSpaceError::io(dest, e))?;
+
+    let entries = fs::read_dir(source).map_err(|e| ThinkingSpaceError::io(source, e))?;
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        // Only configuration dotfiles are copied; the source's metadata never is
+        if top_level
+            && file_name.starts_with('.')
+            && !CLONED_HIDDEN_ENTRIES.contains(&file_name.as_str())
+        {
+            continue;
+        }
+
+        let source_path = entry.path();
+        let dest_path = dest.join(&file_name);
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            copy_space_contents(&source_path, &dest_path, false)?;
+        } else if metadata.len() > MAX_CLONED_FILE_BYTES {
+            eprintln!(
+                "[SPACES] Skipping {} while cloning: larger than 10 MB",
+                source_path.display()
+            );
+        } else {
+            fs::copy(&source_path, &dest_path)
+                .map_err(|e| ThinkingSpaceError::io(&source_path, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn clone_space_in(
+    spaces_dir: &Path,
+    source_id: &str,
+    new_name: String,
+) -> Result<Space, ThinkingSpaceError> {
+    let source_dir = spaces_dir.join(source_id);
+    let source_metadata_path = source_dir.join(METADATA_FILE);
+    let source_metadata = fs::read_to_string(&source_metadata_path)
+        .map_err(|e| ThinkingSpaceError::io(&source_metadata_path, e))?;
+    let source: Space = serde_json::from_str(&source_metadata).map_err(|e| {
+        ThinkingSpaceError::Serialization(format!("Failed to parse source space metadata: {}", e))
+    })?;
+
+    let id = Uuid::new_v4().to_string();
+    let space_dir = spaces_dir.join(&id);
+
+    if let Err(e) = copy_space_contents(&source_dir, &space_dir, true) {
+        // Don't leave a half-copied space behind
+        let _ = fs::remove_dir_all(&space_dir);
+        return Err(e);
+    }
+
+    let space = new_space_metadata(id, &space_dir, new_name, source.template);
+    write_space_metadata(&space_dir, &space)?;
+
+    Ok(space)
+}
+
+/// Create a new space with a copy of an existing space's CLAUDE.md, MCP config and files
+#[tauri::command]
+pub fn clone_space(source_id: String, new_name: String) -> Result<Space, ThinkingSpaceError> {
+    clone_space_in(&get_spaces_dir()?, &source_id, new_name)
+}
+
+/// Resolve `path` and make sure it lives under `root`
+/// Export targets don't exist yet, so for those only the parent is canonicalized
+pub(crate) fn resolve_within(path: &Path, root: &Path) -> Result<PathBuf, ThinkingSpaceError> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| ThinkingSpaceError::io(root, e))?;
+
+    let resolved = if path.exists() {
+        path.canonicalize()
+            .map_err(|e| ThinkingSpaceError::io(path, e))?
+    } else {
+        let file_name = path.file_name().ok_or_else(|| {
+            ThinkingSpaceError::validation("path", "Invalid path: missing file name")
+        })?;
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        parent
+            .canonicalize()
+            .map_err(|e| ThinkingSpaceError::io(parent, e))?
+            .join(file_name)
+    };
+
+    if !resolved.starts_with(&root) {
+        return Err(ThinkingSpaceError::security(
+            "Access denied: path outside allowed directory",
+        ));
+    }
+
+    Ok(resolved)
+}
+
+/// Recursively add a space's files to a zip archive, using the same rules as cloning
+fn add_space_contents_to_zip<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    dir: &Path,
+    prefix: &str,
+    top_level: bool,
+    options: SimpleFileOptions,
+) -> Result<(), ThinkingSpaceError> {
+    let entries = fs::read_dir(dir).map_err(|e| ThinkingSpaceError::io(dir, e))?;
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        // Metadata is written separately, other dotfiles aren't part of the space
+        if top_level
+            && file_name.starts_with('.')
+            && !CLONED_HIDDEN_ENTRIES.contains(&file_name.as_str())
+        {
+            continue;
+        }
+
+        let entry_name = format!("{}{}", prefix, file_name);
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            let dir_name = format!("{}/", entry_name);
+            zip.add_directory(dir_name.as_str(), options)
+                .map_err(|e| ThinkingSpaceError::io(entry.path(), e))?;
+            add_space_contents_to_zip(zip, &entry.path(), &dir_name, false, options)?;
+        } else {
+            zip.start_file(entry_name.as_str(), options)
+                .map_err(|e| ThinkingSpaceError::io(entry.path(), e))?;
+            let mut file = fs::File::open(entry.path())
+                .map_err(|e| ThinkingSpaceError::io(entry.path(), e))?;
+            std::io::copy(&mut file, zip).map_err(|e| ThinkingSpaceError::io(entry.path(), e))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn export_space_in(
+    spaces_dir: &Path,
+    space_id: &str,
+    output_path: &Path,
+) -> Result<(), ThinkingSpaceError> {
+    validate_space_id(space_id)?;
+    let space_dir = spaces_dir.join(space_id);
+    let metadata_path = space_dir.join(METADATA_FILE);
+    let metadata = fs::read_to_string(&metadata_path)
+        .map_err(|e| ThinkingSpaceError::io(&metadata_path, e))?;
+
+    let file = fs::File::create(output_path).map_err(|e| ThinkingSpaceError::io(output_path, e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    add_space_contents_to_zip(&mut zip, &space_dir, "", true, options)?;
+
+    zip.start_file(METADATA_FILE, options)
+        .map_err(|e| ThinkingSpaceError::io(output_path, e))?;
+    zip.write_all(metadata.as_bytes())
+        .map_err(|e| ThinkingSpaceError::io(output_path, e))?;
+
+    zip.finish()
+        .map_err(|e| ThinkingSpaceError::io(output_path, e))?;
+
+    Ok(())
+}
+
+/// Extract every archive entry except the exported metadata into `dest`
+fn extract_space_archive<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    dest: &Path,
+) -> Result<(), ThinkingSpaceError> {
+    fs::create_dir_all(dest).map_err(|e| ThinkingSpaceError::io(dest, e))?;
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| {
+            ThinkingSpaceError::Serialization(format!("Failed to read archive: {}", e))
+        })?;
+
+        // enclosed_name rejects absolute paths and ".." components
+        let Some(relative) = entry.enclosed_name() else {
+            return Err(ThinkingSpaceError::security(format!(
+                "Archive contains an unsafe path: {}",
+                entry.name()
+            )));
+        };
+
+        if relative == Path::new(METADATA_FILE) {
+            continue;
+        }
+
+        let out_path = dest.join(&relative);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path).map_err(|e| ThinkingSpaceError::io(&out_path, e))?;
+        } else {
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| ThinkingSpaceError::io(parent, e))?;
+            }
+            let mut out_file =
+                fs::File::create(&out_path).map_err(|e| ThinkingSpaceError::io(&out_path, e))?;
+            std::io::copy(&mut entry, &mut out_file)
+                .map_err(|e| ThinkingSpaceError::io(&out_path, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn import_space_in(spaces_dir: &Path, zip_path: &Path) -> Result<Space, ThinkingSpaceError> {
+    let file = fs::File::open(zip_path).map_err(|e| ThinkingSpaceError::io(zip_path, e))?;
+    let mut archive = ZipArchive::new(file)
+        .map_err(|e| ThinkingSpaceError::Serialization(format!("Failed to read archive: {}", e)))?;
+
+    let exported: Space = {
+        let mut entry = archive.by_name(METADATA_FILE).map_err(|_| {
+            ThinkingSpaceError::Serialization("Archive is missing space metadata".to_string())
+        })?;
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents).map_err(|e| {
+            ThinkingSpaceError::Serialization(format!("Failed to read metadata: {}", e))
+        })?;
+        serde_json::from_str(&contents).map_err(|e| {
+            ThinkingSpaceError::Serialization(format!("Failed to parse metadata: {}", e))
+        })?
+    };
+
+    let id = Uuid::new_v4().to_string();
+    let space_dir = spaces_dir.join(&id);
+
+    if let Err(e) = extract_space_archive(&mut archive, &space_dir) {
+        // Don't leave a half-extracted space behind
+        let _ = fs::remove_dir_all(&space_dir);
+        return Err(e);
+    }
+
+    let space = new_space_metadata(id, &space_dir, exported.name, exported.template);
+    write_space_metadata(&space_dir, &space)?;
+
+    Ok(space)
+}
+
+/// Write a space's files and metadata to a zip archive
+#[tauri::command]
+pub fn export_space(space_id: String, output_path: String) -> Result<(), ThinkingSpaceError> {
+    let home_dir =
+        dirs::home_dir().ok_or_else(|| ThinkingSpaceError::not_found("Home directory"))?;
+    let output_path = resolve_within(Path::new(&output_path), &home_dir)?;
+
+    export_space_in(&get_spaces_dir()?, &space_id, &output_path)
+}
+
+/// Create a new space from an archive written by `export_space`
+#[tauri::command]
+pub fn import_space(zip_path: String) -> Result<Space, ThinkingSpaceError> {
+    let home_dir =
+        dirs::home_dir().ok_or_else(|| ThinkingSpaceError::not_found("Home directory"))?;
+    let zip_path = resolve_within(Path::new(&zip_path), &home_dir)?;
+
+    import_space_in(&get_spaces_dir()?, &zip_path)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpaceHealth {
+    pub ok: bool,
+    pub issues: Vec<String>,
+    pub repaired: bool,
+}
+
+fn check_space_health_in(spaces_dir: &Path, space_id: &str) -> SpaceHealth {
+    let space_dir = spaces_dir.join(space_id);
+    let mut issues = Vec::new();
+
+    if let Err(e) = validate_space_id(space_id) {
+        issues.push(e.to_string());
+    } else if !space_dir.is_dir() {
+        issues.push("Space directory not found".to_string());
+    } else {
+        match fs::read_to_string(space_dir.join(METADATA_FILE)) {
+            Err(e) => issues.push(format!("Metadata file is unreadable: {}", e)),
+            Ok(contents) => match serde_json::from_str::<Space>(&contents) {
+                Err(e) => issues.push(format!("Metadata file is not valid: {}", e)),
+                Ok(space) => {
+                    if space.path != space_dir.to_string_lossy() {
+                        issues.push(format!(
+                            "Metadata path {} does not match the space location",
+                            space.path
+                        ));
+                    }
+                }
+            },
+        }
+
+        if !space_dir.join("CLAUDE.md").exists() {
+            issues.push("CLAUDE.md is missing".to_string());
+        }
+    }
+
+    SpaceHealth {
+        ok: issues.is_empty(),
+        issues,
+        repaired: false,
+    }
+}
+
+fn repair_space_in(spaces_dir: &Path, space_id: &str) -> Result<SpaceHealth, ThinkingSpaceError> {
+    validate_space_id(space_id)?;
+    let space_dir = spaces_dir.join(space_id);
+    let mut repaired = false;
+
+    // Without a directory or readable metadata there is nothing to repair from
+    let space: Option<Space> = fs::read_to_string(space_dir.join(METADATA_FILE))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok());
+
+    if let Some(mut space) = space {
+        let claude_md_path = space_dir.join("CLAUDE.md");
+
+        if !claude_md_path.exists() {
+            let template = space.template.as_deref().unwrap_or("quick-start");
+            let content = get_template_content(template).replace("{name}", &space.name);
+            atomic_write(&claude_md_path, content.as_bytes())?;
+            repaired = true;
+        }
+
+        if space.path != space_dir.to_string_lossy() {
+            space.path = space_dir.to_string_lossy().to_string();
+            space.claude_md_path = claude_md_path.to_string_lossy().to_string();
+            write_space_metadata(&space_dir, &space)?;
+            repaired = true;
+        }
+    }
+
+    let mut health = check_space_health_in(spaces_dir, space_id);
+    health.repaired = repaired;
+
+    Ok(health)
+}
+
+/// Check a space's directory, metadata and CLAUDE.md for problems
+#[tauri::command]
+pub fn check_space_health(space_id: String) -> Result<SpaceHealth, ThinkingSpaceError> {
+    Ok(check_space_health_in(&get_spaces_dir()?, &space_id))
+}
+
+/// Fix what can be fixed: a missing CLAUDE.md or a stale path in metadata
+#[tauri::command]
+pub fn repair_space(space_id: String) -> Result<SpaceHealth, ThinkingSpaceError> {
+    repair_space_in(&get_spaces_dir()?, &space_id)
+}
+
+#[tauri::command]
+pub fn delete_space(id: String) -> Result<(), ThinkingSpaceError> {
+    validate_space_id(&id)?;
+    let spaces_dir = get_spaces_dir()?;
+    let space_dir = spaces_dir.join(&id);
+
+    invalidate_space_cache(&id);
+    if space_dir.exists() {
+        fs::remove_dir_all(&space_dir).map_err(|e| ThinkingSpaceError::io(&space_dir, e))?;
+    }
+
+    // Also delete the conversation for this space
+    // Note: We don't fail if conversation deletion fails, since the space is already deleted
+    let _ = delete_space_conversation(&id);
+
+    Ok(())
+}
+
+/// Remove a deleted space's conversation from the conversations database
+fn delete_space_conversation(space_id: &str) -> Result<(), ThinkingSpaceError> {
+    let conn = crate::conversations::get_connection()?;
+    crate::conversations::delete_conversation_internal(&conn, space_id)
+}
+
+/// Most spaces one delete_spaces_batch call may remove
+const MAX_BATCH_DELETE: usize = 50;
+
+/// Outcome of delete_spaces_batch (for a dry run, what would be deleted)
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatchDeleteResult {
+    pub deleted: Vec<String>,
+    /// (space id, error) for each space that couldn't be deleted
+    pub failed: Vec<(String, String)>,
+    pub total_bytes_freed: u64,
+}
+
+/// Total size of the files under `dir`
+fn directory_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Delete each space in turn, carrying on past failures
+/// `delete_associated` runs for every deleted space to drop its conversation and sessions
+fn delete_spaces_batch_in(
+    spaces_dir: &Path,
+    ids: &[String],
+    dry_run: bool,
+    mut delete_associated: impl FnMut(&str),
+) -> Result<BatchDeleteResult, ThinkingSpaceError> {
+    if ids.len() > MAX_BATCH_DELETE {
+        return Err(ThinkingSpaceError::validation(
+            "ids",
+            format!(
+                "Cannot delete more than {} spaces at once",
+                MAX_BATCH_DELETE
+            ),
+        ));
+    }
+
+    let mut result = BatchDeleteResult::default();
+    for id in ids {
+        let outcome = existing_space_dir(spaces_dir, id).and_then(|space_dir| {
+            let bytes = directory_size(&space_dir);
+            if !dry_run {
+                invalidate_space_cache(id);
+                fs::remove_dir_all(&space_dir)
+                    .map_err(|e| ThinkingSpaceError::io(&space_dir, e))?;
+                delete_associated(id);
+            }
+            Ok(bytes)
+        });
+
+        match outcome {
+            Ok(bytes) => {
+                result.deleted.push(id.clone());
+                result.total_bytes_freed += bytes;
+            }
+            Err(e) => result.failed.push((id.clone(), e.to_string())),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Delete several spaces at once, or with `dry_run` just report what would be freed
+#[tauri::command]
+pub fn delete_spaces_batch(
+    ids: Vec<String>,
+    dry_run: bool,
+) -> Result<BatchDeleteResult, ThinkingSpaceError> {
+    let spaces_dir = get_spaces_dir()?;
+
+    let result = delete_spaces_batch_in(&spaces_dir, &ids, dry_run, |id| {
+        // As in delete_space, the space is already gone, so these are best effort
+        let _ = delete_space_conversation(id);
+        if let Err(e) = crate::sessions::delete_sessions_for_space(id) {
+            println!("[SPACES] Failed to delete sessions for {}: {}", id, e);
+        }
+    })?;
+
+    if !dry_run {
+        println!(
+            "[SPACES] Batch deleted {} space(s), {} failed, {} bytes freed",
+            result.deleted.len(),
+            result.failed.len(),
+            result.total_bytes_freed
+        );
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn update_last_accessed(app: AppHandle, id: String) -> Result<(), ThinkingSpaceError> {
+    validate_space_id(&id)?;
+    let spaces_dir = get_spaces_dir()?;
+
+    // Missing or unreadable metadata is not an error here
+    if spaces_dir.join(&id).join(METADATA_FILE).exists() {
+        update_space_metadata_in(&spaces_dir, &id, |space| {
+            // Use timestamp_millis() to match JavaScript Date expectations
+            space.last_accessed_at = chrono::Utc::now().timestamp_millis();
+        })?;
+    }
+
+    // The tray lists recently accessed spaces
+    crate::tray::refresh_tray_menu(&app);
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn read_claude_md(space_id: String) -> Result<String, ThinkingSpaceError> {
+    let claude_md_path = get_space_dir(&space_id)?.join("CLAUDE.md");
+
+    fs::read_to_string(&claude_md_path).map_err(|e| ThinkingSpaceError::io(&claude_md_path, e))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClaudeMdVersion {
+    /// Milliseconds since the epoch, also the version's file name
+    pub timestamp: i64,
+    pub size_bytes: u64,
+}
+
+/// Copy the current CLAUDE.md into the history directory and trim old versions
+fn snapshot_claude_md(space_dir: &Path) -> Result<(), ThinkingSpaceError> {
+    let claude_md_path = space_dir.join("CLAUDE.md");
+    if !claude_md_path.exists() {
+        return Ok(());
+    }
+
+    let history_dir = space_dir.join(CLAUDE_MD_HISTORY_DIR);
+    fs::create_dir_all(&history_dir).map_err(|e| ThinkingSpaceError::io(&history_dir, e))?;
+
+    // Versions must sort after every existing one, even for writes within the same millisecond
+    let newest = list_claude_md_history_in(space_dir)?
+        .first()
+        .map(|version| version.timestamp + 1);
+    let timestamp = chrono::Utc::now()
+        .timestamp_millis()
+        .max(newest.unwrap_or(i64::MIN));
+
+    fs::copy(
+        &claude_md_path,
+        history_dir.join(format!("{}.md", timestamp)),
+    )
+    .map_err(|e| ThinkingSpaceError::io(&claude_md_path, e))?;
+
+    let versions = list_claude_md_history_in(space_dir)?;
+    for version in versions.iter().skip(MAX_CLAUDE_MD_VERSIONS) {
+        let _ = fs::remove_file(history_dir.join(format!("{}.md", version.timestamp)));
+    }
+
+    Ok(())
+}
+
+fn write_claude_md_in(space_dir: &Path, content: &str) -> Result<(), ThinkingSpaceError> {
+    snapshot_claude_md(space_dir)?;
+
+    atomic_write(&space_dir.join("CLAUDE.md"), content.as_bytes())
+}
+
+/// Saved CLAUDE.md versions, newest first
+fn list_claude_md_history_in(space_dir: &Path) -> Result<Vec<ClaudeMdVersion>, ThinkingSpaceError> {
+    let mut versions = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(space_dir.join(CLAUDE_MD_HISTORY_DIR)) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("md") {
+                continue;
+            }
+
+            let Some(timestamp) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<i64>().ok())
+            else {
+                continue;
+            };
+
+            if let Ok(metadata) = entry.metadata() {
+                versions.push(ClaudeMdVersion {
+                    timestamp,
+                    size_bytes: metadata.len(),
+                });
+            }
+        }
+    }
+
+    versions.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+    Ok(versions)
+}
+
+fn restore_claude_md_in(space_dir: &Path, timestamp: i64) -> Result<(), ThinkingSpaceError> {
+    let version_path = space_dir
+        .join(CLAUDE_MD_HISTORY_DIR)
+        .join(format!("{}.md", timestamp));
+    let content =
+        fs::read_to_string(&version_path).map_err(|e| ThinkingSpaceError::io(&version_path, e))?;
+
+    // Goes through the normal write so the content being replaced is kept too
+    write_claude_md_in(space_dir, &content)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenEstimate {
+    pub char_count: usize,
+    pub word_count: usize,
+    pub estimated_tokens: usize,
+    pub budget_used_pct: f32,
+    pub over_budget: bool,
+}
+
+/// Estimate tokens at ~4 characters per token and compare against `budget`
+fn estimate_tokens(content: &str, budget: usize) -> TokenEstimate {
+    let char_count = content.chars().count();
+    let estimated_tokens = char_count.div_ceil(CHARS_PER_TOKEN);
+
+    TokenEstimate {
+        char_count,
+        word_count: content.split_whitespace().count(),
+        estimated_tokens,
+        budget_used_pct: estimated_tokens as f32 / budget.max(1) as f32 * 100.0,
+        over_budget: estimated_tokens > budget,
+    }
+}
+
+fn claude_md_token_budget() -> usize {
+    crate::settings::load_settings()
+        .ok()
+        .and_then(|settings| settings.claude_md_token_budget)
+        .unwrap_or(DEFAULT_CLAUDE_MD_TOKEN_BUDGET)
+}
+
+/// Estimate `content` and call `warn` if it is over budget
+fn check_claude_md_budget(
+    content: &str,
+    budget: usize,
+    warn: impl FnOnce(&TokenEstimate),
+) -> TokenEstimate {
+    let estimate = estimate_tokens(content, budget);
+    if estimate.over_budget {
+        warn(&estimate);
+    }
+    estimate
+}
+
+/// Emits "claude-md-budget-warning" when the new content is over the token budget
+#[tauri::command]
+pub fn write_claude_md(
+    app: AppHandle,
+    space_id: String,
+    content: String,
+) -> Result<(), ThinkingSpaceError> {
+    write_claude_md_in(&get_space_dir(&space_id)?, &content)?;
+
+    check_claude_md_budget(&content, claude_md_token_budget(), |estimate| {
+        let _ = app.emit("claude-md-budget-warning", estimate);
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn estimate_claude_md_tokens(space_id: String) -> Result<TokenEstimate, ThinkingSpaceError> {
+    let content = read_claude_md(space_id)?;
+    Ok(estimate_tokens(&content, claude_md_token_budget()))
+}
+
+#[tauri::command]
+pub fn list_claude_md_history(
+    space_id: String,
+) -> Result<Vec<ClaudeMdVersion>, ThinkingSpaceError> {
+    list_claude_md_history_in(&get_space_dir(&space_id)?)
+}
+
+/// Replace CLAUDE.md with a saved version
+#[tauri::command]
+pub fn restore_claude_md(space_id: String, timestamp: i64) -> Result<(), ThinkingSpaceError> {
+    restore_claude_md_in(&get_space_dir(&space_id)?, timestamp)
+}
+
+/// Directory inside a space holding named prompt templates
+const PROMPT_TEMPLATES_DIR: &str = ".templates";
+
+/// A reusable prompt stored as .templates/<name>.md in a space
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    /// First paragraph of the template
+    pub description: String,
+    /// Template text, without front-matter
+    pub content: String,
+    /// Meant to be sent as the system prompt rather than a message
+    pub is_system_prompt: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PromptTemplateFrontMatter {
+    #[serde(default, alias = "is_system_prompt")]
+    system_prompt: bool,
+}
+
+/// `{{ variable_name }}`, with optional spaces inside the braces
+static TEMPLATE_VARIABLE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\{\{\s*([A-Za-z0-9_]+)\s*\}\}").expect("template variable pattern is valid")
+});
+
+fn parse_prompt_template(name: &str, raw: &str) -> PromptTemplate {
+    let mut front_matter = PromptTemplateFrontMatter::default();
+    let mut content = raw;
+
+    if let Some(rest) = raw
+        .strip_prefix("---\n")
+        .or_else(|| raw.strip_prefix("---\r\n"))
+    {
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            if line.trim_end() == "---" {
+                let yaml = &rest[..offset];
+                let parsed = if yaml.trim().is_empty() {
+                    Ok(PromptTemplateFrontMatter::default())
+                } else {
+                    serde_yaml::from_str(yaml)
+                };
+
+                match parsed {
+                    Ok(parsed) => {
+                        front_matter = parsed;
+                        content = &rest[offset + line.len()..];
+                    }
+                    Err(e) => eprintln!(
+                        "[SPACES] Ignoring invalid front-matter in template {}: {}",
+                        name, e
+                    ),
+                }
+                break;
+            }
+            offset += line.len();
+        }
+    }
+
+    let description = content
+        .split("\n\n")
+        .map(|paragraph| {
+            paragraph
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .find(|paragraph| !paragraph.is_empty())
+        .unwrap_or_default();
+
+    PromptTemplate {
+        name: name.to_string(),
+        description,
+        content: content.to_string(),
+        is_system_prompt: front_matter.system_prompt,
+    }
+}
+
+fn list_prompt_templates_in(space_dir: &Path) -> Result<Vec<PromptTemplate>, ThinkingSpaceError> {
+    let templates_dir = space_dir.join(PROMPT_TEMPLATES_DIR);
+    if !templates_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries =
+        fs::read_dir(&templates_dir).map_err(|e| ThinkingSpaceError::io(&templates_dir, e))?;
+
+    let mut templates = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("md") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(raw) => templates.push(parse_prompt_template(name, &raw)),
+            Err(e) => eprintln!("[SPACES] Failed to read template {}: {}", name, e),
+        }
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+/// Replace `{{ name }}` placeholders; unknown variables are left as written
+fn expand_template_variables(content: &str, variables: &HashMap<String, String>) -> String {
+    TEMPLATE_VARIABLE_PATTERN
+        .replace_all(content, |captures: &regex::Captures| {
+            variables
+                .get(&captures[1])
+                .cloned()
+                .unwrap_or_else(|| captures[0].to_string())
+        })
+        .into_owned()
+}
+
+fn apply_prompt_template_in(
+    space_dir: &Path,
+    template_name: &str,
+    variables: &HashMap<String, String>,
+) -> Result<String, ThinkingSpaceError> {
+    // The name becomes a file name, so it can't contain a path
+    let mut components = Path::new(template_name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        return Err(ThinkingSpaceError::validation(
+            "template_name",
+            format!("Invalid template name: {}", template_name),
+        ));
+    }
+
+    let path = space_dir
+        .join(PROMPT_TEMPLATES_DIR)
+        .join(format!("{}.md", template_name));
+    if !path.exists() {
+        return Err(ThinkingSpaceError::not_found(format!(
+            "Template '{}'",
+            template_name
+        )));
+    }
+
+    let raw = fs::read_to_string(&path).map_err(|e| ThinkingSpaceError::io(&path, e))?;
+    let template = parse_prompt_template(template_name, &raw);
+
+    Ok(expand_template_variables(&template.content, variables))
+}
+
+/// Named prompt templates in the space's .templates directory
+#[tauri::command]
+pub fn list_prompt_templates(space_id: String) -> Result<Vec<PromptTemplate>, ThinkingSpaceError> {
+    list_prompt_templates_in(&get_space_dir(&space_id)?)
+}
+
+/// A template's text with `{{ variable }}` placeholders filled in
+#[tauri::command]
+pub fn apply_prompt_template(
+    space_id: String,
+    template_name: String,
+    variables: HashMap<String, String>,
+) -> Result<String, ThinkingSpaceError> {
+    apply_prompt_template_in(&get_space_dir(&space_id)?, &template_name, &variables)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpaceFile {
+    pub name: String,
+    pub path: String,
+    /// Path relative to the space directory, always '/'-separated
+    pub relative_path: String,
+    pub size: u64,
+    pub modified: i64,
+    pub is_directory: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpaceFileListing {
+    pub files: Vec<SpaceFile>,
+    /// True when the listing hit the entry cap
+    pub truncated: bool,
+}
+
+fn space_file_from_metadata(path: &Path, space_dir: &Path, metadata: &fs::Metadata) -> SpaceFile {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| {
+            time.duration_since(SystemTime::UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_millis() as i64)
+        })
+        .unwrap_or(0);
+
+    let relative_path = path
+        .strip_prefix(space_dir)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    SpaceFile {
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        path: path.to_string_lossy().to_string(),
+        relative_path,
+        size: metadata.len(),
+        modified,
+        is_directory: metadata.is_dir(),
+    }
+}
+
+#[tauri::command]
+pub fn list_space_files(space_id: String) -> Result<Vec<SpaceFile>, ThinkingSpaceError> {
+    let space_dir = get_space_dir(&space_id)?;
+
+    let mut files = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(&space_dir) {
+        for entry in entries.flatten() {
+            // Skip hidden files and metadata
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if file_name.starts_with('.') {
+                continue;
+            }
+
+            if let Ok(metadata) = entry.metadata() {
+                files.push(space_file_from_metadata(
+                    &entry.path(),
+                    &space_dir,
+                    &metadata,
+                ));
+            }
+        }
+    }
+
+    // Sort by name
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(files)
+}
+
+fn list_space_files_recursive_in(
+    space_dir: &Path,
+    max_depth: u32,
+    include_hidden: bool,
+) -> Result<SpaceFileListing, ThinkingSpaceError> {
+    if !space_dir.exists() {
+        return Err(ThinkingSpaceError::not_found("Space directory"));
+    }
+
+    let walker = WalkDir::new(space_dir)
+        .min_depth(1)
+        .max_depth(max_depth as usize)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            // Space metadata is internal and never listed
+            name != METADATA_FILE && (include_hidden || !name.starts_with('.'))
+        });
+
+    let mut files = Vec::new();
+    let mut truncated = false;
+
+    for entry in walker.flatten() {
+        if files.len() >= MAX_LISTED_FILES {
+            truncated = true;
+            break;
+        }
+
+        if let Ok(metadata) = entry.metadata() {
+            files.push(space_file_from_metadata(entry.path(), space_dir, &metadata));
+        }
+    }
+
+    Ok(SpaceFileListing { files, truncated })
+}
+
+/// List a space's files and subdirectories, up to `max_depth` levels deep (default 3)
+#[tauri::command]
+pub fn list_space_files_recursive(
+    space_id: String,
+    max_depth: Option<u32>,
+    include_hidden: bool,
+) -> Result<SpaceFileListing, ThinkingSpaceError> {
+    let space_dir = get_space_dir(&space_id)?;
+    list_space_files_recursive_in(
+        &space_dir,
+        max_depth.unwrap_or(DEFAULT_LISTING_DEPTH),
+        include_hidden,
+    )
+}
+
+/// Resolve a user-supplied file name to a path inside `space_dir`
+/// Rejects traversal, absolute paths and dot-files (including the space metadata)
+fn resolve_space_file(space_dir: &Path, file_name: &str) -> Result<PathBuf, ThinkingSpaceError> {
+    if file_name.trim().is_empty() {
+        return Err(ThinkingSpaceError::validation(
+            "file_name",
+            "File name cannot be empty",
+        ));
+    }
+
+    for component in Path::new(file_name).components() {
+        match component {
+            Component::Normal(part) => {
+                if part.to_string_lossy().starts_with('.') {
+                    return Err(ThinkingSpaceError::security(
+                        "Access denied: hidden files cannot be modified",
+                    ));
+                }
+            }
+            _ => {
+                return Err(ThinkingSpaceError::security(
+                    "Access denied: path outside space directory",
+                ))
+            }
+        }
+    }
+
+    let path = space_dir.join(file_name);
+
+    // A symlink inside the space could still point elsewhere, so check the
+    // nearest existing ancestor once symlinks are resolved
+    let canonical_space = space_dir
+        .canonicalize()
+        .map_err(|e| ThinkingSpaceError::io(space_dir, e))?;
+    let mut existing = path.as_path();
+    while !existing.exists() {
+        existing = existing.parent().ok_or_else(|| {
+            ThinkingSpaceError::security("Access denied: path outside space directory")
+        })?;
+    }
+    let canonical = existing
+        .canonicalize()
+        .map_err(|e| ThinkingSpaceError::io(existing, e))?;
+
+    if !canonical.starts_with(&canonical_space) {
+        return Err(ThinkingSpaceError::security(
+            "Access denied: path outside space directory",
+        ));
+    }
+
+    Ok(path)
+}
+
+/// A space id names one directory directly under the spaces directory,
+/// so it must be a single plain path component (not `..`, not `id/notes`)
+pub(crate) fn validate_space_id(space_id: &str) -> Result<(), ThinkingSpaceError> {
+    let mut components = Path::new(space_id).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        return Err(ThinkingSpaceError::validation(
+            "space_id",
+            format!("Invalid space id: {}", space_id),
+        ));
+    }
+
+    Ok(())
+}
+
+fn existing_space_dir(spaces_dir: &Path, space_id: &str) -> Result<PathBuf, ThinkingSpaceError> {
+    validate_space_id(space_id)?;
+    let space_dir = spaces_dir.join(space_id);
+
+    if !space_dir.is_dir() {
+        return Err(ThinkingSpaceError::not_found("Space directory"));
+    }
+
+    Ok(space_dir)
+}
+
+fn write_space_file_in(
+    spaces_dir: &Path,
+    space_id: &str,
+    file_name: &str,
+    content: &str,
+) -> Result<(), ThinkingSpaceError> {
+    let space_dir = existing_space_dir(spaces_dir, space_id)?;
+    let path = resolve_space_file(&space_dir, file_name)?;
+
+    if path.is_dir() {
+        return Err(ThinkingSpaceError::validation(
+            "file_name",
+            format!("{} is a directory", file_name),
+        ));
+    }
+
+    let parent = path
+        .parent()
+        .ok_or_else(|| ThinkingSpaceError::validation("file_name", "Invalid file path"))?;
+    fs::create_dir_all(parent).map_err(|e| ThinkingSpaceError::io(parent, e))?;
+
+    atomic_write(&path, content.as_bytes())
+}
+
+/// What writing new content over a space file would change
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileDiffPreview {
+    /// Bytes, 0 when the file doesn't exist yet
+    pub old_size: usize,
+    pub new_size: usize,
+    /// Lines rewritten in place (also counted in added/removed)
+    pub changed_lines: usize,
+    pub added_lines: usize,
+    pub removed_lines: usize,
+    pub unified_diff: String,
+}
+
+fn file_diff_preview(file_name: &str, old: &str, new: &str) -> FileDiffPreview {
+    let diff = TextDiff::from_lines(old, new);
+
+    let (mut added_lines, mut removed_lines) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => added_lines += 1,
+            ChangeTag::Delete => removed_lines += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    let changed_lines = diff
+        .ops()
+        .iter()
+        .map(|op| match *op {
+            DiffOp::Replace {
+                old_len, new_len, ..
+            } => old_len.min(new_len),
+            _ => 0,
+        })
+        .sum();
+
+    let unified_diff = diff
+        .unified_diff()
+        .header(&format!("a/{}", file_name), &format!("b/{}", file_name))
+        .to_string();
+
+    FileDiffPreview {
+        old_size: old.len(),
+        new_size: new.len(),
+        changed_lines,
+        added_lines,
+        removed_lines,
+        unified_diff,
+    }
+}
+
+fn preview_file_write_in(
+    spaces_dir: &Path,
+    space_id: &str,
+    file_name: &str,
+    new_content: &str,
+) -> Result<FileDiffPreview, ThinkingSpaceError> {
+    let space_dir = existing_space_dir(spaces_dir, space_id)?;
+    let path = resolve_space_file(&space_dir, file_name)?;
+
+    if path.is_dir() {
+        return Err(ThinkingSpaceError::validation(
+            "file_name",
+            format!("{} is a directory", file_name),
+        ));
+    }
+
+    // A new file diffs against nothing, so every line shows as added
+    let old_content = if path.exists() {
+        fs::read_to_string(&path).map_err(|e| ThinkingSpaceError::io(&path, e))?
+    } else {
+        String::new()
+    };
+
+    Ok(file_diff_preview(file_name, &old_content, new_content))
+}
+
+/// Outcome of delete_space_file
+/// A non-empty directory is only counted until the call is repeated with `confirmed`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeleteSpaceFileResult {
+    pub deleted: bool,
+    /// Files removed, or that would be removed once confirmed
+    pub file_count: usize,
+    pub delete_confirmation_required: bool,
+}
+
+fn delete_space_file_in(
+    spaces_dir: &Path,
+    space_id: &str,
+    file_name: &str,
+    is_directory: bool,
+    confirmed: bool,
+) -> Result<DeleteSpaceFileResult, ThinkingSpaceError> {
+    let space_dir = existing_space_dir(spaces_dir, space_id)?;
+    let path = resolve_space_file(&space_dir, file_name)?;
+
+    if !is_directory {
+        if !path.is_file() {
+            return Err(ThinkingSpaceError::not_found(format!("File {}", file_name)));
+        }
+
+        fs::remove_file(&path).map_err(|e| ThinkingSpaceError::io(&path, e))?;
+        return Ok(DeleteSpaceFileResult {
+            deleted: true,
+            file_count: 1,
+            delete_confirmation_required: false,
+        });
+    }
+
+    if !path.is_dir() {
+        return Err(ThinkingSpaceError::not_found(format!(
+            "Directory {}",
+            file_name
+        )));
+    }
+
+    let file_count = WalkDir::new(&path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .count();
+
+    // Dry run: report what would go and wait for the user to confirm
+    if file_count > 0 && !confirmed {
+        return Ok(DeleteSpaceFileResult {
+            deleted: false,
+            file_count,
+            delete_confirmation_required: true,
+        });
+    }
+
+    fs::remove_dir_all(&path).map_err(|e| ThinkingSpaceError::io(&path, e))?;
+    Ok(DeleteSpaceFileResult {
+        deleted: true,
+        file_count,
+        delete_confirmation_required: false,
+    })
+}
+
+fn rename_space_file_in(
+    spaces_dir: &Path,
+    space_id: &str,
+    old_name: &str,
+    new_name: &str,
+) -> Result<(), ThinkingSpaceError> {
+    let space_dir = existing_space_dir(spaces_dir, space_id)?;
+    let old_path = resolve_space_file(&space_dir, old_name)?;
+    let new_path = resolve_space_file(&space_dir, new_name)?;
+
+    if !old_path.exists() {
+        return Err(ThinkingSpaceError::not_found(format!("File {}", old_name)));
+    }
+
+    if new_path.exists() {
+        return Err(ThinkingSpaceError::validation(
+            "new_name",
+            format!("A file named {} already exists", new_name),
+        ));
+    }
+
+    if let Some(parent) = new_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| ThinkingSpaceError::io(parent, e))?;
+    }
+
+    fs::rename(&old_path, &new_path).map_err(|e| ThinkingSpaceError::io(&old_path, e))
+}
+
+/// Diff a space file (or CLAUDE.md) against content that is about to overwrite it
+#[tauri::command]
+pub fn preview_file_write(
+    space_id: String,
+    file_name: String,
+    new_content: String,
+) -> Result<FileDiffPreview, ThinkingSpaceError> {
+    preview_file_write_in(&get_spaces_dir()?, &space_id, &file_name, &new_content)
+}
+
+/// Create or overwrite a file inside a space
+#[tauri::command]
+pub fn write_space_file(
+    space_id: String,
+    file_name: String,
+    content: String,
+) -> Result<(), ThinkingSpaceError> {
+    write_space_file_in(&get_spaces_dir()?, &space_id, &file_name, &content)
+}
+
+#[tauri::command]
+pub fn delete_space_file(
+    space_id: String,
+    file_name: String,
+    is_directory: bool,
+    confirmed: Option<bool>,
+) -> Result<DeleteSpaceFileResult, ThinkingSpaceError> {
+    delete_space_file_in(
+        &get_spaces_dir()?,
+        &space_id,
+        &file_name,
+        is_directory,
+        confirmed.unwrap_or(false),
+    )
+}
+
+/// Rename or move a file within a space
+#[tauri::command]
+pub fn rename_space_file(
+    space_id: String,
+    old_name: String,
+    new_name: String,
+) -> Result<(), ThinkingSpaceError> {
+    rename_space_file_in(&get_spaces_dir()?, &space_id, &old_name, &new_name)
+}
+
+/// One line of a space file matching a content search
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileSearchMatch {
+    pub file_name: String,
+    pub relative_path: String,
+    pub line_number: usize,
+    pub line_text: String,
+}
+
+/// Whether a file should be skipped by content search: too big, or binary
+/// judging by its first few KB
+fn is_unsearchable_file(path: &Path, size: u64) -> bool {
+    if size > MAX_SEARCHED_FILE_BYTES {
+        return true;
+    }
+
+    let mut sample = Vec::with_capacity(FILE_TYPE_SAMPLE_BYTES);
+    let read = fs::File::open(path).and_then(|file| {
+        file.take(FILE_TYPE_SAMPLE_BYTES as u64)
+            .read_to_end(&mut sample)
+    });
+    if read.is_err() {
+        return true;
+    }
+
+    !detect_mime_type(&sample).1
+}
+
+/// Up to `limit` matching lines of one file, read a line at a time
+fn search_file_lines(
+    path: &Path,
+    needle: &str,
+    case_sensitive: bool,
+    limit: usize,
+) -> std::io::Result<Vec<(usize, String)>> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut buf = Vec::new();
+    let mut matches = Vec::new();
+    let mut line_number = 0;
+
+    while matches.len() < limit && reader.read_until(b'\n', &mut buf)? > 0 {
+        line_number += 1;
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        let found = if case_sensitive {
+            line.contains(needle)
+        } else {
+            line.to_lowercase().contains(needle)
+        };
+        if found {
+            matches.push((line_number, line.to_string()));
+        }
+        buf.clear();
+    }
+
+    Ok(matches)
+}
+
+fn search_space_files_in(
+    spaces_dir: &Path,
+    space_id: &str,
+    query: &str,
+    case_sensitive: bool,
+    max_results: usize,
+) -> Result<Vec<FileSearchMatch>, ThinkingSpaceError> {
+    if query.is_empty() {
+        return Err(ThinkingSpaceError::validation(
+            "query",
+            "Search query cannot be empty",
+        ));
+    }
+
+    let space_dir = existing_space_dir(spaces_dir, space_id)?;
+    let needle = if case_sensitive {
+        query.to_string()
+    } else {
+        query.to_lowercase()
+    };
+
+    // Walked in name order so the search can stop as soon as it has enough
+    // matches and still return the same ones every time
+    let walker = WalkDir::new(&space_dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name();
+            name != METADATA_FILE && name != CLAUDE_MD_HISTORY_DIR
+        });
+
+    let limit = max_results.min(MAX_SEARCH_RESULTS);
+    let mut results = Vec::new();
+    for entry in walker.filter_map(Result::ok) {
+        if results.len() >= limit {
+            break;
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(u64::MAX);
+        if is_unsearchable_file(entry.path(), size) {
+            continue;
+        }
+
+        // Unreadable files are skipped rather than failing the whole search
+        let remaining = limit - results.len();
+        let Ok(lines) = search_file_lines(entry.path(), &needle, case_sensitive, remaining) else {
+            continue;
+        };
+
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let relative_path = entry
+            .path()
+            .strip_prefix(&space_dir)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .to_string();
+
+        results.extend(
+            lines
+                .into_iter()
+                .map(|(line_number, line_text)| FileSearchMatch {
+                    file_name: file_name.clone(),
+                    relative_path: relative_path.clone(),
+                    line_number,
+                    line_text,
+                }),
+        );
+    }
+
+    Ok(results)
+}
+
+/// Search the text files of a space for lines containing `query`, in path order
+#[tauri::command]
+pub fn search_space_files(
+    space_id: String,
+    query: String,
+    case_sensitive: bool,
+    max_results: usize,
+) -> Result<Vec<FileSearchMatch>, ThinkingSpaceError> {
+    search_space_files_in(
+        &get_spaces_dir()?,
+        &space_id,
+        &query,
+        case_sensitive,
+        max_results,
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GitChange {
+    Modified,
+    Added,
+    Deleted,
+    Untracked,
+    Renamed,
+}
+
+/// A changed file in the git repository containing a space, relative to the space
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct GitFileStatus {
+    pub path: String,
+    pub status: GitChange,
+}
+
+fn git_change_for(status: git2::Status) -> Option<GitChange> {
+    if status.is_wt_new() {
+        Some(GitChange::Untracked)
+    } else if status.is_index_renamed() || status.is_wt_renamed() {
+        Some(GitChange::Renamed)
+    } else if status.is_index_new() {
+        Some(GitChange::Added)
+    } else if status.is_index_deleted() || status.is_wt_deleted() {
+        Some(GitChange::Deleted)
+    } else if status.is_index_modified()
+        || status.is_wt_modified()
+        || status.is_index_typechange()
+        || status.is_wt_typechange()
+        || status.is_conflicted()
+    {
+        Some(GitChange::Modified)
+    } else {
+        None
+    }
+}
+
+/// Open the repository containing `space_dir`, along with the space's path
+/// relative to the repository root (empty when the space is the root)
+fn open_space_repo(space_dir: &Path) -> Result<(git2::Repository, PathBuf), ThinkingSpaceError> {
+    let repo = git2::Repository::discover(space_dir).map_err(|_| {
+        ThinkingSpaceError::not_found(format!("Git repository containing {}", space_dir.display()))
+    })?;
+
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| ThinkingSpaceError::not_found("Git working directory"))?;
+    let workdir = workdir
+        .canonicalize()
+        .map_err(|e| ThinkingSpaceError::io(workdir, e))?;
+    let canonical_space = space_dir
+        .canonicalize()
+        .map_err(|e| ThinkingSpaceError::io(space_dir, e))?;
+    let prefix = canonical_space
+        .strip_prefix(&workdir)
+        .map_err(|_| {
+            ThinkingSpaceError::security("Space directory is outside the git working tree")
+        })?
+        .to_path_buf();
+
+    Ok((repo, prefix))
+}
+
+/// Git pathspecs always use forward slashes
+fn git_pathspec(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn git_status_in(
+    spaces_dir: &Path,
+    space_id: &str,
+) -> Result<Vec<GitFileStatus>, ThinkingSpaceError> {
+    let space_dir = existing_space_dir(spaces_dir, space_id)?;
+    let (repo, prefix) = open_space_repo(&space_dir)?;
+
+    let mut options = git2::StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true);
+    if !prefix.as_os_str().is_empty() {
+        options.pathspec(git_pathspec(&prefix));
+    }
+
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| ThinkingSpaceError::io(&space_dir, e))?;
+
+    let mut files: Vec<GitFileStatus> = statuses
+        .iter()
+        .filter_map(|entry| {
+            let status = git_change_for(entry.status())?;
+            let repo_path = PathBuf::from(entry.path()?);
+            let path = repo_path.strip_prefix(&prefix).ok()?;
+            Some(GitFileStatus {
+                path: path.to_string_lossy().to_string(),
+                status,
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(files)
+}
+
+fn file_diff_in(
+    spaces_dir: &Path,
+    space_id: &str,
+    file_path: &str,
+) -> Result<String, ThinkingSpaceError> {
+    let space_dir = existing_space_dir(spaces_dir, space_id)?;
+    resolve_space_file(&space_dir, file_path)?;
+    let (repo, prefix) = open_space_repo(&space_dir)?;
+
+    // An unborn HEAD (no commits yet) diffs against the empty tree
+    let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+
+    let mut options = git2::DiffOptions::new();
+    options
+        .pathspec(git_pathspec(&prefix.join(file_path)))
+        .include_untracked(true)
+        .show_untracked_content(true);
+
+    let diff = repo
+        .diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut options))
+        .map_err(|e| ThinkingSpaceError::io(&space_dir, e))?;
+
+    let mut patch = String::new();
+    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            patch.push(line.origin());
+        }
+        patch.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    })
+    .map_err(|e| ThinkingSpaceError::io(&space_dir, e))?;
+
+    Ok(patch)
+}
+
+/// Changed files in the git repository containing the space
+#[tauri::command]
+pub fn get_git_status(space_id: String) -> Result<Vec<GitFileStatus>, ThinkingSpaceError> {
+    git_status_in(&get_spaces_dir()?, &space_id)
+}
+
+/// Unified diff of one space file against HEAD (including staged changes)
+#[tauri::command]
+pub fn get_file_diff(space_id: String, file_path: String) -> Result<String, ThinkingSpaceError> {
+    file_diff_in(&get_spaces_dir()?, &space_id, &file_path)
+}
+
+/// Activity and size figures for one space
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpaceStatistics {
+    pub total_messages: usize,
+    pub user_messages: usize,
+    pub assistant_messages: usize,
+    pub first_message_at: Option<i64>,
+    pub last_message_at: Option<i64>,
+    pub total_file_count: usize,
+    pub total_file_bytes: u64,
+    pub session_count: usize,
+}
+
+/// Gathers space statistics from the space directory, conversations.db and sessions.db
+pub struct SpaceStatisticsService {
+    spaces_dir: PathBuf,
+    conversations_db: PathBuf,
+    sessions_db: PathBuf,
+}
+
+impl SpaceStatisticsService {
+    pub fn new(spaces_dir: PathBuf, conversations_db: PathBuf, sessions_db: PathBuf) -> Self {
+        Self {
+            spaces_dir,
+            conversations_db,
+            sessions_db,
+        }
+    }
+
+    /// Service reading the app's real data locations
+    pub fn from_default_paths() -> Result<Self, ThinkingSpaceError> {
+        Ok(Self::new(
+            get_spaces_dir()?,
+            crate::conversations::get_db_path()?,
+            crate::sessions::get_db_path().map_err(ThinkingSpaceError::Database)?,
+        ))
+    }
+
+    pub fn statistics(&self, space_id: &str) -> Result<SpaceStatistics, ThinkingSpaceError> {
+        let space_dir = existing_space_dir(&self.spaces_dir, space_id)?;
+
+        let conversations = crate::conversations::get_connection_at(&self.conversations_db)?;
+        let messages = crate::conversations::message_stats_internal(&conversations, space_id)?;
+
+        let sessions = crate::sessions::get_connection_at(&self.sessions_db)
+            .map_err(ThinkingSpaceError::Database)?;
+        let session_count = crate::sessions::count_sessions_for_space_internal(&sessions, space_id)
+            .map_err(ThinkingSpaceError::Database)?;
+
+        let (total_file_count, total_file_bytes) = Self::file_totals(&space_dir);
+
+        Ok(SpaceStatistics {
+            total_messages: messages.total,
+            user_messages: messages.user,
+            assistant_messages: messages.assistant,
+            first_message_at: messages.first_at,
+            last_message_at: messages.last_at,
+            total_file_count,
+            total_file_bytes,
+            session_count,
+        })
+    }
+
+    /// Count and size of the space's files, leaving out app bookkeeping
+    /// (metadata and CLAUDE.md history)
+    fn file_totals(space_dir: &Path) -> (usize, u64) {
+        WalkDir::new(space_dir)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|entry| {
+                let name = entry.file_name();
+                name != METADATA_FILE && name != CLAUDE_MD_HISTORY_DIR
+            })
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| entry.metadata().ok())
+            .fold((0, 0), |(count, bytes), metadata| {
+                (count + 1, bytes + metadata.len())
+            })
+    }
+}
+
+#[tauri::command]
+pub fn get_space_statistics(space_id: String) -> Result<SpaceStatistics, ThinkingSpaceError> {
+    SpaceStatisticsService::from_default_paths()?.statistics(&space_id)
+}
+
+#[tauri::command]
+pub fn open_file(path: String) -> Result<(), ThinkingSpaceError> {
+    opener::open(&path).map_err(|e| ThinkingSpaceError::io(&path, e))
+}
+
+/// A command that opens a terminal window, run from the space directory
+#[derive(Debug, Clone, PartialEq)]
+struct TerminalLaunch {
+    program: String,
+    args: Vec<String>,
+}
+
+impl TerminalLaunch {
+    fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+/// Terminals to try in order, falling through when one isn't installed
+#[cfg(target_os = "macos")]
+fn terminal_launches(
+    dir: &Path,
+    preferred: Option<&str>,
+    _env_terminal: Option<&str>,
+) -> Vec<TerminalLaunch> {
+    let dir = dir.to_string_lossy();
+    let mut launches = Vec::new();
+    if let Some(app) = preferred {
+        launches.push(TerminalLaunch::new("open", &["-a", app, &*dir]));
+    }
+    launches.push(TerminalLaunch::new("open", &["-a", "Terminal", &*dir]));
+    launches
+}
+
+/// Terminals to try in order, falling through when one isn't installed
+#[cfg(target_os = "windows")]
+fn terminal_launches(
+    dir: &Path,
+    preferred: Option<&str>,
+    _env_terminal: Option<&str>,
+) -> Vec<TerminalLaunch> {
+    let mut launches = Vec::new();
+    if let Some(program) = preferred {
+        launches.push(TerminalLaunch::new(program, &[]));
+    }
+    launches.push(TerminalLaunch::new("wt", &["-d", &*dir.to_string_lossy()]));
+    launches.push(TerminalLaunch::new("cmd", &["/c", "start", "cmd"]));
+    launches
+}
+
+/// Terminals to try in order, falling through when one isn't installed
+/// They're started in the space directory, which most emulators open at
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn terminal_launches(
+    _dir: &Path,
+    preferred: Option<&str>,
+    env_terminal: Option<&str>,
+) -> Vec<TerminalLaunch> {
+    preferred
+        .into_iter()
+        .chain(env_terminal)
+        .chain(["x-terminal-emulator", "xterm"])
+        .map(|program| TerminalLaunch::new(program, &[]))
+        .collect()
+}
+
+fn open_terminal_in(
+    spaces_dir: &Path,
+    space_id: &str,
+    preferred: Option<&str>,
+) -> Result<(), ThinkingSpaceError> {
+    let space_dir = existing_space_dir(spaces_dir, space_id)?;
+    let env_terminal = std::env::var("TERMINAL").ok();
+    let preferred = preferred.filter(|p| !p.trim().is_empty());
+    let env_terminal = env_terminal.as_deref().filter(|t| !t.trim().is_empty());
+
+    for launch in terminal_launches(&space_dir, preferred, env_terminal) {
+        match std::process::Command::new(&launch.program)
+            .args(&launch.args)
+            .current_dir(&space_dir)
+            .spawn()
+        {
+            Ok(_) => {
+                println!("[SPACES] Opened {} at {:?}", launch.program, space_dir);
+                return Ok(());
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(ThinkingSpaceError::io(&space_dir, e)),
+        }
+    }
+
+    Err(ThinkingSpaceError::not_found("Terminal emulator"))
+}
+
+/// Open the user's terminal emulator at the space's directory
+#[tauri::command]
+pub fn open_terminal_at_space(space_id: String) -> Result<(), ThinkingSpaceError> {
+    let preferred = crate::settings::load_settings()
+        .ok()
+        .and_then(|settings| settings.preferred_terminal);
+    open_terminal_in(&get_spaces_dir()?, &space_id, preferred.as_deref())
+}
+
+/// Paths that read_file_content and friends refuse to read
+/// Matched against the canonical path, so symlinks can't dodge them
+const DEFAULT_BLOCKED_PATTERNS: &[&str] = &[
+    "**/.ssh/**",
+    "**/.aws/**",
+    "**/.gnupg/**",
+    "**/id_*",
+    "**/*.pem",
+    "**/*.key",
+    "**/*.env*",
+    "**/*.netrc*",
+    "**/*credentials*",
+    "**/*config*",
+];
+
+/// Glob rules for files that must never be read through the app
+pub struct PathSecurityPolicy {
+    blocked_patterns: Vec<Glob>,
+    matcher: GlobSet,
+}
+
+impl PathSecurityPolicy {
+    pub fn new(patterns: &[&str]) -> Result<Self, ThinkingSpaceError> {
+        let mut blocked_patterns = Vec::with_capacity(patterns.len());
+        let mut builder = GlobSetBuilder::new();
+
+        for pattern in patterns {
+            // `*` stays within one path component; `**` crosses directories
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| {
+                    ThinkingSpaceError::validation(
+                        "patterns",
+                        format!("Invalid path pattern {}: {}", pattern, e),
+                    )
+                })?;
+            builder.add(glob.clone());
+            blocked_patterns.push(glob);
+        }
+
+        let matcher = builder.build().map_err(|e| {
+            ThinkingSpaceError::validation(
+                "patterns",
+                format!("Failed to compile path patterns: {}", e),
+            )
+        })?;
+
+        Ok(Self {
+            blocked_patterns,
+            matcher,
+        })
+    }
+
+    pub fn is_blocked(&self, path: &Path) -> bool {
+        self.matcher.is_match(path)
+    }
+
+    pub fn patterns(&self) -> Vec<String> {
+        self.blocked_patterns
+            .iter()
+            .map(|glob| glob.glob().to_string())
+            .collect()
+    }
+}
+
+static PATH_SECURITY_POLICY: Lazy<PathSecurityPolicy> = Lazy::new(|| {
+    PathSecurityPolicy::new(DEFAULT_BLOCKED_PATTERNS).expect("Default path patterns are valid")
+});
+
+/// The glob patterns currently blocking file reads
+#[tauri::command]
+pub fn get_path_security_policy() -> Result<Vec<String>, ThinkingSpaceError> {
+    Ok(PATH_SECURITY_POLICY.patterns())
+}
+
+/// Resolve `path` and check it is safe to read: inside the home directory
+/// and not a credentials-style file
+fn validate_readable_path(path: &str) -> Result<PathBuf, ThinkingSpaceError> {
+    let home_dir =
+        dirs::home_dir().ok_or_else(|| ThinkingSpaceError::not_found("Home directory"))?;
+    validate_readable_path_in(path, &home_dir)
+}
+
+/// validate_readable_path with reads allowed under `home_dir`
+pub(crate) fn validate_readable_path_in(
+    path: &str,
+    home_dir: &Path,
+) -> Result<PathBuf, ThinkingSpaceError> {
+    // Security: Validate path to prevent path traversal attacks
+    let path_buf = PathBuf::from(path);
+
+    // Canonicalize to resolve symlinks and relative paths
+    let canonical = path_buf
+        .canonicalize()
+        .map_err(|e| ThinkingSpaceError::io(&path_buf, e))?;
+
+    // Only allow reads from user's home directory
+    if !canonical.starts_with(home_dir) {
+        return Err(ThinkingSpaceError::security(
+            "Access denied: path outside allowed directory",
+        ));
+    }
+
+    // Additional check: Don't allow reading sensitive files
+    if PATH_SECURITY_POLICY.is_blocked(&canonical) {
+        return Err(ThinkingSpaceError::security(
+            "Access denied: cannot read sensitive files",
+        ));
+    }
+
+    Ok(canonical)
+}
+
+#[tauri::command]
+pub fn read_file_content(path: String) -> Result<String, ThinkingSpaceError> {
+    let canonical = validate_readable_path(&path)?;
+    fs::read_to_string(&canonical).map_err(|e| ThinkingSpaceError::io(&canonical, e))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BinaryFileResponse {
+    pub content_base64: String,
+    pub mime_type: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileInfo {
+    pub mime_type: String,
+    pub size_bytes: u64,
+    pub is_text: bool,
+}
+
+/// Whether `sample` looks like UTF-8 text. A multi-byte character cut off at
+/// the end of the sample still counts.
+fn looks_like_text(sample: &[u8]) -> bool {
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+/// MIME type from magic bytes, falling back to plain text or octet-stream
+fn detect_mime_type(sample: &[u8]) -> (String, bool) {
+    match infer::get(sample) {
+        Some(kind) => (
+            kind.mime_type().to_string(),
+            kind.matcher_type() == infer::MatcherType::Text,
+        ),
+        None if looks_like_text(sample) => ("text/plain".to_string(), true),
+        None => ("application/octet-stream".to_string(), false),
+    }
+}
+
+fn read_file_binary_at(path: &Path) -> Result<BinaryFileResponse, ThinkingSpaceError> {
+    let size_bytes = fs::metadata(path)
+        .map_err(|e| ThinkingSpaceError::io(path, e))?
+        .len();
+    if size_bytes > MAX_BINARY_READ_BYTES {
+        return Err(ThinkingSpaceError::validation(
+            "path",
+            format!(
+                "File is too large to read ({} bytes, limit is {} bytes)",
+                size_bytes, MAX_BINARY_READ_BYTES
+            ),
+        ));
+    }
+
+    let content = fs::read(path).map_err(|e| ThinkingSpaceError::io(path, e))?;
+    let sample = &content[..content.len().min(FILE_TYPE_SAMPLE_BYTES)];
+    let (mime_type, _) = detect_mime_type(sample);
+
+    Ok(BinaryFileResponse {
+        content_base64: BASE64.encode(&content),
+        mime_type,
+        size_bytes,
+    })
+}
+
+fn file_info_at(path: &Path) -> Result<FileInfo, ThinkingSpaceError> {
+    let mut file = fs::File::open(path).map_err(|e| ThinkingSpaceError::io(path, e))?;
+    let size_bytes = file
+        .metadata()
+        .map_err(|e| ThinkingSpaceError::io(path, e))?
+        .len();
+
+    // Only the head of the file is needed to tell what it is
+    let mut sample = Vec::with_capacity(FILE_TYPE_SAMPLE_BYTES);
+    file.by_ref()
+        .take(FILE_TYPE_SAMPLE_BYTES as u64)
+        .read_to_end(&mut sample)
+        .map_err(|e| ThinkingSpaceError::io(path, e))?;
+    let (mime_type, is_text) = detect_mime_type(&sample);
+
+    Ok(FileInfo {
+        mime_type,
+        size_bytes,
+        is_text,
+    })
+}
+
+/// Read any file (up to 10 MB) as base64 along with its detected MIME type
+#[tauri::command]
+pub fn read_file_binary(path: String) -> Result<BinaryFileResponse, ThinkingSpaceError> {
+    read_file_binary_at(&validate_readable_path(&path)?)
+}
+
+/// MIME type, size and text/binary for a file, reading only its first few KB
+#[tauri::command]
+pub fn get_file_info(path: String) -> Result<FileInfo, ThinkingSpaceError> {
+    file_info_at(&validate_readable_path(&path)?)
+}
+
+/// What happened to a watched file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+/// A debounced change to a file inside a space
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpaceFileChange {
+    pub space_id: String,
+    /// Relative to the space directory
+    pub file_path: String,
+    pub kind: FileChangeKind,
+}
+
+fn file_change_kind(kind: &notify::EventKind, path: &Path) -> Option<FileChangeKind> {
+    use notify::event::ModifyKind;
+    use notify::EventKind;
+
+    match kind {
+        EventKind::Create(_) => Some(FileChangeKind::Created),
+        EventKind::Remove(_) => Some(FileChangeKind::Deleted),
+        // Atomic saves rename a temp file over the original
+        EventKind::Modify(ModifyKind::Name(_)) if !path.exists() => Some(FileChangeKind::Deleted),
+        EventKind::Modify(_) => Some(FileChangeKind::Modified),
+        _ => None,
+    }
+}
+
+/// Temp files from atomic writes (NamedTempFile's `.tmpXXXXXX`) and the app's
+/// own metadata aren't worth reporting
+fn is_ignored_change(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    name.starts_with(".tmp") || name == METADATA_FILE
+}
+
+/// Collect raw watcher events and report each path once it has been quiet for
+/// FILE_WATCH_DEBOUNCE. Ends when the watcher (and so the sender) is dropped.
+fn debounce_file_events(
+    rx: mpsc::Receiver<notify::Result<notify::Event>>,
+    space_id: String,
+    space_dir: PathBuf,
+    on_change: impl Fn(SpaceFileChange),
+) {
+    let mut pending: HashMap<PathBuf, (FileChangeKind, Instant)> = HashMap::new();
+
+    loop {
+        let timeout = pending
+            .values()
+            .map(|(_, due)| due.saturating_duration_since(Instant::now()))
+            .min()
+            .unwrap_or(Duration::from_secs(60));
+
+        match rx.recv_timeout(timeout) {
+            Ok(Ok(event)) => {
+                for path in event.paths {
+                    if is_ignored_change(&path) {
+                        continue;
+                    }
+                    let Some(kind) = file_change_kind(&event.kind, &path) else {
+                        continue;
+                    };
+
+                    let due = Instant::now() + FILE_WATCH_DEBOUNCE;
+                    let kind = match pending.get(&path) {
+                        // Writing a new file is still a creation
+                        Some((FileChangeKind::Created, _)) if kind == FileChangeKind::Modified => {
+                            FileChangeKind::Created
+                        }
+                        _ => kind,
+                    };
+                    pending.insert(path, (kind, due));
+                }
+            }
+            Ok(Err(e)) => eprintln!("[SPACES] File watcher error for {}: {}", space_id, e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        let due: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, (_, due))| *due <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in due {
+            if let Some((kind, _)) = pending.remove(&path) {
+                let file_path = path.strip_prefix(&space_dir).unwrap_or(&path);
+                on_change(SpaceFileChange {
+                    space_id: space_id.clone(),
+                    file_path: file_path.to_string_lossy().to_string(),
+                    kind,
+                });
+            }
+        }
+    }
+}
+
+/// Watches space directories for changes made outside the app (e.g. CLAUDE.md
+/// edited in another editor)
+#[derive(Default)]
+pub struct FileWatcherManager {
+    watchers: Mutex<HashMap<String, RecommendedWatcher>>,
+}
+
+impl FileWatcherManager {
+    /// Start watching a space's directory, replacing any existing watcher for it
+    pub fn watch(
+        &self,
+        space_id: &str,
+        space_dir: &Path,
+        on_change: impl Fn(SpaceFileChange) + Send + 'static,
+    ) -> Result<(), ThinkingSpaceError> {
+        let space_dir = space_dir
+            .canonicalize()
+            .map_err(|e| ThinkingSpaceError::io(space_dir, e))?;
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(tx).map_err(|e| ThinkingSpaceError::io(&space_dir, e))?;
+        watcher
+            .watch(&space_dir, RecursiveMode::Recursive)
+            .map_err(|e| ThinkingSpaceError::io(&space_dir, e))?;
+
+        let id = space_id.to_string();
+        std::thread::spawn(move || debounce_file_events(rx, id, space_dir, on_change));
+
+        self.watchers.lock().insert(space_id.to_string(), watcher);
+        println!("[SPACES] Watching files for space {}", space_id);
+        Ok(())
+    }
+
+    /// Stop watching a space; pending changes are dropped
+    pub fn unwatch(&self, space_id: &str) -> Result<(), ThinkingSpaceError> {
+        self.watchers
+            .lock()
+            .remove(space_id)
+            .map(|_| println!("[SPACES] Stopped watching files for space {}", space_id))
+            .ok_or_else(|| ThinkingSpaceError::not_found(format!("Watcher for space {}", space_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_path_traversal_prevention() {
+        // Test various path traversal attack vectors
+        let attacks = vec![
+            "../../../etc/passwd",
+            "..\\..\\..\\Windows\\System32",
+            "/etc/shadow",
+            "/root/.ssh/id_rsa",
+        ];
+
+        for attack in attacks {
+            let result = read_file_content(attack.to_string());
+            assert!(result.is_err(), "Failed to block path traversal: {}", attack);
+            assert!(
+                matches!(
+                    result,
+                    Err(ThinkingSpaceError::Io { .. } | ThinkingSpaceError::Security { .. })
+                ),
+                "Expected security error for: {}",
+                attack
+            );
+        }
+    }
+
+    #[test]
+    fn test_sensitive_file_blocking() {
+        // Create a temp file with a blocked name
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sensitive_path = temp_dir.path().join("id_rsa");
+        std::fs::write(&sensitive_path, "sensitive data").unwrap();
+
+        let result = read_file_content(sensitive_path.to_string_lossy().to_string());
+
+        assert!(matches!(
+            result,
+            Err(ThinkingSpaceError::Security { reason }) if reason.contains("sensitive files")
+        ));
+    }
+
+    #[test]
+    fn test_allowed_file_read() {
         // Create a temp file in a safe location
         let mut temp_file = NamedTempFile::new().unwrap();
         let test_content = "test content";
         temp_file.write_all(test_content.as_bytes()).unwrap();
         temp_file.flush().unwrap();
 
-        let result = read_file_content(temp_file.path().to_string_lossy().to_string());
+        let result = read_file_content(temp_file.path().to_string_lossy().to_string());
+
+        assert!(result.is_ok(), "Should allow reading safe file");
+        assert_eq!(result.unwrap(), test_content);
+    }
+
+    #[test]
+    fn test_get_spaces_dir_creates_directory() {
+        let result = get_spaces_dir();
+        assert!(result.is_ok());
+
+        let spaces_dir = result.unwrap();
+        assert!(spaces_dir.exists());
+        assert!(spaces_dir.ends_with("spaces"));
+    }
+
+    fn create_test_space(spaces_dir: &Path, name: &str) -> Space {
+        create_space_in(
+            spaces_dir,
+            CreateSpaceRequest {
+                name: name.to_string(),
+                template: "quick-start".to_string(),
+            },
+            &[],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_clone_space() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let source = create_test_space(spaces_dir.path(), "Original");
+        let source_dir = PathBuf::from(&source.path);
+
+        std::fs::write(source_dir.join("notes.md"), "my notes").unwrap();
+        std::fs::write(source_dir.join(".mcp.json"), r#"{"mcpServers": {}}"#).unwrap();
+        std::fs::write(source_dir.join(".scratch"), "ignored").unwrap();
+        std::fs::create_dir(source_dir.join("docs")).unwrap();
+        std::fs::write(source_dir.join("docs").join("a.txt"), "nested").unwrap();
+
+        let clone = clone_space_in(spaces_dir.path(), &source.id, "Copy".to_string()).unwrap();
+        let clone_dir = PathBuf::from(&clone.path);
+
+        assert_ne!(clone.id, source.id);
+        assert_eq!(clone.name, "Copy");
+        assert_eq!(
+            std::fs::read_to_string(&clone.claude_md_path).unwrap(),
+            std::fs::read_to_string(&source.claude_md_path).unwrap()
+        );
+        assert!(clone_dir.join("notes.md").exists());
+        assert!(clone_dir.join(".mcp.json").exists());
+        assert!(clone_dir.join("docs").join("a.txt").exists());
+        assert!(!clone_dir.join(".scratch").exists());
+
+        // Clone has its own metadata, and the source is untouched
+        let clone_meta: Space =
+            serde_json::from_str(&std::fs::read_to_string(clone_dir.join(METADATA_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(clone_meta.id, clone.id);
+
+        let source_meta: Space =
+            serde_json::from_str(&std::fs::read_to_string(source_dir.join(METADATA_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(source_meta.id, source.id);
+        assert_eq!(source_meta.name, "Original");
+    }
+
+    #[test]
+    fn test_archived_spaces_hidden_by_default() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let visible = create_test_space(spaces_dir.path(), "Visible");
+        let archived = create_test_space(spaces_dir.path(), "Archived");
+
+        update_space_metadata_in(spaces_dir.path(), &archived.id, |s| s.archived = true).unwrap();
+
+        let default_list = list_spaces_in(spaces_dir.path(), false).unwrap();
+        assert_eq!(default_list.len(), 1);
+        assert_eq!(default_list[0].id, visible.id);
+
+        let full_list = list_spaces_in(spaces_dir.path(), true).unwrap();
+        assert_eq!(full_list.len(), 2);
+        assert!(full_list.iter().any(|s| s.id == archived.id && s.archived));
+
+        // Unarchiving brings it back, and no temp file is left behind
+        update_space_metadata_in(spaces_dir.path(), &archived.id, |s| s.archived = false).unwrap();
+        assert_eq!(list_spaces_in(spaces_dir.path(), false).unwrap().len(), 2);
+        assert!(leftover_temp_files(Path::new(&archived.path)).is_empty());
+    }
+
+    #[test]
+    fn test_pinned_spaces_listed_first() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let first = create_test_space(spaces_dir.path(), "First");
+        let second = create_test_space(spaces_dir.path(), "Second");
+        let third = create_test_space(spaces_dir.path(), "Third");
+        let recent = create_test_space(spaces_dir.path(), "Recent");
+
+        update_space_metadata_in(spaces_dir.path(), &recent.id, |s| {
+            s.last_accessed_at += 10_000
+        })
+        .unwrap();
+
+        pin_space_in(spaces_dir.path(), &second.id).unwrap();
+        pin_space_in(spaces_dir.path(), &first.id).unwrap();
+
+        let ids: Vec<String> = list_spaces_in(spaces_dir.path(), false)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids[0], second.id);
+        assert_eq!(ids[1], first.id);
+        assert_eq!(ids[2], recent.id);
+        assert_eq!(ids[3], third.id);
+
+        reorder_pinned_spaces_in(spaces_dir.path(), &[first.id.clone(), second.id.clone()])
+            .unwrap();
+
+        let spaces = list_spaces_in(spaces_dir.path(), false).unwrap();
+        assert_eq!(spaces[0].id, first.id);
+        assert_eq!(spaces[0].pin_order, Some(0));
+        assert_eq!(spaces[1].id, second.id);
+        assert_eq!(spaces[1].pin_order, Some(1));
+    }
+
+    #[test]
+    fn test_unpin_resets_pin_order() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Pinned");
+
+        pin_space_in(spaces_dir.path(), &space.id).unwrap();
+        unpin_space_in(spaces_dir.path(), &space.id).unwrap();
+
+        let spaces = list_spaces_in(spaces_dir.path(), false).unwrap();
+        assert!(!spaces[0].pinned);
+        assert_eq!(spaces[0].pin_order, None);
+
+        // Unpinned spaces can't be reordered
+        assert!(reorder_pinned_spaces_in(spaces_dir.path(), &[space.id.clone()]).is_err());
+    }
+
+    #[test]
+    fn test_healthy_space() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Healthy");
+
+        let health = check_space_health_in(spaces_dir.path(), &space.id);
+        assert!(health.ok);
+        assert!(health.issues.is_empty());
+    }
+
+    #[test]
+    fn test_repair_missing_claude_md() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "No Instructions");
+        fs::remove_file(&space.claude_md_path).unwrap();
+
+        let health = check_space_health_in(spaces_dir.path(), &space.id);
+        assert!(!health.ok);
+        assert_eq!(health.issues, vec!["CLAUDE.md is missing"]);
+
+        let repaired = repair_space_in(spaces_dir.path(), &space.id).unwrap();
+        assert!(repaired.ok);
+        assert!(repaired.repaired);
+        assert!(fs::read_to_string(&space.claude_md_path)
+            .unwrap()
+            .starts_with("# No Instructions"));
+    }
+
+    #[test]
+    fn test_repair_stale_path() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Moved");
+        update_space_metadata_in(spaces_dir.path(), &space.id, |s| {
+            s.path = "/old/location".to_string()
+        })
+        .unwrap();
+
+        let health = check_space_health_in(spaces_dir.path(), &space.id);
+        assert!(!health.ok);
+        assert_eq!(health.issues.len(), 1);
+
+        let repaired = repair_space_in(spaces_dir.path(), &space.id).unwrap();
+        assert!(repaired.ok);
+        assert!(repaired.repaired);
+
+        let spaces = list_spaces_in(spaces_dir.path(), false).unwrap();
+        assert_eq!(spaces[0].path, space.path);
+    }
+
+    #[test]
+    fn test_unrepairable_spaces() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+
+        // Missing directory
+        let missing = repair_space_in(spaces_dir.path(), "does-not-exist").unwrap();
+        assert!(!missing.ok);
+        assert!(!missing.repaired);
+        assert_eq!(missing.issues, vec!["Space directory not found"]);
+
+        // Corrupt metadata
+        let space = create_test_space(spaces_dir.path(), "Corrupt");
+        fs::write(PathBuf::from(&space.path).join(METADATA_FILE), "{not json").unwrap();
+
+        let corrupt = repair_space_in(spaces_dir.path(), &space.id).unwrap();
+        assert!(!corrupt.ok);
+        assert!(!corrupt.repaired);
+        assert!(corrupt.issues[0].starts_with("Metadata file is not valid"));
+    }
+
+    #[test]
+    fn test_space_file_path_traversal_rejected() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Files");
+
+        let attacks = [
+            "../escape.txt",
+            "notes/../../escape.txt",
+            "/etc/passwd",
+            ".space-metadata.json",
+            ".hidden/notes.txt",
+            "",
+        ];
+
+        for attack in attacks {
+            assert!(
+                write_space_file_in(spaces_dir.path(), &space.id, attack, "x").is_err(),
+                "write allowed: {}",
+                attack
+            );
+            for is_directory in [false, true] {
+                assert!(
+                    delete_space_file_in(spaces_dir.path(), &space.id, attack, is_directory, true)
+                        .is_err(),
+                    "delete allowed: {}",
+                    attack
+                );
+            }
+            assert!(
+                rename_space_file_in(spaces_dir.path(), &space.id, "CLAUDE.md", attack).is_err(),
+                "rename allowed: {}",
+                attack
+            );
+        }
+
+        assert!(!spaces_dir.path().join("escape.txt").exists());
+        assert!(PathBuf::from(&space.path).join(METADATA_FILE).exists());
+
+        // A space id that climbs out of the spaces directory is rejected too
+        let outside = spaces_dir.path().parent().unwrap().join("escape.txt");
+        for space_id in ["..", "../..", ".", ""] {
+            assert!(
+                write_space_file_in(spaces_dir.path(), space_id, "escape.txt", "x").is_err(),
+                "write allowed in space {:?}",
+                space_id
+            );
+            assert!(
+                delete_space_file_in(spaces_dir.path(), space_id, "escape.txt", false, true)
+                    .is_err(),
+                "delete allowed in space {:?}",
+                space_id
+            );
+            assert!(
+                update_space_metadata_in(spaces_dir.path(), space_id, |_| {}).is_err(),
+                "metadata update allowed in space {:?}",
+                space_id
+            );
+        }
+        assert!(!outside.exists());
+    }
+
+    #[test]
+    fn test_write_and_delete_space_file() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Files");
+        let space_dir = PathBuf::from(&space.path);
+
+        // Large content, written into a new subdirectory
+        let large = "x".repeat(5 * 1024 * 1024);
+        write_space_file_in(spaces_dir.path(), &space.id, "data/big.txt", &large).unwrap();
+        assert_eq!(
+            fs::metadata(space_dir.join("data").join("big.txt"))
+                .unwrap()
+                .len(),
+            large.len() as u64
+        );
+        assert!(leftover_temp_files(&space_dir.join("data")).is_empty());
+
+        write_space_file_in(spaces_dir.path(), &space.id, "data/big.txt", "small").unwrap();
+        assert_eq!(
+            fs::read_to_string(space_dir.join("data").join("big.txt")).unwrap(),
+            "small"
+        );
+
+        delete_space_file_in(spaces_dir.path(), &space.id, "data/big.txt", false, false).unwrap();
+        assert!(!space_dir.join("data").join("big.txt").exists());
+        assert!(
+            delete_space_file_in(spaces_dir.path(), &space.id, "data/big.txt", false, false)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_two_phase_directory_delete() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Files");
+        let space_dir = PathBuf::from(&space.path);
+
+        for file in ["data/a.txt", "data/b.txt", "data/nested/c.txt"] {
+            write_space_file_in(spaces_dir.path(), &space.id, file, "x").unwrap();
+        }
+
+        // First call only counts
+        let dry_run =
+            delete_space_file_in(spaces_dir.path(), &space.id, "data", true, false).unwrap();
+        assert_eq!(
+            dry_run,
+            DeleteSpaceFileResult {
+                deleted: false,
+                file_count: 3,
+                delete_confirmation_required: true,
+            }
+        );
+        assert!(space_dir.join("data").join("nested").join("c.txt").exists());
+
+        let confirmed =
+            delete_space_file_in(spaces_dir.path(), &space.id, "data", true, true).unwrap();
+        assert!(confirmed.deleted);
+        assert_eq!(confirmed.file_count, 3);
+        assert!(!space_dir.join("data").exists());
+        assert!(space_dir.join("CLAUDE.md").exists());
+    }
+
+    #[test]
+    fn test_delete_empty_directory_and_kind_mismatch() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Files");
+        let space_dir = PathBuf::from(&space.path);
+        fs::create_dir_all(space_dir.join("empty").join("inner")).unwrap();
+
+        // Nothing to lose, so no confirmation needed
+        let result =
+            delete_space_file_in(spaces_dir.path(), &space.id, "empty", true, false).unwrap();
+        assert!(result.deleted);
+        assert_eq!(result.file_count, 0);
+        assert!(!space_dir.join("empty").exists());
+
+        fs::create_dir(space_dir.join("dir")).unwrap();
+        assert!(delete_space_file_in(spaces_dir.path(), &space.id, "dir", false, true).is_err());
+        assert!(
+            delete_space_file_in(spaces_dir.path(), &space.id, "CLAUDE.md", true, true).is_err()
+        );
+        assert!(space_dir.join("CLAUDE.md").exists());
+    }
+
+    #[test]
+    fn test_rename_space_file_across_subdirectories() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Files");
+        let space_dir = PathBuf::from(&space.path);
+
+        write_space_file_in(spaces_dir.path(), &space.id, "drafts/idea.md", "idea").unwrap();
+        write_space_file_in(spaces_dir.path(), &space.id, "taken.md", "taken").unwrap();
+
+        rename_space_file_in(
+            spaces_dir.path(),
+            &space.id,
+            "drafts/idea.md",
+            "archive/2024/idea.md",
+        )
+        .unwrap();
+        assert!(!space_dir.join("drafts").join("idea.md").exists());
+        assert_eq!(
+            fs::read_to_string(space_dir.join("archive").join("2024").join("idea.md")).unwrap(),
+            "idea"
+        );
+
+        // Existing targets are not overwritten
+        assert!(rename_space_file_in(
+            spaces_dir.path(),
+            &space.id,
+            "archive/2024/idea.md",
+            "taken.md"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_recursive_listing_depth_and_hidden() {
+        let space_dir = tempfile::tempdir().unwrap();
+        let root = space_dir.path();
+        fs::create_dir_all(root.join("a").join("b").join("c")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("top.md"), "top").unwrap();
+        fs::write(root.join("a").join("one.md"), "1").unwrap();
+        fs::write(root.join("a").join("b").join("two.md"), "2").unwrap();
+        fs::write(root.join("a").join("b").join("c").join("three.md"), "3").unwrap();
+        fs::write(root.join(".git").join("HEAD"), "ref").unwrap();
+        fs::write(root.join(METADATA_FILE), "{}").unwrap();
+
+        let relative_paths = |listing: SpaceFileListing| -> Vec<String> {
+            listing.files.into_iter().map(|f| f.relative_path).collect()
+        };
+
+        let shallow = list_space_files_recursive_in(root, 1, false).unwrap();
+        assert!(!shallow.truncated);
+        assert_eq!(relative_paths(shallow), vec!["a", "top.md"]);
+
+        let default_depth = relative_paths(
+            list_space_files_recursive_in(root, DEFAULT_LISTING_DEPTH, false).unwrap(),
+        );
+        assert_eq!(
+            default_depth,
+            vec!["a", "a/b", "a/b/c", "a/b/two.md", "a/one.md", "top.md"]
+        );
+
+        let with_hidden = relative_paths(
+            list_space_files_recursive_in(root, DEFAULT_LISTING_DEPTH, true).unwrap(),
+        );
+        assert!(with_hidden.contains(&".git/HEAD".to_string()));
+        assert!(!with_hidden.contains(&METADATA_FILE.to_string()));
+    }
+
+    #[test]
+    fn test_claude_md_history_capped() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "History");
+        let space_dir = PathBuf::from(&space.path);
+
+        for i in 0..25 {
+            write_claude_md_in(&space_dir, &format!("version {}", i)).unwrap();
+        }
+
+        let history = list_claude_md_history_in(&space_dir).unwrap();
+        assert_eq!(history.len(), MAX_CLAUDE_MD_VERSIONS);
+
+        // Newest first, and the newest saved version is the one before the current content
+        let newest = fs::read_to_string(
+            space_dir
+                .join(CLAUDE_MD_HISTORY_DIR)
+                .join(format!("{}.md", history[0].timestamp)),
+        )
+        .unwrap();
+        assert_eq!(newest, "version 23");
+        assert!(history.windows(2).all(|w| w[0].timestamp > w[1].timestamp));
+    }
+
+    #[test]
+    fn test_restore_claude_md_adds_version() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Restore");
+        let space_dir = PathBuf::from(&space.path);
+        let original = fs::read_to_string(&space.claude_md_path).unwrap();
+
+        write_claude_md_in(&space_dir, "first edit").unwrap();
+        write_claude_md_in(&space_dir, "second edit").unwrap();
+
+        let history = list_claude_md_history_in(&space_dir).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].size_bytes, original.len() as u64);
+
+        restore_claude_md_in(&space_dir, history[1].timestamp).unwrap();
+
+        assert_eq!(fs::read_to_string(&space.claude_md_path).unwrap(), original);
+        let history = list_claude_md_history_in(&space_dir).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].size_bytes, "second edit".len() as u64);
+
+        assert!(restore_claude_md_in(&space_dir, 42).is_err());
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        let estimate = estimate_tokens("one two three four", 10);
+        assert_eq!(estimate.char_count, 18);
+        assert_eq!(estimate.word_count, 4);
+        // 18 chars / 4 per token, rounded up
+        assert_eq!(estimate.estimated_tokens, 5);
+        assert!((estimate.budget_used_pct - 50.0).abs() < f32::EPSILON);
+        assert!(!estimate.over_budget);
+
+        let empty = estimate_tokens("", DEFAULT_CLAUDE_MD_TOKEN_BUDGET);
+        assert_eq!(empty.estimated_tokens, 0);
+        assert_eq!(empty.word_count, 0);
+
+        let large = "a".repeat(32_004);
+        let estimate = estimate_tokens(&large, DEFAULT_CLAUDE_MD_TOKEN_BUDGET);
+        assert_eq!(estimate.estimated_tokens, 8001);
+        assert!(estimate.over_budget);
+    }
+
+    #[test]
+    fn test_budget_warning_only_when_over_budget() {
+        let mut warned = None;
+        check_claude_md_budget(&"a".repeat(400), 50, |estimate| {
+            warned = Some(estimate.estimated_tokens)
+        });
+        assert_eq!(warned, Some(100));
+
+        let mut warned = false;
+        check_claude_md_budget(&"a".repeat(200), 50, |_| warned = true);
+        assert!(!warned);
+    }
+
+    #[test]
+    fn test_search_spaces() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        create_test_space(spaces_dir.path(), "Rust");
+        create_test_space(spaces_dir.path(), "Learning rust");
+        create_test_space(spaces_dir.path(), "Gardening");
+
+        // Empty results
+        assert!(search_spaces_in(spaces_dir.path(), "python")
+            .unwrap()
+            .is_empty());
+
+        // Exact and substring matches, case-insensitive
+        let mut names: Vec<String> = search_spaces_in(spaces_dir.path(), "RUST")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["Learning rust", "Rust"]);
+
+        // Substring of a single name
+        let garden = search_spaces_in(spaces_dir.path(), "garden").unwrap();
+        assert_eq!(garden.len(), 1);
+        assert_eq!(garden[0].name, "Gardening");
+    }
+
+    #[test]
+    fn test_filter_spaces_by_template() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        create_test_space(spaces_dir.path(), "One");
+        create_test_space(spaces_dir.path(), "Two");
+        create_space_in(
+            spaces_dir.path(),
+            CreateSpaceRequest {
+                name: "Three".to_string(),
+                template: "custom".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            filter_spaces_by_template_in(spaces_dir.path(), "quick-start")
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            filter_spaces_by_template_in(spaces_dir.path(), "custom")
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Template filter is exact, not substring
+        assert!(filter_spaces_by_template_in(spaces_dir.path(), "quick")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let source = create_test_space(spaces_dir.path(), "Portable");
+        let source_dir = spaces_dir.path().join(&source.id);
+        fs::write(source_dir.join("CLAUDE.md"), "# Portable\n\nCustom notes").unwrap();
+        fs::write(source_dir.join("notes.txt"), "hello").unwrap();
+        fs::create_dir_all(source_dir.join("docs")).unwrap();
+        fs::write(source_dir.join("docs").join("plan.md"), "plan").unwrap();
+        fs::write(source_dir.join(".mcp.json"), r#"{"mcpServers":{}}"#).unwrap();
+        fs::write(source_dir.join(".DS_Store"), "junk").unwrap();
+
+        let out_dir = tempfile::tempdir().unwrap();
+        let archive = out_dir.path().join("portable.zip");
+        export_space_in(spaces_dir.path(), &source.id, &archive).unwrap();
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let imported = import_space_in(target_dir.path(), &archive).unwrap();
+        let imported_dir = target_dir.path().join(&imported.id);
+
+        assert_ne!(imported.id, source.id);
+        assert_eq!(imported.name, "Portable");
+        assert_eq!(imported.template, source.template);
+        assert_eq!(imported.path, imported_dir.to_string_lossy());
+        assert_eq!(
+            fs::read_to_string(imported_dir.join("CLAUDE.md")).unwrap(),
+            "# Portable\n\nCustom notes"
+        );
+        assert_eq!(
+            fs::read_to_string(imported_dir.join("notes.txt")).unwrap(),
+            "hello"
+        );
+        assert_eq!(
+            fs::read_to_string(imported_dir.join("docs").join("plan.md")).unwrap(),
+            "plan"
+        );
+        assert!(imported_dir.join(".mcp.json").exists());
+        assert!(!imported_dir.join(".DS_Store").exists());
+
+        // Fresh metadata on disk carries the new id
+        let on_disk: Space =
+            serde_json::from_str(&fs::read_to_string(imported_dir.join(METADATA_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(on_disk.id, imported.id);
+    }
+
+    #[test]
+    fn test_resolve_within_rejects_outside_paths() {
+        let root = tempfile::tempdir().unwrap();
+        let other = tempfile::tempdir().unwrap();
+
+        assert!(resolve_within(&root.path().join("export.zip"), root.path()).is_ok());
+        assert!(resolve_within(&other.path().join("export.zip"), root.path()).is_err());
+        assert!(resolve_within(&root.path().join("..").join("export.zip"), root.path()).is_err());
+    }
+
+    #[test]
+    fn test_clone_missing_space_fails() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        assert!(clone_space_in(spaces_dir.path(), "missing", "Copy".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_get_template_content_quick_start() {
+        let template = get_template_content("quick-start");
+        assert!(template.contains("# {name}"));
+        assert!(template.contains("## Purpose"));
+        assert!(template.contains("## Context"));
+        assert!(template.contains("## Guidelines"));
+    }
+
+    #[test]
+    fn test_get_template_content_custom() {
+        let template = get_template_content("custom");
+        assert!(template.contains("# {name}"));
+        assert!(template.contains("[Write your own instructions for Claude]"));
+    }
+
+    #[test]
+    fn test_get_template_content_invalid_defaults_to_quick_start() {
+        let template = get_template_content("invalid-template-name");
+        assert_eq!(template, get_template_content("quick-start"));
+    }
+
+    #[test]
+    fn test_space_statistics() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let spaces_dir = temp_dir.path().join("spaces");
+        fs::create_dir_all(&spaces_dir).unwrap();
+        let space = create_test_space(&spaces_dir, "Stats");
+        let space_dir = spaces_dir.join(&space.id);
+
+        // Files: CLAUDE.md from the template plus two of our own
+        fs::write(space_dir.join("notes.md"), "12345").unwrap();
+        fs::create_dir(space_dir.join("docs")).unwrap();
+        fs::write(space_dir.join("docs/plan.txt"), "1234567890").unwrap();
+        write_claude_md_in(&space_dir, "# Stats\nUpdated").unwrap();
+        let claude_md_bytes = fs::metadata(space_dir.join("CLAUDE.md")).unwrap().len();
+
+        let conversations_db = temp_dir.path().join("conversations.db");
+        let conn = crate::conversations::get_connection_at(&conversations_db).unwrap();
+        let message = |id: &str, role: &str, timestamp: i64| crate::conversations::Message {
+            id: id.to_string(),
+            role: role.to_string(),
+            content: "Hi".to_string(),
+            timestamp,
+            metadata: serde_json::json!({}),
+        };
+        crate::conversations::save_conversation_internal(
+            &conn,
+            &space.id,
+            "Stats",
+            &[
+                message("1", "user", 300),
+                message("2", "assistant", 100),
+                message("3", "user", 500),
+            ],
+        )
+        .unwrap();
+
+        let sessions_db = temp_dir.path().join("sessions.db");
+        let conn = crate::sessions::get_connection_at(&sessions_db).unwrap();
+        for (session_id, space_id) in [
+            ("s1", space.id.as_str()),
+            ("s2", space.id.as_str()),
+            ("s3", "other"),
+        ] {
+            conn.execute(
+                "INSERT INTO sessions (session_id, space_id, created_at, last_active) VALUES (?1, ?2, 0, 0)",
+                rusqlite::params![session_id, space_id],
+            )
+            .unwrap();
+        }
+
+        let service = SpaceStatisticsService::new(spaces_dir, conversations_db, sessions_db);
+        let stats = service.statistics(&space.id).unwrap();
+
+        assert_eq!(
+            stats,
+            SpaceStatistics {
+                total_messages: 3,
+                user_messages: 2,
+                assistant_messages: 1,
+                first_message_at: Some(100),
+                last_message_at: Some(500),
+                total_file_count: 3,
+                total_file_bytes: claude_md_bytes + 5 + 10,
+                session_count: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_space_statistics_empty_space() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let spaces_dir = temp_dir.path().join("spaces");
+        fs::create_dir_all(&spaces_dir).unwrap();
+        let space = create_test_space(&spaces_dir, "Empty");
+
+        let service = SpaceStatisticsService::new(
+            spaces_dir,
+            temp_dir.path().join("conversations.db"),
+            temp_dir.path().join("sessions.db"),
+        );
+        let stats = service.statistics(&space.id).unwrap();
+
+        assert_eq!(stats.total_messages, 0);
+        assert_eq!(stats.first_message_at, None);
+        assert_eq!(stats.session_count, 0);
+        assert_eq!(stats.total_file_count, 1);
+
+        assert!(service.statistics("missing").is_err());
+    }
+
+    /// Temp files an atomic write left behind in `dir`
+    fn leftover_temp_files(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| is_ignored_change(path) && !path.ends_with(METADATA_FILE))
+            .collect()
+    }
+
+    #[test]
+    fn test_atomic_write_replaces_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("notes.md");
+        fs::write(&path, "old").unwrap();
+        // A user file named like the old fixed temp path is left alone
+        fs::write(temp_dir.path().join("notes.md.tmp"), "mine").unwrap();
+
+        atomic_write(&path, b"new").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("notes.md.tmp")).unwrap(),
+            "mine"
+        );
+        assert!(leftover_temp_files(temp_dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_atomic_write_failure_keeps_original() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("CLAUDE.md");
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("original.md"), "original").unwrap();
+
+        // A non-empty directory can't be renamed over, so the write cannot go through
+        assert!(atomic_write(&path, b"replacement").is_err());
+        assert_eq!(
+            fs::read_to_string(path.join("original.md")).unwrap(),
+            "original"
+        );
+        assert!(leftover_temp_files(temp_dir.path()).is_empty());
+    }
+
+    /// A minimal 1x1 PNG
+    const PNG_BYTES: &[u8] = &[
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1F,
+        0x15, 0xC4, 0x89,
+    ];
+
+    #[test]
+    fn test_read_png_as_binary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("pixel.png");
+        fs::write(&path, PNG_BYTES).unwrap();
+
+        let response = read_file_binary_at(&path).unwrap();
+
+        assert_eq!(response.mime_type, "image/png");
+        assert_eq!(response.size_bytes, PNG_BYTES.len() as u64);
+        assert_eq!(BASE64.decode(&response.content_base64).unwrap(), PNG_BYTES);
+
+        let info = file_info_at(&path).unwrap();
+        assert_eq!(info.mime_type, "image/png");
+        assert!(!info.is_text);
+    }
+
+    #[test]
+    fn test_read_pdf_as_binary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("doc.pdf");
+        fs::write(
+            &path,
+            b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n1 0 obj\n<<>>\nendobj\n",
+        )
+        .unwrap();
+
+        let response = read_file_binary_at(&path).unwrap();
+        assert_eq!(response.mime_type, "application/pdf");
+
+        let info = file_info_at(&path).unwrap();
+        assert_eq!(info.mime_type, "application/pdf");
+        assert!(!info.is_text);
+    }
+
+    #[test]
+    fn test_text_file_info() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("notes.md");
+        fs::write(&path, "# Notes\nCafé ☕").unwrap();
+
+        let info = file_info_at(&path).unwrap();
+        assert_eq!(
+            info,
+            FileInfo {
+                mime_type: "text/plain".to_string(),
+                size_bytes: fs::metadata(&path).unwrap().len(),
+                is_text: true,
+            }
+        );
 
-        assert!(result.is_ok(), "Should allow reading safe file");
-        assert_eq!(result.unwrap(), test_content);
+        let response = read_file_binary_at(&path).unwrap();
+        assert_eq!(response.mime_type, "text/plain");
     }
 
     #[test]
-    fn test_get_spaces_dir_creates_directory() {
-        let result = get_spaces_dir();
-        assert!(result.is_ok());
+    fn test_text_sample_cut_mid_character() {
+        // "é" is two bytes; a sample ending between them is still text
+        let bytes = "aé".as_bytes();
+        assert!(looks_like_text(&bytes[..2]));
+        assert!(!looks_like_text(&[0x61, 0xFF, 0x62]));
+    }
 
-        let spaces_dir = result.unwrap();
-        assert!(spaces_dir.exists());
-        assert!(spaces_dir.ends_with(".thinking-space/spaces"));
+    #[test]
+    fn test_binary_read_size_cap() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("big.bin");
+        let file = fs::File::create(&path).unwrap();
+        file.set_len(MAX_BINARY_READ_BYTES + 1).unwrap();
+
+        let err = read_file_binary_at(&path).unwrap_err();
+        assert!(matches!(err, ThinkingSpaceError::ValidationFailed { .. }));
+        assert!(err
+            .to_string()
+            .contains(&(MAX_BINARY_READ_BYTES + 1).to_string()));
     }
 
     #[test]
-    fn test_get_template_content_quick_start() {
-        let template = get_template_content("quick-start");
-        assert!(template.contains("# {name}"));
-        assert!(template.contains("## Purpose"));
-        assert!(template.contains("## Context"));
-        assert!(template.contains("## Guidelines"));
+    fn test_read_file_binary_outside_home_denied() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("pixel.png");
+        fs::write(&path, PNG_BYTES).unwrap();
+
+        // Temp dirs normally live outside the home directory
+        let home = dirs::home_dir().unwrap();
+        if !path.canonicalize().unwrap().starts_with(&home) {
+            assert!(read_file_binary(path.to_string_lossy().to_string()).is_err());
+            assert!(get_file_info(path.to_string_lossy().to_string()).is_err());
+        }
     }
 
     #[test]
-    fn test_get_template_content_custom() {
-        let template = get_template_content("custom");
-        assert!(template.contains("# {name}"));
-        assert!(template.contains("[Write your own instructions for Claude]"));
+    fn test_search_space_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(temp_dir.path(), "Search");
+        let space_dir = temp_dir.path().join(&space.id);
+
+        fs::write(space_dir.join("b.md"), "nothing here\nRoadmap for Q3\n").unwrap();
+        fs::write(
+            space_dir.join("a.txt"),
+            "roadmap draft\nother\nROADMAP final\n",
+        )
+        .unwrap();
+        fs::create_dir(space_dir.join("notes")).unwrap();
+        fs::write(
+            space_dir.join("notes").join("c.md"),
+            "no match\r\nold roadmap\r\n",
+        )
+        .unwrap();
+        fs::write(space_dir.join("image.png"), PNG_BYTES).unwrap();
+
+        let matches =
+            search_space_files_in(temp_dir.path(), &space.id, "roadmap", false, 100).unwrap();
+        let found: Vec<(&str, usize, &str)> = matches
+            .iter()
+            .map(|m| (m.file_name.as_str(), m.line_number, m.line_text.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("a.txt", 1, "roadmap draft"),
+                ("a.txt", 3, "ROADMAP final"),
+                ("b.md", 2, "Roadmap for Q3"),
+                ("c.md", 2, "old roadmap"),
+            ]
+        );
+        assert_eq!(
+            PathBuf::from(&matches[3].relative_path),
+            PathBuf::from("notes").join("c.md")
+        );
+
+        let case_sensitive =
+            search_space_files_in(temp_dir.path(), &space.id, "roadmap", true, 100).unwrap();
+        assert_eq!(case_sensitive.len(), 2);
     }
 
     #[test]
-    fn test_get_template_content_invalid_defaults_to_quick_start() {
-        let template = get_template_content("invalid-template-name");
-        assert_eq!(template, get_template_content("quick-start"));
+    fn test_search_space_files_skips_large_files_and_caps_results() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(temp_dir.path(), "Search");
+        let space_dir = temp_dir.path().join(&space.id);
+
+        let mut big = "needle\n".repeat(10);
+        big.push_str(&"x".repeat(MAX_SEARCHED_FILE_BYTES as usize));
+        fs::write(space_dir.join("big.txt"), big).unwrap();
+        fs::write(
+            space_dir.join("many.txt"),
+            "needle\n".repeat(MAX_SEARCH_RESULTS + 50),
+        )
+        .unwrap();
+
+        let matches =
+            search_space_files_in(temp_dir.path(), &space.id, "needle", true, 10_000).unwrap();
+        assert_eq!(matches.len(), MAX_SEARCH_RESULTS);
+        assert!(matches.iter().all(|m| m.file_name == "many.txt"));
+
+        // The first matches in path order, not an arbitrary three
+        fs::write(space_dir.join("aaa.txt"), "needle\nneedle\n").unwrap();
+        let limited = search_space_files_in(temp_dir.path(), &space.id, "needle", true, 3).unwrap();
+        let found: Vec<(&str, usize)> = limited
+            .iter()
+            .map(|m| (m.file_name.as_str(), m.line_number))
+            .collect();
+        assert_eq!(found, vec![("aaa.txt", 1), ("aaa.txt", 2), ("many.txt", 1)]);
+
+        assert!(search_space_files_in(temp_dir.path(), &space.id, "", true, 3).is_err());
+    }
+
+    fn commit_all(repo: &git2::Repository, message: &str) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_git_status_reports_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(temp_dir.path(), "Repo");
+        let space_dir = temp_dir.path().join(&space.id);
+        let repo = git2::Repository::init(&space_dir).unwrap();
+
+        fs::write(space_dir.join("tracked.md"), "one\n").unwrap();
+        fs::write(space_dir.join("gone.md"), "bye\n").unwrap();
+        commit_all(&repo, "initial");
+
+        fs::write(space_dir.join("tracked.md"), "one\ntwo\n").unwrap();
+        fs::remove_file(space_dir.join("gone.md")).unwrap();
+        fs::write(space_dir.join("staged.md"), "new\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("staged.md")).unwrap();
+        index.write().unwrap();
+        fs::write(space_dir.join("scratch.md"), "draft\n").unwrap();
+
+        let statuses = git_status_in(temp_dir.path(), &space.id).unwrap();
+        let status_of = |path: &str| statuses.iter().find(|s| s.path == path).map(|s| s.status);
+        assert_eq!(status_of("tracked.md"), Some(GitChange::Modified));
+        assert_eq!(status_of("gone.md"), Some(GitChange::Deleted));
+        assert_eq!(status_of("staged.md"), Some(GitChange::Added));
+        assert_eq!(status_of("scratch.md"), Some(GitChange::Untracked));
+        assert_eq!(status_of("CLAUDE.md"), None);
+    }
+
+    #[test]
+    fn test_git_status_for_space_in_repo_subdirectory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(temp_dir.path()).unwrap();
+        let spaces_dir = temp_dir.path().join("spaces");
+        fs::create_dir(&spaces_dir).unwrap();
+        let space = create_test_space(&spaces_dir, "Nested");
+        fs::write(temp_dir.path().join("outside.md"), "not in the space\n").unwrap();
+        commit_all(&repo, "initial");
+
+        fs::write(spaces_dir.join(&space.id).join("notes.md"), "hello\n").unwrap();
+        fs::write(temp_dir.path().join("outside.md"), "changed\n").unwrap();
+
+        let statuses = git_status_in(&spaces_dir, &space.id).unwrap();
+        assert_eq!(
+            statuses,
+            vec![GitFileStatus {
+                path: "notes.md".to_string(),
+                status: GitChange::Untracked,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_file_diff() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(temp_dir.path(), "Repo");
+        let space_dir = temp_dir.path().join(&space.id);
+        let repo = git2::Repository::init(&space_dir).unwrap();
+        fs::write(space_dir.join("notes.md"), "keep\nold line\n").unwrap();
+        commit_all(&repo, "initial");
+
+        fs::write(space_dir.join("notes.md"), "keep\nnew line\n").unwrap();
+
+        let diff = file_diff_in(temp_dir.path(), &space.id, "notes.md").unwrap();
+        assert!(diff.contains("-old line\n"));
+        assert!(diff.contains("+new line\n"));
+        assert!(diff.contains(" keep\n"));
+        assert!(!diff.contains("CLAUDE.md"));
+    }
+
+    #[test]
+    fn test_git_commands_outside_repository() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(temp_dir.path(), "Plain");
+
+        // Only meaningful when the temp directory itself isn't inside a repo
+        if git2::Repository::discover(temp_dir.path()).is_err() {
+            let err = git_status_in(temp_dir.path(), &space.id).unwrap_err();
+            assert!(
+                matches!(&err, ThinkingSpaceError::NotFound { resource } if resource.starts_with("Git repository"))
+            );
+            assert!(file_diff_in(temp_dir.path(), &space.id, "CLAUDE.md").is_err());
+        }
+    }
+
+    #[test]
+    fn test_path_security_policy_default_patterns() {
+        let home = Path::new("/home/user");
+        let blocked = [
+            home.join(".ssh").join("known_hosts"),
+            home.join(".ssh").join("keys").join("work"),
+            home.join(".aws").join("credentials"),
+            home.join(".aws").join("sso").join("cache.json"),
+            home.join(".gnupg").join("pubring.kbx"),
+            home.join("id_rsa"),
+            home.join("backups").join("id_ed25519.pub"),
+            home.join("certs").join("server.pem"),
+            home.join("tls.key"),
+            home.join("project").join(".env.local"),
+            home.join(".netrc"),
+            home.join(".git-credentials"),
+        ];
+        for path in &blocked {
+            assert!(
+                PATH_SECURITY_POLICY.is_blocked(path),
+                "{} should be blocked",
+                path.display()
+            );
+        }
+
+        let allowed = [
+            home.join("notes.md"),
+            home.join("projects").join("keyboard.txt"),
+            home.join("ssh-notes").join("readme.md"),
+            home.join("docs").join("valid_ids.csv"),
+        ];
+        for path in &allowed {
+            assert!(
+                !PATH_SECURITY_POLICY.is_blocked(path),
+                "{} should be readable",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_path_security_policy_lists_patterns() {
+        let patterns = get_path_security_policy().unwrap();
+        for expected in [
+            "**/.ssh/**",
+            "**/.aws/**",
+            "**/.gnupg/**",
+            "**/id_*",
+            "**/*.pem",
+            "**/*.key",
+        ] {
+            assert!(
+                patterns.iter().any(|p| p == expected),
+                "missing {}",
+                expected
+            );
+        }
+
+        assert!(PathSecurityPolicy::new(&["**/[unclosed"]).is_err());
+    }
+
+    #[test]
+    fn test_space_cache_serves_listing_until_invalidated() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Cached");
+        let cache = Mutex::new(SpaceMetadataCache::new(DEFAULT_SPACE_CACHE_CAPACITY));
+
+        assert_eq!(
+            load_all_spaces_cached(spaces_dir.path(), &cache).unwrap()[0].name,
+            "Cached"
+        );
+
+        // An edit behind the cache's back isn't seen...
+        let mut edited = space.clone();
+        edited.name = "Edited".to_string();
+        fs::write(
+            PathBuf::from(&space.path).join(METADATA_FILE),
+            serde_json::to_string(&edited).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            load_all_spaces_cached(spaces_dir.path(), &cache).unwrap()[0].name,
+            "Cached"
+        );
+
+        // ...until the entry is invalidated
+        cache.lock().invalidate(&space.id);
+        assert_eq!(
+            load_all_spaces_cached(spaces_dir.path(), &cache).unwrap()[0].name,
+            "Edited"
+        );
+
+        // Deleted spaces drop out of the listing even while cached
+        fs::remove_dir_all(&space.path).unwrap();
+        assert!(load_all_spaces_cached(spaces_dir.path(), &cache)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_space_cache_updated_by_metadata_writes() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Writes");
+
+        update_space_metadata_in(spaces_dir.path(), &space.id, |s| s.archived = true).unwrap();
+        let cached = SPACE_METADATA_CACHE
+            .lock()
+            .get(spaces_dir.path(), &space.id)
+            .unwrap();
+        assert!(cached.archived);
+
+        // Entries loaded from another spaces directory are ignored
+        let other_dir = tempfile::tempdir().unwrap();
+        assert!(SPACE_METADATA_CACHE
+            .lock()
+            .get(other_dir.path(), &space.id)
+            .is_none());
+
+        invalidate_space_cache(&space.id);
+        assert!(SPACE_METADATA_CACHE
+            .lock()
+            .get(spaces_dir.path(), &space.id)
+            .is_none());
+    }
+
+    #[test]
+    fn test_space_cache_evicts_least_recently_used() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let first = create_test_space(spaces_dir.path(), "First");
+        let second = create_test_space(spaces_dir.path(), "Second");
+        let third = create_test_space(spaces_dir.path(), "Third");
+
+        let mut cache = SpaceMetadataCache::new(2);
+        cache.put(first.clone());
+        cache.put(second.clone());
+        cache.get(spaces_dir.path(), &first.id);
+        cache.put(third.clone());
+
+        assert!(cache.get(spaces_dir.path(), &first.id).is_some());
+        assert!(cache.get(spaces_dir.path(), &second.id).is_none());
+        assert!(cache.get(spaces_dir.path(), &third.id).is_some());
+    }
+
+    #[test]
+    fn test_cache_grows_to_fit_every_space() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        for i in 0..DEFAULT_SPACE_CACHE_CAPACITY * 2 {
+            create_test_space(spaces_dir.path(), &format!("Space {}", i));
+        }
+        let cache = Mutex::new(SpaceMetadataCache::new(DEFAULT_SPACE_CACHE_CAPACITY));
+
+        // The first listing reads everything from disk...
+        let listed = load_all_spaces_cached(spaces_dir.path(), &cache).unwrap();
+        assert_eq!(listed.len(), DEFAULT_SPACE_CACHE_CAPACITY * 2);
+        assert_eq!(cache.lock().hits, 0);
+
+        // ...and the next one is served entirely from memory
+        load_all_spaces_cached(spaces_dir.path(), &cache).unwrap();
+        assert_eq!(cache.lock().hits, DEFAULT_SPACE_CACHE_CAPACITY * 2);
+    }
+
+    #[test]
+    fn test_file_watcher_reports_changes_until_stopped() {
+        let space_dir = tempfile::tempdir().unwrap();
+        let watchers = FileWatcherManager::default();
+        let (tx, rx) = mpsc::channel();
+
+        watchers
+            .watch("space-1", space_dir.path(), move |change| {
+                let _ = tx.send(change);
+            })
+            .unwrap();
+
+        fs::write(space_dir.path().join("CLAUDE.md"), "# Edited elsewhere\n").unwrap();
+
+        let change = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(change.space_id, "space-1");
+        assert_eq!(change.file_path, "CLAUDE.md");
+        assert_eq!(change.kind, FileChangeKind::Created);
+
+        // The create and the write were debounced into one event
+        std::thread::sleep(FILE_WATCH_DEBOUNCE * 2);
+        assert!(rx.try_recv().is_err());
+
+        watchers.unwatch("space-1").unwrap();
+        fs::write(space_dir.path().join("notes.md"), "after stop\n").unwrap();
+        assert!(rx.recv_timeout(FILE_WATCH_DEBOUNCE * 4).is_err());
+
+        assert!(watchers.unwatch("space-1").is_err());
+    }
+
+    #[test]
+    fn test_file_change_kind() {
+        use notify::event::{CreateKind, DataChange, ModifyKind, RemoveKind, RenameMode};
+        use notify::EventKind;
+
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("CLAUDE.md");
+        fs::write(&existing, "").unwrap();
+        let gone = dir.path().join("gone.md");
+
+        assert_eq!(
+            file_change_kind(&EventKind::Create(CreateKind::File), &existing),
+            Some(FileChangeKind::Created)
+        );
+        assert_eq!(
+            file_change_kind(
+                &EventKind::Modify(ModifyKind::Data(DataChange::Content)),
+                &existing
+            ),
+            Some(FileChangeKind::Modified)
+        );
+        assert_eq!(
+            file_change_kind(&EventKind::Remove(RemoveKind::File), &gone),
+            Some(FileChangeKind::Deleted)
+        );
+        assert_eq!(
+            file_change_kind(
+                &EventKind::Modify(ModifyKind::Name(RenameMode::To)),
+                &existing
+            ),
+            Some(FileChangeKind::Modified)
+        );
+        assert_eq!(
+            file_change_kind(
+                &EventKind::Modify(ModifyKind::Name(RenameMode::From)),
+                &gone
+            ),
+            Some(FileChangeKind::Deleted)
+        );
+        assert_eq!(
+            file_change_kind(
+                &EventKind::Access(notify::event::AccessKind::Any),
+                &existing
+            ),
+            None
+        );
+
+        assert!(is_ignored_change(Path::new("/space/.tmpA1b2C3")));
+        assert!(is_ignored_change(Path::new("/space/.space-metadata.json")));
+        assert!(!is_ignored_change(Path::new("/space/CLAUDE.md")));
+        // User files that merely end in .tmp are still reported
+        assert!(!is_ignored_change(Path::new("/space/draft.tmp")));
+    }
+
+    fn write_template(space_dir: &Path, name: &str, content: &str) {
+        let templates_dir = space_dir.join(PROMPT_TEMPLATES_DIR);
+        fs::create_dir_all(&templates_dir).unwrap();
+        fs::write(templates_dir.join(format!("{}.md", name)), content).unwrap();
+    }
+
+    #[test]
+    fn test_list_prompt_templates() {
+        let space_dir = tempfile::tempdir().unwrap();
+        assert!(list_prompt_templates_in(space_dir.path())
+            .unwrap()
+            .is_empty());
+
+        write_template(
+            space_dir.path(),
+            "reviewer",
+            "---\nsystem_prompt: true\n---\n# Reviewer\n\nYou review drafts\nfor {{ audience }}.\n\nBe direct.",
+        );
+        write_template(space_dir.path(), "summary", "Summarise {{topic}}.");
+        fs::write(
+            space_dir
+                .path()
+                .join(PROMPT_TEMPLATES_DIR)
+                .join("notes.txt"),
+            "not a template",
+        )
+        .unwrap();
+
+        let templates = list_prompt_templates_in(space_dir.path()).unwrap();
+
+        assert_eq!(templates.len(), 2);
+        assert_eq!(templates[0].name, "reviewer");
+        assert!(templates[0].is_system_prompt);
+        assert_eq!(
+            templates[0].description,
+            "You review drafts for {{ audience }}."
+        );
+        assert!(templates[0].content.starts_with("# Reviewer"));
+        assert_eq!(templates[1].name, "summary");
+        assert!(!templates[1].is_system_prompt);
+        assert_eq!(templates[1].description, "Summarise {{topic}}.");
+    }
+
+    #[test]
+    fn test_prompt_template_system_prompt_flag() {
+        let flagged = parse_prompt_template("a", "---\nis_system_prompt: true\n---\nBody");
+        assert!(flagged.is_system_prompt);
+        assert_eq!(flagged.content, "Body");
+
+        let unflagged = parse_prompt_template("b", "---\nsystem_prompt: false\n---\nBody");
+        assert!(!unflagged.is_system_prompt);
+
+        let empty = parse_prompt_template("c", "---\n---\nBody");
+        assert!(!empty.is_system_prompt);
+        assert_eq!(empty.content, "Body");
+
+        // Invalid front-matter is kept as part of the template
+        let invalid = parse_prompt_template("d", "---\nsystem_prompt: [\n---\nBody");
+        assert!(!invalid.is_system_prompt);
+        assert!(invalid.content.starts_with("---"));
+    }
+
+    #[test]
+    fn test_apply_prompt_template_variables() {
+        let space_dir = tempfile::tempdir().unwrap();
+        write_template(
+            space_dir.path(),
+            "letter",
+            "Dear {{ name }},\n\nThanks for {{gift}}. See you in {{ city }}, {{ name }}.",
+        );
+
+        let variables = HashMap::from([
+            ("name".to_string(), "Ada".to_string()),
+            ("gift".to_string(), "the book".to_string()),
+        ]);
+        let expanded = apply_prompt_template_in(space_dir.path(), "letter", &variables).unwrap();
+
+        // Missing variables keep their placeholder
+        assert_eq!(
+            expanded,
+            "Dear Ada,\n\nThanks for the book. See you in {{ city }}, Ada."
+        );
+    }
+
+    #[test]
+    fn test_apply_prompt_template_rejects_bad_names() {
+        let space_dir = tempfile::tempdir().unwrap();
+        let variables = HashMap::new();
+
+        assert!(apply_prompt_template_in(space_dir.path(), "missing", &variables).is_err());
+        assert!(apply_prompt_template_in(space_dir.path(), "../CLAUDE", &variables).is_err());
+        assert!(apply_prompt_template_in(space_dir.path(), "", &variables).is_err());
+    }
+
+    fn batch_test_spaces() -> tempfile::TempDir {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        for (id, content) in [("alpha", "12345"), ("beta", "1234567890")] {
+            let space_dir = spaces_dir.path().join(id);
+            fs::create_dir_all(space_dir.join("notes")).unwrap();
+            fs::write(space_dir.join("CLAUDE.md"), content).unwrap();
+            fs::write(space_dir.join("notes").join("todo.md"), "abc").unwrap();
+        }
+        spaces_dir
+    }
+
+    #[test]
+    fn test_delete_spaces_batch_dry_run() {
+        let spaces_dir = batch_test_spaces();
+        let ids = vec!["alpha".to_string(), "beta".to_string()];
+        let mut cleaned = Vec::new();
+
+        let result = delete_spaces_batch_in(spaces_dir.path(), &ids, true, |id| {
+            cleaned.push(id.to_string())
+        })
+        .unwrap();
+
+        assert_eq!(result.deleted, ids);
+        assert!(result.failed.is_empty());
+        assert_eq!(result.total_bytes_freed, (5 + 3) + (10 + 3));
+        // Nothing was touched
+        assert!(cleaned.is_empty());
+        assert!(spaces_dir.path().join("alpha").is_dir());
+        assert!(spaces_dir.path().join("beta").is_dir());
+    }
+
+    #[test]
+    fn test_delete_spaces_batch_continues_past_failures() {
+        let spaces_dir = batch_test_spaces();
+        let ids = vec![
+            "alpha".to_string(),
+            "missing".to_string(),
+            "../beta".to_string(),
+            "alpha/notes".to_string(),
+            "beta".to_string(),
+        ];
+        let mut cleaned = Vec::new();
+
+        let result = delete_spaces_batch_in(spaces_dir.path(), &ids, false, |id| {
+            cleaned.push(id.to_string())
+        })
+        .unwrap();
+
+        assert_eq!(
+            result.deleted,
+            vec!["alpha".to_string(), "beta".to_string()]
+        );
+        assert_eq!(
+            result.failed,
+            vec![
+                (
+                    "missing".to_string(),
+                    "Space directory not found".to_string()
+                ),
+                (
+                    "../beta".to_string(),
+                    "Invalid space id: ../beta".to_string()
+                ),
+                (
+                    "alpha/notes".to_string(),
+                    "Invalid space id: alpha/notes".to_string()
+                ),
+            ]
+        );
+        assert_eq!(result.total_bytes_freed, 8 + 13);
+        assert_eq!(cleaned, vec!["alpha".to_string(), "beta".to_string()]);
+        assert!(!spaces_dir.path().join("alpha").exists());
+        assert!(!spaces_dir.path().join("beta").exists());
+    }
+
+    #[test]
+    fn test_delete_spaces_batch_size_capped() {
+        let spaces_dir = batch_test_spaces();
+        let ids: Vec<String> = (0..=MAX_BATCH_DELETE)
+            .map(|i| format!("space-{}", i))
+            .collect();
+
+        let error = delete_spaces_batch_in(spaces_dir.path(), &ids, true, |_| {}).unwrap_err();
+        assert!(
+            matches!(error, ThinkingSpaceError::ValidationFailed { ref field, .. } if field == "ids")
+        );
+        assert!(error.to_string().contains("50"));
+    }
+
+    #[test]
+    fn test_rename_claude_md_heading() {
+        assert_eq!(
+            rename_claude_md_heading("# Old\n\n## Purpose\nOld plans", "Old", "New"),
+            Some("# New\n\n## Purpose\nOld plans".to_string())
+        );
+        // A heading the user rewrote is left alone
+        assert_eq!(rename_claude_md_heading("# My notes\n", "Old", "New"), None);
+        assert_eq!(rename_claude_md_heading("No heading", "Old", "New"), None);
+    }
+
+    #[test]
+    fn test_rename_space_updates_metadata_and_claude_md() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let conn =
+            crate::conversations::get_connection_at(&temp_dir.path().join("conversations.db"))
+                .unwrap();
+        let space = create_test_space(temp_dir.path(), "Old Name");
+        let space_dir = temp_dir.path().join(&space.id);
+
+        let renamed = rename_space_in(temp_dir.path(), &conn, &space.id, "New Name").unwrap();
+
+        assert_eq!(renamed.name, "New Name");
+        let metadata: Space =
+            serde_json::from_str(&fs::read_to_string(space_dir.join(METADATA_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(metadata.name, "New Name");
+        let claude_md = fs::read_to_string(space_dir.join("CLAUDE.md")).unwrap();
+        assert!(claude_md.starts_with("# New Name\n"));
+        assert!(claude_md.contains("## Purpose"));
+        // The previous version is kept in the history
+        assert_eq!(list_claude_md_history_in(&space_dir).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rename_space_keeps_edited_claude_md() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let conn =
+            crate::conversations::get_connection_at(&temp_dir.path().join("conversations.db"))
+                .unwrap();
+        let space = create_test_space(temp_dir.path(), "Old Name");
+        let space_dir = temp_dir.path().join(&space.id);
+        fs::write(space_dir.join("CLAUDE.md"), "# Research log\nOld Name").unwrap();
+
+        rename_space_in(temp_dir.path(), &conn, &space.id, "New Name").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(space_dir.join("CLAUDE.md")).unwrap(),
+            "# Research log\nOld Name"
+        );
+    }
+
+    #[test]
+    fn test_rename_space_syncs_conversation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let conn =
+            crate::conversations::get_connection_at(&temp_dir.path().join("conversations.db"))
+                .unwrap();
+        let space = create_test_space(temp_dir.path(), "Old Name");
+        let other = create_test_space(temp_dir.path(), "Other");
+        for (id, name) in [(&space.id, "Old Name"), (&other.id, "Other")] {
+            crate::conversations::save_conversation_internal(
+                &conn,
+                id,
+                name,
+                &[crate::conversations::Message {
+                    id: "1".to_string(),
+                    role: "user".to_string(),
+                    content: "Hi".to_string(),
+                    timestamp: 100,
+                    metadata: serde_json::json!({}),
+                }],
+            )
+            .unwrap();
+        }
+
+        rename_space_in(temp_dir.path(), &conn, &space.id, "New Name").unwrap();
+
+        let name = |id: &str| -> String {
+            conn.query_row(
+                "SELECT space_name FROM conversations WHERE space_id = ?1",
+                rusqlite::params![id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(name(&space.id), "New Name");
+        assert_eq!(name(&other.id), "Other");
+    }
+
+    #[test]
+    fn test_rename_space_validates_name() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let conn =
+            crate::conversations::get_connection_at(&temp_dir.path().join("conversations.db"))
+                .unwrap();
+        let space = create_test_space(temp_dir.path(), "Old Name");
+
+        for name in [
+            "".to_string(),
+            "   ".to_string(),
+            "a/b".to_string(),
+            "a\\b".to_string(),
+            "x".repeat(MAX_SPACE_NAME_CHARS + 1),
+        ] {
+            assert!(
+                rename_space_in(temp_dir.path(), &conn, &space.id, &name).is_err(),
+                "accepted {:?}",
+                name
+            );
+        }
+        assert!(rename_space_in(
+            temp_dir.path(),
+            &conn,
+            &space.id,
+            &"x".repeat(MAX_SPACE_NAME_CHARS)
+        )
+        .is_ok());
+        assert!(rename_space_in(temp_dir.path(), &conn, "missing", "New Name").is_err());
+    }
+
+    fn community_template(id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "name": "Reading List",
+            "description": "Track books",
+            "author": "someone",
+            "tags": ["books"],
+            "content": "# {name}\n\nBooks to read",
+        })
+    }
+
+    #[tokio::test]
+    async fn test_fetch_community_templates_skips_malformed() {
+        let mut server = mockito::Server::new_async().await;
+        let body = serde_json::json!([
+            community_template("reading-list"),
+            // Missing fields, a bad id, a built-in id and a duplicate
+            { "id": "no-content", "name": "Broken" },
+            community_template("../escape"),
+            community_template("custom"),
+            community_template("reading-list"),
+            { "id": "empty", "name": "Empty", "description": "", "author": "a", "content": " " },
+        ]);
+        let mock = server
+            .mock("GET", "/templates.json")
+            .with_body(body.to_string())
+            .create_async()
+            .await;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_path = temp_dir.path().join(TEMPLATE_CACHE_FILE);
+
+        let templates = fetch_community_templates_with(
+            &cache_path,
+            &format!("{}/templates.json", server.url()),
+            1_000,
+        )
+        .await
+        .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].id, "reading-list");
+        assert_eq!(templates[0].tags, vec!["books"]);
+        assert_eq!(
+            read_template_cache(&cache_path).unwrap().templates,
+            templates
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_community_templates_cache_ttl() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/templates.json")
+            .with_body(serde_json::json!([community_template("reading-list")]).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_path = temp_dir.path().join(TEMPLATE_CACHE_FILE);
+        let url = format!("{}/templates.json", server.url());
+
+        let fetched_at = 1_000;
+        fetch_community_templates_with(&cache_path, &url, fetched_at)
+            .await
+            .unwrap();
+        // Within the TTL the cache answers
+        let cached = fetch_community_templates_with(
+            &cache_path,
+            &url,
+            fetched_at + TEMPLATE_CACHE_TTL_MS - 1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(cached.len(), 1);
+        // Once it expires the server is asked again
+        fetch_community_templates_with(&cache_path, &url, fetched_at + TEMPLATE_CACHE_TTL_MS)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_create_space_from_community_template() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let templates = vec![validate_space_template(community_template("reading-list")).unwrap()];
+
+        let space = create_space_in(
+            spaces_dir.path(),
+            CreateSpaceRequest {
+                name: "Books".to_string(),
+                template: "reading-list".to_string(),
+            },
+            &templates,
+        )
+        .unwrap();
+
+        let claude_md =
+            fs::read_to_string(spaces_dir.path().join(&space.id).join("CLAUDE.md")).unwrap();
+        assert_eq!(claude_md, "# Books\n\nBooks to read");
+        assert_eq!(space.template.as_deref(), Some("reading-list"));
+    }
+
+    #[test]
+    fn test_unknown_template_falls_back_to_built_in() {
+        // Empty cache: the community id is unknown
+        assert_eq!(
+            resolve_template_content("reading-list", &[]),
+            get_template_content("quick-start")
+        );
+        assert_eq!(
+            resolve_template_content("custom", &[]),
+            get_template_content("custom")
+        );
+
+        let request: CreateSpaceRequest =
+            serde_json::from_str(r#"{"name": "Books", "template_id": "reading-list"}"#).unwrap();
+        assert_eq!(request.template, "reading-list");
+    }
+
+    #[test]
+    fn test_file_diff_preview_insertion() {
+        let preview = file_diff_preview("notes.md", "one\nthree\n", "one\ntwo\nthree\nfour\n");
+
+        assert_eq!(preview.added_lines, 2);
+        assert_eq!(preview.removed_lines, 0);
+        assert_eq!(preview.changed_lines, 0);
+        assert_eq!(preview.old_size, 10);
+        assert_eq!(preview.new_size, 19);
+        assert!(preview.unified_diff.contains("+two\n"));
+        assert!(preview.unified_diff.contains("+++ b/notes.md"));
+    }
+
+    #[test]
+    fn test_file_diff_preview_deletion() {
+        let preview = file_diff_preview("notes.md", "one\ntwo\nthree\n", "one\n");
+
+        assert_eq!(preview.added_lines, 0);
+        assert_eq!(preview.removed_lines, 2);
+        assert_eq!(preview.changed_lines, 0);
+        assert!(preview.unified_diff.contains("-three\n"));
+    }
+
+    #[test]
+    fn test_file_diff_preview_mixed() {
+        let preview = file_diff_preview(
+            "notes.md",
+            "one\ntwo\nthree\nfour\n",
+            "one\n2\nthree\nfour\nfive\n",
+        );
+
+        assert_eq!(preview.added_lines, 2);
+        assert_eq!(preview.removed_lines, 1);
+        assert_eq!(preview.changed_lines, 1);
+        assert!(preview.unified_diff.contains("-two\n+2\n"));
+    }
+
+    #[test]
+    fn test_preview_file_write_new_file() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Preview");
+
+        let preview =
+            preview_file_write_in(spaces_dir.path(), &space.id, "notes/new.md", "a\nb\n").unwrap();
+        assert_eq!(preview.old_size, 0);
+        assert_eq!(preview.new_size, 4);
+        assert_eq!((preview.added_lines, preview.removed_lines), (2, 0));
+        // Previewing doesn't write anything
+        assert!(!spaces_dir.path().join(&space.id).join("notes").exists());
+
+        let claude_md =
+            fs::read_to_string(spaces_dir.path().join(&space.id).join("CLAUDE.md")).unwrap();
+        let preview =
+            preview_file_write_in(spaces_dir.path(), &space.id, "CLAUDE.md", &claude_md).unwrap();
+        assert_eq!(preview.added_lines + preview.removed_lines, 0);
+        assert!(preview.unified_diff.is_empty());
+
+        assert!(
+            preview_file_write_in(spaces_dir.path(), &space.id, ".space-metadata.json", "{}")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_failures_report_their_variant() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+        let space = create_test_space(spaces_dir.path(), "Errors");
+        let space_dir = spaces_dir.path().join(&space.id);
+
+        assert_eq!(
+            existing_space_dir(spaces_dir.path(), "missing").unwrap_err(),
+            ThinkingSpaceError::not_found("Space directory")
+        );
+        assert!(matches!(
+            resolve_space_file(&space_dir, "../outside.md"),
+            Err(ThinkingSpaceError::Security { .. })
+        ));
+        assert!(matches!(
+            validate_space_name("a/b"),
+            Err(ThinkingSpaceError::ValidationFailed { field, .. }) if field == "name"
+        ));
+        assert!(matches!(
+            restore_claude_md_in(&space_dir, 42),
+            Err(ThinkingSpaceError::Io { path, .. }) if path.ends_with("42.md")
+        ));
+
+        fs::write(space_dir.join(METADATA_FILE), "not json").unwrap();
+        assert!(matches!(
+            update_space_metadata_in(spaces_dir.path(), &space.id, |space| space.pinned = true),
+            Err(ThinkingSpaceError::Serialization(_))
+        ));
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    #[test]
+    fn test_terminal_launches_linux() {
+        let dir = Path::new("/spaces/abc");
+        let programs = |preferred, env_terminal| {
+            terminal_launches(dir, preferred, env_terminal)
+                .into_iter()
+                .map(|launch| {
+                    assert!(launch.args.is_empty());
+                    launch.program
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(programs(None, None), ["x-terminal-emulator", "xterm"]);
+        assert_eq!(
+            programs(None, Some("alacritty")),
+            ["alacritty", "x-terminal-emulator", "xterm"]
+        );
+        assert_eq!(
+            programs(Some("kitty"), Some("alacritty")),
+            ["kitty", "alacritty", "x-terminal-emulator", "xterm"]
+        );
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_terminal_launches_macos() {
+        let dir = Path::new("/spaces/abc");
+
+        assert_eq!(
+            terminal_launches(dir, None, Some("ignored")),
+            [TerminalLaunch::new(
+                "open",
+                &["-a", "Terminal", "/spaces/abc"]
+            )]
+        );
+        assert_eq!(
+            terminal_launches(dir, Some("iTerm"), None),
+            [
+                TerminalLaunch::new("open", &["-a", "iTerm", "/spaces/abc"]),
+                TerminalLaunch::new("open", &["-a", "Terminal", "/spaces/abc"]),
+            ]
+        );
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_terminal_launches_windows() {
+        let dir = Path::new(r"C:\spaces\abc");
+
+        assert_eq!(
+            terminal_launches(dir, None, None),
+            [
+                TerminalLaunch::new("wt", &["-d", r"C:\spaces\abc"]),
+                TerminalLaunch::new("cmd", &["/c", "start", "cmd"]),
+            ]
+        );
+        assert_eq!(
+            terminal_launches(dir, Some("alacritty"), None)[0],
+            TerminalLaunch::new("alacritty", &[])
+        );
+    }
+
+    #[test]
+    fn test_open_terminal_requires_existing_space() {
+        let spaces_dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(
+            open_terminal_in(spaces_dir.path(), "missing", Some("xterm")),
+            Err(ThinkingSpaceError::not_found("Space directory"))
+        );
     }
 }
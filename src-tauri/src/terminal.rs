@@ -3,63 +3,246 @@
 
 use agent_client_protocol_schema::TerminalId;
 use parking_lot::Mutex;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::{Child, Command};
 
+/// How long an exited terminal is kept after its output was last fetched
+const EXITED_TERMINAL_RETENTION: Duration = Duration::from_secs(60);
+
+/// Output kept per terminal when the agent doesn't set a byte limit
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 1_000_000;
+
+/// Lines kept per terminal before the oldest are dropped
+const DEFAULT_MAX_OUTPUT_LINES: usize = 10_000;
+
 /// Represents a single terminal instance
 pub struct Terminal {
     pub id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
     pub process: Option<Child>,
-    pub output: String,
+    /// Retained output, one element per line (oldest first)
+    pub output: VecDeque<String>,
+    /// Bytes held in `output`, counting each line's newline
+    pub output_bytes: usize,
+    /// Lines dropped from the front since the terminal started
+    pub truncated_lines: usize,
     pub exit_status: Option<i32>,
     pub max_output_bytes: usize,
+    pub max_lines: usize,
+    /// When the output was last read (used to prune exited terminals)
+    pub last_fetched_at: Instant,
+}
+
+/// Serializable view of a terminal, used to restore the frontend display
+/// after the ACP adapter reconnects without re-running the command
+#[derive(Debug, Clone, Serialize)]
+pub struct TerminalSnapshot {
+    pub id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub accumulated_output: String,
+    pub exit_status: Option<i32>,
+    pub cwd: Option<String>,
+}
+
+/// A slice of a terminal's retained output
+#[derive(Debug, Clone, Serialize)]
+pub struct TerminalOutputRange {
+    pub lines: Vec<String>,
+    /// Lines currently retained (line indexes run from 0 to this)
+    pub total_lines: usize,
+    /// Lines dropped from the front since the terminal started
+    pub truncated_lines: usize,
 }
 
 impl Terminal {
-    fn new(id: String, process: Child, max_output_bytes: usize) -> Self {
+    fn new(
+        id: String,
+        command: String,
+        args: Vec<String>,
+        cwd: Option<PathBuf>,
+        process: Child,
+        max_output_bytes: usize,
+    ) -> Self {
         Self {
             id,
+            command,
+            args,
+            cwd,
             process: Some(process),
-            output: String::new(),
+            output: VecDeque::new(),
+            output_bytes: 0,
+            truncated_lines: 0,
             exit_status: None,
             max_output_bytes,
+            max_lines: DEFAULT_MAX_OUTPUT_LINES,
+            last_fetched_at: Instant::now(),
+        }
+    }
+
+    fn snapshot(&self) -> TerminalSnapshot {
+        TerminalSnapshot {
+            id: self.id.clone(),
+            command: self.command.clone(),
+            args: self.args.clone(),
+            accumulated_output: self.output_text(),
+            exit_status: self.exit_status,
+            cwd: self.cwd.as_ref().map(|p| p.to_string_lossy().to_string()),
+        }
+    }
+
+    /// Append a line of output, dropping the oldest lines once max_lines or
+    /// max_output_bytes is exceeded. Returns the number of lines dropped.
+    fn append_line(&mut self, line: &str) -> usize {
+        self.output_bytes += line.len() + 1;
+        self.output.push_back(line.to_string());
+
+        let mut dropped = 0;
+        while self.output.len() > 1
+            && (self.output.len() > self.max_lines || self.output_bytes > self.max_output_bytes)
+        {
+            if let Some(oldest) = self.output.pop_front() {
+                self.output_bytes -= oldest.len() + 1;
+                dropped += 1;
+            }
+        }
+
+        // A single line longer than the byte limit keeps only its tail
+        if self.output_bytes > self.max_output_bytes {
+            if let Some(last) = self.output.back_mut() {
+                let mut truncate_at = self.output_bytes - self.max_output_bytes;
+                while truncate_at < last.len() && !last.is_char_boundary(truncate_at) {
+                    truncate_at += 1;
+                }
+                last.drain(..truncate_at);
+                self.output_bytes = last.len() + 1;
+            }
+        }
+
+        self.truncated_lines += dropped;
+        dropped
+    }
+
+    /// Retained output as a single newline-terminated string
+    fn output_text(&self) -> String {
+        let mut text = String::with_capacity(self.output_bytes);
+        for line in &self.output {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Retained lines from start_line up to (not including) end_line
+    /// The end is clamped to the number of retained lines
+    fn output_range(
+        &self,
+        start_line: usize,
+        end_line: usize,
+    ) -> Result<TerminalOutputRange, String> {
+        if start_line > end_line {
+            return Err(format!(
+                "Invalid line range: start {} is after end {}",
+                start_line, end_line
+            ));
         }
+
+        let total_lines = self.output.len();
+        let end = end_line.min(total_lines);
+        let lines = self
+            .output
+            .range(start_line.min(end)..end)
+            .cloned()
+            .collect();
+
+        Ok(TerminalOutputRange {
+            lines,
+            total_lines,
+            truncated_lines: self.truncated_lines,
+        })
     }
+}
+
+/// A named set of terminals whose output can be read together
+#[derive(Debug, Clone, Serialize)]
+pub struct TerminalGroup {
+    pub group_id: String,
+    pub label: String,
+    pub terminal_ids: Vec<String>,
+    pub created_at: i64,
+}
+
+/// Allow/deny rules for commands the agent may run in a terminal
+/// Commands are matched by basename, so "/usr/bin/rm" matches "rm"
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TerminalPolicy {
+    /// When set, only these commands may run
+    #[serde(default)]
+    pub allowed_commands: Option<Vec<String>>,
+    /// Commands that may never run (checked before the allow list)
+    #[serde(default)]
+    pub denied_commands: Vec<String>,
+}
 
-    /// Append output while respecting max_output_bytes limit
-    /// Truncates from the beginning if limit exceeded
-    fn append_output(&mut self, new_output: &str) {
-        self.output.push_str(new_output);
+impl TerminalPolicy {
+    /// Check whether a command may be spawned under this policy
+    pub fn check_command(&self, command: &str) -> Result<(), String> {
+        let basename = Path::new(command)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(command);
 
-        // Truncate from beginning if exceeds limit
-        if self.output.len() > self.max_output_bytes {
-            let excess = self.output.len() - self.max_output_bytes;
-            // Find a character boundary to truncate at
-            let mut truncate_at = excess;
-            while truncate_at < self.output.len() && !self.output.is_char_boundary(truncate_at) {
-                truncate_at += 1;
+        if self.denied_commands.iter().any(|c| c == basename) {
+            return Err("command denied by policy".to_string());
+        }
+
+        if let Some(ref allowed) = self.allowed_commands {
+            if !allowed.iter().any(|c| c == basename) {
+                return Err("command denied by policy".to_string());
             }
-            self.output = self.output[truncate_at..].to_string();
         }
+
+        Ok(())
     }
 }
 
 /// Manages multiple terminal instances
 pub struct TerminalManager {
     terminals: Arc<Mutex<HashMap<String, Terminal>>>,
+    groups: Mutex<HashMap<String, TerminalGroup>>,
+    policy: Mutex<TerminalPolicy>,
+    /// Used to tell the frontend when output is dropped
+    app_handle: Arc<Mutex<Option<AppHandle>>>,
 }
 
 impl TerminalManager {
     pub fn new() -> Self {
         Self {
             terminals: Arc::new(Mutex::new(HashMap::new())),
+            groups: Mutex::new(HashMap::new()),
+            policy: Mutex::new(TerminalPolicy::default()),
+            app_handle: Arc::new(Mutex::new(None)),
         }
     }
 
+    pub fn set_app_handle(&self, handle: AppHandle) {
+        *self.app_handle.lock() = Some(handle);
+    }
+
+    /// Replace the command allow/deny policy used for new terminals
+    pub fn set_policy(&self, policy: TerminalPolicy) {
+        *self.policy.lock() = policy;
+    }
+
     /// Create a new terminal and start capturing output
     pub async fn create_terminal(
         &self,
@@ -69,6 +252,11 @@ impl TerminalManager {
         cwd: Option<PathBuf>,
         max_output_bytes: Option<usize>,
     ) -> Result<TerminalId, String> {
+        if let Err(e) = self.policy.lock().check_command(&command) {
+            println!("[TERMINAL] Refusing to run '{}': {}", command, e);
+            return Err(e);
+        }
+
         // Generate unique ID
         let terminal_id = uuid::Uuid::new_v4().to_string();
 
@@ -90,7 +278,7 @@ impl TerminalManager {
         }
 
         // Set working directory
-        if let Some(cwd) = cwd {
+        if let Some(ref cwd) = cwd {
             cmd.current_dir(cwd);
         }
 
@@ -112,8 +300,11 @@ impl TerminalManager {
         // Store terminal
         let terminal = Terminal::new(
             terminal_id.clone(),
+            command,
+            args,
+            cwd,
             child,
-            max_output_bytes.unwrap_or(1_000_000), // 1MB default
+            max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES),
         );
 
         self.terminals.lock().insert(terminal_id.clone(), terminal);
@@ -126,12 +317,61 @@ impl TerminalManager {
 
     /// Get current output and exit status for a terminal
     pub fn get_output(&self, terminal_id: &str) -> Result<(String, Option<i32>), String> {
-        let terminals = self.terminals.lock();
+        let mut terminals = self.terminals.lock();
+        let terminal = terminals
+            .get_mut(terminal_id)
+            .ok_or_else(|| "Terminal not found".to_string())?;
+
+        terminal.last_fetched_at = Instant::now();
+
+        Ok((terminal.output_text(), terminal.exit_status))
+    }
+
+    /// Retained output lines in [start_line, end_line) for a terminal
+    pub fn get_output_range(
+        &self,
+        terminal_id: &str,
+        start_line: usize,
+        end_line: usize,
+    ) -> Result<TerminalOutputRange, String> {
+        let mut terminals = self.terminals.lock();
         let terminal = terminals
-            .get(terminal_id)
+            .get_mut(terminal_id)
             .ok_or_else(|| "Terminal not found".to_string())?;
 
-        Ok((terminal.output.clone(), terminal.exit_status))
+        terminal.last_fetched_at = Instant::now();
+
+        terminal.output_range(start_line, end_line)
+    }
+
+    /// Snapshot all terminals (output, exit status, command) keyed by terminal ID
+    pub fn snapshot(&self) -> HashMap<String, TerminalSnapshot> {
+        self.terminals
+            .lock()
+            .iter()
+            .map(|(id, terminal)| (id.clone(), terminal.snapshot()))
+            .collect()
+    }
+
+    /// Remove exited terminals whose output hasn't been fetched in the last 60 seconds
+    /// Returns the number of terminals removed
+    pub fn prune_exited_terminals(&self) -> usize {
+        self.prune_exited_terminals_older_than(EXITED_TERMINAL_RETENTION)
+    }
+
+    fn prune_exited_terminals_older_than(&self, retention: Duration) -> usize {
+        let mut terminals = self.terminals.lock();
+        let before = terminals.len();
+
+        terminals.retain(|_, terminal| {
+            terminal.exit_status.is_none() || terminal.last_fetched_at.elapsed() < retention
+        });
+
+        let pruned = before - terminals.len();
+        if pruned > 0 {
+            println!("[TERMINAL] Pruned {} exited terminal(s)", pruned);
+        }
+        pruned
     }
 
     /// Kill a running terminal process
@@ -156,9 +396,71 @@ impl TerminalManager {
 
         let mut terminals = self.terminals.lock();
         terminals.remove(terminal_id);
+        drop(terminals);
+
+        for group in self.groups.lock().values_mut() {
+            group.terminal_ids.retain(|id| id != terminal_id);
+        }
         Ok(())
     }
 
+    /// Create an empty terminal group, returning its ID
+    pub fn create_group(&self, label: String) -> Result<String, String> {
+        if label.trim().is_empty() {
+            return Err("Group label cannot be empty".to_string());
+        }
+
+        let group_id = uuid::Uuid::new_v4().to_string();
+        println!("[TERMINAL] Creating group {}: {}", group_id, label);
+
+        self.groups.lock().insert(
+            group_id.clone(),
+            TerminalGroup {
+                group_id: group_id.clone(),
+                label,
+                terminal_ids: Vec::new(),
+                created_at: chrono::Utc::now().timestamp(),
+            },
+        );
+        Ok(group_id)
+    }
+
+    /// Add a managed terminal to a group (a terminal may belong to several groups)
+    pub fn add_to_group(&self, group_id: &str, terminal_id: &str) -> Result<(), String> {
+        if !self.terminals.lock().contains_key(terminal_id) {
+            return Err("Terminal not found".to_string());
+        }
+
+        let mut groups = self.groups.lock();
+        let group = groups
+            .get_mut(group_id)
+            .ok_or_else(|| "Terminal group not found".to_string())?;
+
+        if !group.terminal_ids.iter().any(|id| id == terminal_id) {
+            group.terminal_ids.push(terminal_id.to_string());
+        }
+        Ok(())
+    }
+
+    /// Accumulated output of each terminal in a group, keyed by terminal ID
+    pub fn group_output(&self, group_id: &str) -> Result<HashMap<String, String>, String> {
+        let terminal_ids = self
+            .groups
+            .lock()
+            .get(group_id)
+            .map(|group| group.terminal_ids.clone())
+            .ok_or_else(|| "Terminal group not found".to_string())?;
+
+        let terminals = self.terminals.lock();
+        Ok(terminal_ids
+            .into_iter()
+            .filter_map(|id| {
+                let output = terminals.get(&id)?.output_text();
+                Some((id, output))
+            })
+            .collect())
+    }
+
     /// Wait for a terminal to exit and return its exit status
     pub async fn wait_for_exit(&self, terminal_id: &str) -> Result<i32, String> {
         println!("[TERMINAL] Waiting for terminal to exit: {}", terminal_id);
@@ -181,6 +483,43 @@ impl TerminalManager {
         }
     }
 
+    /// Append each line from a process stream to the terminal's output
+    async fn capture_lines<R>(
+        terminals: Arc<Mutex<HashMap<String, Terminal>>>,
+        app_handle: Arc<Mutex<Option<AppHandle>>>,
+        terminal_id: String,
+        stream: R,
+        stream_name: &'static str,
+    ) where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        let mut reader = BufReader::new(stream).lines();
+
+        while let Ok(Some(line)) = reader.next_line().await {
+            let dropped = match terminals.lock().get_mut(&terminal_id) {
+                Some(terminal) => terminal.append_line(&line),
+                None => 0,
+            };
+
+            if dropped > 0 {
+                if let Some(ref handle) = *app_handle.lock() {
+                    let _ = handle.emit(
+                        "terminal-output-truncated",
+                        serde_json::json!({
+                            "terminalId": terminal_id,
+                            "linesDropped": dropped,
+                        }),
+                    );
+                }
+            }
+        }
+
+        println!(
+            "[TERMINAL] {} capture ended for: {}",
+            stream_name, terminal_id
+        );
+    }
+
     /// Start async tasks to capture stdout and stderr
     fn start_output_capture(
         &self,
@@ -188,48 +527,26 @@ impl TerminalManager {
         stdout: tokio::process::ChildStdout,
         stderr: tokio::process::ChildStderr,
     ) {
-        let terminals_stdout = self.terminals.clone();
-        let terminals_stderr = self.terminals.clone();
         let terminals_exit = self.terminals.clone();
-        let terminal_id_stdout = terminal_id.clone();
-        let terminal_id_stderr = terminal_id.clone();
         let terminal_id_exit = terminal_id.clone();
 
         // Capture stdout
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stdout).lines();
-
-            while let Ok(Some(line)) = reader.next_line().await {
-                let mut terminals = terminals_stdout.lock();
-                if let Some(terminal) = terminals.get_mut(&terminal_id_stdout) {
-                    terminal.append_output(&line);
-                    terminal.append_output("\n");
-                }
-            }
-
-            println!(
-                "[TERMINAL] Stdout capture ended for: {}",
-                terminal_id_stdout
-            );
-        });
+        tokio::spawn(Self::capture_lines(
+            self.terminals.clone(),
+            self.app_handle.clone(),
+            terminal_id.clone(),
+            stdout,
+            "Stdout",
+        ));
 
         // Capture stderr
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stderr).lines();
-
-            while let Ok(Some(line)) = reader.next_line().await {
-                let mut terminals = terminals_stderr.lock();
-                if let Some(terminal) = terminals.get_mut(&terminal_id_stderr) {
-                    terminal.append_output(&line);
-                    terminal.append_output("\n");
-                }
-            }
-
-            println!(
-                "[TERMINAL] Stderr capture ended for: {}",
-                terminal_id_stderr
-            );
-        });
+        tokio::spawn(Self::capture_lines(
+            self.terminals.clone(),
+            self.app_handle.clone(),
+            terminal_id,
+            stderr,
+            "Stderr",
+        ));
 
         // Wait for process exit and capture exit status
         tokio::spawn(async move {
@@ -338,4 +655,286 @@ mod tests {
         // Output should be truncated to <= 100 bytes
         assert!(output.len() <= 100);
     }
+
+    #[tokio::test]
+    async fn test_snapshot_includes_command_and_output() {
+        let manager = TerminalManager::new();
+
+        let terminal_id = manager
+            .create_terminal(
+                "echo".to_string(),
+                vec!["snapshot".to_string()],
+                vec![],
+                Some(std::env::temp_dir()),
+                None,
+            )
+            .await
+            .expect("Failed to create terminal");
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+        let snapshots = manager.snapshot();
+        let snapshot = snapshots
+            .get(terminal_id.0.as_ref())
+            .expect("Snapshot missing terminal");
+
+        assert_eq!(snapshot.command, "echo");
+        assert_eq!(snapshot.args, vec!["snapshot".to_string()]);
+        assert!(snapshot.accumulated_output.contains("snapshot"));
+        assert!(snapshot.exit_status.is_some());
+        assert!(snapshot.cwd.is_some());
+    }
+
+    #[test]
+    fn test_policy_explicitly_allowed() {
+        let policy = TerminalPolicy {
+            allowed_commands: Some(vec!["ls".to_string(), "git".to_string()]),
+            denied_commands: vec![],
+        };
+
+        assert!(policy.check_command("git").is_ok());
+        assert!(policy.check_command("/usr/bin/ls").is_ok());
+    }
+
+    #[test]
+    fn test_policy_explicitly_denied() {
+        let policy = TerminalPolicy {
+            allowed_commands: Some(vec!["rm".to_string()]),
+            denied_commands: vec!["rm".to_string()],
+        };
+
+        // Deny list wins even if the command is also allowed
+        assert_eq!(
+            policy.check_command("/bin/rm").unwrap_err(),
+            "command denied by policy"
+        );
+    }
+
+    #[test]
+    fn test_policy_implicitly_allowed_without_allow_list() {
+        let policy = TerminalPolicy {
+            allowed_commands: None,
+            denied_commands: vec!["rm".to_string()],
+        };
+
+        assert!(policy.check_command("echo").is_ok());
+    }
+
+    #[test]
+    fn test_policy_implicitly_denied_when_not_in_allow_list() {
+        let policy = TerminalPolicy {
+            allowed_commands: Some(vec!["ls".to_string()]),
+            denied_commands: vec![],
+        };
+
+        assert_eq!(
+            policy.check_command("curl").unwrap_err(),
+            "command denied by policy"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_terminal_respects_policy() {
+        let manager = TerminalManager::new();
+        manager.set_policy(TerminalPolicy {
+            allowed_commands: None,
+            denied_commands: vec!["echo".to_string()],
+        });
+
+        let result = manager
+            .create_terminal("echo".to_string(), vec![], vec![], None, None)
+            .await;
+
+        assert!(result.is_err());
+        assert!(manager.terminals.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prune_exited_terminals() {
+        let manager = TerminalManager::new();
+
+        manager
+            .create_terminal("echo".to_string(), vec![], vec![], None, None)
+            .await
+            .expect("Failed to create terminal");
+        manager
+            .create_terminal(
+                "sleep".to_string(),
+                vec!["5".to_string()],
+                vec![],
+                None,
+                None,
+            )
+            .await
+            .expect("Failed to create terminal");
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+        // Recently fetched (just created) terminals are kept
+        assert_eq!(manager.prune_exited_terminals(), 0);
+
+        // Only the exited terminal is pruned; the running one stays
+        assert_eq!(manager.prune_exited_terminals_older_than(Duration::ZERO), 1);
+        assert_eq!(manager.terminals.lock().len(), 1);
+    }
+
+    async fn echo_terminal(manager: &TerminalManager, text: &str) -> String {
+        manager
+            .create_terminal(
+                "echo".to_string(),
+                vec![text.to_string()],
+                vec![],
+                None,
+                None,
+            )
+            .await
+            .expect("Failed to create terminal")
+            .0
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_group_output_only_includes_group_members() {
+        let manager = TerminalManager::new();
+        let build = echo_terminal(&manager, "building").await;
+        let test = echo_terminal(&manager, "testing").await;
+        let other = echo_terminal(&manager, "unrelated").await;
+
+        let group = manager.create_group("CI".to_string()).unwrap();
+        manager.add_to_group(&group, &build).unwrap();
+        manager.add_to_group(&group, &test).unwrap();
+        // Adding twice doesn't duplicate
+        manager.add_to_group(&group, &test).unwrap();
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+        let output = manager.group_output(&group).unwrap();
+        assert_eq!(output.len(), 2);
+        assert!(output[&build].contains("building"));
+        assert!(output[&test].contains("testing"));
+        assert!(!output.contains_key(&other));
+    }
+
+    #[tokio::test]
+    async fn test_release_removes_terminal_from_groups() {
+        let manager = TerminalManager::new();
+        let first = echo_terminal(&manager, "one").await;
+        let second = echo_terminal(&manager, "two").await;
+
+        let group = manager.create_group("Pair".to_string()).unwrap();
+        manager.add_to_group(&group, &first).unwrap();
+        manager.add_to_group(&group, &second).unwrap();
+
+        manager.release(&first).unwrap();
+
+        assert_eq!(
+            manager.groups.lock()[&group].terminal_ids,
+            vec![second.clone()]
+        );
+        assert!(!manager.group_output(&group).unwrap().contains_key(&first));
+    }
+
+    #[test]
+    fn test_terminal_group_errors() {
+        let manager = TerminalManager::new();
+
+        assert!(manager.create_group("  ".to_string()).is_err());
+        assert!(manager.add_to_group("missing", "terminal").is_err());
+        assert!(manager.group_output("missing").is_err());
+
+        let group = manager.create_group("Empty".to_string()).unwrap();
+        assert!(manager.add_to_group(&group, "no-such-terminal").is_err());
+        assert!(manager.group_output(&group).unwrap().is_empty());
+    }
+
+    fn idle_terminal(max_lines: usize, max_output_bytes: usize) -> Terminal {
+        Terminal {
+            id: "idle".to_string(),
+            command: "true".to_string(),
+            args: vec![],
+            cwd: None,
+            process: None,
+            output: VecDeque::new(),
+            output_bytes: 0,
+            truncated_lines: 0,
+            exit_status: None,
+            max_output_bytes,
+            max_lines,
+            last_fetched_at: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_output_truncated_by_line_count() {
+        let mut terminal = idle_terminal(3, DEFAULT_MAX_OUTPUT_BYTES);
+
+        for i in 0..3 {
+            assert_eq!(terminal.append_line(&format!("line {}", i)), 0);
+        }
+        assert_eq!(terminal.append_line("line 3"), 1);
+        assert_eq!(terminal.append_line("line 4"), 1);
+
+        assert_eq!(terminal.truncated_lines, 2);
+        assert_eq!(terminal.output_text(), "line 2\nline 3\nline 4\n");
+    }
+
+    #[test]
+    fn test_output_truncated_by_bytes_drops_whole_lines() {
+        // Each line is 4 bytes with its newline
+        let mut terminal = idle_terminal(DEFAULT_MAX_OUTPUT_LINES, 10);
+
+        terminal.append_line("aaa");
+        terminal.append_line("bbb");
+        assert_eq!(terminal.append_line("ccc"), 1);
+
+        assert_eq!(terminal.output_text(), "bbb\nccc\n");
+        assert_eq!(terminal.output_bytes, 8);
+
+        // A line over the byte limit on its own keeps its tail
+        assert_eq!(terminal.append_line(&"x".repeat(20)), 2);
+        assert_eq!(terminal.output_text(), format!("{}\n", "x".repeat(9)));
+        assert_eq!(terminal.truncated_lines, 3);
+    }
+
+    #[test]
+    fn test_output_range() {
+        let mut terminal = idle_terminal(4, DEFAULT_MAX_OUTPUT_BYTES);
+        for i in 0..6 {
+            terminal.append_line(&format!("line {}", i));
+        }
+
+        let range = terminal.output_range(1, 3).unwrap();
+        assert_eq!(range.lines, vec!["line 3", "line 4"]);
+        assert_eq!(range.total_lines, 4);
+        assert_eq!(range.truncated_lines, 2);
+
+        // End is clamped to the retained lines
+        assert_eq!(terminal.output_range(3, 100).unwrap().lines, vec!["line 5"]);
+        assert!(terminal.output_range(10, 20).unwrap().lines.is_empty());
+        assert!(terminal.output_range(3, 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_output_range_from_manager() {
+        let manager = TerminalManager::new();
+        let terminal_id = manager
+            .create_terminal(
+                "printf".to_string(),
+                vec!["one\ntwo\nthree\n".to_string()],
+                vec![],
+                None,
+                None,
+            )
+            .await
+            .expect("Failed to create terminal");
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+        let range = manager.get_output_range(&terminal_id.0, 1, 3).unwrap();
+        assert_eq!(range.lines, vec!["two", "three"]);
+        assert_eq!(range.total_lines, 3);
+        assert_eq!(range.truncated_lines, 0);
+
+        assert!(manager.get_output_range("missing", 0, 1).is_err());
+    }
 }
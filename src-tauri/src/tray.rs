@@ -0,0 +1,284 @@
+use crate::spaces::Space;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::image::Image;
+use tauri::menu::{Menu, MenuBuilder};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+const TRAY_ID: &str = "main";
+const SHOW_WINDOW_ID: &str = "show-window";
+const QUIT_ID: &str = "quit";
+/// Menu IDs for space items are this prefix followed by the space ID
+const SPACE_ITEM_PREFIX: &str = "space:";
+
+/// Recently accessed spaces listed in the tray menu
+const RECENT_SPACES_IN_TRAY: usize = 5;
+
+/// Which tray icon to show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayIconState {
+    Idle,
+    Busy,
+}
+
+/// Number of agent requests in flight
+/// begin/end report a new icon state only when the tray needs to change
+#[derive(Debug, Default)]
+pub struct ActivityCounter {
+    active: AtomicUsize,
+}
+
+impl ActivityCounter {
+    /// Record a request starting; Some(Busy) if the agent was idle
+    pub fn begin(&self) -> Option<TrayIconState> {
+        match self.active.fetch_add(1, Ordering::SeqCst) {
+            0 => Some(TrayIconState::Busy),
+            _ => None,
+        }
+    }
+
+    /// Record a request finishing; Some(Idle) if it was the last one
+    pub fn end(&self) -> Option<TrayIconState> {
+        match self
+            .active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        {
+            Ok(1) => Some(TrayIconState::Idle),
+            // Already idle: an unmatched end is ignored rather than wrapping
+            _ => None,
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+}
+
+/// What a tray menu click asks for
+#[derive(Debug, PartialEq)]
+enum TrayAction {
+    ShowWindow,
+    OpenSpace(String),
+    Quit,
+}
+
+fn tray_action(menu_id: &str) -> Option<TrayAction> {
+    match menu_id {
+        SHOW_WINDOW_ID => Some(TrayAction::ShowWindow),
+        QUIT_ID => Some(TrayAction::Quit),
+        _ => menu_id
+            .strip_prefix(SPACE_ITEM_PREFIX)
+            .filter(|id| !id.is_empty())
+            .map(|id| TrayAction::OpenSpace(id.to_string())),
+    }
+}
+
+/// The most recently accessed spaces, newest first
+fn recent_spaces(mut spaces: Vec<Space>) -> Vec<Space> {
+    spaces.sort_by(|a, b| b.last_accessed_at.cmp(&a.last_accessed_at));
+    spaces.truncate(RECENT_SPACES_IN_TRAY);
+    spaces
+}
+
+/// The app icon with a green dot in the bottom-right corner, shown while the agent works
+fn busy_icon(base: &Image<'_>) -> Image<'static> {
+    let (width, height) = (base.width(), base.height());
+    let mut rgba = base.rgba().to_vec();
+
+    let radius = (width.min(height) / 4) as i64;
+    let (cx, cy) = (width as i64 - radius, height as i64 - radius);
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            if (x - cx).pow(2) + (y - cy).pow(2) <= radius.pow(2) {
+                let i = ((y * width as i64 + x) * 4) as usize;
+                rgba[i..i + 4].copy_from_slice(&[0x22, 0xC5, 0x5E, 0xFF]);
+            }
+        }
+    }
+
+    Image::new_owned(rgba, width, height)
+}
+
+fn build_menu(app: &AppHandle) -> Result<Menu<Wry>, String> {
+    let spaces = crate::spaces::list_spaces(None).unwrap_or_else(|e| {
+        eprintln!("[TRAY] Failed to list spaces: {}", e);
+        Vec::new()
+    });
+
+    let mut builder = MenuBuilder::new(app)
+        .text(SHOW_WINDOW_ID, "Show Window")
+        .separator();
+    for space in recent_spaces(spaces) {
+        builder = builder.text(format!("{}{}", SPACE_ITEM_PREFIX, space.id), &space.name);
+    }
+
+    builder
+        .separator()
+        .text(QUIT_ID, "Quit")
+        .build()
+        .map_err(|e| format!("Failed to build tray menu: {}", e))
+}
+
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn handle_menu_event(app: &AppHandle, menu_id: &str) {
+    match tray_action(menu_id) {
+        Some(TrayAction::ShowWindow) => show_main_window(app),
+        Some(TrayAction::OpenSpace(space_id)) => {
+            show_main_window(app);
+            if let Err(e) = app.emit("tray-open-space", json!({ "spaceId": space_id })) {
+                eprintln!("[TRAY] Failed to emit tray-open-space: {}", e);
+            }
+        }
+        Some(TrayAction::Quit) => app.exit(0),
+        None => {}
+    }
+}
+
+/// Add the tray icon with its quick-access menu
+pub fn create_tray(app: &AppHandle) -> Result<(), String> {
+    let icon = app
+        .default_window_icon()
+        .cloned()
+        .ok_or("No app icon available for the tray")?;
+
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(icon)
+        .tooltip("Thinking Space")
+        .menu(&build_menu(app)?)
+        .on_menu_event(|app, event| handle_menu_event(app, event.id().as_ref()))
+        .build(app)
+        .map_err(|e| format!("Failed to create tray icon: {}", e))?;
+
+    Ok(())
+}
+
+/// Rebuild the menu so the recent spaces list is current
+pub fn refresh_tray_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    match build_menu(app) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                eprintln!("[TRAY] Failed to update tray menu: {}", e);
+            }
+        }
+        Err(e) => eprintln!("[TRAY] {}", e),
+    }
+}
+
+/// Switch between the idle and busy tray icons
+pub fn set_tray_state(app: &AppHandle, state: TrayIconState) {
+    let (Some(tray), Some(base)) = (app.tray_by_id(TRAY_ID), app.default_window_icon()) else {
+        return;
+    };
+
+    let icon = match state {
+        TrayIconState::Idle => base.clone(),
+        TrayIconState::Busy => busy_icon(base),
+    };
+    if let Err(e) = tray.set_icon(Some(icon)) {
+        eprintln!("[TRAY] Failed to update tray icon: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn space(id: &str, last_accessed_at: i64) -> Space {
+        Space {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: String::new(),
+            claude_md_path: String::new(),
+            created_at: 0,
+            last_accessed_at,
+            template: None,
+            archived: false,
+            pinned: false,
+            pin_order: None,
+        }
+    }
+
+    #[test]
+    fn test_activity_counter_signals_only_on_transitions() {
+        let counter = ActivityCounter::default();
+
+        assert_eq!(counter.begin(), Some(TrayIconState::Busy));
+        assert_eq!(counter.begin(), None);
+        assert_eq!(counter.active(), 2);
+
+        assert_eq!(counter.end(), None);
+        assert_eq!(counter.end(), Some(TrayIconState::Idle));
+        assert_eq!(counter.active(), 0);
+
+        // Unmatched end stays at zero
+        assert_eq!(counter.end(), None);
+        assert_eq!(counter.active(), 0);
+
+        assert_eq!(counter.begin(), Some(TrayIconState::Busy));
+    }
+
+    #[test]
+    fn test_activity_counter_across_threads() {
+        let counter = std::sync::Arc::new(ActivityCounter::default());
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let counter = counter.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        counter.begin();
+                        counter.end();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(counter.active(), 0);
+    }
+
+    #[test]
+    fn test_tray_action() {
+        assert_eq!(tray_action("show-window"), Some(TrayAction::ShowWindow));
+        assert_eq!(tray_action("quit"), Some(TrayAction::Quit));
+        assert_eq!(
+            tray_action("space:abc-123"),
+            Some(TrayAction::OpenSpace("abc-123".to_string()))
+        );
+        assert_eq!(tray_action("space:"), None);
+        assert_eq!(tray_action("other"), None);
+    }
+
+    #[test]
+    fn test_recent_spaces_limited_to_five() {
+        let spaces = (0..8).map(|i| space(&format!("s{}", i), i)).collect();
+
+        let ids: Vec<String> = recent_spaces(spaces).into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec!["s7", "s6", "s5", "s4", "s3"]);
+    }
+
+    #[test]
+    fn test_busy_icon_marks_corner() {
+        let base = Image::new_owned(vec![0; 16 * 16 * 4], 16, 16);
+        let busy = busy_icon(&base);
+
+        assert_eq!((busy.width(), busy.height()), (16, 16));
+        let pixel = |x: usize, y: usize| &busy.rgba()[(y * 16 + x) * 4..(y * 16 + x) * 4 + 4];
+        assert_eq!(pixel(14, 14), &[0x22, 0xC5, 0x5E, 0xFF]);
+        assert_eq!(pixel(0, 0), &[0, 0, 0, 0]);
+    }
+}
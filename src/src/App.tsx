@@ -1,4 +1,6 @@
 import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { SpaceList } from "./components/SpaceList";
 import { CreateSpaceModal } from "./components/CreateSpaceModal";
 import { ChatArea } from "./components/ChatArea";
@@ -6,6 +8,7 @@ import { ClaudeMdEditor } from "./components/ClaudeMdEditor";
 import { SettingsPanel } from "./components/SettingsPanel";
 import { ArtifactViewer } from "./components/ArtifactViewer";
 import { WelcomeScreen } from "./components/WelcomeScreen";
+import { CredentialReminder } from "./components/CredentialReminder";
 import { useSettingsStore } from "./stores/settingsStore";
 import { useSpacesStore } from "./stores/spacesStore";
 import { useChatStore } from "./stores/chatStore";
@@ -32,6 +35,41 @@ function App() {
     // No need to start it eagerly here
   }, [loadSettings]);
 
+  // Open spaces from thinkingspace:// links, including the one we launched with
+  useEffect(() => {
+    const openLinkedSpace = async (spaceId: string) => {
+      const spacesStore = useSpacesStore.getState();
+      if (!spacesStore.spaces.some((s) => s.id === spaceId)) {
+        await spacesStore.loadSpaces();
+      }
+      await useSpacesStore.getState().selectSpace(spaceId);
+    };
+
+    invoke<{ event: string; spaceId: string } | null>("take_pending_deep_link")
+      .then((link) => {
+        if (link?.event === "deep-link-open-space") {
+          openLinkedSpace(link.spaceId);
+        } else if (link) {
+          console.warn("[DEEP LINK] Space not found:", link.spaceId);
+        }
+      })
+      .catch(console.error);
+
+    const unlistenOpen = listen<{ spaceId: string }>(
+      "deep-link-open-space",
+      (event) => openLinkedSpace(event.payload.spaceId),
+    );
+    const unlistenNotFound = listen<{ spaceId: string }>(
+      "deep-link-not-found",
+      (event) => console.warn("[DEEP LINK] Space not found:", event.payload.spaceId),
+    );
+
+    return () => {
+      unlistenOpen.then((unlisten) => unlisten());
+      unlistenNotFound.then((unlisten) => unlisten());
+    };
+  }, []);
+
   // Load conversation when Space changes
   useEffect(() => {
     if (currentSpace) {
@@ -107,7 +145,10 @@ function App() {
         </div>
 
         {/* Main Area */}
-        <ChatArea />
+        <div className="flex-1 flex flex-col min-w-0">
+          <CredentialReminder />
+          <ChatArea />
+        </div>
 
         {/* Modals */}
         <CreateSpaceModal
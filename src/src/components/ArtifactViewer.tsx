@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "../services/errors";
 
 interface SpaceFile {
   name: string;
@@ -41,7 +42,7 @@ export function ArtifactViewer({
       });
       setFiles(spaceFiles);
     } catch (err) {
-      setError(String(err));
+      setError(errorMessage(err));
     } finally {
       setLoading(false);
     }
@@ -1,6 +1,7 @@
 import { useState, useEffect } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { useSpacesStore } from '../stores/spacesStore'
+import { errorMessage } from '../services/errors'
 
 interface ClaudeMdEditorProps {
   isOpen: boolean
@@ -32,7 +33,7 @@ export function ClaudeMdEditor({ isOpen, onClose }: ClaudeMdEditorProps) {
       setContent(md)
     } catch (err) {
       console.error('Failed to load CLAUDE.md:', err)
-      setError(String(err))
+      setError(errorMessage(err))
     } finally {
       setLoading(false)
     }
@@ -51,7 +52,7 @@ export function ClaudeMdEditor({ isOpen, onClose }: ClaudeMdEditorProps) {
       onClose()
     } catch (err) {
       console.error('Failed to save CLAUDE.md:', err)
-      setError(String(err))
+      setError(errorMessage(err))
     } finally {
       setSaving(false)
     }
@@ -0,0 +1,62 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+
+interface CredentialStatus {
+  has_api_key: boolean;
+  has_oauth: boolean;
+  oauth_expires_at: number | null;
+  oauth_expires_in_hours: number | null;
+  should_refresh: boolean;
+  is_expired: boolean;
+}
+
+/**
+ * Shows a daily reminder when the Claude Code OAuth token is about to expire
+ */
+export function CredentialReminder() {
+  const [status, setStatus] = useState<CredentialStatus | null>(null);
+
+  useEffect(() => {
+    invoke<CredentialStatus | null>("take_rotation_reminder")
+      .then(setStatus)
+      .catch((error) => {
+        console.error("[AUTH] Failed to check credential status:", error);
+      });
+  }, []);
+
+  if (!status) {
+    return null;
+  }
+
+  const hours = Math.max(0, Math.round(status.oauth_expires_in_hours ?? 0));
+
+  return (
+    <div className="px-6 py-3 border-b bg-amber-50 dark:bg-amber-900/20 border-amber-300 dark:border-amber-700">
+      <div className="max-w-3xl mx-auto flex items-start gap-3">
+        <div className="flex-1 min-w-0">
+          <p className="text-sm font-medium text-amber-800 dark:text-amber-200">
+            {status.is_expired
+              ? "Claude sign-in expired"
+              : "Claude sign-in expires soon"}
+          </p>
+          <p className="text-xs mt-1 text-amber-700 dark:text-amber-300">
+            {status.is_expired ? (
+              <>Run `claude` in a terminal to sign in again.</>
+            ) : (
+              <>
+                Your Claude Code token expires in about {hours} hours. Run
+                `claude` in a terminal to refresh it.
+              </>
+            )}
+          </p>
+        </div>
+        <button
+          onClick={() => setStatus(null)}
+          className="text-xs text-amber-700 dark:text-amber-300 hover:underline"
+        >
+          Dismiss
+        </button>
+      </div>
+    </div>
+  );
+}
@@ -9,8 +9,15 @@ interface SettingsPanelProps {
 }
 
 export function SettingsPanel({ isOpen, onClose }: SettingsPanelProps) {
-  const { apiKey, theme, setApiKey, setTheme, dataLocation, openDataFolder } =
-    useSettingsStore();
+  const {
+    apiKey,
+    apiKeyError,
+    theme,
+    setApiKey,
+    setTheme,
+    dataLocation,
+    openDataFolder,
+  } = useSettingsStore();
   const [localApiKey, setLocalApiKey] = useState(apiKey);
   const [showKey, setShowKey] = useState(false);
   const [saving, setSaving] = useState(false);
@@ -292,6 +299,11 @@ export function SettingsPanel({ isOpen, onClose }: SettingsPanelProps) {
                   {showKey ? "Hide" : "Show"}
                 </button>
               </div>
+              {apiKeyError && (
+                <p className="mt-2 text-xs text-red-600 dark:text-red-400">
+                  {apiKeyError}
+                </p>
+              )}
               <p className="mt-2 text-xs text-gray-500 dark:text-gray-400">
                 Get your API key from{" "}
                 <a
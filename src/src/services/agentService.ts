@@ -8,7 +8,7 @@ export interface AgentMessage {
 }
 
 export interface ConversationMessage {
-  role: "user" | "assistant";
+  role: "user" | "assistant" | "summary";
   content: string;
 }
 
@@ -0,0 +1,34 @@
+/**
+ * Errors returned by Tauri commands
+ *
+ * Migrated commands reject with a ThinkingSpaceError object; the rest still
+ * reject with a plain string.
+ */
+
+export type ThinkingSpaceError =
+  | { type: "io"; message: string; path: string; source: string }
+  | { type: "database"; message: string }
+  | { type: "serialization"; message: string }
+  | { type: "security"; message: string; reason: string }
+  | { type: "agentNotConnected"; message: string }
+  | { type: "clipboardUnavailable"; message: string; reason: string }
+  | { type: "notFound"; message: string; resource: string }
+  | { type: "validationFailed"; message: string; field: string };
+
+export function isThinkingSpaceError(
+  error: unknown,
+): error is ThinkingSpaceError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    typeof (error as { type?: unknown }).type === "string" &&
+    typeof (error as { message?: unknown }).message === "string"
+  );
+}
+
+export function errorMessage(error: unknown): string {
+  if (error instanceof Error || isThinkingSpaceError(error)) {
+    return error.message;
+  }
+  return String(error);
+}
@@ -1,16 +1,20 @@
 import { create } from "zustand";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { agentService } from "../services/agentService";
+import { errorMessage } from "../services/errors";
 import { useSpacesStore } from "./spacesStore";
 
 export interface Message {
   id: string;
-  role: "user" | "assistant";
+  role: "user" | "assistant" | "summary";
   content: string;
   timestamp: number;
   metadata?: {
     toolCalls?: any[];
     files?: string[];
+    auto_summarised?: boolean;
+    original_message_count?: number;
   };
 }
 
@@ -18,6 +22,7 @@ export interface Message {
 const CHARS_PER_TOKEN = 4;
 const CONTEXT_WARNING_THRESHOLD = 150000; // Warn at 150K tokens (~200K limit)
 const CONTEXT_LIMIT = 200000; // Hard limit
+const MIN_MESSAGES_FOR_TITLE = 5; // Matches the backend's threshold
 
 function estimateTokens(text: string): number {
   return Math.ceil(text.length / CHARS_PER_TOKEN);
@@ -35,11 +40,13 @@ interface ChatState {
   error: string | null;
   currentStreamingMessage: string;
   contextTokens: number; // Estimated tokens in current conversation
+  pendingSummary: Message | null; // Replaces the messages once the current reply is added
 
   // Actions
   sendMessage: (content: string, files?: string[]) => Promise<void>;
   clearMessages: () => void;
   addMessage: (message: Message) => void;
+  applySummary: (summary: Message) => void;
   loadMessagesForSpace: (spaceId: string) => Promise<void>;
   saveCurrentConversation: (
     spaceId: string,
@@ -59,6 +66,7 @@ export const useChatStore = create<ChatState>((set, get) => ({
   error: null,
   currentStreamingMessage: "",
   contextTokens: 0,
+  pendingSummary: null,
 
   sendMessage: async (content: string, files?: string[]) => {
     console.log("[CHAT STORE] sendMessage called with:", content);
@@ -88,7 +96,7 @@ export const useChatStore = create<ChatState>((set, get) => ({
               console.error(`Failed to read ${filePath}:`, error);
               return {
                 path: filePath,
-                content: `[Error reading file: ${error}]`,
+                content: `[Error reading file: ${errorMessage(error)}]`,
               };
             }
           }),
@@ -197,14 +205,39 @@ export const useChatStore = create<ChatState>((set, get) => ({
       get().addMessage(assistantMessage);
       set({ streaming: false, currentStreamingMessage: "" });
 
+      // The agent summarised the conversation at the context limit; the
+      // summary now stands in for everything before it
+      const summary = get().pendingSummary;
+      if (summary) {
+        set({
+          messages: [summary],
+          contextTokens: estimateConversationTokens([summary]),
+          pendingSummary: null,
+        });
+      }
+
       // Save conversation to database
       await get().saveCurrentConversation(currentSpace.id, currentSpace.name);
+
+      // Name the conversation once it's long enough; the backend keeps an
+      // existing title, so later calls don't prompt the agent again
+      if (get().messages.length >= MIN_MESSAGES_FOR_TITLE) {
+        invoke("generate_conversation_title", {
+          spaceId: currentSpace.id,
+        }).catch((error) => {
+          console.error(
+            "[CHAT STORE] Failed to generate title:",
+            errorMessage(error),
+          );
+        });
+      }
     } catch (error) {
       console.error("Failed to send message:", error);
       set({
-        error: error instanceof Error ? error.message : String(error),
+        error: errorMessage(error),
         streaming: false,
         currentStreamingMessage: "",
+        pendingSummary: null,
       });
     }
   },
@@ -223,6 +256,12 @@ export const useChatStore = create<ChatState>((set, get) => ({
     });
   },
 
+  applySummary: (summary: Message) => {
+    // Arrives before the reply that hit the limit finishes streaming;
+    // sendMessage applies it once that reply has been added
+    set({ pendingSummary: summary });
+  },
+
   loadMessagesForSpace: async (spaceId: string) => {
     try {
       console.log("[CHAT STORE] Loading messages for space:", spaceId);
@@ -236,7 +275,7 @@ export const useChatStore = create<ChatState>((set, get) => ({
     } catch (error) {
       console.error("[CHAT STORE] Failed to load messages:", error);
       set({
-        error: error instanceof Error ? error.message : String(error),
+        error: errorMessage(error),
         messages: [],
         contextTokens: 0,
       });
@@ -270,3 +309,8 @@ export const useChatStore = create<ChatState>((set, get) => ({
     };
   },
 }));
+
+listen<{ summary: Message }>("agent-conversation-summarised", (event) => {
+  console.log("[CHAT STORE] Conversation summarised");
+  useChatStore.getState().applySummary(event.payload.summary);
+}).catch(console.error);
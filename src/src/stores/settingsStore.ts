@@ -3,12 +3,15 @@ import { invoke } from "@tauri-apps/api/core";
 
 interface Settings {
   api_key: string | null;
+  api_key_undecryptable?: boolean;
   theme: string;
   has_completed_onboarding: boolean;
 }
 
 interface SettingsState {
   apiKey: string;
+  /** Set when the saved API key exists but can't be decrypted on this machine */
+  apiKeyError: string | null;
   theme: "light" | "dark" | "system";
   dataLocation: string;
   hasCompletedOnboarding: boolean;
@@ -21,6 +24,9 @@ interface SettingsState {
   completeOnboarding: () => Promise<void>;
 }
 
+const API_KEY_UNDECRYPTABLE_MESSAGE =
+  "Your saved API key can't be decrypted on this machine. Enter it again to replace it.";
+
 function applyTheme(theme: "light" | "dark" | "system") {
   if (
     theme === "dark" ||
@@ -35,6 +41,7 @@ function applyTheme(theme: "light" | "dark" | "system") {
 
 export const useSettingsStore = create<SettingsState>((set) => ({
   apiKey: "",
+  apiKeyError: null,
   theme: "system",
   dataLocation: "~/.thinking-space",
   hasCompletedOnboarding: false,
@@ -47,7 +54,8 @@ export const useSettingsStore = create<SettingsState>((set) => ({
         api_key: key || null,
       };
       await invoke("save_settings", { settings: newSettings });
-      set({ apiKey: key });
+      // A new key replaces the one that couldn't be decrypted
+      set(key ? { apiKey: key, apiKeyError: null } : { apiKey: key });
     } catch (error) {
       console.error("Failed to save API key:", error);
       throw error;
@@ -77,10 +85,14 @@ export const useSettingsStore = create<SettingsState>((set) => ({
 
       const theme = (settings.theme || "system") as "light" | "dark" | "system";
       const apiKey = settings.api_key || "";
+      const apiKeyError = settings.api_key_undecryptable
+        ? API_KEY_UNDECRYPTABLE_MESSAGE
+        : null;
       const hasCompletedOnboarding = settings.has_completed_onboarding || false;
 
       set({
         apiKey,
+        apiKeyError,
         theme,
         dataLocation,
         hasCompletedOnboarding,
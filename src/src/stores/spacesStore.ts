@@ -1,6 +1,7 @@
 import { create } from "zustand";
 import { invoke } from "@tauri-apps/api/core";
 import { agentService } from "../services/agentService";
+import { errorMessage } from "../services/errors";
 
 export interface Space {
   id: string;
@@ -62,7 +63,7 @@ export const useSpacesStore = create<SpacesState>((set, get) => ({
       set({ spaces: spaces.map(toSpaceUI), loading: false });
     } catch (error) {
       console.error("Failed to load spaces:", error);
-      set({ error: String(error), loading: false });
+      set({ error: errorMessage(error), loading: false });
     }
   },
 
@@ -81,7 +82,7 @@ export const useSpacesStore = create<SpacesState>((set, get) => ({
       }));
     } catch (error) {
       console.error("Failed to create space:", error);
-      set({ error: String(error), loading: false });
+      set({ error: errorMessage(error), loading: false });
     }
   },
 
@@ -111,7 +112,7 @@ export const useSpacesStore = create<SpacesState>((set, get) => ({
       }));
     } catch (error) {
       console.error("Failed to delete space:", error);
-      set({ error: String(error), loading: false });
+      set({ error: errorMessage(error), loading: false });
     }
   },
 